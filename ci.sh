@@ -6,6 +6,7 @@ if [ -z "$TARGET" ]
 then
     make all
     make test
+    make test-hardened
 else
     make libs
 fi
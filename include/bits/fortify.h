@@ -0,0 +1,19 @@
+#ifndef _BITS_FORTIFY_H
+#define _BITS_FORTIFY_H
+
+// With _FORTIFY_SOURCE, calls where the compiler knows the size of the
+// destination go to the checked versions in header/_fortify, which abort
+// instead of overflowing it. The size is only known when optimizing.
+#if defined(_FORTIFY_SOURCE) && _FORTIFY_SOURCE > 0 && defined(__OPTIMIZE__) && __OPTIMIZE__ > 0 \
+    && defined(__GNUC__)
+#define __RELIBC_FORTIFY 1
+
+#define __relibc_fortify extern __inline __attribute__((__always_inline__, __gnu_inline__, __artificial__))
+
+// The size of the object a pointer points into, or (size_t) -1 if unknown.
+// Level 2 uses the closest enclosing subobject, like a struct member.
+#define __relibc_bos(ptr) __builtin_object_size((ptr), _FORTIFY_SOURCE > 1)
+#define __relibc_bos0(ptr) __builtin_object_size((ptr), 0)
+#endif
+
+#endif
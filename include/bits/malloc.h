@@ -1,7 +1,5 @@
-#ifndef _MALLOC_H
-#define _MALLOC_H
-
-#include <stddef.h>
+#ifndef _BITS_MALLOC_H
+#define _BITS_MALLOC_H
 
 // Generated from:
 // `grep "malloc\|calloc\|realloc\|free\|valloc\|memalign" target/include/stdlib.h`
@@ -0,0 +1,20 @@
+#ifndef _BITS_PTHREAD_H
+#define _BITS_PTHREAD_H
+
+// An all-zero value is valid for these, see header/sys_types
+#define PTHREAD_COND_INITIALIZER {0}
+#define PTHREAD_MUTEX_INITIALIZER {0}
+#define PTHREAD_RWLOCK_INITIALIZER {0}
+
+#define PTHREAD_CANCELED ((void *) -1)
+
+// The handler lives on the stack until it is popped, see header/pthread/cancel
+#define pthread_cleanup_push(routine, arg) do { \
+    struct __pthread_cleanup __relibc_cleanup = { (routine), (arg), 0 }; \
+    __pthread_cleanup_push(&__relibc_cleanup);
+
+#define pthread_cleanup_pop(execute) \
+    __pthread_cleanup_pop(&__relibc_cleanup, (execute)); \
+} while (0)
+
+#endif
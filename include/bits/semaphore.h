@@ -0,0 +1,16 @@
+#ifndef _BITS_SEMAPHORE_H
+#define _BITS_SEMAPHORE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SEM_FAILED ((sem_t *) 0)
+
+sem_t *sem_open(const char *name, int oflag, ...);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif
+
+#endif
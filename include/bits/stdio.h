@@ -10,6 +10,8 @@ typedef struct FILE FILE;
 // STDIO was loaded.
 #define FILE FILE
 
+#include <bits/fortify.h>
+
 #ifdef __cplusplus
 extern "C" {
 #endif
@@ -23,6 +25,41 @@ int fscanf(FILE * stream, const char * fmt, ...);
 int scanf(const char * fmt, ...);
 int sscanf(const char * input, const char * fmt, ...);
 
+#ifdef __RELIBC_FORTIFY
+char *__fgets_chk(char *s, size_t size, int n, FILE *stream);
+size_t __fread_chk(void *ptr, size_t ptrlen, size_t size, size_t nitems, FILE *stream);
+
+// The unchecked functions, which the wrappers below can't call by name
+char *__relibc_fgets(char *s, int n, FILE *stream) __asm__("fgets");
+size_t __relibc_fread(void *ptr, size_t size, size_t nitems, FILE *stream) __asm__("fread");
+
+__relibc_fortify char *fgets(char *s, int n, FILE *stream) {
+    if (__relibc_bos(s) != (size_t) -1)
+        return __fgets_chk(s, __relibc_bos(s), n, stream);
+    return __relibc_fgets(s, n, stream);
+}
+
+__relibc_fortify size_t fread(void *ptr, size_t size, size_t nitems, FILE *stream) {
+    if (__relibc_bos0(ptr) != (size_t) -1)
+        return __fread_chk(ptr, __relibc_bos0(ptr), size, nitems, stream);
+    return __relibc_fread(ptr, size, nitems, stream);
+}
+
+__relibc_fortify int vsnprintf(char *s, size_t n, const char *fmt, va_list ap) {
+    return __builtin___vsnprintf_chk(s, n, _FORTIFY_SOURCE - 1, __relibc_bos(s), fmt, ap);
+}
+
+__relibc_fortify int vsprintf(char *s, const char *fmt, va_list ap) {
+    return __builtin___vsprintf_chk(s, _FORTIFY_SOURCE - 1, __relibc_bos(s), fmt, ap);
+}
+
+// Variadic functions can't be wrapped portably, so these are macros
+#define snprintf(s, n, ...) \
+    __builtin___snprintf_chk((s), (n), _FORTIFY_SOURCE - 1, __relibc_bos(s), __VA_ARGS__)
+#define sprintf(s, ...) \
+    __builtin___sprintf_chk((s), _FORTIFY_SOURCE - 1, __relibc_bos(s), __VA_ARGS__)
+#endif
+
 #ifdef __cplusplus
 } // extern "C"
 #endif
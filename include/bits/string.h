@@ -0,0 +1,44 @@
+#ifndef _BITS_STRING_H
+#define _BITS_STRING_H
+
+#include <bits/fortify.h>
+
+#ifdef __RELIBC_FORTIFY
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+__relibc_fortify void *memcpy(void *dest, const void *src, size_t n) {
+    return __builtin___memcpy_chk(dest, src, n, __relibc_bos0(dest));
+}
+
+__relibc_fortify void *memmove(void *dest, const void *src, size_t n) {
+    return __builtin___memmove_chk(dest, src, n, __relibc_bos0(dest));
+}
+
+__relibc_fortify void *memset(void *dest, int c, size_t n) {
+    return __builtin___memset_chk(dest, c, n, __relibc_bos0(dest));
+}
+
+__relibc_fortify char *strcat(char *dest, const char *src) {
+    return __builtin___strcat_chk(dest, src, __relibc_bos(dest));
+}
+
+__relibc_fortify char *strcpy(char *dest, const char *src) {
+    return __builtin___strcpy_chk(dest, src, __relibc_bos(dest));
+}
+
+__relibc_fortify char *strncat(char *dest, const char *src, size_t n) {
+    return __builtin___strncat_chk(dest, src, n, __relibc_bos(dest));
+}
+
+__relibc_fortify char *strncpy(char *dest, const char *src, size_t n) {
+    return __builtin___strncpy_chk(dest, src, n, __relibc_bos(dest));
+}
+
+#ifdef __cplusplus
+} // extern "C"
+#endif
+#endif
+
+#endif /* _BITS_STRING_H */
@@ -0,0 +1,11 @@
+#ifndef _BITS_THREADS_H
+#define _BITS_THREADS_H
+
+// An all-zero value is valid for once_flag, see header/pthread/once
+#define ONCE_FLAG_INIT 0
+
+#if !defined(__cplusplus) && __STDC_VERSION__ < 202311L
+#define thread_local _Thread_local
+#endif
+
+#endif
@@ -0,0 +1,11 @@
+#ifndef _BITS_UCHAR_H
+#define _BITS_UCHAR_H
+#include <stdint.h>
+
+// These are keywords in C++11
+#ifndef __cplusplus
+typedef uint_least16_t char16_t;
+typedef uint_least32_t char32_t;
+#endif
+
+#endif /* _BITS_UCHAR_H */
@@ -1,6 +1,8 @@
 #ifndef _BITS_UNISTD_H
 #define _BITS_UNISTD_H
 
+#include <bits/fortify.h>
+
 #define _POSIX_VERSION 200809L
 
 #ifdef __cplusplus
@@ -11,6 +13,51 @@ int execl(const char *path, const char* argv0, ...);
 int execle(const char *path, const char* argv0, ...);
 int execlp(const char *file, const char* argv0, ...);
 
+#ifdef __RELIBC_FORTIFY
+char *__getcwd_chk(char *buf, size_t size, size_t buflen);
+int __gethostname_chk(char *name, size_t len, size_t buflen);
+ssize_t __pread_chk(int fildes, void *buf, size_t nbyte, off_t offset, size_t buflen);
+ssize_t __read_chk(int fildes, void *buf, size_t nbyte, size_t buflen);
+ssize_t __readlink_chk(const char *path, char *buf, size_t bufsize, size_t buflen);
+
+// The unchecked functions, which the wrappers below can't call by name
+char *__relibc_getcwd(char *buf, size_t size) __asm__("getcwd");
+int __relibc_gethostname(char *name, size_t len) __asm__("gethostname");
+ssize_t __relibc_pread(int fildes, void *buf, size_t nbyte, off_t offset) __asm__("pread");
+ssize_t __relibc_read(int fildes, void *buf, size_t nbyte) __asm__("read");
+ssize_t __relibc_readlink(const char *path, char *buf, size_t bufsize) __asm__("readlink");
+
+__relibc_fortify char *getcwd(char *buf, size_t size) {
+    if (__relibc_bos(buf) != (size_t) -1)
+        return __getcwd_chk(buf, size, __relibc_bos(buf));
+    return __relibc_getcwd(buf, size);
+}
+
+__relibc_fortify int gethostname(char *name, size_t len) {
+    if (__relibc_bos(name) != (size_t) -1)
+        return __gethostname_chk(name, len, __relibc_bos(name));
+    return __relibc_gethostname(name, len);
+}
+
+__relibc_fortify ssize_t pread(int fildes, void *buf, size_t nbyte, off_t offset) {
+    if (__relibc_bos0(buf) != (size_t) -1)
+        return __pread_chk(fildes, buf, nbyte, offset, __relibc_bos0(buf));
+    return __relibc_pread(fildes, buf, nbyte, offset);
+}
+
+__relibc_fortify ssize_t read(int fildes, void *buf, size_t nbyte) {
+    if (__relibc_bos0(buf) != (size_t) -1)
+        return __read_chk(fildes, buf, nbyte, __relibc_bos0(buf));
+    return __relibc_read(fildes, buf, nbyte);
+}
+
+__relibc_fortify ssize_t readlink(const char *path, char *buf, size_t bufsize) {
+    if (__relibc_bos(buf) != (size_t) -1)
+        return __readlink_chk(path, buf, bufsize, __relibc_bos(buf));
+    return __relibc_readlink(path, buf, bufsize);
+}
+#endif
+
 #ifdef __cplusplus
 } // extern "C"
 #endif
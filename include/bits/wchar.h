@@ -10,5 +10,80 @@
 #define __need_wchar_t
 #define __need_wint_t
 #define __need_NULL
+#include <stddef.h>
+
+#include <bits/fortify.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+long double wcstold(const wchar_t *nptr, wchar_t **endptr);
+
+#ifdef __RELIBC_FORTIFY
+// Sizes passed to the checked functions are in wide characters
+wchar_t *__wcscat_chk(wchar_t *dest, const wchar_t *src, size_t destlen);
+wchar_t *__wcscpy_chk(wchar_t *dest, const wchar_t *src, size_t destlen);
+wchar_t *__wcsncat_chk(wchar_t *dest, const wchar_t *src, size_t n, size_t destlen);
+wchar_t *__wcsncpy_chk(wchar_t *dest, const wchar_t *src, size_t n, size_t destlen);
+wchar_t *__wmemcpy_chk(wchar_t *dest, const wchar_t *src, size_t n, size_t destlen);
+wchar_t *__wmemmove_chk(wchar_t *dest, const wchar_t *src, size_t n, size_t destlen);
+wchar_t *__wmemset_chk(wchar_t *dest, wchar_t wc, size_t n, size_t destlen);
+
+// The unchecked functions, which the wrappers below can't call by name
+wchar_t *__relibc_wcscat(wchar_t *dest, const wchar_t *src) __asm__("wcscat");
+wchar_t *__relibc_wcscpy(wchar_t *dest, const wchar_t *src) __asm__("wcscpy");
+wchar_t *__relibc_wcsncat(wchar_t *dest, const wchar_t *src, size_t n) __asm__("wcsncat");
+wchar_t *__relibc_wcsncpy(wchar_t *dest, const wchar_t *src, size_t n) __asm__("wcsncpy");
+wchar_t *__relibc_wmemcpy(wchar_t *dest, const wchar_t *src, size_t n) __asm__("wmemcpy");
+wchar_t *__relibc_wmemmove(wchar_t *dest, const wchar_t *src, size_t n) __asm__("wmemmove");
+wchar_t *__relibc_wmemset(wchar_t *dest, wchar_t wc, size_t n) __asm__("wmemset");
+
+__relibc_fortify wchar_t *wcscat(wchar_t *dest, const wchar_t *src) {
+    if (__relibc_bos(dest) != (size_t) -1)
+        return __wcscat_chk(dest, src, __relibc_bos(dest) / sizeof(wchar_t));
+    return __relibc_wcscat(dest, src);
+}
+
+__relibc_fortify wchar_t *wcscpy(wchar_t *dest, const wchar_t *src) {
+    if (__relibc_bos(dest) != (size_t) -1)
+        return __wcscpy_chk(dest, src, __relibc_bos(dest) / sizeof(wchar_t));
+    return __relibc_wcscpy(dest, src);
+}
+
+__relibc_fortify wchar_t *wcsncat(wchar_t *dest, const wchar_t *src, size_t n) {
+    if (__relibc_bos(dest) != (size_t) -1)
+        return __wcsncat_chk(dest, src, n, __relibc_bos(dest) / sizeof(wchar_t));
+    return __relibc_wcsncat(dest, src, n);
+}
+
+__relibc_fortify wchar_t *wcsncpy(wchar_t *dest, const wchar_t *src, size_t n) {
+    if (__relibc_bos(dest) != (size_t) -1)
+        return __wcsncpy_chk(dest, src, n, __relibc_bos(dest) / sizeof(wchar_t));
+    return __relibc_wcsncpy(dest, src, n);
+}
+
+__relibc_fortify wchar_t *wmemcpy(wchar_t *dest, const wchar_t *src, size_t n) {
+    if (__relibc_bos0(dest) != (size_t) -1)
+        return __wmemcpy_chk(dest, src, n, __relibc_bos0(dest) / sizeof(wchar_t));
+    return __relibc_wmemcpy(dest, src, n);
+}
+
+__relibc_fortify wchar_t *wmemmove(wchar_t *dest, const wchar_t *src, size_t n) {
+    if (__relibc_bos0(dest) != (size_t) -1)
+        return __wmemmove_chk(dest, src, n, __relibc_bos0(dest) / sizeof(wchar_t));
+    return __relibc_wmemmove(dest, src, n);
+}
+
+__relibc_fortify wchar_t *wmemset(wchar_t *dest, wchar_t wc, size_t n) {
+    if (__relibc_bos0(dest) != (size_t) -1)
+        return __wmemset_chk(dest, wc, n, __relibc_bos0(dest) / sizeof(wchar_t));
+    return __relibc_wmemset(dest, wc, n);
+}
+#endif
+
+#ifdef __cplusplus
+} // extern "C"
+#endif
 
 #endif /* _BITS_WCHAR_H */
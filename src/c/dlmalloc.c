@@ -1446,6 +1446,13 @@ DLMALLOC_EXPORT struct mallinfo mspace_mallinfo(mspace msp);
 */
 DLMALLOC_EXPORT size_t mspace_usable_size(const void* mem);
 
+/*
+  mspace_contains returns 1 if mem lies in one of the segments of the
+  given space, and 0 otherwise. Chunks mmapped on their own are only in
+  a segment while large chunks are tracked.
+*/
+DLMALLOC_EXPORT int mspace_contains(mspace msp, const void* mem);
+
 /*
   mspace_malloc_stats behaves as malloc_stats, but reports
   properties of the given space.
@@ -6013,6 +6020,21 @@ size_t mspace_usable_size(const void* mem) {
   return 0;
 }
 
+int mspace_contains(mspace msp, const void* mem) {
+  int result = 0;
+  mstate ms = (mstate)msp;
+  if (ok_magic(ms)) {
+    if (!PREACTION(ms)) {
+      result = segment_holding(ms, (char*)mem) != 0;
+      POSTACTION(ms);
+    }
+  }
+  else {
+    USAGE_ERROR_ACTION(ms,ms);
+  }
+  return result;
+}
+
 int mspace_mallopt(int param_number, int value) {
   return change_mparam(param_number, value);
 }
@@ -1,10 +1,16 @@
+#include <stddef.h>
 #include <stdint.h>
 
 void abort();
+long write(int fd, const void *buf, size_t count);
 
-uintptr_t __stack_chk_guard = 0xd048c37519fcadfe;
+// Replaced with random bytes from AT_RANDOM at startup, see init_auxv in
+// src/start.rs
+uintptr_t __stack_chk_guard = 0xd048c37519fcad00;
 
 __attribute__((noreturn))
 void __stack_chk_fail(void) {
+	static const char msg[] = "*** stack smashing detected ***: terminated\n";
+	write(2, msg, sizeof(msg) - 1);
 	abort();
 }
@@ -92,3 +92,60 @@ int sscanf(const char * input, const char * fmt, ...) {
     va_end(ap);
     return ret;
 }
+
+// Called by code built with _FORTIFY_SOURCE, see header/_fortify
+
+int __vasprintf_chk(char ** strp, int flag, const char * fmt, va_list ap);
+
+int __asprintf_chk(char ** strp, int flag, const char * fmt, ...) {
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = __vasprintf_chk(strp, flag, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int __vfprintf_chk(FILE * stream, int flag, const char * fmt, va_list ap);
+
+int __fprintf_chk(FILE * stream, int flag, const char * fmt, ...) {
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = __vfprintf_chk(stream, flag, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int __vprintf_chk(int flag, const char * fmt, va_list ap);
+
+int __printf_chk(int flag, const char * fmt, ...) {
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = __vprintf_chk(flag, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int __vsnprintf_chk(char * s, size_t maxlen, int flag, size_t slen, const char * fmt, va_list ap);
+
+int __snprintf_chk(char * s, size_t maxlen, int flag, size_t slen, const char * fmt, ...) {
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = __vsnprintf_chk(s, maxlen, flag, slen, fmt, ap);
+    va_end(ap);
+    return ret;
+}
+
+int __vsprintf_chk(char * s, int flag, size_t slen, const char * fmt, va_list ap);
+
+int __sprintf_chk(char * s, int flag, size_t slen, const char * fmt, ...) {
+    int ret;
+    va_list ap;
+    va_start(ap, fmt);
+    ret = __vsprintf_chk(s, flag, slen, fmt, ap);
+    va_end(ap);
+    return ret;
+}
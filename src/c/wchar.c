@@ -0,0 +1,7 @@
+#include <stddef.h>
+
+double wcstod(const wchar_t *nptr, wchar_t **endptr);
+
+long double wcstold(const wchar_t *nptr, wchar_t **endptr) {
+    return (long double)wcstod(nptr, endptr);
+}
@@ -0,0 +1,617 @@
+//! The checked functions called by code built with _FORTIFY_SOURCE, following
+//! the glibc ABI. Each takes the size of the destination as seen by the
+//! compiler, and aborts through __chk_fail instead of writing past it.
+//!
+//! The fortified wrappers in include/bits only call these when the size is
+//! known. Variadic ones are in src/c/stdio.c.
+
+use core::{ffi::VaList as va_list, intrinsics, mem, ptr};
+
+use crate::{
+    header::{
+        fcntl::{self, O_CREAT},
+        limits::PATH_MAX,
+        poll::{self, nfds_t, pollfd},
+        signal::{self, stack_t, SS_ONSTACK},
+        stdio::{self, FILE},
+        stdlib::{self, MB_CUR_MAX},
+        string,
+        sys_select::FD_SETSIZE,
+        sys_socket::{self, sockaddr, socklen_t},
+        unistd,
+        wchar::{self, mbstate_t},
+        wctype::WEOF,
+    },
+    platform::{types::*, Pal, Sys},
+};
+
+/// Aborts with `*** msg ***: terminated` on stderr, like glibc
+unsafe fn fortify_fail(msg: &[u8]) -> ! {
+    Sys::write(2, b"*** ");
+    Sys::write(2, msg);
+    Sys::write(2, b" ***: terminated\n");
+    intrinsics::abort()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __chk_fail() -> ! {
+    fortify_fail(b"buffer overflow detected")
+}
+
+unsafe fn check(ok: bool) {
+    if !ok {
+        __chk_fail();
+    }
+}
+
+// string.h
+
+#[no_mangle]
+pub unsafe extern "C" fn __memcpy_chk(
+    dest: *mut c_void,
+    src: *const c_void,
+    len: size_t,
+    destlen: size_t,
+) -> *mut c_void {
+    check(len <= destlen);
+    string::memcpy(dest, src, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __memmove_chk(
+    dest: *mut c_void,
+    src: *const c_void,
+    len: size_t,
+    destlen: size_t,
+) -> *mut c_void {
+    check(len <= destlen);
+    string::memmove(dest, src, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __memset_chk(
+    dest: *mut c_void,
+    c: c_int,
+    len: size_t,
+    destlen: size_t,
+) -> *mut c_void {
+    check(len <= destlen);
+    string::memset(dest, c, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __strcat_chk(
+    dest: *mut c_char,
+    src: *const c_char,
+    destlen: size_t,
+) -> *mut c_char {
+    __strncat_chk(dest, src, size_t::max_value(), destlen)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __strcpy_chk(
+    dest: *mut c_char,
+    src: *const c_char,
+    destlen: size_t,
+) -> *mut c_char {
+    check(string::strlen(src) < destlen);
+    string::strcpy(dest, src)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __strncat_chk(
+    dest: *mut c_char,
+    src: *const c_char,
+    n: size_t,
+    destlen: size_t,
+) -> *mut c_char {
+    // dest must already be terminated within its object
+    let len = string::strnlen(dest, destlen);
+    check(len < destlen);
+    check(string::strnlen(src, n) < destlen - len);
+    string::strncat(dest, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __strncpy_chk(
+    dest: *mut c_char,
+    src: *const c_char,
+    n: size_t,
+    destlen: size_t,
+) -> *mut c_char {
+    check(n <= destlen);
+    string::strncpy(dest, src, n)
+}
+
+// fcntl.h
+
+/// open without a mode, which aborts if `oflag` needs one
+#[no_mangle]
+pub unsafe extern "C" fn __open_2(path: *const c_char, oflag: c_int) -> c_int {
+    if oflag & O_CREAT != 0 {
+        fortify_fail(b"invalid open call: O_CREAT without mode");
+    }
+    fcntl::sys_open(path, oflag, 0)
+}
+
+// setjmp.h
+
+extern "C" {
+    fn longjmp(env: *mut c_ulong, val: c_int) -> !;
+}
+
+/// Aborts instead of jumping down the stack, into a frame that has already
+/// returned, unless on the alternate signal stack, which may lie anywhere
+#[cfg(target_arch = "x86_64")]
+#[no_mangle]
+pub unsafe extern "C" fn __longjmp_chk(env: *mut c_ulong, val: c_int) -> ! {
+    // The stack pointer saved by setjmp
+    let target = *env.add(6) as usize;
+    if target < &target as *const usize as usize {
+        let mut ss = stack_t {
+            ss_sp: ptr::null_mut(),
+            ss_flags: 0,
+            ss_size: 0,
+        };
+        signal::sigaltstack(ptr::null(), &mut ss);
+        if ss.ss_flags & SS_ONSTACK as c_int == 0 {
+            fortify_fail(b"longjmp causes uninitialized stack frame");
+        }
+    }
+    longjmp(env, val)
+}
+
+// stdio.h
+
+/// Reads at most `size` bytes of what fgets would read with `n`, and aborts
+/// if it would have read more
+#[no_mangle]
+pub unsafe extern "C" fn __fgets_chk(
+    s: *mut c_char,
+    size: size_t,
+    n: c_int,
+    stream: *mut FILE,
+) -> *mut c_char {
+    if n <= 0 || n as size_t <= size {
+        return stdio::fgets(s, n, stream);
+    }
+    check(size > 0);
+    let ret = stdio::fgets(s, size as c_int, stream);
+    if !ret.is_null() {
+        // A full buffer without the end of the line means fgets would go on
+        let len = string::strlen(s);
+        if len == size - 1 && (len == 0 || *s.add(len - 1) != b'\n' as c_char) {
+            check(stdio::fgetc(stream) == stdio::EOF);
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __fread_chk(
+    ptr: *mut c_void,
+    ptrlen: size_t,
+    size: size_t,
+    nitems: size_t,
+    stream: *mut FILE,
+) -> size_t {
+    match size.checked_mul(nitems) {
+        Some(len) => check(len <= ptrlen),
+        None => __chk_fail(),
+    }
+    stdio::fread(ptr, size, nitems, stream)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __gets_chk(s: *mut c_char, size: size_t) -> *mut c_char {
+    let mut len = 0;
+    loop {
+        let c = stdio::fgetc(stdio::stdin);
+        if c == stdio::EOF {
+            if len == 0 {
+                return ptr::null_mut();
+            }
+            break;
+        }
+        if c == c_int::from(b'\n') {
+            break;
+        }
+        check(len + 1 < size);
+        *s.add(len) = c as c_char;
+        len += 1;
+    }
+    *s.add(len) = 0;
+    s
+}
+
+// The flag asks for extra checks of the format string, which are not done
+
+#[no_mangle]
+pub unsafe extern "C" fn __vasprintf_chk(
+    strp: *mut *mut c_char,
+    flag: c_int,
+    format: *const c_char,
+    ap: va_list,
+) -> c_int {
+    stdio::vasprintf(strp, format, ap)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __vfprintf_chk(
+    stream: *mut FILE,
+    flag: c_int,
+    format: *const c_char,
+    ap: va_list,
+) -> c_int {
+    stdio::vfprintf(stream, format, ap)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __vprintf_chk(flag: c_int, format: *const c_char, ap: va_list) -> c_int {
+    stdio::vprintf(format, ap)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __vsnprintf_chk(
+    s: *mut c_char,
+    maxlen: size_t,
+    flag: c_int,
+    slen: size_t,
+    format: *const c_char,
+    ap: va_list,
+) -> c_int {
+    check(maxlen <= slen);
+    stdio::vsnprintf(s, maxlen, format, ap)
+}
+
+/// Formats with the size of `s` as a limit, and aborts if it was reached
+#[no_mangle]
+pub unsafe extern "C" fn __vsprintf_chk(
+    s: *mut c_char,
+    flag: c_int,
+    slen: size_t,
+    format: *const c_char,
+    ap: va_list,
+) -> c_int {
+    check(slen > 0);
+    let ret = stdio::vsnprintf(s, slen, format, ap);
+    check(ret < 0 || (ret as size_t) < slen);
+    ret
+}
+
+// stdlib.h
+
+#[no_mangle]
+pub unsafe extern "C" fn __mbstowcs_chk(
+    dest: *mut wchar_t,
+    src: *const c_char,
+    len: size_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    stdlib::mbstowcs(dest, src, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __realpath_chk(
+    path: *const c_char,
+    resolved: *mut c_char,
+    resolvedlen: size_t,
+) -> *mut c_char {
+    check(resolvedlen >= PATH_MAX);
+    stdlib::realpath(path, resolved)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcstombs_chk(
+    dest: *mut c_char,
+    src: *const wchar_t,
+    len: size_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    stdlib::wcstombs(dest, src, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wctomb_chk(s: *mut c_char, wc: wchar_t, buflen: size_t) -> c_int {
+    check(buflen >= MB_CUR_MAX as size_t);
+    stdlib::wctomb(s, wc)
+}
+
+// unistd.h
+
+#[no_mangle]
+pub unsafe extern "C" fn __getcwd_chk(
+    buf: *mut c_char,
+    size: size_t,
+    buflen: size_t,
+) -> *mut c_char {
+    check(size <= buflen);
+    unistd::getcwd(buf, size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __gethostname_chk(
+    name: *mut c_char,
+    len: size_t,
+    buflen: size_t,
+) -> c_int {
+    check(len <= buflen);
+    unistd::gethostname(name, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __getlogin_r_chk(
+    name: *mut c_char,
+    namesize: size_t,
+    buflen: size_t,
+) -> c_int {
+    check(namesize <= buflen);
+    unistd::getlogin_r(name, namesize)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __getwd_chk(path_name: *mut c_char, buflen: size_t) -> *mut c_char {
+    check(buflen >= PATH_MAX);
+    unistd::getwd(path_name)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __pread_chk(
+    fildes: c_int,
+    buf: *mut c_void,
+    nbyte: size_t,
+    offset: off_t,
+    buflen: size_t,
+) -> ssize_t {
+    check(nbyte <= buflen);
+    unistd::pread(fildes, buf, nbyte, offset)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __read_chk(
+    fildes: c_int,
+    buf: *mut c_void,
+    nbyte: size_t,
+    buflen: size_t,
+) -> ssize_t {
+    check(nbyte <= buflen);
+    unistd::read(fildes, buf, nbyte)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __readlink_chk(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsize: size_t,
+    buflen: size_t,
+) -> ssize_t {
+    check(bufsize <= buflen);
+    unistd::readlink(path, buf, bufsize)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __ttyname_r_chk(
+    fildes: c_int,
+    name: *mut c_char,
+    namesize: size_t,
+    buflen: size_t,
+) -> c_int {
+    check(namesize <= buflen);
+    unistd::ttyname_r(fildes, name, namesize)
+}
+
+// wchar.h, where lengths are in wide characters
+
+/// Reads at most `size` characters of what fgetws would read with `n`, and
+/// aborts if it would have read more
+#[no_mangle]
+pub unsafe extern "C" fn __fgetws_chk(
+    ws: *mut wchar_t,
+    size: size_t,
+    n: c_int,
+    stream: *mut FILE,
+) -> *mut wchar_t {
+    if n <= 0 || n as size_t <= size {
+        return wchar::fgetws(ws, n, stream);
+    }
+    check(size > 0);
+    let ret = wchar::fgetws(ws, size as c_int, stream);
+    if !ret.is_null() {
+        let len = wchar::wcslen(ws);
+        if len == size - 1 && (len == 0 || *ws.add(len - 1) != b'\n' as wchar_t) {
+            check(wchar::fgetwc(stream) == WEOF);
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __mbsnrtowcs_chk(
+    dest: *mut wchar_t,
+    src: *mut *const c_char,
+    nmc: size_t,
+    len: size_t,
+    ps: *mut mbstate_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    wchar::mbsnrtowcs(dest, src, nmc, len, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __mbsrtowcs_chk(
+    dest: *mut wchar_t,
+    src: *mut *const c_char,
+    len: size_t,
+    ps: *mut mbstate_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    wchar::mbsrtowcs(dest, src, len, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcrtomb_chk(
+    s: *mut c_char,
+    wc: wchar_t,
+    ps: *mut mbstate_t,
+    buflen: size_t,
+) -> size_t {
+    check(buflen >= MB_CUR_MAX as size_t);
+    wchar::wcrtomb(s, wc, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcscat_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    __wcsncat_chk(dest, src, size_t::max_value(), destlen)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcscpy_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    check(wchar::wcslen(src) < destlen);
+    wchar::wcscpy(dest, src)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcsncat_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    n: size_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    let mut len = 0;
+    while len < destlen && *dest.add(len) != 0 {
+        len += 1;
+    }
+    check(len < destlen);
+    let mut src_len = 0;
+    while src_len < n && *src.add(src_len) != 0 {
+        src_len += 1;
+    }
+    check(src_len < destlen - len);
+    wchar::wcsncat(dest, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcsncpy_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    n: size_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    check(n <= destlen);
+    wchar::wcsncpy(dest, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcsnrtombs_chk(
+    dest: *mut c_char,
+    src: *mut *const wchar_t,
+    nwc: size_t,
+    len: size_t,
+    ps: *mut mbstate_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    wchar::wcsnrtombs(dest, src, nwc, len, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wcsrtombs_chk(
+    dest: *mut c_char,
+    src: *mut *const wchar_t,
+    len: size_t,
+    ps: *mut mbstate_t,
+    destlen: size_t,
+) -> size_t {
+    check(len <= destlen);
+    wchar::wcsrtombs(dest, src, len, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wmemcpy_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    n: size_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    check(n <= destlen);
+    wchar::wmemcpy(dest, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wmemmove_chk(
+    dest: *mut wchar_t,
+    src: *const wchar_t,
+    n: size_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    check(n <= destlen);
+    wchar::wmemmove(dest, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __wmemset_chk(
+    dest: *mut wchar_t,
+    wc: wchar_t,
+    n: size_t,
+    destlen: size_t,
+) -> *mut wchar_t {
+    check(n <= destlen);
+    wchar::wmemset(dest, wc, n)
+}
+
+// poll.h, sys/select.h and sys/socket.h
+
+#[no_mangle]
+pub unsafe extern "C" fn __fdelt_chk(fd: c_long) -> c_long {
+    check(fd >= 0 && fd < FD_SETSIZE as c_long);
+    fd / (8 * mem::size_of::<c_ulong>() as c_long)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __poll_chk(
+    fds: *mut pollfd,
+    nfds: nfds_t,
+    timeout: c_int,
+    fdslen: size_t,
+) -> c_int {
+    check(nfds as size_t <= fdslen / mem::size_of::<pollfd>());
+    poll::poll(fds, nfds, timeout)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __recv_chk(
+    socket: c_int,
+    buffer: *mut c_void,
+    length: size_t,
+    buflen: size_t,
+    flags: c_int,
+) -> ssize_t {
+    check(length <= buflen);
+    sys_socket::recv(socket, buffer, length, flags)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __recvfrom_chk(
+    socket: c_int,
+    buffer: *mut c_void,
+    length: size_t,
+    buflen: size_t,
+    flags: c_int,
+    address: *mut sockaddr,
+    address_len: *mut socklen_t,
+) -> ssize_t {
+    check(length <= buflen);
+    sys_socket::recvfrom(socket, buffer, length, flags, address, address_len)
+}
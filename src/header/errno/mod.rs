@@ -113,6 +113,7 @@ pub const ENOPROTOOPT: c_int = 92; /* Protocol not available */
 pub const EPROTONOSUPPORT: c_int = 93; /* Protocol not supported */
 pub const ESOCKTNOSUPPORT: c_int = 94; /* Socket type not supported */
 pub const EOPNOTSUPP: c_int = 95; /* Operation not supported on transport endpoint */
+pub const ENOTSUP: c_int = EOPNOTSUPP; /* Operation not supported */
 pub const EPFNOSUPPORT: c_int = 96; /* Protocol family not supported */
 pub const EAFNOSUPPORT: c_int = 97; /* Address family not supported by protocol */
 pub const EADDRINUSE: c_int = 98; /* Address already in use */
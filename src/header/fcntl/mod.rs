@@ -2,6 +2,7 @@
 
 use crate::{
     c_str::CStr,
+    header::pthread::cancellation_point,
     platform::{types::*, Pal, Sys},
 };
 
@@ -35,11 +36,16 @@ pub unsafe extern "C" fn creat(path: *const c_char, mode: mode_t) -> c_int {
 
 #[no_mangle]
 pub extern "C" fn sys_fcntl(fildes: c_int, cmd: c_int, arg: c_int) -> c_int {
-    Sys::fcntl(fildes, cmd, arg)
+    // Waiting for a lock is a cancellation point
+    if cmd == F_SETLKW {
+        cancellation_point(|| Sys::fcntl(fildes, cmd, arg))
+    } else {
+        Sys::fcntl(fildes, cmd, arg)
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn sys_open(path: *const c_char, oflag: c_int, mode: mode_t) -> c_int {
     let path = CStr::from_ptr(path);
-    Sys::open(path, oflag, mode)
+    cancellation_point(|| Sys::open(path, oflag, mode))
 }
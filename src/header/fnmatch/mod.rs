@@ -1,116 +1,424 @@
 //! fnmatch implementation
 
-use alloc::{borrow::Cow, vec::Vec};
-use core::slice;
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{cell::RefCell, char, slice, str};
 
-use crate::platform::types::*;
-use posix_regex::{
-    compile::{Collation, Range, Token},
-    PosixRegex,
+use crate::{
+    header::{
+        string::strlen,
+        wctype::{iswctype, towlower, towupper, wctype},
+    },
+    platform::types::*,
 };
 
-const ONCE: Range = Range(1, Some(1));
-
 pub const FNM_NOMATCH: c_int = 1;
 
 pub const FNM_NOESCAPE: c_int = 1;
 pub const FNM_PATHNAME: c_int = 2;
 pub const FNM_PERIOD: c_int = 4;
 pub const FNM_CASEFOLD: c_int = 8;
-// TODO: FNM_EXTMATCH
+pub const FNM_LEADING_DIR: c_int = 16;
+pub const FNM_EXTMATCH: c_int = 32;
 
-unsafe fn tokenize(mut pattern: *const u8, flags: c_int) -> Vec<(Token, Range)> {
-    fn any(leading: bool, flags: c_int) -> Token {
-        let mut list = Vec::new();
-        if flags & FNM_PATHNAME == FNM_PATHNAME {
-            list.push(Collation::Char(b'/'))
-        }
-        if leading && flags & FNM_PERIOD == FNM_PERIOD {
-            list.push(Collation::Char(b'.'))
+enum SetItem {
+    Char(u32),
+    Range(u32, u32),
+    Class(wctype_t),
+}
+
+enum Token {
+    Char(u32),
+    Any,
+    Star,
+    Set {
+        invert: bool,
+        items: Vec<SetItem>,
+    },
+    /// An extglob group like `@(a|b)`, where `kind` is the leading character
+    Group {
+        kind: u32,
+        alternatives: Vec<Vec<Token>>,
+    },
+}
+
+/// Decodes UTF-8, keeping every byte of an invalid sequence as a lone
+/// surrogate so that it can still be matched literally
+fn decode(mut bytes: &[u8]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(bytes.len());
+    loop {
+        match str::from_utf8(bytes) {
+            Ok(valid) => {
+                out.extend(valid.chars().map(|c| c as u32));
+                return out;
+            }
+            Err(err) => {
+                let (valid, rest) = bytes.split_at(err.valid_up_to());
+                let valid = unsafe { str::from_utf8_unchecked(valid) };
+                out.extend(valid.chars().map(|c| c as u32));
+                out.push(0xDC00 | rest[0] as u32);
+                bytes = &rest[1..];
+            }
         }
-        Token::OneOf { invert: true, list }
     }
-    fn can_push(leading: bool, flags: c_int, c: u8) -> bool {
-        (c != b'/' || flags & FNM_PATHNAME != FNM_PATHNAME)
-            && (c != b'.' || !leading || flags & FNM_PERIOD != FNM_PERIOD)
+}
+
+struct Tokenizer<'a> {
+    pattern: &'a [u32],
+    pos: usize,
+    flags: c_int,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn peek(&self, offset: usize) -> Option<u32> {
+        self.pattern.get(self.pos + offset).copied()
     }
-    fn is_leading(flags: c_int, c: u8) -> bool {
-        c == b'/' && flags & FNM_PATHNAME == FNM_PATHNAME
+
+    /// Parses a single character of a bracket expression, which may be
+    /// escaped or a collating symbol like `[.-.]`
+    fn bracket_char(&mut self) -> Option<u32> {
+        let c = self.peek(0)?;
+        if c == '\\' as u32 && self.flags & FNM_NOESCAPE != FNM_NOESCAPE {
+            self.pos += 1;
+        } else if c == '[' as u32 && self.peek(1) == Some('.' as u32) {
+            // Only single characters are valid collating elements
+            if self.peek(3) != Some('.' as u32) || self.peek(4) != Some(']' as u32) {
+                return None;
+            }
+            self.pos += 5;
+            return Some(self.pattern[self.pos - 3]);
+        }
+        let c = self.peek(0)?;
+        self.pos += 1;
+        Some(c)
     }
 
-    let mut tokens = Vec::new();
-    let mut leading = true;
+    /// Parses a bracket expression after the `[`, or returns None if it is
+    /// invalid so that the `[` is taken literally
+    fn bracket(&mut self) -> Option<Token> {
+        let invert = match self.peek(0) {
+            Some(c) if c == '!' as u32 || c == '^' as u32 => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        };
 
-    while *pattern != 0 {
-        let was_leading = leading;
-        leading = false;
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek(0)?;
+            if c == ']' as u32 && !first {
+                self.pos += 1;
+                return Some(Token::Set { invert, items });
+            }
+            first = false;
 
-        let c = *pattern;
-        pattern = pattern.offset(1);
+            if c == '[' as u32
+                && (self.peek(1) == Some(':' as u32) || self.peek(1) == Some('=' as u32))
+            {
+                let delimiter = self.peek(1).unwrap();
+                let start = self.pos + 2;
+                let len = self.pattern[start..]
+                    .windows(2)
+                    .position(|w| w[0] == delimiter && w[1] == ']' as u32)?;
+                let name = &self.pattern[start..start + len];
+                self.pos = start + len + 2;
 
-        tokens.push(match c {
-            b'\\' if flags & FNM_NOESCAPE == FNM_NOESCAPE => {
-                let c = *pattern;
-                if c == 0 {
-                    // Trailing backslash. Maybe error here?
-                    break;
-                }
-                pattern = pattern.offset(1);
-                leading = is_leading(flags, c);
-                (Token::Char(c), ONCE)
-            }
-            b'?' => (any(was_leading, flags), ONCE),
-            b'*' => (any(was_leading, flags), Range(0, None)),
-            b'[' => {
-                let mut list: Vec<Collation> = Vec::new();
-                let invert = if *pattern == b'!' {
-                    pattern = pattern.offset(1);
-                    true
+                if delimiter == ':' as u32 {
+                    let mut bytes = Vec::with_capacity(name.len() + 1);
+                    for &c in name {
+                        if c >= 0x80 {
+                            return None;
+                        }
+                        bytes.push(c as u8);
+                    }
+                    bytes.push(0);
+                    let class = unsafe { wctype(bytes.as_ptr() as *const c_char) };
+                    if class == 0 {
+                        return None;
+                    }
+                    items.push(SetItem::Class(class));
                 } else {
-                    false
-                };
-
-                loop {
-                    let mut c = *pattern;
-                    if c == 0 {
-                        break;
+                    // Every character is only equivalent to itself
+                    if name.len() != 1 {
+                        return None;
                     }
-                    pattern = pattern.offset(1);
-                    match c {
-                        b']' => break,
-                        b'\\' => {
-                            c = *pattern;
-                            pattern = pattern.offset(1);
-                            if c == 0 {
-                                // Trailing backslash. Maybe error?
-                                break;
-                            }
-                        }
-                        _ => (),
+                    items.push(SetItem::Char(name[0]));
+                }
+                continue;
+            }
+
+            let start = self.bracket_char()?;
+            if self.peek(0) == Some('-' as u32) && self.peek(1).map_or(false, |c| c != ']' as u32) {
+                self.pos += 1;
+                let end = self.bracket_char()?;
+                items.push(SetItem::Range(start, end));
+            } else {
+                items.push(SetItem::Char(start));
+            }
+        }
+    }
+
+    /// Parses the alternatives of an extglob group after the `(`, or returns
+    /// None if it is never closed
+    fn group(&mut self, kind: u32) -> Option<Token> {
+        let mut alternatives = Vec::new();
+        loop {
+            alternatives.push(self.sequence(true));
+            match self.peek(0) {
+                Some(c) if c == '|' as u32 => self.pos += 1,
+                Some(c) if c == ')' as u32 => {
+                    self.pos += 1;
+                    return Some(Token::Group { kind, alternatives });
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Parses tokens until the end of the pattern, or until the `|` or `)`
+    /// that ends an alternative in a group
+    fn sequence(&mut self, nested: bool) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek(0) {
+            if nested && (c == '|' as u32 || c == ')' as u32) {
+                break;
+            }
+            self.pos += 1;
+
+            if self.flags & FNM_EXTMATCH == FNM_EXTMATCH
+                && self.peek(0) == Some('(' as u32)
+                && ['@', '!', '+', '*', '?']
+                    .iter()
+                    .any(|&kind| c == kind as u32)
+            {
+                let start = self.pos;
+                self.pos += 1;
+                if let Some(group) = self.group(c) {
+                    tokens.push(group);
+                    continue;
+                }
+                self.pos = start;
+            }
+
+            tokens.push(match char::from_u32(c) {
+                Some('\\') if self.flags & FNM_NOESCAPE != FNM_NOESCAPE => match self.peek(0) {
+                    Some(c) => {
+                        self.pos += 1;
+                        Token::Char(c)
                     }
-                    if *pattern == b'-' && *pattern.offset(1) != 0 {
-                        let end = *pattern.offset(1);
-                        pattern = pattern.offset(2);
-                        for c in c..=end {
-                            if can_push(was_leading, flags, c) {
-                                list.push(Collation::Char(c));
-                            }
+                    // A trailing backslash matches itself
+                    None => Token::Char(c),
+                },
+                Some('?') => Token::Any,
+                Some('*') => Token::Star,
+                Some('[') => {
+                    let start = self.pos;
+                    match self.bracket() {
+                        Some(set) => set,
+                        None => {
+                            self.pos = start;
+                            Token::Char(c)
                         }
-                    } else if can_push(was_leading, flags, c) {
-                        list.push(Collation::Char(c));
                     }
                 }
-                // Otherwise, there was no closing ]. Maybe error?
+                _ => Token::Char(c),
+            });
+        }
+        tokens
+    }
+}
+
+/// Identifies a match by where its tokens are and how many, or for a
+/// repetition where the alternatives are and how many, along with the bounds
+/// of the input and whether FNM_LEADING_DIR applies. A group is always
+/// followed by the same tokens, so its alternatives stand for those too.
+type Key = (usize, usize, usize, usize, bool);
+
+struct Matcher<'a> {
+    input: &'a [u32],
+    flags: c_int,
+    /// Results of matches that can branch, so that nested wildcards and
+    /// groups don't try the same split of the input over and over
+    memo: RefCell<BTreeMap<Key, bool>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn has(&self, flag: c_int) -> bool {
+        self.flags & flag == flag
+    }
+
+    /// Whether the character at `pos` is a period that must be matched
+    /// explicitly
+    fn leading_period(&self, pos: usize) -> bool {
+        self.has(FNM_PERIOD)
+            && self.input.get(pos) == Some(&('.' as u32))
+            && (pos == 0 || (self.has(FNM_PATHNAME) && self.input[pos - 1] == '/' as u32))
+    }
+
+    /// Whether a wildcard may match the character at `pos`
+    fn wildcard(&self, pos: usize) -> bool {
+        !(self.has(FNM_PATHNAME) && self.input[pos] == '/' as u32) && !self.leading_period(pos)
+    }
+
+    fn fold(&self, c: u32) -> u32 {
+        if self.has(FNM_CASEFOLD) {
+            towlower(c)
+        } else {
+            c
+        }
+    }
+
+    fn in_set(&self, items: &[SetItem], c: u32) -> bool {
+        let cases = if self.has(FNM_CASEFOLD) {
+            [c, towlower(c), towupper(c)]
+        } else {
+            [c, c, c]
+        };
+        items.iter().any(|item| {
+            cases.iter().any(|&c| match *item {
+                SetItem::Char(expected) => c == expected,
+                SetItem::Range(start, end) => start <= c && c <= end,
+                SetItem::Class(class) => iswctype(c, class) != 0,
+            })
+        })
+    }
+
+    /// Looks `key` up in the memo, and otherwise works it out with `f`
+    fn memoized(&self, key: Key, f: impl FnOnce() -> bool) -> bool {
+        if let Some(&result) = self.memo.borrow().get(&key) {
+            return result;
+        }
+        let result = f();
+        self.memo.borrow_mut().insert(key, result);
+        result
+    }
+
+    /// Whether any alternative matches all of `input[pos..end]`
+    fn alternatives(&self, alternatives: &[Vec<Token>], pos: usize, end: usize) -> bool {
+        alternatives
+            .iter()
+            .any(|tokens| self.matches(tokens, pos, end, false))
+    }
 
-                (Token::OneOf { invert, list }, ONCE)
+    /// Matches one or more repetitions of a group from `pos`, followed by the
+    /// remaining tokens
+    fn repeat(
+        &self,
+        alternatives: &[Vec<Token>],
+        rest: &[Token],
+        pos: usize,
+        end: usize,
+        leading_dir: bool,
+    ) -> bool {
+        let key = (
+            alternatives.as_ptr() as usize,
+            alternatives.len(),
+            pos,
+            end,
+            leading_dir,
+        );
+        self.memoized(key, || {
+            (pos + 1..=end).any(|mid| {
+                self.alternatives(alternatives, pos, mid)
+                    && (self.matches(rest, mid, end, leading_dir)
+                        || self.repeat(alternatives, rest, mid, end, leading_dir))
+            })
+        })
+    }
+
+    /// Matches `tokens` against `input[pos..end]`
+    fn matches(&self, tokens: &[Token], pos: usize, end: usize, leading_dir: bool) -> bool {
+        let (token, rest) = match tokens.split_first() {
+            Some(split) => split,
+            None => {
+                return pos == end || (leading_dir && self.input[pos] == '/' as u32);
             }
-            c => {
-                leading = is_leading(flags, c);
-                (Token::Char(c), ONCE)
+        };
+
+        match token {
+            Token::Star | Token::Group { .. } => {
+                let key = (
+                    tokens.as_ptr() as usize,
+                    tokens.len(),
+                    pos,
+                    end,
+                    leading_dir,
+                );
+                self.memoized(key, || self.branch(token, rest, pos, end, leading_dir))
             }
-        })
+            Token::Char(c) => {
+                pos < end
+                    && self.fold(self.input[pos]) == self.fold(*c)
+                    && self.matches(rest, pos + 1, end, leading_dir)
+            }
+            Token::Any => {
+                pos < end && self.wildcard(pos) && self.matches(rest, pos + 1, end, leading_dir)
+            }
+            Token::Set { invert, items } => {
+                pos < end
+                    && self.wildcard(pos)
+                    && self.in_set(items, self.input[pos]) != *invert
+                    && self.matches(rest, pos + 1, end, leading_dir)
+            }
+        }
+    }
+
+    /// Matches a wildcard or group followed by `rest`, which can be done in
+    /// more than one way
+    fn branch(
+        &self,
+        token: &Token,
+        rest: &[Token],
+        pos: usize,
+        end: usize,
+        leading_dir: bool,
+    ) -> bool {
+        match token {
+            Token::Star => {
+                let mut mid = pos;
+                loop {
+                    if self.matches(rest, mid, end, leading_dir) {
+                        return true;
+                    }
+                    if mid == end || !self.wildcard(mid) {
+                        return false;
+                    }
+                    mid += 1;
+                }
+            }
+            Token::Group { kind, alternatives } => match char::from_u32(*kind) {
+                Some('@') => (pos..=end).any(|mid| {
+                    self.alternatives(alternatives, pos, mid)
+                        && self.matches(rest, mid, end, leading_dir)
+                }),
+                Some('?') => {
+                    self.matches(rest, pos, end, leading_dir)
+                        || (pos..=end).any(|mid| {
+                            self.alternatives(alternatives, pos, mid)
+                                && self.matches(rest, mid, end, leading_dir)
+                        })
+                }
+                Some('*') => {
+                    self.matches(rest, pos, end, leading_dir)
+                        || self.repeat(alternatives, rest, pos, end, leading_dir)
+                }
+                Some('+') => {
+                    self.alternatives(alternatives, pos, pos)
+                        && self.matches(rest, pos, end, leading_dir)
+                        || self.repeat(alternatives, rest, pos, end, leading_dir)
+                }
+                _ => (pos..=end).any(|mid| {
+                    // Like a wildcard, a negated group can't match a slash or
+                    // a leading period
+                    (pos..mid).all(|i| self.wildcard(i))
+                        && !self.alternatives(alternatives, pos, mid)
+                        && self.matches(rest, mid, end, leading_dir)
+                }),
+            },
+            _ => unreachable!(),
+        }
     }
-    tokens
 }
 
 #[no_mangle]
@@ -120,20 +428,27 @@ pub unsafe extern "C" fn fnmatch(
     input: *const c_char,
     flags: c_int,
 ) -> c_int {
-    let mut len = 0;
-    while *input.offset(len) != 0 {
-        len += 1;
-    }
-    let input = slice::from_raw_parts(input as *const u8, len as usize);
+    let pattern = decode(slice::from_raw_parts(pattern as *const u8, strlen(pattern)));
+    let input = decode(slice::from_raw_parts(input as *const u8, strlen(input)));
 
-    let mut tokens = tokenize(pattern as *const u8, flags);
-    tokens.push((Token::End, ONCE));
+    let tokens = Tokenizer {
+        pattern: &pattern,
+        pos: 0,
+        flags,
+    }
+    .sequence(false);
 
-    if PosixRegex::new(Cow::Owned(vec![tokens]))
-        .case_insensitive(flags & FNM_CASEFOLD == FNM_CASEFOLD)
-        .matches_exact(input)
-        .is_some()
-    {
+    let matcher = Matcher {
+        input: &input,
+        flags,
+        memo: RefCell::new(BTreeMap::new()),
+    };
+    if matcher.matches(
+        &tokens,
+        0,
+        input.len(),
+        flags & FNM_LEADING_DIR == FNM_LEADING_DIR,
+    ) {
         0
     } else {
         FNM_NOMATCH
@@ -0,0 +1,466 @@
+//! ftw implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/ftw.h.html
+
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{mem, slice};
+
+use crate::{
+    header::{
+        dirent::{closedir, dirfd, opendir, readdir, DIR},
+        errno::{EACCES, EINVAL, ENOENT},
+        fcntl::{sys_open, O_CLOEXEC, O_DIRECTORY, O_RDONLY},
+        string::strlen,
+        sys_stat::{lstat, stat, S_IFDIR, S_IFLNK, S_IFMT},
+        unistd::{chdir, close, fchdir},
+    },
+    platform::{self, types::*},
+};
+
+pub const FTW_F: c_int = 0;
+pub const FTW_D: c_int = 1;
+pub const FTW_DNR: c_int = 2;
+pub const FTW_NS: c_int = 3;
+pub const FTW_SL: c_int = 4;
+pub const FTW_DP: c_int = 5;
+pub const FTW_SLN: c_int = 6;
+
+pub const FTW_PHYS: c_int = 1;
+pub const FTW_MOUNT: c_int = 2;
+pub const FTW_CHDIR: c_int = 4;
+pub const FTW_DEPTH: c_int = 8;
+pub const FTW_ACTIONRETVAL: c_int = 16;
+
+// Return values of the callback with FTW_ACTIONRETVAL
+pub const FTW_CONTINUE: c_int = 0;
+pub const FTW_STOP: c_int = 1;
+pub const FTW_SKIP_SUBTREE: c_int = 2;
+pub const FTW_SKIP_SIBLINGS: c_int = 3;
+
+#[repr(C)]
+pub struct FTW {
+    pub base: c_int,
+    pub level: c_int,
+}
+
+type FtwFn = unsafe extern "C" fn(fpath: *const c_char, sb: *const stat, typeflag: c_int) -> c_int;
+type NftwFn = unsafe extern "C" fn(
+    fpath: *const c_char,
+    sb: *const stat,
+    typeflag: c_int,
+    ftwbuf: *mut FTW,
+) -> c_int;
+
+enum Callback {
+    Ftw(FtwFn),
+    Nftw(NftwFn),
+}
+
+enum Entries {
+    Open(*mut DIR),
+    // The rest of a directory, read ahead to stay within nopenfd
+    Buffered(Vec<Vec<u8>>),
+    Done,
+}
+
+/// A directory being walked. The stack of these replaces recursion, so deep
+/// trees only cost heap memory.
+struct Dir {
+    entries: Entries,
+    st: stat,
+    // Length of the directory's path, without the nul terminator
+    len: usize,
+    base: c_int,
+    level: c_int,
+}
+
+struct Walk {
+    callback: Callback,
+    flags: c_int,
+    nopenfd: usize,
+    open: usize,
+    dev: dev_t,
+    // Directories already walked, so that symbolic links can't cause loops
+    visited: BTreeSet<(dev_t, ino_t)>,
+    // The path of the current entry, always nul terminated
+    path: Vec<u8>,
+    stack: Vec<Dir>,
+    // The working directory to return to with FTW_CHDIR
+    cwd: c_int,
+}
+
+/// Returns the next entry of a directory stream other than . and ..
+unsafe fn read_name<'a>(dir: *mut DIR) -> Option<&'a [u8]> {
+    loop {
+        let entry = readdir(dir);
+        if entry.is_null() {
+            return None;
+        }
+        let name = (*entry).d_name.as_ptr();
+        let name = slice::from_raw_parts(name as *const u8, strlen(name));
+        if name != b"." && name != b".." {
+            return Some(name);
+        }
+    }
+}
+
+/// Replaces everything after the first `len` bytes of `path` with `/name`,
+/// returning the offset of the name
+fn join(path: &mut Vec<u8>, len: usize, name: &[u8]) -> usize {
+    path.truncate(len);
+    if path.last() != Some(&b'/') {
+        path.push(b'/');
+    }
+    let base = path.len();
+    path.extend_from_slice(name);
+    path.push(0);
+    base
+}
+
+impl Walk {
+    fn flag(&self, flag: c_int) -> bool {
+        self.flags & flag == flag
+    }
+
+    /// The current path, relative to the working directory
+    fn relative(&self, base: c_int, level: c_int) -> *const c_char {
+        if self.flag(FTW_CHDIR) && level > 0 {
+            self.path[base as usize..].as_ptr() as *const c_char
+        } else {
+            self.path.as_ptr() as *const c_char
+        }
+    }
+
+    unsafe fn call(&self, st: &stat, typeflag: c_int, base: c_int, level: c_int) -> c_int {
+        let path = self.path.as_ptr() as *const c_char;
+        match self.callback {
+            Callback::Ftw(func) => {
+                // ftw has no type for dangling symbolic links
+                let typeflag = if typeflag == FTW_SLN {
+                    FTW_NS
+                } else {
+                    typeflag
+                };
+                func(path, st, typeflag)
+            }
+            Callback::Nftw(func) => {
+                let mut ftw = FTW { base, level };
+                func(path, st, typeflag, &mut ftw)
+            }
+        }
+    }
+
+    /// Turns a callback result into whether and how to go on
+    fn settle(&mut self, ret: c_int) -> c_int {
+        if !self.flag(FTW_ACTIONRETVAL) {
+            return ret;
+        }
+        match ret {
+            FTW_SKIP_SUBTREE => 0,
+            FTW_SKIP_SIBLINGS => {
+                if let Some(dir) = self.stack.last_mut() {
+                    if let Entries::Open(handle) = dir.entries {
+                        unsafe { closedir(handle) };
+                        self.open -= 1;
+                    }
+                    dir.entries = Entries::Done;
+                }
+                0
+            }
+            ret => ret,
+        }
+    }
+
+    /// Reports the entry at the current path, entering it if it is a
+    /// directory
+    unsafe fn entry(&mut self, base: c_int, level: c_int) -> c_int {
+        let name = self.relative(base, level);
+        let follow = !self.flag(FTW_PHYS);
+
+        let mut st: stat = mem::zeroed();
+        let ret = if follow {
+            stat(name, &mut st)
+        } else {
+            lstat(name, &mut st)
+        };
+        let typeflag = if ret < 0 {
+            if platform::errno != EACCES && platform::errno != ENOENT {
+                return -1;
+            }
+            if follow && lstat(name, &mut st) == 0 && st.st_mode as c_int & S_IFMT == S_IFLNK {
+                FTW_SLN
+            } else if level == 0 {
+                return -1;
+            } else {
+                FTW_NS
+            }
+        } else {
+            match st.st_mode as c_int & S_IFMT {
+                S_IFDIR => FTW_D,
+                S_IFLNK => FTW_SL,
+                _ => FTW_F,
+            }
+        };
+
+        if level == 0 {
+            self.dev = st.st_dev;
+        } else if self.flag(FTW_MOUNT) && typeflag != FTW_NS && st.st_dev != self.dev {
+            return 0;
+        }
+
+        if typeflag != FTW_D {
+            return self.call(&st, typeflag, base, level);
+        }
+        if follow && !self.visited.insert((st.st_dev, st.st_ino)) {
+            return 0;
+        }
+        self.enter(st, base, level)
+    }
+
+    /// Reads the rest of the outermost open directory into memory, freeing
+    /// its descriptor
+    unsafe fn release(&mut self) {
+        let dir = match self
+            .stack
+            .iter_mut()
+            .find(|dir| matches!(dir.entries, Entries::Open(_)))
+        {
+            Some(dir) => dir,
+            None => return,
+        };
+        if let Entries::Open(handle) = dir.entries {
+            let mut names = Vec::new();
+            while let Some(name) = read_name(handle) {
+                names.push(name.to_vec());
+            }
+            // Entries are taken from the end
+            names.reverse();
+            closedir(handle);
+            dir.entries = Entries::Buffered(names);
+            self.open -= 1;
+        }
+    }
+
+    unsafe fn enter(&mut self, st: stat, base: c_int, level: c_int) -> c_int {
+        if self.open >= self.nopenfd {
+            self.release();
+        }
+        let handle = opendir(self.relative(base, level));
+        if handle.is_null() {
+            return if platform::errno == EACCES {
+                self.call(&st, FTW_DNR, base, level)
+            } else {
+                -1
+            };
+        }
+        self.open += 1;
+
+        if !self.flag(FTW_DEPTH) {
+            let ret = self.call(&st, FTW_D, base, level);
+            if ret != 0 {
+                let errno = platform::errno;
+                closedir(handle);
+                platform::errno = errno;
+                self.open -= 1;
+                return ret;
+            }
+        }
+        if self.flag(FTW_CHDIR) && fchdir(dirfd(handle)) < 0 {
+            let errno = platform::errno;
+            closedir(handle);
+            platform::errno = errno;
+            self.open -= 1;
+            return -1;
+        }
+
+        self.stack.push(Dir {
+            entries: Entries::Open(handle),
+            st,
+            len: self.path.len() - 1,
+            base,
+            level,
+        });
+        0
+    }
+
+    /// Changes back to the directory on top of the stack, or the original
+    /// working directory
+    unsafe fn chdir_parent(&self) -> c_int {
+        match self.stack.last() {
+            Some(Dir {
+                entries: Entries::Open(handle),
+                ..
+            }) => fchdir(dirfd(*handle)),
+            Some(dir) => {
+                // The full path may be too long, so try .. first. That leads
+                // elsewhere after following a symbolic link though.
+                let mut st: stat = mem::zeroed();
+                if chdir(b"..\0".as_ptr() as *const c_char) == 0
+                    && stat(b".\0".as_ptr() as *const c_char, &mut st) == 0
+                    && st.st_dev == dir.st.st_dev
+                    && st.st_ino == dir.st.st_ino
+                {
+                    return 0;
+                }
+                if fchdir(self.cwd) < 0 {
+                    return -1;
+                }
+                let mut path = self.path[..dir.len].to_vec();
+                path.push(0);
+                chdir(path.as_ptr() as *const c_char)
+            }
+            None => fchdir(self.cwd),
+        }
+    }
+
+    /// Finishes the directory on top of the stack
+    unsafe fn leave(&mut self) -> c_int {
+        let dir = match self.stack.pop() {
+            Some(dir) => dir,
+            None => return 0,
+        };
+        if let Entries::Open(handle) = dir.entries {
+            closedir(handle);
+            self.open -= 1;
+        }
+        self.path.truncate(dir.len);
+        self.path.push(0);
+
+        if self.flag(FTW_CHDIR) && self.chdir_parent() < 0 {
+            return -1;
+        }
+        if self.flag(FTW_DEPTH) {
+            self.call(&dir.st, FTW_DP, dir.base, dir.level)
+        } else {
+            0
+        }
+    }
+
+    unsafe fn run(&mut self, base: c_int) -> c_int {
+        let ret = self.entry(base, 0);
+        let mut ret = self.settle(ret);
+
+        while ret == 0 {
+            let dir = match self.stack.last_mut() {
+                Some(dir) => dir,
+                None => break,
+            };
+            let (len, level) = (dir.len, dir.level + 1);
+            let base = match dir.entries {
+                Entries::Open(handle) => match read_name(handle) {
+                    Some(name) => Some(join(&mut self.path, len, name)),
+                    None => None,
+                },
+                Entries::Buffered(ref mut names) => match names.pop() {
+                    Some(name) => Some(join(&mut self.path, len, &name)),
+                    None => None,
+                },
+                Entries::Done => None,
+            };
+            ret = match base {
+                Some(base) => self.entry(base as c_int, level),
+                None => self.leave(),
+            };
+            ret = self.settle(ret);
+        }
+        ret
+    }
+
+    /// Closes whatever is still open after the walk stopped early
+    unsafe fn finish(&mut self) {
+        let errno = platform::errno;
+        for dir in self.stack.drain(..) {
+            if let Entries::Open(handle) = dir.entries {
+                closedir(handle);
+            }
+        }
+        if self.cwd >= 0 {
+            if self.flag(FTW_CHDIR) {
+                fchdir(self.cwd);
+            }
+            close(self.cwd);
+        }
+        platform::errno = errno;
+    }
+}
+
+unsafe fn walk(path: *const c_char, callback: Callback, nopenfd: c_int, flags: c_int) -> c_int {
+    let valid = FTW_PHYS | FTW_MOUNT | FTW_CHDIR | FTW_DEPTH | FTW_ACTIONRETVAL;
+    if flags & !valid != 0 {
+        platform::errno = EINVAL;
+        return -1;
+    }
+
+    let path = slice::from_raw_parts(path as *const u8, strlen(path));
+    if path.is_empty() {
+        platform::errno = ENOENT;
+        return -1;
+    }
+    // Trailing slashes are not part of the name, except for the root
+    let mut len = path.len();
+    while len > 1 && path[len - 1] == b'/' {
+        len -= 1;
+    }
+    let base = match path[..len - 1].iter().rposition(|&c| c == b'/') {
+        Some(slash) => slash + 1,
+        None => 0,
+    };
+
+    let mut walk = Walk {
+        callback,
+        flags,
+        nopenfd: nopenfd.max(1) as usize,
+        open: 0,
+        dev: 0,
+        visited: BTreeSet::new(),
+        path: path[..len].to_vec(),
+        stack: Vec::new(),
+        cwd: -1,
+    };
+    walk.path.push(0);
+
+    if flags & FTW_CHDIR == FTW_CHDIR {
+        walk.cwd = sys_open(
+            b".\0".as_ptr() as *const c_char,
+            O_RDONLY | O_DIRECTORY | O_CLOEXEC,
+            0,
+        );
+        if walk.cwd < 0 {
+            return -1;
+        }
+    }
+
+    let mut ret = walk.run(base as c_int);
+    walk.finish();
+    if flags & FTW_ACTIONRETVAL == FTW_ACTIONRETVAL
+        && (ret == FTW_SKIP_SUBTREE || ret == FTW_SKIP_SIBLINGS)
+    {
+        ret = 0;
+    }
+    ret
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ftw(path: *const c_char, func: Option<FtwFn>, nopenfd: c_int) -> c_int {
+    match func {
+        Some(func) => walk(path, Callback::Ftw(func), nopenfd, 0),
+        None => {
+            platform::errno = EINVAL;
+            -1
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nftw(
+    path: *const c_char,
+    func: Option<NftwFn>,
+    nopenfd: c_int,
+    flags: c_int,
+) -> c_int {
+    match func {
+        Some(func) => walk(path, Callback::Nftw(func), nopenfd, flags),
+        None => {
+            platform::errno = EINVAL;
+            -1
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! glob implementation, following http://pubs.opengroup.org/onlinepubs/9699919799/basedefs/glob.h.html
+
+use alloc::vec::Vec;
+use core::{mem, ptr, slice};
+
+use crate::{
+    header::{
+        dirent::{closedir, opendir, readdir},
+        errno::{ENOENT, ENOTDIR},
+        fnmatch::{fnmatch, FNM_NOESCAPE, FNM_PATHNAME, FNM_PERIOD},
+        pwd::{getpwnam, getpwuid},
+        stdlib::getenv,
+        string::strlen,
+        sys_stat::{lstat, stat, S_IFDIR, S_IFMT},
+        unistd::getuid,
+    },
+    platform::{self, types::*},
+};
+
+pub const GLOB_ERR: c_int = 1 << 0;
+pub const GLOB_MARK: c_int = 1 << 1;
+pub const GLOB_NOSORT: c_int = 1 << 2;
+pub const GLOB_DOOFFS: c_int = 1 << 3;
+pub const GLOB_NOCHECK: c_int = 1 << 4;
+pub const GLOB_APPEND: c_int = 1 << 5;
+pub const GLOB_NOESCAPE: c_int = 1 << 6;
+pub const GLOB_PERIOD: c_int = 1 << 7;
+pub const GLOB_MAGCHAR: c_int = 1 << 8;
+pub const GLOB_BRACE: c_int = 1 << 10;
+pub const GLOB_TILDE: c_int = 1 << 12;
+pub const GLOB_ONLYDIR: c_int = 1 << 13;
+
+pub const GLOB_NOSPACE: c_int = 1;
+pub const GLOB_ABORTED: c_int = 2;
+pub const GLOB_NOMATCH: c_int = 3;
+
+#[repr(C)]
+pub struct glob_t {
+    pub gl_pathc: size_t,
+    pub gl_pathv: *mut *mut c_char,
+    pub gl_offs: size_t,
+    pub gl_flags: c_int,
+}
+
+type ErrFunc = Option<unsafe extern "C" fn(epath: *const c_char, eerrno: c_int) -> c_int>;
+
+struct Glob {
+    flags: c_int,
+    errfunc: ErrFunc,
+    magic: bool,
+}
+
+fn with_nul(path: &[u8]) -> Vec<u8> {
+    let mut path = path.to_vec();
+    path.push(0);
+    path
+}
+
+/// Returns the index of the byte after the closing bracket of the bracket
+/// expression starting at `i`, if it is closed
+fn bracket_end(pattern: &[u8], i: usize) -> Option<usize> {
+    let mut j = i + 1;
+    if pattern.get(j) == Some(&b'!') || pattern.get(j) == Some(&b'^') {
+        j += 1;
+    }
+    // A leading ] is part of the set
+    if pattern.get(j) == Some(&b']') {
+        j += 1;
+    }
+    while j < pattern.len() {
+        match pattern[j] {
+            b']' => return Some(j + 1),
+            b'[' if pattern.get(j + 1) == Some(&b':') => {
+                let end = pattern[j + 2..].windows(2).position(|w| w == b":]")?;
+                j += end + 4;
+            }
+            _ => j += 1,
+        }
+    }
+    None
+}
+
+impl Glob {
+    fn escapes(&self) -> bool {
+        self.flags & GLOB_NOESCAPE != GLOB_NOESCAPE
+    }
+
+    /// Whether a path component contains any unescaped wildcards
+    fn has_magic(&self, component: &[u8]) -> bool {
+        let mut i = 0;
+        while i < component.len() {
+            match component[i] {
+                b'\\' if self.escapes() => i += 1,
+                b'*' | b'?' => return true,
+                b'[' if bracket_end(component, i).is_some() => return true,
+                _ => (),
+            }
+            i += 1;
+        }
+        false
+    }
+
+    fn unescape(&self, component: &[u8]) -> Vec<u8> {
+        if !self.escapes() {
+            return component.to_vec();
+        }
+        let mut out = Vec::with_capacity(component.len());
+        let mut i = 0;
+        while i < component.len() {
+            if component[i] == b'\\' && i + 1 < component.len() {
+                i += 1;
+            }
+            out.push(component[i]);
+            i += 1;
+        }
+        out
+    }
+
+    /// Splits `a{b,c}d` into `abd` and `acd`, recursively and in order
+    fn expand_braces(&self, pattern: &[u8], out: &mut Vec<Vec<u8>>) {
+        let mut i = 0;
+        while i < pattern.len() {
+            match pattern[i] {
+                b'\\' if self.escapes() => i += 1,
+                b'{' => {
+                    // Find the commas at this nesting level and the closing brace
+                    let mut depth = 0;
+                    let mut commas = Vec::new();
+                    let mut j = i + 1;
+                    let mut end = None;
+                    while j < pattern.len() {
+                        match pattern[j] {
+                            b'\\' if self.escapes() => j += 1,
+                            b'{' => depth += 1,
+                            b'}' if depth == 0 => {
+                                end = Some(j);
+                                break;
+                            }
+                            b'}' => depth -= 1,
+                            b',' if depth == 0 => commas.push(j),
+                            _ => (),
+                        }
+                        j += 1;
+                    }
+                    let end = match end {
+                        Some(end) => end,
+                        // An unclosed brace is taken literally
+                        None => break,
+                    };
+
+                    let mut start = i + 1;
+                    commas.push(end);
+                    for comma in commas {
+                        let mut alternative = pattern[..i].to_vec();
+                        alternative.extend_from_slice(&pattern[start..comma]);
+                        alternative.extend_from_slice(&pattern[end + 1..]);
+                        self.expand_braces(&alternative, out);
+                        start = comma + 1;
+                    }
+                    return;
+                }
+                _ => (),
+            }
+            i += 1;
+        }
+        out.push(pattern.to_vec());
+    }
+
+    /// Replaces a leading `~` or `~user` with the home directory
+    unsafe fn expand_tilde(&self, pattern: &[u8]) -> Vec<u8> {
+        let end = pattern
+            .iter()
+            .position(|&c| c == b'/')
+            .unwrap_or(pattern.len());
+        let name = &pattern[1..end];
+
+        let mut home = ptr::null();
+        if name.is_empty() {
+            home = getenv(b"HOME\0".as_ptr() as *const c_char);
+            if home.is_null() {
+                let pwd = getpwuid(getuid());
+                if !pwd.is_null() {
+                    home = (*pwd).pw_dir;
+                }
+            }
+        } else {
+            let pwd = getpwnam(with_nul(name).as_ptr() as *const c_char);
+            if !pwd.is_null() {
+                home = (*pwd).pw_dir;
+            }
+        }
+        if home.is_null() {
+            return pattern.to_vec();
+        }
+
+        let mut out = slice::from_raw_parts(home as *const u8, strlen(home)).to_vec();
+        out.extend_from_slice(&pattern[end..]);
+        out
+    }
+
+    /// Calls the error callback for a directory that could not be read, and
+    /// returns whether globbing should stop
+    unsafe fn error(&self, path: &[u8], errno: c_int) -> bool {
+        if errno == ENOENT || errno == ENOTDIR {
+            return false;
+        }
+        if let Some(errfunc) = self.errfunc {
+            if errfunc(with_nul(path).as_ptr() as *const c_char, errno) != 0 {
+                return true;
+            }
+        }
+        self.flags & GLOB_ERR == GLOB_ERR
+    }
+
+    /// Appends the entries of `dir` matching `component`
+    unsafe fn read_dir(
+        &self,
+        dir: &[u8],
+        component: &[u8],
+        only_dirs: bool,
+        out: &mut Vec<Vec<u8>>,
+    ) -> Result<(), c_int> {
+        let path = if dir.is_empty() { &b"."[..] } else { dir };
+        let handle = opendir(with_nul(path).as_ptr() as *const c_char);
+        if handle.is_null() {
+            return if self.error(path, platform::errno) {
+                Err(GLOB_ABORTED)
+            } else {
+                Ok(())
+            };
+        }
+
+        let pattern = with_nul(component);
+        let mut flags = FNM_PATHNAME;
+        if self.flags & GLOB_PERIOD != GLOB_PERIOD {
+            flags |= FNM_PERIOD;
+        }
+        if !self.escapes() {
+            flags |= FNM_NOESCAPE;
+        }
+
+        loop {
+            let entry = readdir(handle);
+            if entry.is_null() {
+                break;
+            }
+            let name = (*entry).d_name.as_ptr();
+            let name_bytes = slice::from_raw_parts(name as *const u8, strlen(name));
+
+            if fnmatch(pattern.as_ptr() as *const c_char, name, flags) != 0 {
+                continue;
+            }
+
+            let mut path = dir.to_vec();
+            if !path.is_empty() && path.last() != Some(&b'/') {
+                path.push(b'/');
+            }
+            path.extend_from_slice(name_bytes);
+            if !only_dirs || is_dir(&path) {
+                out.push(path);
+            }
+        }
+        closedir(handle);
+        Ok(())
+    }
+
+    /// Matches one pattern, walking the file system one component at a time
+    unsafe fn walk(&mut self, pattern: &[u8], out: &mut Vec<Vec<u8>>) -> Result<(), c_int> {
+        let mut paths = Vec::new();
+        paths.push(if pattern.first() == Some(&b'/') {
+            b"/".to_vec()
+        } else {
+            Vec::new()
+        });
+
+        // A trailing slash only matches directories
+        let trailing_slash = pattern.len() > 1 && pattern.last() == Some(&b'/');
+        let components: Vec<&[u8]> = pattern
+            .split(|&c| c == b'/')
+            .filter(|component| !component.is_empty())
+            .collect();
+
+        for (i, component) in components.iter().enumerate() {
+            let last = i + 1 == components.len();
+            let only_dirs = !last || trailing_slash;
+
+            let mut next = Vec::new();
+            if self.has_magic(component) {
+                self.magic = true;
+                for path in &paths {
+                    self.read_dir(path, component, only_dirs, &mut next)?;
+                }
+            } else {
+                let component = self.unescape(component);
+                for mut path in paths {
+                    if !path.is_empty() && path.last() != Some(&b'/') {
+                        path.push(b'/');
+                    }
+                    path.extend_from_slice(&component);
+                    next.push(path);
+                }
+            }
+            paths = next;
+            if paths.is_empty() {
+                return Ok(());
+            }
+        }
+
+        for mut path in paths {
+            let mut st = mem::zeroed();
+            if lstat(with_nul(&path).as_ptr() as *const c_char, &mut st) != 0 {
+                continue;
+            }
+            if trailing_slash || self.flags & GLOB_ONLYDIR == GLOB_ONLYDIR {
+                if !is_dir(&path) {
+                    continue;
+                }
+            }
+            if (trailing_slash || self.flags & GLOB_MARK == GLOB_MARK)
+                && path.last() != Some(&b'/')
+                && is_dir(&path)
+            {
+                path.push(b'/');
+            }
+            out.push(path);
+        }
+        Ok(())
+    }
+}
+
+unsafe fn is_dir(path: &[u8]) -> bool {
+    let mut st = mem::zeroed();
+    stat(with_nul(path).as_ptr() as *const c_char, &mut st) == 0
+        && st.st_mode as c_int & S_IFMT == S_IFDIR
+}
+
+/// Copies the new paths onto the end of gl_pathv, which may already hold
+/// paths from an earlier call with GLOB_APPEND
+unsafe fn store(pglob: &mut glob_t, paths: &[Vec<u8>]) -> Result<(), c_int> {
+    let offs = if pglob.gl_flags & GLOB_DOOFFS == GLOB_DOOFFS {
+        pglob.gl_offs
+    } else {
+        0
+    };
+    let old = pglob.gl_pathc;
+    let len = offs + old + paths.len() + 1;
+
+    let pathv = platform::realloc(
+        pglob.gl_pathv as *mut c_void,
+        len * mem::size_of::<*mut c_char>(),
+    ) as *mut *mut c_char;
+    if pathv.is_null() {
+        return Err(GLOB_NOSPACE);
+    }
+    if pglob.gl_pathv.is_null() {
+        for i in 0..offs {
+            *pathv.add(i) = ptr::null_mut();
+        }
+    }
+    pglob.gl_pathv = pathv;
+
+    for path in paths {
+        let copy = platform::alloc(path.len() + 1) as *mut c_char;
+        if copy.is_null() {
+            *pathv.add(offs + pglob.gl_pathc) = ptr::null_mut();
+            return Err(GLOB_NOSPACE);
+        }
+        ptr::copy_nonoverlapping(path.as_ptr() as *const c_char, copy, path.len());
+        *copy.add(path.len()) = 0;
+        *pathv.add(offs + pglob.gl_pathc) = copy;
+        pglob.gl_pathc += 1;
+    }
+    *pathv.add(offs + pglob.gl_pathc) = ptr::null_mut();
+    Ok(())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn glob(
+    pattern: *const c_char,
+    flags: c_int,
+    errfunc: ErrFunc,
+    pglob: *mut glob_t,
+) -> c_int {
+    let pglob = &mut *pglob;
+    if flags & GLOB_APPEND != GLOB_APPEND {
+        pglob.gl_pathc = 0;
+        pglob.gl_pathv = ptr::null_mut();
+        if flags & GLOB_DOOFFS != GLOB_DOOFFS {
+            pglob.gl_offs = 0;
+        }
+    }
+    pglob.gl_flags = flags;
+
+    let pattern = slice::from_raw_parts(pattern as *const u8, strlen(pattern));
+    let mut state = Glob {
+        flags,
+        errfunc,
+        magic: false,
+    };
+
+    let mut patterns = Vec::new();
+    if flags & GLOB_BRACE == GLOB_BRACE {
+        state.expand_braces(pattern, &mut patterns);
+    } else {
+        patterns.push(pattern.to_vec());
+    }
+
+    // Every alternative of a brace expression is sorted on its own, as if
+    // globbed separately with GLOB_APPEND
+    let mut paths = Vec::new();
+    for pattern in &patterns {
+        let pattern = if flags & GLOB_TILDE == GLOB_TILDE && pattern.first() == Some(&b'~') {
+            state.expand_tilde(pattern)
+        } else {
+            pattern.clone()
+        };
+
+        let start = paths.len();
+        if let Err(err) = state.walk(&pattern, &mut paths) {
+            let _ = store(pglob, &paths);
+            return err;
+        }
+        if flags & GLOB_NOSORT != GLOB_NOSORT {
+            paths[start..].sort();
+        }
+    }
+
+    if state.magic {
+        pglob.gl_flags |= GLOB_MAGCHAR;
+    }
+
+    if paths.is_empty() {
+        if flags & GLOB_NOCHECK != GLOB_NOCHECK {
+            return GLOB_NOMATCH;
+        }
+        paths.push(pattern.to_vec());
+    }
+
+    match store(pglob, &paths) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn globfree(pglob: *mut glob_t) {
+    let pglob = &mut *pglob;
+    if pglob.gl_pathv.is_null() {
+        return;
+    }
+
+    let offs = if pglob.gl_flags & GLOB_DOOFFS == GLOB_DOOFFS {
+        pglob.gl_offs
+    } else {
+        0
+    };
+    for i in 0..pglob.gl_pathc {
+        platform::free(*pglob.gl_pathv.add(offs + i) as *mut c_void);
+    }
+    platform::free(pglob.gl_pathv as *mut c_void);
+
+    pglob.gl_pathc = 0;
+    pglob.gl_pathv = ptr::null_mut();
+}
@@ -0,0 +1,501 @@
+//! The charsets known to iconv, and how to convert each of them to and from
+//! Unicode code points
+
+use alloc::vec::Vec;
+use core::{char, cmp::Ordering};
+
+use crate::sync::Once;
+
+use super::{cjk, tables::*};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    Big,
+    Little,
+    /// Big endian unless the input starts with a byte order mark, which is
+    /// also written at the start of the output
+    Bom,
+}
+
+#[derive(Clone, Copy)]
+pub enum Charset {
+    Ascii,
+    Utf8,
+    Utf16(Order),
+    Utf32(Order),
+    Ucs2(Order),
+    /// Upper half of a charset that is ASCII below 0x80
+    SingleByte(&'static [u16; 128]),
+    Gbk,
+    Big5,
+    ShiftJis,
+    EucJp,
+    EucKr,
+}
+
+pub enum DecodeError {
+    /// The input is not valid, and this many bytes should be skipped to
+    /// resynchronize
+    Invalid(usize),
+    /// The input ends in the middle of a character
+    Incomplete,
+}
+
+pub enum EncodeError {
+    /// Not enough room in the output
+    TooBig,
+    /// The character doesn't exist in this charset
+    Unrepresentable,
+}
+
+/// A double byte table, along with an index to look up code points
+struct Table {
+    data: &'static [u16],
+    /// Positions in `data`, sorted by code point
+    index: Once<Vec<u16>>,
+}
+
+impl Table {
+    const fn new(data: &'static [u16]) -> Self {
+        Self {
+            data,
+            index: Once::new(),
+        }
+    }
+
+    fn decode(&self, position: usize) -> Option<u32> {
+        match self.data.get(position) {
+            Some(&c) if c != 0 => Some(c as u32),
+            _ => None,
+        }
+    }
+
+    fn encode(&self, c: u32) -> Option<usize> {
+        if c == 0 || c > 0xFFFF {
+            return None;
+        }
+        let data = self.data;
+        let index = self.index.call_once(|| {
+            let mut index: Vec<u16> = (0..data.len() as u16)
+                .filter(|&i| data[i as usize] != 0)
+                .collect();
+            // Stable, so the first of several encodings is preferred
+            index.sort_by_key(|&i| data[i as usize]);
+            index
+        });
+
+        // Find the first entry that isn't less than c
+        let first = index
+            .binary_search_by(|&i| data[i as usize].cmp(&(c as u16)).then(Ordering::Greater))
+            .unwrap_err();
+        match index.get(first) {
+            Some(&i) if data[i as usize] == c as u16 => Some(i as usize),
+            _ => None,
+        }
+    }
+}
+
+static GBK: Table = Table::new(&cjk::GBK);
+static BIG5: Table = Table::new(&cjk::BIG5);
+static JIS0208: Table = Table::new(&cjk::JIS0208);
+static JIS0212: Table = Table::new(&cjk::JIS0212);
+static KSC5601: Table = Table::new(&cjk::KSC5601);
+
+/// Bytes per row in the GBK and Big5 tables, for trail bytes 0x40 to 0xFE
+const WIDE_ROW: usize = 191;
+/// Bytes per row in the tables indexed by two bytes from 0xA1 to 0xFE
+const ROW: usize = 94;
+
+impl Charset {
+    /// Looks up a charset, ignoring case and punctuation in its name
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        let name: Vec<u8> = name
+            .iter()
+            .filter(|b| b.is_ascii_alphanumeric() || **b == b'.')
+            .map(|b| b.to_ascii_uppercase())
+            .collect();
+
+        Some(match &name[..] {
+            b"ASCII" | b"USASCII" | b"ANSIX3.41968" | b"646" => Charset::Ascii,
+            // relibc's multibyte functions always use UTF-8
+            b"UTF8" | b"" | b"CHAR" => Charset::Utf8,
+            b"UTF16" => Charset::Utf16(Order::Bom),
+            b"UTF16BE" => Charset::Utf16(Order::Big),
+            b"UTF16LE" => Charset::Utf16(Order::Little),
+            b"UTF32" => Charset::Utf32(Order::Bom),
+            b"UTF32BE" | b"UCS4" | b"UCS4BE" => Charset::Utf32(Order::Big),
+            b"UTF32LE" | b"UCS4LE" => Charset::Utf32(Order::Little),
+            #[cfg(target_endian = "little")]
+            b"WCHART" => Charset::Utf32(Order::Little),
+            #[cfg(target_endian = "big")]
+            b"WCHART" => Charset::Utf32(Order::Big),
+            b"UCS2" | b"UCS2BE" => Charset::Ucs2(Order::Big),
+            b"UCS2LE" => Charset::Ucs2(Order::Little),
+            b"ISO88591" | b"LATIN1" => Charset::SingleByte(&ISO_8859_1),
+            b"ISO88592" | b"LATIN2" => Charset::SingleByte(&ISO_8859_2),
+            b"ISO88593" | b"LATIN3" => Charset::SingleByte(&ISO_8859_3),
+            b"ISO88594" | b"LATIN4" => Charset::SingleByte(&ISO_8859_4),
+            b"ISO88595" => Charset::SingleByte(&ISO_8859_5),
+            b"ISO88596" => Charset::SingleByte(&ISO_8859_6),
+            b"ISO88597" => Charset::SingleByte(&ISO_8859_7),
+            b"ISO88598" => Charset::SingleByte(&ISO_8859_8),
+            b"ISO88599" | b"LATIN5" => Charset::SingleByte(&ISO_8859_9),
+            b"ISO885910" | b"LATIN6" => Charset::SingleByte(&ISO_8859_10),
+            b"ISO885911" => Charset::SingleByte(&ISO_8859_11),
+            b"ISO885913" | b"LATIN7" => Charset::SingleByte(&ISO_8859_13),
+            b"ISO885914" | b"LATIN8" => Charset::SingleByte(&ISO_8859_14),
+            b"ISO885915" | b"LATIN9" => Charset::SingleByte(&ISO_8859_15),
+            b"ISO885916" | b"LATIN10" => Charset::SingleByte(&ISO_8859_16),
+            b"WINDOWS1250" | b"CP1250" => Charset::SingleByte(&WINDOWS_1250),
+            b"WINDOWS1251" | b"CP1251" => Charset::SingleByte(&WINDOWS_1251),
+            b"WINDOWS1252" | b"CP1252" => Charset::SingleByte(&WINDOWS_1252),
+            b"WINDOWS1253" | b"CP1253" => Charset::SingleByte(&WINDOWS_1253),
+            b"WINDOWS1254" | b"CP1254" => Charset::SingleByte(&WINDOWS_1254),
+            b"WINDOWS1255" | b"CP1255" => Charset::SingleByte(&WINDOWS_1255),
+            b"WINDOWS1256" | b"CP1256" => Charset::SingleByte(&WINDOWS_1256),
+            b"WINDOWS1257" | b"CP1257" => Charset::SingleByte(&WINDOWS_1257),
+            b"WINDOWS1258" | b"CP1258" => Charset::SingleByte(&WINDOWS_1258),
+            // GB2312 is a subset of GBK
+            b"GBK" | b"CP936" | b"GB2312" | b"EUCCN" => Charset::Gbk,
+            b"BIG5" | b"CP950" => Charset::Big5,
+            b"SHIFTJIS" | b"SJIS" => Charset::ShiftJis,
+            b"EUCJP" => Charset::EucJp,
+            b"EUCKR" => Charset::EucKr,
+            _ => return None,
+        })
+    }
+
+    /// Decodes the first character of `input`, which must not be empty.
+    /// `order` is the byte order detected so far for charsets with a BOM.
+    /// Returns None instead of a character if only a BOM was consumed.
+    pub fn decode(
+        self,
+        input: &[u8],
+        order: &mut Option<Order>,
+    ) -> Result<(Option<u32>, usize), DecodeError> {
+        let b = input[0];
+        let byte = |i: usize| input.get(i).cloned().ok_or(DecodeError::Incomplete);
+
+        let (c, len) = match self {
+            Charset::Ascii => {
+                if b >= 0x80 {
+                    return Err(DecodeError::Invalid(1));
+                }
+                (b as u32, 1)
+            }
+            Charset::Utf8 => return decode_utf8(input).map(|(c, len)| (Some(c), len)),
+            Charset::Utf16(o) | Charset::Utf32(o) | Charset::Ucs2(o) => {
+                let width = match self {
+                    Charset::Utf32(_) => 4,
+                    _ => 2,
+                };
+                if input.len() < width {
+                    return Err(DecodeError::Incomplete);
+                }
+
+                let o = match (o, *order) {
+                    (Order::Bom, Some(detected)) => detected,
+                    (Order::Bom, None) => {
+                        let (big, little) = if width == 2 {
+                            (&[0xFE, 0xFF][..], &[0xFF, 0xFE][..])
+                        } else {
+                            (&[0, 0, 0xFE, 0xFF][..], &[0xFF, 0xFE, 0, 0][..])
+                        };
+                        if &input[..width] == big {
+                            *order = Some(Order::Big);
+                            return Ok((None, width));
+                        } else if &input[..width] == little {
+                            *order = Some(Order::Little);
+                            return Ok((None, width));
+                        }
+                        *order = Some(Order::Big);
+                        Order::Big
+                    }
+                    (o, _) => o,
+                };
+
+                let unit = |i: usize| {
+                    let mut unit = 0;
+                    for j in 0..width {
+                        let shift = match o {
+                            Order::Little => 8 * j,
+                            _ => 8 * (width - 1 - j),
+                        };
+                        unit |= (input[i + j] as u32) << shift;
+                    }
+                    unit
+                };
+                let c = unit(0);
+                match (self, c) {
+                    (Charset::Utf16(_), 0xD800..=0xDBFF) => {
+                        if input.len() < 4 {
+                            return Err(DecodeError::Incomplete);
+                        }
+                        let low = unit(2);
+                        if low < 0xDC00 || low > 0xDFFF {
+                            return Err(DecodeError::Invalid(2));
+                        }
+                        (0x10000 + ((c - 0xD800) << 10) + (low - 0xDC00), 4)
+                    }
+                    (_, 0xD800..=0xDFFF) | (_, 0x11_0000..=0xFFFF_FFFF) => {
+                        return Err(DecodeError::Invalid(width));
+                    }
+                    _ => (c, width),
+                }
+            }
+            Charset::SingleByte(table) => {
+                if b < 0x80 {
+                    (b as u32, 1)
+                } else {
+                    match table[b as usize - 0x80] {
+                        0 => return Err(DecodeError::Invalid(1)),
+                        c => (c as u32, 1),
+                    }
+                }
+            }
+            Charset::Gbk | Charset::Big5 => {
+                if b < 0x80 {
+                    (b as u32, 1)
+                } else {
+                    let (table, first_lead) = match self {
+                        Charset::Gbk => (&GBK, 0x81),
+                        _ => (&BIG5, 0xA1),
+                    };
+                    if b < first_lead || b == 0xFF {
+                        return Err(DecodeError::Invalid(1));
+                    }
+                    let trail = byte(1)?;
+                    if trail < 0x40 || trail == 0xFF {
+                        return Err(DecodeError::Invalid(1));
+                    }
+                    let position = (b - first_lead) as usize * WIDE_ROW + (trail - 0x40) as usize;
+                    match table.decode(position) {
+                        Some(c) => (c, 2),
+                        None => return Err(DecodeError::Invalid(1)),
+                    }
+                }
+            }
+            Charset::ShiftJis => match b {
+                0x00..=0x7F => (b as u32, 1),
+                // Half width katakana
+                0xA1..=0xDF => (0xFF61 + (b - 0xA1) as u32, 1),
+                0x81..=0x9F | 0xE0..=0xEF => {
+                    let trail = byte(1)?;
+                    let pair = if b <= 0x9F { b - 0x81 } else { b - 0xC1 };
+                    let pair = pair as usize;
+                    let (row, col) = match trail {
+                        0x40..=0x7E => (2 * pair, trail - 0x40),
+                        0x80..=0x9E => (2 * pair, trail - 0x41),
+                        0x9F..=0xFC => (2 * pair + 1, trail - 0x9F),
+                        _ => return Err(DecodeError::Invalid(1)),
+                    };
+                    match JIS0208.decode(row * ROW + col as usize) {
+                        Some(c) => (c, 2),
+                        None => return Err(DecodeError::Invalid(1)),
+                    }
+                }
+                _ => return Err(DecodeError::Invalid(1)),
+            },
+            Charset::EucJp | Charset::EucKr => {
+                let (table, first) = match (self, b) {
+                    (_, 0x00..=0x7F) => return Ok((Some(b as u32), 1)),
+                    (Charset::EucJp, 0x8E) => {
+                        let kana = byte(1)?;
+                        if kana < 0xA1 || kana > 0xDF {
+                            return Err(DecodeError::Invalid(1));
+                        }
+                        return Ok((Some(0xFF61 + (kana - 0xA1) as u32), 2));
+                    }
+                    (Charset::EucJp, 0x8F) => (&JIS0212, 1),
+                    (Charset::EucJp, _) => (&JIS0208, 0),
+                    _ => (&KSC5601, 0),
+                };
+                let lead = byte(first)?;
+                let trail = byte(first + 1)?;
+                if lead < 0xA1 || lead == 0xFF || trail < 0xA1 || trail == 0xFF {
+                    return Err(DecodeError::Invalid(1));
+                }
+                match table.decode((lead - 0xA1) as usize * ROW + (trail - 0xA1) as usize) {
+                    Some(c) => (c, first + 2),
+                    None => return Err(DecodeError::Invalid(1)),
+                }
+            }
+        };
+        Ok((Some(c), len))
+    }
+
+    /// Encodes a character into `output`, returning the number of bytes
+    /// written. `bom_written` tracks whether charsets with a BOM still need
+    /// to write one.
+    pub fn encode(
+        self,
+        c: u32,
+        output: &mut [u8],
+        bom_written: &mut bool,
+    ) -> Result<usize, EncodeError> {
+        let mut bytes = [0; 8];
+        let len = match self {
+            Charset::Ascii => {
+                if c >= 0x80 {
+                    return Err(EncodeError::Unrepresentable);
+                }
+                bytes[0] = c as u8;
+                1
+            }
+            Charset::Utf8 => {
+                let c = char::from_u32(c).ok_or(EncodeError::Unrepresentable)?;
+                c.encode_utf8(&mut bytes).len()
+            }
+            Charset::Utf16(o) | Charset::Utf32(o) | Charset::Ucs2(o) => {
+                if char::from_u32(c).is_none() {
+                    return Err(EncodeError::Unrepresentable);
+                }
+                let (width, units) = match self {
+                    Charset::Utf32(_) => (4, [c, 0]),
+                    _ if c < 0x10000 => (2, [c, 0]),
+                    Charset::Ucs2(_) => return Err(EncodeError::Unrepresentable),
+                    _ => {
+                        let c = c - 0x10000;
+                        (2, [0xD800 + (c >> 10), 0xDC00 + (c & 0x3FF)])
+                    }
+                };
+
+                let mut len = 0;
+                let mut put = |unit: u32| {
+                    for j in 0..width {
+                        let shift = match o {
+                            Order::Little => 8 * j,
+                            _ => 8 * (width - 1 - j),
+                        };
+                        bytes[len] = (unit >> shift) as u8;
+                        len += 1;
+                    }
+                };
+                let bom = o == Order::Bom && !*bom_written;
+                if bom {
+                    put(0xFEFF);
+                }
+                put(units[0]);
+                if units[1] != 0 {
+                    put(units[1]);
+                }
+
+                if len > output.len() {
+                    return Err(EncodeError::TooBig);
+                }
+                if bom {
+                    *bom_written = true;
+                }
+                len
+            }
+            Charset::SingleByte(table) => {
+                if c < 0x80 {
+                    bytes[0] = c as u8;
+                } else {
+                    let position = table
+                        .iter()
+                        .position(|&t| t != 0 && t as u32 == c)
+                        .ok_or(EncodeError::Unrepresentable)?;
+                    bytes[0] = 0x80 + position as u8;
+                }
+                1
+            }
+            _ if c < 0x80 => {
+                bytes[0] = c as u8;
+                1
+            }
+            Charset::Gbk | Charset::Big5 => {
+                let (table, first_lead) = match self {
+                    Charset::Gbk => (&GBK, 0x81),
+                    _ => (&BIG5, 0xA1),
+                };
+                let position = table.encode(c).ok_or(EncodeError::Unrepresentable)?;
+                bytes[0] = first_lead + (position / WIDE_ROW) as u8;
+                bytes[1] = 0x40 + (position % WIDE_ROW) as u8;
+                2
+            }
+            Charset::ShiftJis => {
+                if c >= 0xFF61 && c <= 0xFF9F {
+                    bytes[0] = (c - 0xFF61) as u8 + 0xA1;
+                    1
+                } else {
+                    let position = JIS0208.encode(c).ok_or(EncodeError::Unrepresentable)?;
+                    let (row, col) = (position / ROW, (position % ROW) as u8);
+                    let pair = (row / 2) as u8;
+                    bytes[0] = if pair < 31 { 0x81 + pair } else { 0xC1 + pair };
+                    bytes[1] = if row % 2 == 1 {
+                        col + 0x9F
+                    } else if col + 0x40 >= 0x7F {
+                        col + 0x41
+                    } else {
+                        col + 0x40
+                    };
+                    2
+                }
+            }
+            Charset::EucJp if c >= 0xFF61 && c <= 0xFF9F => {
+                bytes[0] = 0x8E;
+                bytes[1] = (c - 0xFF61) as u8 + 0xA1;
+                2
+            }
+            Charset::EucJp | Charset::EucKr => {
+                let (position, prefix) = match self {
+                    Charset::EucKr => (KSC5601.encode(c), None),
+                    _ => match JIS0208.encode(c) {
+                        Some(position) => (Some(position), None),
+                        None => (JIS0212.encode(c), Some(0x8F)),
+                    },
+                };
+                let position = position.ok_or(EncodeError::Unrepresentable)?;
+                let mut len = 0;
+                if let Some(prefix) = prefix {
+                    bytes[0] = prefix;
+                    len = 1;
+                }
+                bytes[len] = 0xA1 + (position / ROW) as u8;
+                bytes[len + 1] = 0xA1 + (position % ROW) as u8;
+                len + 2
+            }
+        };
+
+        if len > output.len() {
+            return Err(EncodeError::TooBig);
+        }
+        output[..len].copy_from_slice(&bytes[..len]);
+        Ok(len)
+    }
+}
+
+/// Decodes UTF-8, rejecting overlong forms, surrogates and code points above
+/// U+10FFFF like `str::from_utf8` does
+fn decode_utf8(input: &[u8]) -> Result<(u32, usize), DecodeError> {
+    let b = input[0];
+    let (len, mut c, second) = match b {
+        0x00..=0x7F => return Ok((b as u32, 1)),
+        0xC2..=0xDF => (2, b as u32 & 0x1F, 0x80..=0xBF),
+        0xE0 => (3, 0, 0xA0..=0xBF),
+        0xED => (3, 0xD, 0x80..=0x9F),
+        0xE1..=0xEF => (3, b as u32 & 0x0F, 0x80..=0xBF),
+        0xF0 => (4, 0, 0x90..=0xBF),
+        0xF1..=0xF3 => (4, b as u32 & 0x07, 0x80..=0xBF),
+        0xF4 => (4, 4, 0x80..=0x8F),
+        _ => return Err(DecodeError::Invalid(1)),
+    };
+
+    for i in 1..len {
+        let continuation = match input.get(i) {
+            Some(&continuation) => continuation,
+            None => return Err(DecodeError::Incomplete),
+        };
+        let valid = if i == 1 {
+            second.contains(&continuation)
+        } else {
+            continuation & 0xC0 == 0x80
+        };
+        if !valid {
+            return Err(DecodeError::Invalid(i));
+        }
+        c = c << 6 | (continuation & 0x3F) as u32;
+    }
+    Ok((c, len))
+}
@@ -0,0 +1,198 @@
+//! iconv implementation for Redox, following http://pubs.opengroup.org/onlinepubs/9699919799/basedefs/iconv.h.html
+
+use alloc::boxed::Box;
+use core::slice;
+
+use crate::{
+    c_str::CStr,
+    header::errno::{E2BIG, EILSEQ, EINVAL},
+    platform::{self, types::*},
+};
+
+use self::charset::{Charset, DecodeError, EncodeError, Order};
+
+mod charset;
+mod cjk;
+mod tables;
+
+pub type iconv_t = *mut c_void;
+
+struct Converter {
+    from: Charset,
+    to: Charset,
+    /// Replace characters that `to` can't represent with similar ones
+    translit: bool,
+    /// Skip invalid input and characters that `to` can't represent
+    ignore: bool,
+    /// The byte order found in the input, for charsets with a BOM
+    from_order: Option<Order>,
+    bom_written: bool,
+}
+
+impl Converter {
+    /// Writes the closest match to `c` that `to` can represent, which is `?`
+    /// if nothing better is known
+    fn transliterate(&mut self, c: u32, output: &mut [u8]) -> Result<usize, EncodeError> {
+        let replacement = match tables::TRANSLIT.binary_search_by_key(&c, |&(c, _)| c) {
+            Ok(i) => tables::TRANSLIT[i].1,
+            Err(_) => "?",
+        };
+
+        for replacement in &[replacement, "?"] {
+            let mut written = 0;
+            let mut bom_written = self.bom_written;
+            let result = replacement.bytes().try_for_each(|b| {
+                written += self
+                    .to
+                    .encode(b as u32, &mut output[written..], &mut bom_written)?;
+                Ok(())
+            });
+            match result {
+                Ok(()) => {
+                    self.bom_written = bom_written;
+                    return Ok(written);
+                }
+                Err(EncodeError::TooBig) => return Err(EncodeError::TooBig),
+                Err(EncodeError::Unrepresentable) => (),
+            }
+        }
+        Err(EncodeError::Unrepresentable)
+    }
+}
+
+/// Splits `//TRANSLIT` and `//IGNORE` off a charset name
+fn parse_name(name: &[u8]) -> (&[u8], bool, bool) {
+    let mut parts = name.split(|&b| b == b'/');
+    let charset = parts.next().unwrap_or(b"");
+    let (mut translit, mut ignore) = (false, false);
+    for flag in parts {
+        if flag.eq_ignore_ascii_case(b"TRANSLIT") {
+            translit = true;
+        } else if flag.eq_ignore_ascii_case(b"IGNORE") {
+            ignore = true;
+        }
+    }
+    (charset, translit, ignore)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn iconv(
+    cd: iconv_t,
+    inbuf: *mut *mut c_char,
+    inbytesleft: *mut size_t,
+    outbuf: *mut *mut c_char,
+    outbytesleft: *mut size_t,
+) -> size_t {
+    let cd = &mut *(cd as *mut Converter);
+
+    if inbuf.is_null() || (*inbuf).is_null() {
+        // Back to the initial state, which needs no bytes to get to since
+        // none of the charsets have shift sequences
+        cd.from_order = None;
+        cd.bom_written = false;
+        return 0;
+    }
+
+    let input = slice::from_raw_parts(*inbuf as *const u8, *inbytesleft);
+    let output = if outbuf.is_null() || (*outbuf).is_null() {
+        &mut [][..]
+    } else {
+        slice::from_raw_parts_mut(*outbuf as *mut u8, *outbytesleft)
+    };
+
+    let mut read = 0;
+    let mut written = 0;
+    let mut irreversible = 0;
+    let mut ignored = false;
+    let mut error = 0;
+    while read < input.len() {
+        let (c, len) = match cd.from.decode(&input[read..], &mut cd.from_order) {
+            Ok((Some(c), len)) => (c, len),
+            // Byte order mark
+            Ok((None, len)) => {
+                read += len;
+                continue;
+            }
+            Err(DecodeError::Invalid(len)) if cd.ignore => {
+                read += len;
+                ignored = true;
+                continue;
+            }
+            Err(DecodeError::Invalid(_)) => {
+                error = EILSEQ;
+                break;
+            }
+            Err(DecodeError::Incomplete) => {
+                error = EINVAL;
+                break;
+            }
+        };
+
+        let result = match cd.to.encode(c, &mut output[written..], &mut cd.bom_written) {
+            Err(EncodeError::Unrepresentable) if cd.translit => {
+                cd.transliterate(c, &mut output[written..]).map(|len| {
+                    irreversible += 1;
+                    len
+                })
+            }
+            result => result,
+        };
+        match result {
+            Ok(len) => written += len,
+            Err(EncodeError::TooBig) => {
+                error = E2BIG;
+                break;
+            }
+            Err(EncodeError::Unrepresentable) if cd.ignore => ignored = true,
+            Err(EncodeError::Unrepresentable) => {
+                error = EILSEQ;
+                break;
+            }
+        }
+        read += len;
+    }
+
+    *inbuf = (*inbuf).add(read);
+    *inbytesleft -= read;
+    if !outbuf.is_null() && !(*outbuf).is_null() {
+        *outbuf = (*outbuf).add(written);
+        *outbytesleft -= written;
+    }
+
+    // Like glibc, report skipped input once everything else is converted
+    if error == 0 && ignored {
+        error = EILSEQ;
+    }
+    if error != 0 {
+        platform::errno = error;
+        return !0;
+    }
+    irreversible
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn iconv_close(cd: iconv_t) -> c_int {
+    drop(Box::from_raw(cd as *mut Converter));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn iconv_open(tocode: *const c_char, fromcode: *const c_char) -> iconv_t {
+    let (to, translit, ignore) = parse_name(CStr::from_ptr(tocode).to_bytes());
+    let (from, _, _) = parse_name(CStr::from_ptr(fromcode).to_bytes());
+
+    match (Charset::from_name(to), Charset::from_name(from)) {
+        (Some(to), Some(from)) => Box::into_raw(Box::new(Converter {
+            from,
+            to,
+            translit,
+            ignore,
+            from_order: None,
+            bom_written: false,
+        })) as iconv_t,
+        _ => {
+            platform::errno = EINVAL;
+            !0usize as iconv_t
+        }
+    }
+}
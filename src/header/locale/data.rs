@@ -0,0 +1,382 @@
+//! Locale data, built in and loaded from disk.
+//!
+//! Locales other than `C`, `POSIX` and `C.UTF-8` are read from a file named
+//! after the locale in `$LOCPATH`, or `/usr/share/relibc/locale` if that is
+//! not set. The file is made of `key = value` lines, grouped by category:
+//!
+//! ```text
+//! # German, as spoken in Germany
+//! [LC_CTYPE]
+//! codeset = UTF-8
+//!
+//! [LC_NUMERIC]
+//! decimal_point = ,
+//! thousands_sep = .
+//! grouping = 3;3
+//!
+//! [LC_TIME]
+//! abday = So;Mo;Di;Mi;Do;Fr;Sa
+//! d_fmt = %d.%m.%Y
+//! ```
+//!
+//! Values may be surrounded by double quotes to keep leading or trailing
+//! spaces. Lists are separated by `;`, and numeric values of -1 stand for
+//! CHAR_MAX. Anything that isn't specified is taken from the C locale.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    c_str::CStr,
+    fs::File,
+    header::{fcntl, stdlib},
+    io::Read,
+    platform::types::*,
+    sync::Mutex,
+};
+
+const DEFAULT_LOCPATH: &[u8] = b"/usr/share/relibc/locale";
+/// The longest locale name that will be looked up on disk
+pub const NAME_MAX: usize = 255;
+
+macro_rules! c {
+    ($lit:expr) => {
+        concat!($lit, "\0").as_ptr() as *const c_char
+    };
+}
+
+#[derive(Clone, Copy)]
+pub struct Ctype {
+    pub codeset: *const c_char,
+}
+
+#[derive(Clone, Copy)]
+pub struct Numeric {
+    pub decimal_point: *const c_char,
+    pub thousands_sep: *const c_char,
+    pub grouping: *const c_char,
+}
+
+#[derive(Clone, Copy)]
+pub struct Monetary {
+    pub int_curr_symbol: *const c_char,
+    pub currency_symbol: *const c_char,
+    pub mon_decimal_point: *const c_char,
+    pub mon_thousands_sep: *const c_char,
+    pub mon_grouping: *const c_char,
+    pub positive_sign: *const c_char,
+    pub negative_sign: *const c_char,
+    pub int_frac_digits: c_char,
+    pub frac_digits: c_char,
+    pub p_cs_precedes: c_char,
+    pub p_sep_by_space: c_char,
+    pub n_cs_precedes: c_char,
+    pub n_sep_by_space: c_char,
+    pub p_sign_posn: c_char,
+    pub n_sign_posn: c_char,
+}
+
+#[derive(Clone, Copy)]
+pub struct Time {
+    pub abday: [*const c_char; 7],
+    pub day: [*const c_char; 7],
+    pub abmon: [*const c_char; 12],
+    pub mon: [*const c_char; 12],
+    pub am_pm: [*const c_char; 2],
+    pub d_t_fmt: *const c_char,
+    pub d_fmt: *const c_char,
+    pub t_fmt: *const c_char,
+    pub t_fmt_ampm: *const c_char,
+}
+
+#[derive(Clone, Copy)]
+pub struct Messages {
+    pub yesexpr: *const c_char,
+    pub noexpr: *const c_char,
+}
+
+/// Everything a locale defines. Locale objects refer to one of these per
+/// category, so that categories can come from different locales.
+#[derive(Clone, Copy)]
+pub struct LocaleData {
+    pub name: *const c_char,
+    pub ctype: Ctype,
+    pub numeric: Numeric,
+    pub monetary: Monetary,
+    pub time: Time,
+    pub messages: Messages,
+}
+unsafe impl Send for LocaleData {}
+unsafe impl Sync for LocaleData {}
+
+const CHAR_MAX: c_char = c_char::max_value();
+
+const C: LocaleData = LocaleData {
+    name: c!("C"),
+    ctype: Ctype {
+        codeset: c!("ANSI_X3.4-1968"),
+    },
+    numeric: Numeric {
+        decimal_point: c!("."),
+        thousands_sep: c!(""),
+        grouping: c!(""),
+    },
+    monetary: Monetary {
+        int_curr_symbol: c!(""),
+        currency_symbol: c!(""),
+        mon_decimal_point: c!(""),
+        mon_thousands_sep: c!(""),
+        mon_grouping: c!(""),
+        positive_sign: c!(""),
+        negative_sign: c!(""),
+        int_frac_digits: CHAR_MAX,
+        frac_digits: CHAR_MAX,
+        p_cs_precedes: CHAR_MAX,
+        p_sep_by_space: CHAR_MAX,
+        n_cs_precedes: CHAR_MAX,
+        n_sep_by_space: CHAR_MAX,
+        p_sign_posn: CHAR_MAX,
+        n_sign_posn: CHAR_MAX,
+    },
+    time: Time {
+        abday: [
+            c!("Sun"),
+            c!("Mon"),
+            c!("Tue"),
+            c!("Wed"),
+            c!("Thu"),
+            c!("Fri"),
+            c!("Sat"),
+        ],
+        day: [
+            c!("Sunday"),
+            c!("Monday"),
+            c!("Tuesday"),
+            c!("Wednesday"),
+            c!("Thursday"),
+            c!("Friday"),
+            c!("Saturday"),
+        ],
+        abmon: [
+            c!("Jan"),
+            c!("Feb"),
+            c!("Mar"),
+            c!("Apr"),
+            c!("May"),
+            c!("Jun"),
+            c!("Jul"),
+            c!("Aug"),
+            c!("Sep"),
+            c!("Oct"),
+            c!("Nov"),
+            c!("Dec"),
+        ],
+        mon: [
+            c!("January"),
+            c!("February"),
+            c!("March"),
+            c!("April"),
+            c!("May"),
+            c!("June"),
+            c!("July"),
+            c!("August"),
+            c!("September"),
+            c!("October"),
+            c!("November"),
+            c!("December"),
+        ],
+        am_pm: [c!("AM"), c!("PM")],
+        d_t_fmt: c!("%a %b %e %H:%M:%S %Y"),
+        d_fmt: c!("%m/%d/%y"),
+        t_fmt: c!("%H:%M:%S"),
+        t_fmt_ampm: c!("%I:%M:%S %p"),
+    },
+    messages: Messages {
+        yesexpr: c!("^[yY]"),
+        noexpr: c!("^[nN]"),
+    },
+};
+
+pub static C_LOCALE: LocaleData = C;
+
+pub static C_UTF8_LOCALE: LocaleData = LocaleData {
+    name: c!("C.UTF-8"),
+    ctype: Ctype {
+        codeset: c!("UTF-8"),
+    },
+    ..C
+};
+
+/// Locales that were loaded from disk. They are never freed, since
+/// `setlocale` and `nl_langinfo` hand out pointers into them.
+static LOADED: Mutex<Vec<&'static LocaleData>> = Mutex::new(Vec::new());
+
+/// Looks up a locale by name, loading it from disk the first time
+pub fn find(name: &[u8]) -> Option<&'static LocaleData> {
+    match name {
+        b"C" | b"POSIX" => return Some(&C_LOCALE),
+        b"C.UTF-8" | b"C.utf8" => return Some(&C_UTF8_LOCALE),
+        _ => (),
+    }
+
+    // Don't let the name escape the locale directory
+    if name.is_empty() || name.len() > NAME_MAX || name.contains(&b'/') || name == b".." {
+        return None;
+    }
+
+    let mut loaded = LOADED.lock();
+    if let Some(data) = loaded
+        .iter()
+        .find(|data| unsafe { CStr::from_ptr(data.name) }.to_bytes() == name)
+    {
+        return Some(data);
+    }
+
+    let data: &'static LocaleData = Box::leak(Box::new(load(name)?));
+    loaded.push(data);
+    Some(data)
+}
+
+fn load(name: &[u8]) -> Option<LocaleData> {
+    let mut path = unsafe {
+        let locpath = stdlib::getenv(c!("LOCPATH"));
+        if locpath.is_null() {
+            DEFAULT_LOCPATH.to_vec()
+        } else {
+            CStr::from_ptr(locpath).to_bytes().to_vec()
+        }
+    };
+    path.push(b'/');
+    path.extend_from_slice(name);
+    path.push(0);
+
+    let path = CStr::from_bytes_with_nul(&path).ok()?;
+    let file = File::open(path, fcntl::O_RDONLY | fcntl::O_CLOEXEC).ok()?;
+    let mut text = Vec::new();
+    (&file).read_to_end(&mut text).ok()?;
+
+    parse(name, &text)
+}
+
+/// Copies a string into a NUL terminated allocation that is never freed
+fn leak(s: &[u8]) -> *const c_char {
+    let mut buf = Vec::with_capacity(s.len() + 1);
+    buf.extend_from_slice(s);
+    buf.push(0);
+    Box::leak(buf.into_boxed_slice()).as_ptr() as *const c_char
+}
+
+fn trim(mut s: &[u8]) -> &[u8] {
+    while let Some((first, rest)) = s.split_first() {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        s = rest;
+    }
+    while let Some((last, rest)) = s.split_last() {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+fn parse_char(value: &[u8]) -> Option<c_char> {
+    let value = core::str::from_utf8(value).ok()?;
+    match value.parse::<i8>().ok()? {
+        -1 => Some(CHAR_MAX),
+        n if n >= 0 => Some(n as c_char),
+        _ => None,
+    }
+}
+
+fn parse_list(value: &[u8], out: &mut [*const c_char]) -> Option<()> {
+    let mut items = value.split(|&b| b == b';');
+    for slot in out.iter_mut() {
+        *slot = leak(trim(items.next()?));
+    }
+    match items.next() {
+        Some(_) => None,
+        None => Some(()),
+    }
+}
+
+/// Parses a grouping such as `3;2` into the byte string used by lconv
+fn parse_grouping(value: &[u8]) -> Option<*const c_char> {
+    let mut grouping = Vec::new();
+    if !value.is_empty() {
+        for item in value.split(|&b| b == b';') {
+            grouping.push(parse_char(trim(item))? as u8);
+        }
+    }
+    Some(leak(&grouping))
+}
+
+fn parse(name: &[u8], text: &[u8]) -> Option<LocaleData> {
+    let mut data = C;
+    data.name = leak(name);
+
+    let mut section: &[u8] = b"";
+    for line in text.split(|&b| b == b'\n') {
+        let line = trim(line);
+        if line.is_empty() || line[0] == b'#' {
+            continue;
+        }
+        if line[0] == b'[' && line[line.len() - 1] == b']' {
+            section = trim(&line[1..line.len() - 1]);
+            continue;
+        }
+
+        let eq = line.iter().position(|&b| b == b'=')?;
+        let key = trim(&line[..eq]);
+        let mut value = trim(&line[eq + 1..]);
+        if value.len() >= 2 && value[0] == b'"' && value[value.len() - 1] == b'"' {
+            value = &value[1..value.len() - 1];
+        }
+
+        let string = |field: &mut *const c_char| *field = leak(value);
+        let m = &mut data.monetary;
+        let t = &mut data.time;
+        match (section, key) {
+            (b"LC_CTYPE", b"codeset") => string(&mut data.ctype.codeset),
+
+            (b"LC_NUMERIC", b"decimal_point") => string(&mut data.numeric.decimal_point),
+            (b"LC_NUMERIC", b"thousands_sep") => string(&mut data.numeric.thousands_sep),
+            (b"LC_NUMERIC", b"grouping") => data.numeric.grouping = parse_grouping(value)?,
+
+            (b"LC_MONETARY", b"int_curr_symbol") => string(&mut m.int_curr_symbol),
+            (b"LC_MONETARY", b"currency_symbol") => string(&mut m.currency_symbol),
+            (b"LC_MONETARY", b"mon_decimal_point") => string(&mut m.mon_decimal_point),
+            (b"LC_MONETARY", b"mon_thousands_sep") => string(&mut m.mon_thousands_sep),
+            (b"LC_MONETARY", b"mon_grouping") => m.mon_grouping = parse_grouping(value)?,
+            (b"LC_MONETARY", b"positive_sign") => string(&mut m.positive_sign),
+            (b"LC_MONETARY", b"negative_sign") => string(&mut m.negative_sign),
+            (b"LC_MONETARY", b"int_frac_digits") => m.int_frac_digits = parse_char(value)?,
+            (b"LC_MONETARY", b"frac_digits") => m.frac_digits = parse_char(value)?,
+            (b"LC_MONETARY", b"p_cs_precedes") => m.p_cs_precedes = parse_char(value)?,
+            (b"LC_MONETARY", b"p_sep_by_space") => m.p_sep_by_space = parse_char(value)?,
+            (b"LC_MONETARY", b"n_cs_precedes") => m.n_cs_precedes = parse_char(value)?,
+            (b"LC_MONETARY", b"n_sep_by_space") => m.n_sep_by_space = parse_char(value)?,
+            (b"LC_MONETARY", b"p_sign_posn") => m.p_sign_posn = parse_char(value)?,
+            (b"LC_MONETARY", b"n_sign_posn") => m.n_sign_posn = parse_char(value)?,
+
+            (b"LC_TIME", b"abday") => parse_list(value, &mut t.abday)?,
+            (b"LC_TIME", b"day") => parse_list(value, &mut t.day)?,
+            (b"LC_TIME", b"abmon") => parse_list(value, &mut t.abmon)?,
+            (b"LC_TIME", b"mon") => parse_list(value, &mut t.mon)?,
+            (b"LC_TIME", b"am_pm") => parse_list(value, &mut t.am_pm)?,
+            (b"LC_TIME", b"d_t_fmt") => string(&mut t.d_t_fmt),
+            (b"LC_TIME", b"d_fmt") => string(&mut t.d_fmt),
+            (b"LC_TIME", b"t_fmt") => string(&mut t.t_fmt),
+            (b"LC_TIME", b"t_fmt_ampm") => string(&mut t.t_fmt_ampm),
+
+            (b"LC_MESSAGES", b"yesexpr") => string(&mut data.messages.yesexpr),
+            (b"LC_MESSAGES", b"noexpr") => string(&mut data.messages.noexpr),
+
+            // Newer locale files may define more than we know about
+            _ => (),
+        }
+    }
+
+    Some(data)
+}
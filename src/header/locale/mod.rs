@@ -1,12 +1,51 @@
-//! locale implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/locale.h.html
+//! locale implementation for Redox, following http://pubs.opengroup.org/onlinepubs/9699919799/basedefs/locale.h.html
 
+use alloc::boxed::Box;
 use core::ptr;
 
-use crate::platform::types::*;
+use crate::{
+    c_str::CStr,
+    header::{errno::*, stdlib},
+    platform::{self, types::*},
+};
+
+pub use self::data::LocaleData;
+
+mod data;
+
+pub const LC_ALL: c_int = 0;
+pub const LC_COLLATE: c_int = 1;
+pub const LC_CTYPE: c_int = 2;
+pub const LC_MESSAGES: c_int = 3;
+pub const LC_MONETARY: c_int = 4;
+pub const LC_NUMERIC: c_int = 5;
+pub const LC_TIME: c_int = 6;
+
+pub const LC_COLLATE_MASK: c_int = 1 << LC_COLLATE;
+pub const LC_CTYPE_MASK: c_int = 1 << LC_CTYPE;
+pub const LC_MESSAGES_MASK: c_int = 1 << LC_MESSAGES;
+pub const LC_MONETARY_MASK: c_int = 1 << LC_MONETARY;
+pub const LC_NUMERIC_MASK: c_int = 1 << LC_NUMERIC;
+pub const LC_TIME_MASK: c_int = 1 << LC_TIME;
+pub const LC_ALL_MASK: c_int = LC_COLLATE_MASK
+    | LC_CTYPE_MASK
+    | LC_MESSAGES_MASK
+    | LC_MONETARY_MASK
+    | LC_NUMERIC_MASK
+    | LC_TIME_MASK;
 
-const EMPTY_PTR: *const c_char = "\0" as *const _ as *const c_char;
-// Can't use &str because of the mutability
-static mut C_LOCALE: [c_char; 2] = [b'C' as c_char, 0];
+/// Same as the LC_GLOBAL_LOCALE macro in bits/locale.h
+const GLOBAL_LOCALE: locale_t = -1isize as locale_t;
+
+const CATEGORY_NAMES: [&[u8]; 7] = [
+    b"LC_ALL",
+    b"LC_COLLATE",
+    b"LC_CTYPE",
+    b"LC_MESSAGES",
+    b"LC_MONETARY",
+    b"LC_NUMERIC",
+    b"LC_TIME",
+];
 
 #[repr(C)]
 #[no_mangle]
@@ -30,39 +69,295 @@ pub struct lconv {
     p_sign_posn: c_char,
     thousands_sep: *const c_char,
 }
-unsafe impl Sync for lconv {}
-
-static CURRENT_LOCALE: lconv = lconv {
-    currency_symbol: EMPTY_PTR,
-    decimal_point: ".\0" as *const _ as *const c_char,
-    frac_digits: c_char::max_value(),
-    grouping: EMPTY_PTR,
-    int_curr_symbol: EMPTY_PTR,
-    int_frac_digits: c_char::max_value(),
-    mon_decimal_point: EMPTY_PTR,
-    mon_grouping: EMPTY_PTR,
-    mon_thousands_sep: EMPTY_PTR,
-    negative_sign: EMPTY_PTR,
-    n_cs_precedes: c_char::max_value(),
-    n_sep_by_space: c_char::max_value(),
-    n_sign_posn: c_char::max_value(),
-    positive_sign: EMPTY_PTR,
-    p_cs_precedes: c_char::max_value(),
-    p_sep_by_space: c_char::max_value(),
-    p_sign_posn: c_char::max_value(),
-    thousands_sep: EMPTY_PTR,
+
+/// A locale object, which picks the data of each category from a
+/// (possibly different) locale
+#[derive(Clone, Copy)]
+pub struct __locale_struct {
+    categories: [&'static LocaleData; 6],
+}
+
+pub type locale_t = *mut __locale_struct;
+
+impl __locale_struct {
+    fn c() -> Self {
+        Self {
+            categories: [&data::C_LOCALE; 6],
+        }
+    }
+
+    fn category(&self, category: c_int) -> &'static LocaleData {
+        self.categories[category as usize - 1]
+    }
+
+    pub fn ctype(&self) -> &'static data::Ctype {
+        &self.category(LC_CTYPE).ctype
+    }
+
+    pub fn numeric(&self) -> &'static data::Numeric {
+        &self.category(LC_NUMERIC).numeric
+    }
+
+    pub fn monetary(&self) -> &'static data::Monetary {
+        &self.category(LC_MONETARY).monetary
+    }
+
+    pub fn time(&self) -> &'static data::Time {
+        &self.category(LC_TIME).time
+    }
+
+    pub fn messages(&self) -> &'static data::Messages {
+        &self.category(LC_MESSAGES).messages
+    }
+
+    /// Switches every category in `mask` to the locale called `name`, which
+    /// may also be a composite name as returned by `setlocale(LC_ALL, NULL)`.
+    /// Nothing is changed unless all of them could be found.
+    unsafe fn set(&mut self, mask: c_int, name: &[u8]) -> bool {
+        let mut new = *self;
+        if name.contains(&b'=') {
+            for part in name.split(|&b| b == b';') {
+                let eq = match part.iter().position(|&b| b == b'=') {
+                    Some(eq) => eq,
+                    None => return false,
+                };
+                let category = match CATEGORY_NAMES[1..].iter().position(|&n| n == &part[..eq]) {
+                    Some(i) => i as c_int + 1,
+                    None => return false,
+                };
+                if mask & (1 << category) != 0 {
+                    match resolve(category, &part[eq + 1..]) {
+                        Some(data) => new.categories[category as usize - 1] = data,
+                        None => return false,
+                    }
+                }
+            }
+        } else {
+            for category in LC_COLLATE..=LC_TIME {
+                if mask & (1 << category) != 0 {
+                    match resolve(category, name) {
+                        Some(data) => new.categories[category as usize - 1] = data,
+                        None => return false,
+                    }
+                }
+            }
+        }
+        *self = new;
+        true
+    }
+
+    /// The name of a category, or of the whole locale for LC_ALL
+    unsafe fn name(&self, category: c_int) -> *const c_char {
+        if category != LC_ALL {
+            return self.category(category).name;
+        }
+
+        let first = self.categories[0].name;
+        if self.categories.iter().all(|data| data.name == first) {
+            return first;
+        }
+
+        let mut len = 0;
+        let mut push = |bytes: &[u8]| {
+            COMPOSITE_NAME[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        };
+        for category in LC_COLLATE..=LC_TIME {
+            if category != LC_COLLATE {
+                push(b";");
+            }
+            push(CATEGORY_NAMES[category as usize]);
+            push(b"=");
+            push(CStr::from_ptr(self.category(category).name).to_bytes());
+        }
+        push(b"\0");
+        COMPOSITE_NAME.as_ptr() as *const c_char
+    }
+}
+
+static mut GLOBAL: __locale_struct = __locale_struct {
+    categories: [&data::C_LOCALE; 6],
 };
+static mut LCONV: lconv = lconv {
+    currency_symbol: ptr::null(),
+    decimal_point: ptr::null(),
+    frac_digits: 0,
+    grouping: ptr::null(),
+    int_curr_symbol: ptr::null(),
+    int_frac_digits: 0,
+    mon_decimal_point: ptr::null(),
+    mon_grouping: ptr::null(),
+    mon_thousands_sep: ptr::null(),
+    negative_sign: ptr::null(),
+    n_cs_precedes: 0,
+    n_sep_by_space: 0,
+    n_sign_posn: 0,
+    positive_sign: ptr::null(),
+    p_cs_precedes: 0,
+    p_sep_by_space: 0,
+    p_sign_posn: 0,
+    thousands_sep: ptr::null(),
+};
+/// Where `setlocale(LC_ALL, NULL)` writes a composite name. It is per thread,
+/// as threads with different locales may ask at the same time.
+#[thread_local]
+static mut COMPOSITE_NAME: [u8; COMPOSITE_MAX] = [0; COMPOSITE_MAX];
+/// Room for "LC_MESSAGES=name;" for every category
+const COMPOSITE_MAX: usize = 6 * (b"LC_MESSAGES=;".len() + data::NAME_MAX);
+
+/// The locale set by `uselocale`, or null when using the global locale
+#[thread_local]
+static mut THREAD_LOCALE: locale_t = ptr::null_mut();
+
+/// The locale of the calling thread
+pub fn current() -> &'static __locale_struct {
+    unsafe {
+        if THREAD_LOCALE.is_null() {
+            &GLOBAL
+        } else {
+            &*THREAD_LOCALE
+        }
+    }
+}
+
+/// The locale object behind a `locale_t`, which may be LC_GLOBAL_LOCALE
+pub unsafe fn get(locobj: locale_t) -> &'static __locale_struct {
+    if locobj == GLOBAL_LOCALE {
+        &GLOBAL
+    } else {
+        &*locobj
+    }
+}
+
+/// Finds the locale data for `category`, where an empty name means to look
+/// at the environment
+unsafe fn resolve(category: c_int, name: &[u8]) -> Option<&'static LocaleData> {
+    if !name.is_empty() {
+        return data::find(name);
+    }
+
+    let mut category_var = CATEGORY_NAMES[category as usize].to_vec();
+    category_var.push(0);
+    for var in &[&b"LC_ALL\0"[..], &category_var[..], &b"LANG\0"[..]] {
+        let value = stdlib::getenv(var.as_ptr() as *const c_char);
+        if !value.is_null() && *value != 0 {
+            return data::find(CStr::from_ptr(value).to_bytes());
+        }
+    }
+    Some(&data::C_LOCALE)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn duplocale(locobj: locale_t) -> locale_t {
+    let locale = if locobj == GLOBAL_LOCALE {
+        GLOBAL
+    } else if locobj.is_null() {
+        platform::errno = EINVAL;
+        return ptr::null_mut();
+    } else {
+        *locobj
+    };
+    Box::into_raw(Box::new(locale))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn freelocale(locobj: locale_t) {
+    if !locobj.is_null() && locobj != GLOBAL_LOCALE {
+        drop(Box::from_raw(locobj));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn localeconv() -> *mut lconv {
+    let locale = current();
+    let numeric = locale.numeric();
+    let monetary = locale.monetary();
+    LCONV = lconv {
+        currency_symbol: monetary.currency_symbol,
+        decimal_point: numeric.decimal_point,
+        frac_digits: monetary.frac_digits,
+        grouping: numeric.grouping,
+        int_curr_symbol: monetary.int_curr_symbol,
+        int_frac_digits: monetary.int_frac_digits,
+        mon_decimal_point: monetary.mon_decimal_point,
+        mon_grouping: monetary.mon_grouping,
+        mon_thousands_sep: monetary.mon_thousands_sep,
+        negative_sign: monetary.negative_sign,
+        n_cs_precedes: monetary.n_cs_precedes,
+        n_sep_by_space: monetary.n_sep_by_space,
+        n_sign_posn: monetary.n_sign_posn,
+        positive_sign: monetary.positive_sign,
+        p_cs_precedes: monetary.p_cs_precedes,
+        p_sep_by_space: monetary.p_sep_by_space,
+        p_sign_posn: monetary.p_sign_posn,
+        thousands_sep: numeric.thousands_sep,
+    };
+    &mut LCONV
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn newlocale(
+    category_mask: c_int,
+    locale: *const c_char,
+    base: locale_t,
+) -> locale_t {
+    if locale.is_null() || category_mask & !LC_ALL_MASK != 0 {
+        platform::errno = EINVAL;
+        return ptr::null_mut();
+    }
+
+    let mut new = if base.is_null() {
+        __locale_struct::c()
+    } else if base == GLOBAL_LOCALE {
+        GLOBAL
+    } else {
+        *base
+    };
+    if !new.set(category_mask, CStr::from_ptr(locale).to_bytes()) {
+        platform::errno = ENOENT;
+        return ptr::null_mut();
+    }
+
+    // The base object may be reused for the result
+    if base.is_null() || base == GLOBAL_LOCALE {
+        Box::into_raw(Box::new(new))
+    } else {
+        *base = new;
+        base
+    }
+}
 
 #[no_mangle]
-pub extern "C" fn localeconv() -> *const lconv {
-    &CURRENT_LOCALE as *const _
+pub unsafe extern "C" fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char {
+    if category < LC_ALL || category > LC_TIME {
+        platform::errno = EINVAL;
+        return ptr::null_mut();
+    }
+
+    if !locale.is_null() {
+        let mask = if category == LC_ALL {
+            LC_ALL_MASK
+        } else {
+            1 << category
+        };
+        if !GLOBAL.set(mask, CStr::from_ptr(locale).to_bytes()) {
+            return ptr::null_mut();
+        }
+    }
+    GLOBAL.name(category) as *mut c_char
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn setlocale(_option: c_int, val: *const c_char) -> *mut c_char {
-    if val.is_null() {
-        return C_LOCALE.as_mut_ptr() as *mut c_char;
+pub unsafe extern "C" fn uselocale(newloc: locale_t) -> locale_t {
+    let old = if THREAD_LOCALE.is_null() {
+        GLOBAL_LOCALE
+    } else {
+        THREAD_LOCALE
+    };
+    if newloc == GLOBAL_LOCALE {
+        THREAD_LOCALE = ptr::null_mut();
+    } else if !newloc.is_null() {
+        THREAD_LOCALE = newloc;
     }
-    // TODO actually implement
-    ptr::null_mut()
+    old
 }
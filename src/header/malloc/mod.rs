@@ -0,0 +1,118 @@
+//! malloc.h implementation, following the GNU extensions for inspecting and
+//! tuning the heap
+
+use core::fmt::Write;
+
+use crate::platform::{self, types::*};
+
+pub const M_TRIM_THRESHOLD: c_int = -1;
+pub const M_TOP_PAD: c_int = -2;
+pub const M_MMAP_THRESHOLD: c_int = -3;
+pub const M_MMAP_MAX: c_int = -4;
+pub const M_CHECK_ACTION: c_int = -5;
+pub const M_PERTURB: c_int = -6;
+pub const M_ARENA_TEST: c_int = -7;
+pub const M_ARENA_MAX: c_int = -8;
+
+// Extensions only understood by the hardened_malloc feature
+
+/// Blocks of at least this many bytes end in a guard page. -1 turns guard
+/// pages off.
+pub const M_GUARD_THRESHOLD: c_int = -100;
+/// How many bytes of freed blocks are kept from reuse. 0 reuses them right
+/// away, and -1 keeps as many blocks as fit in the quarantine.
+pub const M_QUARANTINE_MAX: c_int = -101;
+
+#[repr(C)]
+pub struct mallinfo {
+    /// Bytes obtained from the system, except for mapped blocks
+    pub arena: c_int,
+    /// Free chunks
+    pub ordblks: c_int,
+    pub smblks: c_int,
+    /// Blocks in mappings of their own
+    pub hblks: c_int,
+    /// Bytes in mappings of their own
+    pub hblkhd: c_int,
+    /// Most bytes ever obtained from the system
+    pub usmblks: c_int,
+    pub fsmblks: c_int,
+    /// Bytes in use
+    pub uordblks: c_int,
+    /// Bytes free
+    pub fordblks: c_int,
+    /// Bytes malloc_trim could give back
+    pub keepcost: c_int,
+}
+
+/// Like mallinfo, but with fields that don't overflow past 2 GiB
+#[repr(C)]
+pub struct mallinfo2 {
+    pub arena: size_t,
+    pub ordblks: size_t,
+    pub smblks: size_t,
+    pub hblks: size_t,
+    pub hblkhd: size_t,
+    pub usmblks: size_t,
+    pub fsmblks: size_t,
+    pub uordblks: size_t,
+    pub fordblks: size_t,
+    pub keepcost: size_t,
+}
+
+/// Obsolete, as the fields wrap around on a large heap
+#[no_mangle]
+pub unsafe extern "C" fn mallinfo() -> mallinfo {
+    let info = mallinfo2();
+    mallinfo {
+        arena: info.arena as c_int,
+        ordblks: info.ordblks as c_int,
+        smblks: info.smblks as c_int,
+        hblks: info.hblks as c_int,
+        hblkhd: info.hblkhd as c_int,
+        usmblks: info.usmblks as c_int,
+        fsmblks: info.fsmblks as c_int,
+        uordblks: info.uordblks as c_int,
+        fordblks: info.fordblks as c_int,
+        keepcost: info.keepcost as c_int,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mallinfo2() -> mallinfo2 {
+    platform::heap_info()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn malloc_stats() {
+    let info = mallinfo2();
+    let _ = write!(
+        platform::FileWriter(2),
+        "max system bytes = {:>10}\nsystem bytes     = {:>10}\nin use bytes     = {:>10}\n",
+        info.usmblks,
+        info.arena + info.hblkhd,
+        info.uordblks,
+    );
+}
+
+/// Returns 1 if any memory went back to the system, and 0 otherwise
+#[no_mangle]
+pub unsafe extern "C" fn malloc_trim(pad: size_t) -> c_int {
+    platform::heap_trim(pad)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn malloc_usable_size(ptr: *mut c_void) -> size_t {
+    if ptr.is_null() {
+        0
+    } else {
+        platform::usable_size(ptr)
+    }
+}
+
+/// Returns 1 if the parameter was changed, and 0 if it is not supported or
+/// the value is out of range
+#[no_mangle]
+pub unsafe extern "C" fn mallopt(param: c_int, value: c_int) -> c_int {
+    platform::set_heap_param(param, value)
+}
@@ -1,5 +1,6 @@
 pub mod _aio;
 pub mod _fenv;
+pub mod _fortify;
 pub mod arpa_inet;
 pub mod assert;
 pub mod ctype;
@@ -11,23 +12,32 @@ pub mod errno;
 pub mod fcntl;
 pub mod float;
 pub mod fnmatch;
+pub mod ftw;
 pub mod getopt;
+pub mod glob;
 pub mod grp;
+pub mod iconv;
 pub mod inttypes;
+pub mod langinfo;
 pub mod libgen;
 pub mod limits;
 pub mod locale;
+pub mod malloc;
 pub mod netdb;
 pub mod netinet_in;
 pub mod netinet_ip;
 pub mod netinet_tcp;
 pub mod poll;
+pub mod pthread;
 pub mod pwd;
 pub mod regex;
+pub mod sched;
+pub mod search;
 pub mod semaphore;
 pub mod setjmp;
 pub mod sgtty;
 pub mod signal;
+pub mod spawn;
 pub mod stdio;
 pub mod stdlib;
 pub mod string;
@@ -46,7 +56,6 @@ pub mod sys_statvfs;
 pub mod sys_time;
 pub mod sys_timeb;
 //pub mod sys_times;
-pub mod _wctype;
 pub mod arch_aarch64_user;
 pub mod arch_x64_user;
 pub mod sys_procfs;
@@ -57,8 +66,11 @@ pub mod sys_un;
 pub mod sys_utsname;
 pub mod sys_wait;
 pub mod termios;
+pub mod threads;
 pub mod time;
+pub mod uchar;
 pub mod unistd;
 pub mod utime;
 pub mod wchar;
 pub mod wctype;
+pub mod wordexp;
@@ -5,8 +5,8 @@ use core::{mem, slice};
 use crate::{
     fs::File,
     header::sys_epoll::{
-        epoll_create1, epoll_ctl, epoll_data, epoll_event, epoll_wait, EPOLLERR, EPOLLHUP, EPOLLIN,
-        EPOLLNVAL, EPOLLOUT, EPOLLPRI, EPOLL_CLOEXEC, EPOLL_CTL_ADD,
+        epoll_create1, epoll_ctl, epoll_data, epoll_event, epoll_wait_once, EPOLLERR, EPOLLHUP,
+        EPOLLIN, EPOLLNVAL, EPOLLOUT, EPOLLPRI, EPOLL_CLOEXEC, EPOLL_CTL_ADD,
     },
     platform::types::*,
 };
@@ -68,7 +68,7 @@ pub fn poll_epoll(fds: &mut [pollfd], timeout: c_int) -> c_int {
     }
 
     let mut events: [epoll_event; 32] = unsafe { mem::zeroed() };
-    let res = epoll_wait(*ep, events.as_mut_ptr(), events.len() as c_int, timeout);
+    let res = epoll_wait_once(*ep, events.as_mut_ptr(), events.len() as c_int, timeout);
     if res < 0 {
         return -1;
     }
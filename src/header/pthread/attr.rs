@@ -0,0 +1,228 @@
+//! Thread attributes
+
+use core::{mem, ptr};
+
+use super::*;
+
+#[derive(Clone, Copy)]
+pub(crate) struct RlctAttr {
+    pub detachstate: c_uchar,
+    pub inheritsched: c_uchar,
+    pub schedpolicy: c_uchar,
+    pub scope: c_uchar,
+    pub guardsize: size_t,
+    pub stacksize: size_t,
+    /// Lowest address of a caller provided stack, or null
+    pub stack: *mut c_void,
+    pub param: sched_param,
+}
+
+impl Default for RlctAttr {
+    fn default() -> Self {
+        Self {
+            detachstate: PTHREAD_CREATE_JOINABLE as c_uchar,
+            inheritsched: PTHREAD_INHERIT_SCHED as c_uchar,
+            schedpolicy: SCHED_OTHER as c_uchar,
+            scope: PTHREAD_SCOPE_SYSTEM as c_uchar,
+            guardsize: PAGE_SIZE,
+            stacksize: DEFAULT_STACK_SIZE,
+            stack: ptr::null_mut(),
+            param: sched_param::default(),
+        }
+    }
+}
+
+// Fails to compile if the C type is too small
+const _: [(); 0] = [(); (mem::size_of::<RlctAttr>() > mem::size_of::<pthread_attr_t>()) as usize];
+
+unsafe fn attr<'a>(attr: *const pthread_attr_t) -> &'a RlctAttr {
+    &*(attr as *const RlctAttr)
+}
+
+unsafe fn attr_mut<'a>(attr: *mut pthread_attr_t) -> &'a mut RlctAttr {
+    &mut *(attr as *mut RlctAttr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_destroy(attr: *mut pthread_attr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getdetachstate(
+    attr: *const pthread_attr_t,
+    detachstate: *mut c_int,
+) -> c_int {
+    *detachstate = self::attr(attr).detachstate.into();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getguardsize(
+    attr: *const pthread_attr_t,
+    guardsize: *mut size_t,
+) -> c_int {
+    *guardsize = self::attr(attr).guardsize;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getinheritsched(
+    attr: *const pthread_attr_t,
+    inheritsched: *mut c_int,
+) -> c_int {
+    *inheritsched = self::attr(attr).inheritsched.into();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getschedparam(
+    attr: *const pthread_attr_t,
+    param: *mut sched_param,
+) -> c_int {
+    *param = self::attr(attr).param;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getschedpolicy(
+    attr: *const pthread_attr_t,
+    policy: *mut c_int,
+) -> c_int {
+    *policy = self::attr(attr).schedpolicy.into();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getscope(
+    attr: *const pthread_attr_t,
+    scope: *mut c_int,
+) -> c_int {
+    *scope = self::attr(attr).scope.into();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getstack(
+    attr: *const pthread_attr_t,
+    stackaddr: *mut *mut c_void,
+    stacksize: *mut size_t,
+) -> c_int {
+    *stackaddr = self::attr(attr).stack;
+    *stacksize = self::attr(attr).stacksize;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_getstacksize(
+    attr: *const pthread_attr_t,
+    stacksize: *mut size_t,
+) -> c_int {
+    *stacksize = self::attr(attr).stacksize;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_init(attr: *mut pthread_attr_t) -> c_int {
+    ptr::write(attr as *mut RlctAttr, RlctAttr::default());
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setdetachstate(
+    attr: *mut pthread_attr_t,
+    detachstate: c_int,
+) -> c_int {
+    match detachstate {
+        PTHREAD_CREATE_JOINABLE | PTHREAD_CREATE_DETACHED => {
+            attr_mut(attr).detachstate = detachstate as c_uchar;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setguardsize(
+    attr: *mut pthread_attr_t,
+    guardsize: size_t,
+) -> c_int {
+    attr_mut(attr).guardsize = guardsize;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setinheritsched(
+    attr: *mut pthread_attr_t,
+    inheritsched: c_int,
+) -> c_int {
+    match inheritsched {
+        PTHREAD_INHERIT_SCHED | PTHREAD_EXPLICIT_SCHED => {
+            attr_mut(attr).inheritsched = inheritsched as c_uchar;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setschedparam(
+    attr: *mut pthread_attr_t,
+    param: *const sched_param,
+) -> c_int {
+    attr_mut(attr).param = *param;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setschedpolicy(
+    attr: *mut pthread_attr_t,
+    policy: c_int,
+) -> c_int {
+    match policy {
+        SCHED_OTHER | SCHED_FIFO | SCHED_RR => {
+            attr_mut(attr).schedpolicy = policy as c_uchar;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setscope(attr: *mut pthread_attr_t, scope: c_int) -> c_int {
+    match scope {
+        PTHREAD_SCOPE_SYSTEM => {
+            attr_mut(attr).scope = scope as c_uchar;
+            0
+        }
+        // Every thread is scheduled by the kernel
+        PTHREAD_SCOPE_PROCESS => ENOTSUP,
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setstack(
+    attr: *mut pthread_attr_t,
+    stackaddr: *mut c_void,
+    stacksize: size_t,
+) -> c_int {
+    if stacksize < PTHREAD_STACK_MIN {
+        return EINVAL;
+    }
+    attr_mut(attr).stack = stackaddr;
+    attr_mut(attr).stacksize = stacksize;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_attr_setstacksize(
+    attr: *mut pthread_attr_t,
+    stacksize: size_t,
+) -> c_int {
+    if stacksize < PTHREAD_STACK_MIN {
+        return EINVAL;
+    }
+    attr_mut(attr).stacksize = stacksize;
+    0
+}
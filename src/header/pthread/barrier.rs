@@ -0,0 +1,82 @@
+//! Barriers and their attributes
+
+use core::{mem, ptr};
+
+use super::*;
+use crate::sync::Barrier;
+
+#[derive(Clone, Copy)]
+pub(crate) struct RlctBarrierAttr {
+    pshared: c_int,
+}
+
+// Fail to compile if the C types are too small
+const _: [(); 0] = [(); (mem::size_of::<Barrier>() > mem::size_of::<pthread_barrier_t>()) as usize];
+const _: [(); 0] =
+    [(); (mem::size_of::<RlctBarrierAttr>() > mem::size_of::<pthread_barrierattr_t>()) as usize];
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_destroy(barrier: *mut pthread_barrier_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_init(
+    barrier: *mut pthread_barrier_t,
+    attr: *const pthread_barrierattr_t,
+    count: c_uint,
+) -> c_int {
+    if count == 0 {
+        return EINVAL;
+    }
+    ptr::write(barrier as *mut Barrier, Barrier::new(count));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrier_wait(barrier: *mut pthread_barrier_t) -> c_int {
+    if (*(barrier as *const Barrier)).wait() {
+        PTHREAD_BARRIER_SERIAL_THREAD
+    } else {
+        0
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrierattr_destroy(attr: *mut pthread_barrierattr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrierattr_getpshared(
+    attr: *const pthread_barrierattr_t,
+    pshared: *mut c_int,
+) -> c_int {
+    *pshared = (*(attr as *const RlctBarrierAttr)).pshared;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrierattr_init(attr: *mut pthread_barrierattr_t) -> c_int {
+    ptr::write(
+        attr as *mut RlctBarrierAttr,
+        RlctBarrierAttr {
+            pshared: PTHREAD_PROCESS_PRIVATE,
+        },
+    );
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_barrierattr_setpshared(
+    attr: *mut pthread_barrierattr_t,
+    pshared: c_int,
+) -> c_int {
+    match pshared {
+        PTHREAD_PROCESS_PRIVATE | PTHREAD_PROCESS_SHARED => {
+            (*(attr as *mut RlctBarrierAttr)).pshared = pshared;
+            0
+        }
+        _ => EINVAL,
+    }
+}
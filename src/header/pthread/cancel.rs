@@ -0,0 +1,361 @@
+//! Thread cancellation and cleanup handlers
+//!
+//! A cancellation request sets a flag in the target thread, and sends it
+//! SIGCANCEL if it accepts asynchronous cancellation or is in a
+//! cancellation point. Cancellation points act on a request found before
+//! their system call, and on one interrupting it, but not after it returned
+//! so that nothing it did is lost.
+//!
+//! On x86_64 Linux, their system calls go through `__relibc_syscall_cp`,
+//! which checks for a request right before the syscall instruction. The
+//! signal handler makes a thread interrupted anywhere from that check up to
+//! the syscall instruction, or in it with EINTR, act on the request once
+//! the handler returns, so that no request is missed in between. Elsewhere
+//! the signal merely makes the system call fail with EINTR, and a request
+//! arriving between the check and the system call waits for the next
+//! cancellation point.
+
+use super::*;
+use crate::header::signal::{self, sigaction, SA_SIGINFO, SIGCANCEL};
+use core::mem;
+
+pub const PTHREAD_CANCEL_ENABLE: c_int = 0;
+pub const PTHREAD_CANCEL_DISABLE: c_int = 1;
+
+pub const PTHREAD_CANCEL_DEFERRED: c_int = 0;
+pub const PTHREAD_CANCEL_ASYNCHRONOUS: c_int = 1;
+
+/// Defined as PTHREAD_CANCELED in bits/pthread.h
+const CANCELED: *mut c_void = !0 as *mut c_void;
+
+// Bits of Pthread::cancel
+pub(super) const CANCEL_DISABLED: c_int = 1;
+const CANCEL_ASYNC: c_int = 2;
+const CANCEL_PENDING: c_int = 4;
+/// Set while in a cancellation point
+const CANCEL_POINT: c_int = 8;
+
+/// A cleanup handler, linked into a list by pthread_cleanup_push. It lives
+/// on the stack of the thread, see bits/pthread.h.
+#[repr(C)]
+pub struct __pthread_cleanup {
+    pub routine: extern "C" fn(arg: *mut c_void),
+    pub arg: *mut c_void,
+    pub prev: *mut __pthread_cleanup,
+}
+
+fn should_act(state: c_int) -> bool {
+    state & (CANCEL_DISABLED | CANCEL_PENDING) == CANCEL_PENDING
+}
+
+/// Exits the calling thread after a cancellation request, which runs the
+/// cleanup handlers
+unsafe fn act() -> ! {
+    exit_current_thread(CANCELED)
+}
+
+// __relibc_syscall_cp(cancel, nr, a, b, c, d, e, f) issues system call nr
+// with arguments a to f, unless Pthread::cancel at `cancel` says the thread
+// is in a cancellation point with a request, in which case it goes to
+// __relibc_cp_cancel instead. The mask and value compared against are
+// CANCEL_DISABLED | CANCEL_PENDING | CANCEL_POINT and
+// CANCEL_PENDING | CANCEL_POINT.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+global_asm!(
+    "
+    .text
+    .global __relibc_syscall_cp
+    .hidden __relibc_syscall_cp
+    .type __relibc_syscall_cp, @function
+__relibc_syscall_cp:
+    mov %rdi, %r11
+    mov %rsi, %rax
+    mov %rdx, %rdi
+    mov %rcx, %rsi
+    mov %r8, %rdx
+    mov %r9, %r10
+    mov 8(%rsp), %r8
+    mov 16(%rsp), %r9
+
+    .global __relibc_cp_begin
+    .hidden __relibc_cp_begin
+__relibc_cp_begin:
+    mov (%r11), %ecx
+    and $13, %ecx
+    cmp $12, %ecx
+    je __relibc_cp_cancel
+    syscall
+
+    .global __relibc_cp_end
+    .hidden __relibc_cp_end
+__relibc_cp_end:
+    ret
+
+    .global __relibc_cp_cancel
+    .hidden __relibc_cp_cancel
+__relibc_cp_cancel:
+    jmp __relibc_cp_act@PLT
+    .size __relibc_syscall_cp, . - __relibc_syscall_cp
+    "
+);
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" {
+    fn __relibc_syscall_cp(
+        cancel: *const c_int,
+        nr: usize,
+        a: usize,
+        b: usize,
+        c: usize,
+        d: usize,
+        e: usize,
+        f: usize,
+    ) -> usize;
+    static __relibc_cp_begin: u8;
+    static __relibc_cp_end: u8;
+    static __relibc_cp_cancel: u8;
+}
+
+/// Where __relibc_syscall_cp goes on a cancellation request
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[no_mangle]
+unsafe extern "C" fn __relibc_cp_act() -> ! {
+    act()
+}
+
+/// Set by `init`. Before that the thread may not even have TLS, as in the
+/// dynamic linker, let alone a Pthread.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+static mut READY: bool = false;
+
+/// Issues system call `nr`, returning the raw result. Within a cancellation
+/// point, a cancellation request found before the system call or
+/// interrupting it is acted on.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub(crate) unsafe fn syscall_cp(nr: usize, args: [usize; 6]) -> usize {
+    // Nothing is cancelled before the thread is set up
+    let never: c_int = 0;
+    let cancel = if !READY || SELF.is_null() {
+        &never
+    } else {
+        &(*SELF).cancel as *const AtomicI32 as *const c_int
+    };
+    __relibc_syscall_cp(
+        cancel, nr, args[0], args[1], args[2], args[3], args[4], args[5],
+    )
+}
+
+// Offsets of the saved rax and rip within the ucontext_t passed to signal
+// handlers, in uc_mcontext.gregs after uc_flags, uc_link and uc_stack
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const UC_RAX: usize = 40 + 13 * 8;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const UC_RIP: usize = 40 + 16 * 8;
+
+/// Makes a thread interrupted in __relibc_syscall_cp before its system call
+/// did anything go to __relibc_cp_cancel once the signal handler returns
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+unsafe fn redirect_syscall_cp(context: *mut c_void) {
+    let rip = (context as *mut u8).add(UC_RIP) as *mut usize;
+    let rax = (context as *const u8).add(UC_RAX) as *const usize;
+    let begin = &__relibc_cp_begin as *const u8 as usize;
+    let end = &__relibc_cp_end as *const u8 as usize;
+    if (begin..end).contains(&*rip) || *rip == end && *rax == -EINTR as usize {
+        *rip = &__relibc_cp_cancel as *const u8 as usize;
+    }
+}
+
+extern "C" fn cancel_handler(_sig: c_int, _info: *mut c_void, _context: *mut c_void) {
+    unsafe {
+        if SELF.is_null() {
+            return;
+        }
+        let state = (*SELF).cancel.load(SeqCst);
+        if !should_act(state) {
+            return;
+        }
+        if state & CANCEL_ASYNC != 0 {
+            act();
+        }
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        {
+            if state & CANCEL_POINT != 0 {
+                redirect_syscall_cp(_context);
+            }
+        }
+    }
+}
+
+/// Installs the handler of SIGCANCEL
+pub(super) unsafe fn init() {
+    let act = sigaction {
+        sa_handler: Some(mem::transmute(
+            cancel_handler as extern "C" fn(c_int, *mut c_void, *mut c_void),
+        )),
+        // Cancellation points blocked in a system call get EINTR. Others
+        // could too, but only threads in a cancellation point or accepting
+        // asynchronous cancellation are sent the signal.
+        sa_flags: SA_SIGINFO as c_ulong,
+        sa_restorer: None,
+        sa_mask: 0,
+    };
+    signal::set_action(SIGCANCEL as c_int, &act, ptr::null_mut());
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    {
+        READY = true;
+    }
+}
+
+/// Runs and removes the cleanup handlers of the current thread, most
+/// recently pushed first
+pub(super) unsafe fn run_cleanup_handlers() {
+    while !(*SELF).cleanup.is_null() {
+        let cleanup = (*SELF).cleanup;
+        (*SELF).cleanup = (*cleanup).prev;
+        ((*cleanup).routine)((*cleanup).arg);
+    }
+}
+
+/// Marks the current thread as being in a cancellation point, acting on a
+/// pending cancellation request. Returns what to pass to
+/// `leave_cancellation_point`.
+pub(crate) fn enter_cancellation_point() -> c_int {
+    unsafe {
+        if SELF.is_null() {
+            return CANCEL_POINT;
+        }
+        // A request sent before this sees the flag is found here, and one
+        // sent after interrupts the system call
+        let state = (*SELF).cancel.fetch_or(CANCEL_POINT, SeqCst);
+        if should_act(state) {
+            act();
+        }
+        state
+    }
+}
+pub(crate) fn leave_cancellation_point(state: c_int) {
+    unsafe {
+        // Nested cancellation points leave the flag to the outermost one
+        if state & CANCEL_POINT == 0 {
+            (*SELF).cancel.fetch_and(!CANCEL_POINT, SeqCst);
+        }
+    }
+}
+/// Runs `f` as a cancellation point. `f` reports errors through errno, and
+/// fails with EINTR only if it was interrupted before doing anything, like
+/// a single system call.
+pub(crate) fn cancellation_point<T, F: FnOnce() -> T>(f: F) -> T {
+    let state = enter_cancellation_point();
+    unsafe {
+        // Cleared so that an EINTR left by an earlier call is not mistaken
+        // for one of f
+        let errno = mem::replace(&mut platform::errno, 0);
+        let ret = f();
+        leave_cancellation_point(state);
+        match platform::errno {
+            0 => platform::errno = errno,
+            EINTR if !SELF.is_null() => pthread_testcancel(),
+            _ => (),
+        }
+        ret
+    }
+}
+
+/// Runs `f` with a cleanup handler calling `routine(arg)` pushed, so that
+/// it runs if the thread is cancelled or exits in the meantime
+pub(crate) fn with_cleanup<T, F: FnOnce() -> T>(
+    routine: extern "C" fn(arg: *mut c_void),
+    arg: *mut c_void,
+    f: F,
+) -> T {
+    let mut cleanup = __pthread_cleanup {
+        routine,
+        arg,
+        prev: ptr::null_mut(),
+    };
+    unsafe {
+        __pthread_cleanup_push(&mut cleanup);
+        let ret = f();
+        __pthread_cleanup_pop(&mut cleanup, 0);
+        ret
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cancel(thread: pthread_t) -> c_int {
+    let pthread = &*(thread as *const Pthread);
+    let state = pthread.cancel.fetch_or(CANCEL_PENDING, SeqCst);
+    // Any other thread finds the request itself at its next cancellation
+    // point, or once it enables cancellation. If the thread has exited
+    // already, the signal goes nowhere.
+    let interruptible = state & (CANCEL_ASYNC | CANCEL_POINT) != 0;
+    if state & (CANCEL_DISABLED | CANCEL_PENDING) == 0 && interruptible {
+        Sys::rlct_kill(pthread.os_tid(), SIGCANCEL as c_int);
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setcancelstate(state: c_int, oldstate: *mut c_int) -> c_int {
+    let old = match state {
+        PTHREAD_CANCEL_ENABLE => (*SELF).cancel.fetch_and(!CANCEL_DISABLED, SeqCst),
+        PTHREAD_CANCEL_DISABLE => (*SELF).cancel.fetch_or(CANCEL_DISABLED, SeqCst),
+        _ => return EINVAL,
+    };
+    if !oldstate.is_null() {
+        *oldstate = if old & CANCEL_DISABLED != 0 {
+            PTHREAD_CANCEL_DISABLE
+        } else {
+            PTHREAD_CANCEL_ENABLE
+        };
+    }
+    let new = (*SELF).cancel.load(SeqCst);
+    if should_act(new) && new & CANCEL_ASYNC != 0 {
+        act();
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setcanceltype(type_: c_int, oldtype: *mut c_int) -> c_int {
+    let old = match type_ {
+        PTHREAD_CANCEL_DEFERRED => (*SELF).cancel.fetch_and(!CANCEL_ASYNC, SeqCst),
+        PTHREAD_CANCEL_ASYNCHRONOUS => (*SELF).cancel.fetch_or(CANCEL_ASYNC, SeqCst),
+        _ => return EINVAL,
+    };
+    if !oldtype.is_null() {
+        *oldtype = if old & CANCEL_ASYNC != 0 {
+            PTHREAD_CANCEL_ASYNCHRONOUS
+        } else {
+            PTHREAD_CANCEL_DEFERRED
+        };
+    }
+    let new = (*SELF).cancel.load(SeqCst);
+    if should_act(new) && new & CANCEL_ASYNC != 0 {
+        act();
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_testcancel() {
+    if should_act((*SELF).cancel.load(SeqCst)) {
+        act();
+    }
+}
+
+/// Called by the pthread_cleanup_push macro
+#[no_mangle]
+pub unsafe extern "C" fn __pthread_cleanup_push(cleanup: *mut __pthread_cleanup) {
+    (*cleanup).prev = (*SELF).cleanup;
+    (*SELF).cleanup = cleanup;
+}
+
+/// Called by the pthread_cleanup_pop macro
+#[no_mangle]
+pub unsafe extern "C" fn __pthread_cleanup_pop(cleanup: *mut __pthread_cleanup, execute: c_int) {
+    (*SELF).cleanup = (*cleanup).prev;
+    if execute != 0 {
+        ((*cleanup).routine)((*cleanup).arg);
+    }
+}
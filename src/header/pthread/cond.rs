@@ -0,0 +1,199 @@
+//! Condition variables and their attributes
+
+use core::{cell::Cell, mem, ptr};
+
+use super::*;
+use crate::sync::Condvar;
+
+#[derive(Clone, Copy)]
+pub(crate) struct RlctCondAttr {
+    clock: clockid_t,
+    pshared: c_int,
+}
+
+impl Default for RlctCondAttr {
+    fn default() -> Self {
+        Self {
+            clock: CLOCK_REALTIME,
+            pshared: PTHREAD_PROCESS_PRIVATE,
+        }
+    }
+}
+
+// Fail to compile if the C types are too small
+const _: [(); 0] = [(); (mem::size_of::<Condvar>() > mem::size_of::<pthread_cond_t>()) as usize];
+const _: [(); 0] =
+    [(); (mem::size_of::<RlctCondAttr>() > mem::size_of::<pthread_condattr_t>()) as usize];
+
+unsafe fn cond<'a>(cond: *mut pthread_cond_t) -> &'a Condvar {
+    &*(cond as *const Condvar)
+}
+
+/// What a waiter cancelled while blocked needs to clean up
+struct Canceled {
+    cond: *mut pthread_cond_t,
+    mutex: *const RlctMutex,
+}
+
+extern "C" fn wait_canceled(arg: *mut c_void) {
+    unsafe {
+        let canceled = &*(arg as *const Canceled);
+        // Pass on any notification this waiter may have taken, and lock the
+        // mutex again before the handlers of the caller run
+        cond(canceled.cond).notify_one();
+        (*canceled.mutex).lock();
+    }
+}
+
+unsafe fn wait(
+    cond: *mut pthread_cond_t,
+    mutex: *mut pthread_mutex_t,
+    abstime: Option<&timespec>,
+) -> c_int {
+    let mutex = mutex as *const RlctMutex;
+    let mut canceled = Canceled { cond, mutex };
+
+    // The wait is only cancellable while the mutex is unlocked
+    let point = Cell::new(0);
+    let ret = with_cleanup(
+        wait_canceled,
+        &mut canceled as *mut Canceled as *mut c_void,
+        || {
+            self::cond(cond).wait_with(
+                || match (*mutex).unlock() {
+                    0 => {
+                        point.set(enter_cancellation_point());
+                        Ok(())
+                    }
+                    err => Err(err),
+                },
+                // The mutex is reacquired even on timeout. A waiter cancelled
+                // here has wait_canceled do it, which also passes on any
+                // notification it took.
+                || {
+                    leave_cancellation_point(point.get());
+                    pthread_testcancel();
+                    (*mutex).lock();
+                },
+                abstime,
+            )
+        },
+    );
+    match ret {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_broadcast(cond: *mut pthread_cond_t) -> c_int {
+    self::cond(cond).notify_all();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_destroy(cond: *mut pthread_cond_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_init(
+    cond: *mut pthread_cond_t,
+    attr: *const pthread_condattr_t,
+) -> c_int {
+    let attr = if attr.is_null() {
+        RlctCondAttr::default()
+    } else {
+        *(attr as *const RlctCondAttr)
+    };
+    ptr::write(cond as *mut Condvar, Condvar::with_clock(attr.clock));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_signal(cond: *mut pthread_cond_t) -> c_int {
+    self::cond(cond).notify_one();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_timedwait(
+    cond: *mut pthread_cond_t,
+    mutex: *mut pthread_mutex_t,
+    abstime: *const timespec,
+) -> c_int {
+    wait(cond, mutex, Some(&*abstime))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_wait(
+    cond: *mut pthread_cond_t,
+    mutex: *mut pthread_mutex_t,
+) -> c_int {
+    wait(cond, mutex, None)
+}
+
+unsafe fn condattr<'a>(attr: *const pthread_condattr_t) -> &'a RlctCondAttr {
+    &*(attr as *const RlctCondAttr)
+}
+
+unsafe fn condattr_mut<'a>(attr: *mut pthread_condattr_t) -> &'a mut RlctCondAttr {
+    &mut *(attr as *mut RlctCondAttr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_destroy(attr: *mut pthread_condattr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_getclock(
+    attr: *const pthread_condattr_t,
+    clock_id: *mut clockid_t,
+) -> c_int {
+    *clock_id = condattr(attr).clock;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_getpshared(
+    attr: *const pthread_condattr_t,
+    pshared: *mut c_int,
+) -> c_int {
+    *pshared = condattr(attr).pshared;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_init(attr: *mut pthread_condattr_t) -> c_int {
+    ptr::write(attr as *mut RlctCondAttr, RlctCondAttr::default());
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_setclock(
+    attr: *mut pthread_condattr_t,
+    clock_id: clockid_t,
+) -> c_int {
+    match clock_id {
+        CLOCK_REALTIME | CLOCK_MONOTONIC => {
+            condattr_mut(attr).clock = clock_id;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_condattr_setpshared(
+    attr: *mut pthread_condattr_t,
+    pshared: c_int,
+) -> c_int {
+    match pshared {
+        PTHREAD_PROCESS_PRIVATE | PTHREAD_PROCESS_SHARED => {
+            condattr_mut(attr).pshared = pshared;
+            0
+        }
+        _ => EINVAL,
+    }
+}
@@ -0,0 +1,503 @@
+//! pthread.h implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/pthread.h.html
+
+use alloc::boxed::Box;
+use core::{
+    ptr,
+    sync::atomic::{AtomicI32, AtomicUsize, Ordering::SeqCst},
+};
+
+use crate::{
+    header::{
+        errno::*,
+        sched::*,
+        signal::{sigset_t, SIG_SETMASK},
+        stdlib, sys_mman,
+        sys_resource::{rlimit, RLIMIT_STACK, RLIM_INFINITY},
+        sys_types::*,
+        time::{timespec, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    },
+    ld_so::{
+        linker::Linker,
+        tcb::{Master, Tcb},
+        PAGE_SIZE,
+    },
+    platform::{self, types::*, Pal, PalSignal, Sys},
+    sync::{AtomicLock, Mutex},
+};
+
+pub use self::{
+    attr::*, barrier::*, cancel::*, cond::*, mutex::*, once::*, rwlock::*, spin::*, tls::*,
+};
+
+pub mod attr;
+pub mod barrier;
+pub mod cancel;
+pub mod cond;
+pub mod mutex;
+pub mod once;
+pub mod rwlock;
+pub mod spin;
+mod stack;
+pub mod tls;
+
+pub const PTHREAD_BARRIER_SERIAL_THREAD: c_int = -1;
+
+pub const PTHREAD_CREATE_JOINABLE: c_int = 0;
+pub const PTHREAD_CREATE_DETACHED: c_int = 1;
+
+pub const PTHREAD_INHERIT_SCHED: c_int = 0;
+pub const PTHREAD_EXPLICIT_SCHED: c_int = 1;
+
+pub const PTHREAD_PROCESS_PRIVATE: c_int = 0;
+pub const PTHREAD_PROCESS_SHARED: c_int = 1;
+
+pub const PTHREAD_SCOPE_SYSTEM: c_int = 0;
+pub const PTHREAD_SCOPE_PROCESS: c_int = 1;
+
+pub const PTHREAD_STACK_MIN: size_t = 16384;
+
+const DEFAULT_STACK_SIZE: usize = 1024 * 1024;
+
+// States of a thread
+const RUNNING: c_int = 0;
+const DETACHED: c_int = 1;
+const EXITED: c_int = 2;
+
+/// The structure a pthread_t points to
+pub(crate) struct Pthread {
+    /// RUNNING, DETACHED or EXITED, deciding who frees the thread
+    state: AtomicI32,
+    /// Cleared by Sys::exit_thread once the thread is done with its stack
+    alive: AtomicLock,
+    /// Return value, valid once the thread is no longer alive
+    retval: *mut c_void,
+    /// Kernel thread ID, stored before pthread_create returns: by the kernel
+    /// on Linux, and by the new thread itself on Redox
+    os_tid: AtomicI32,
+    /// The stack, which the thread unmaps on exit if it was not provided
+    stack_base: *mut c_void,
+    stack_size: usize,
+    own_stack: bool,
+    guardsize: usize,
+    /// TLS and TCB allocated for this thread, if any
+    tcb: *mut Tcb,
+    /// Scheduling policy and parameters
+    sched: Mutex<(c_int, sched_param)>,
+    /// Cancellation state and type, and whether it was requested
+    cancel: AtomicI32,
+    /// The most recently pushed cleanup handler
+    cleanup: *mut __pthread_cleanup,
+}
+
+impl Pthread {
+    /// The kernel thread ID
+    pub(crate) fn os_tid(&self) -> pid_t {
+        self.os_tid.load(SeqCst)
+    }
+
+    /// Waits for the thread to exit, after which it can be freed. Fails with
+    /// EINTR if a signal handler interrupts the wait.
+    fn wait_exited(&self) -> Result<(), c_int> {
+        loop {
+            let alive = self.alive.load(SeqCst);
+            if alive == 0 {
+                return Ok(());
+            }
+            self.alive.wait_if_interruptible(alive, None)?;
+        }
+    }
+}
+
+/// Everything a new thread needs, freed by the thread once it is running
+struct StartArgs {
+    entry: extern "C" fn(arg: *mut c_void) -> *mut c_void,
+    arg: *mut c_void,
+    pthread: *mut Pthread,
+    explicit_sched: bool,
+    sigmask: sigset_t,
+    tls_len: usize,
+    masters_ptr: *mut Master,
+    masters_len: usize,
+    linker_ptr: *const Mutex<Linker>,
+    mspace: usize,
+    /// Set once the thread has stored its ID, which Redox cannot do for it
+    #[cfg(target_os = "redox")]
+    started: *const AtomicLock,
+}
+
+#[thread_local]
+static mut SELF: *mut Pthread = ptr::null_mut();
+
+/// Number of threads that have not exited. The last one to exit ends the
+/// process
+static THREADS: AtomicUsize = AtomicUsize::new(1);
+
+static CONCURRENCY: AtomicI32 = AtomicI32::new(0);
+
+/// Sets up the main thread, called before any constructors
+pub unsafe fn init() {
+    // The initial stack grows down from somewhere above this frame
+    let mut limit = rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let stack_size = if Sys::getrlimit(RLIMIT_STACK as c_int, &mut limit) == 0
+        && limit.rlim_cur != RLIM_INFINITY
+    {
+        limit.rlim_cur as usize
+    } else {
+        8 * 1024 * 1024
+    };
+    let top = (&limit as *const rlimit as usize + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+
+    SELF = Box::into_raw(Box::new(Pthread {
+        state: AtomicI32::new(RUNNING),
+        alive: AtomicLock::new(1),
+        retval: ptr::null_mut(),
+        os_tid: AtomicI32::new(Sys::gettid()),
+        stack_base: (top - stack_size) as *mut c_void,
+        stack_size,
+        own_stack: false,
+        guardsize: 0,
+        tcb: ptr::null_mut(),
+        sched: Mutex::new((SCHED_OTHER, sched_param::default())),
+        cancel: AtomicI32::new(0),
+        cleanup: ptr::null_mut(),
+    }));
+
+    cancel::init();
+}
+
+/// The current thread
+pub(crate) unsafe fn current() -> &'static Pthread {
+    &*SELF
+}
+
+/// Runs cleanup handlers and key destructors and exits the calling thread
+pub(crate) unsafe fn exit_current_thread(retval: *mut c_void) -> ! {
+    // Nothing from here on is cancelled
+    (*SELF).cancel.fetch_or(CANCEL_DISABLED, SeqCst);
+    cancel::run_cleanup_handlers();
+    tls::run_destructors();
+
+    if THREADS.fetch_sub(1, SeqCst) == 1 {
+        // Exiting the last thread exits the process
+        stdlib::exit(0);
+    }
+
+    // The stack may be given to another thread as soon as this one is no
+    // longer alive, and a signal handler must not run on it after that
+    let all: sigset_t = !0;
+    Sys::sigprocmask(SIG_SETMASK, &all, ptr::null_mut());
+
+    // Nothing here may be used once the thread is joined or, if detached,
+    // freed, so copy what is needed on the way out
+    let this = SELF;
+    let tcb = (*this).tcb;
+
+    (*this).retval = retval;
+    let (stack_base, stack_size, alive) = match (*this)
+        .state
+        .compare_exchange(RUNNING, EXITED, SeqCst, SeqCst)
+    {
+        // Whoever joins the thread disposes of the stack
+        Ok(_) => (ptr::null_mut(), 0, (*this).alive.as_mut_ptr()),
+        Err(_) => {
+            let alive = (*this).alive.as_mut_ptr();
+            if stack::cache(this) {
+                (ptr::null_mut(), 0, alive)
+            } else {
+                let (stack_base, stack_size) = stack::mapping(&*this);
+                drop(Box::from_raw(this));
+                (stack_base, stack_size, ptr::null_mut())
+            }
+        }
+    };
+
+    // Thread local variables are gone after this, including errno
+    if !tcb.is_null() {
+        (*tcb).unmap();
+    }
+    Sys::exit_thread(stack_base, stack_size, alive)
+}
+
+/// Entry point of new threads, popped off their stack by Sys::rlct_clone
+unsafe extern "C" fn new_thread_shim(args: *mut StartArgs) -> ! {
+    let args = *Box::from_raw(args);
+    let pthread = args.pthread;
+
+    // Kernel allocated TLS has no masters and is copied by the kernel
+    if !args.masters_ptr.is_null() {
+        let tcb = Tcb::new(args.tls_len).expect("failed to allocate thread TCB");
+        tcb.masters_ptr = args.masters_ptr;
+        tcb.masters_len = args.masters_len;
+        tcb.linker_ptr = args.linker_ptr;
+        tcb.mspace = args.mspace;
+        tcb.copy_masters().expect("failed to copy thread TLS");
+        tcb.activate();
+        (*pthread).tcb = tcb;
+    }
+
+    SELF = pthread;
+    #[cfg(target_os = "redox")]
+    {
+        (*pthread).os_tid.store(Sys::gettid(), SeqCst);
+        (*args.started).store(1, SeqCst);
+        (*args.started).notify_one();
+    }
+
+    if args.explicit_sched {
+        let sched = (*pthread).sched.lock();
+        Sys::sched_setscheduler(0, sched.0, &sched.1);
+    }
+
+    Sys::sigprocmask(SIG_SETMASK, &args.sigmask, ptr::null_mut());
+
+    exit_current_thread((args.entry)(args.arg))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_create(
+    thread: *mut pthread_t,
+    attr: *const pthread_attr_t,
+    start_routine: extern "C" fn(arg: *mut c_void) -> *mut c_void,
+    arg: *mut c_void,
+) -> c_int {
+    let attr = if attr.is_null() {
+        RlctAttr::default()
+    } else {
+        *(attr as *const RlctAttr)
+    };
+
+    // A stack provided by the caller has no guard
+    let (stack_base, stack_size, guardsize, own_stack) = if attr.stack.is_null() {
+        match stack::alloc(attr.stacksize, attr.guardsize) {
+            Some((stack_base, stack_size, guardsize)) => (stack_base, stack_size, guardsize, true),
+            None => return EAGAIN,
+        }
+    } else {
+        (attr.stack, attr.stacksize, 0, false)
+    };
+
+    let state = if attr.detachstate == PTHREAD_CREATE_DETACHED as c_uchar {
+        DETACHED
+    } else {
+        RUNNING
+    };
+    let pthread = Box::into_raw(Box::new(Pthread {
+        state: AtomicI32::new(state),
+        alive: AtomicLock::new(1),
+        retval: ptr::null_mut(),
+        os_tid: AtomicI32::new(0),
+        stack_base,
+        stack_size,
+        own_stack,
+        guardsize,
+        tcb: ptr::null_mut(),
+        sched: if attr.inheritsched == PTHREAD_EXPLICIT_SCHED as c_uchar {
+            Mutex::new((c_int::from(attr.schedpolicy), attr.param))
+        } else {
+            Mutex::new(*current().sched.lock())
+        },
+        cancel: AtomicI32::new(0),
+        cleanup: ptr::null_mut(),
+    }));
+
+    #[cfg(target_os = "redox")]
+    let started = AtomicLock::new(0);
+    let mut args = Box::new(StartArgs {
+        entry: start_routine,
+        arg,
+        pthread,
+        explicit_sched: attr.inheritsched == PTHREAD_EXPLICIT_SCHED as c_uchar,
+        sigmask: 0,
+        tls_len: 0,
+        masters_ptr: ptr::null_mut(),
+        masters_len: 0,
+        linker_ptr: ptr::null(),
+        mspace: 0,
+        #[cfg(target_os = "redox")]
+        started: &started,
+    });
+    if let Some(tcb) = Tcb::current() {
+        args.tls_len = tcb.tls_len;
+        args.masters_ptr = tcb.masters_ptr;
+        args.masters_len = tcb.masters_len;
+        args.linker_ptr = tcb.linker_ptr;
+        args.mspace = tcb.mspace;
+    }
+
+    // Signals stay blocked until the new thread has set itself up
+    let all: sigset_t = !0;
+    Sys::sigprocmask(SIG_SETMASK, &all, &mut args.sigmask);
+    let sigmask = args.sigmask;
+    let args = Box::into_raw(args);
+
+    //WARNING: Stack must be 128-bit aligned for SSE
+    let mut stack = ((stack_base as usize + stack_size) & !0xF) as *mut usize;
+    {
+        let mut push = |value: usize| {
+            stack = stack.offset(-1);
+            *stack = value;
+        };
+        push(args as usize);
+        push(new_thread_shim as usize);
+    }
+
+    THREADS.fetch_add(1, SeqCst);
+    let os_tid = Sys::rlct_clone(stack, &(*pthread).os_tid as *const AtomicI32 as *mut pid_t);
+    let err = platform::errno;
+    Sys::sigprocmask(SIG_SETMASK, &sigmask, ptr::null_mut());
+
+    if os_tid < 0 {
+        THREADS.fetch_sub(1, SeqCst);
+        drop(Box::from_raw(args));
+        stack::free(pthread);
+        return if err == ENOMEM { EAGAIN } else { err };
+    }
+
+    // Without the kernel storing it, the ID is only known once the thread
+    // runs. Storing it from here could race with a detached thread freeing
+    // itself.
+    #[cfg(target_os = "redox")]
+    while started.load(SeqCst) == 0 {
+        started.wait_if(0);
+    }
+
+    *thread = pthread as pthread_t;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_detach(thread: pthread_t) -> c_int {
+    let pthread = thread as *mut Pthread;
+    match (*pthread)
+        .state
+        .compare_exchange(RUNNING, DETACHED, SeqCst, SeqCst)
+    {
+        Ok(_) => 0,
+        Err(EXITED) => {
+            // Nobody will join it, so clean up now
+            while (*pthread).wait_exited().is_err() {}
+            stack::release(pthread);
+            0
+        }
+        Err(_) => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn pthread_equal(t1: pthread_t, t2: pthread_t) -> c_int {
+    (t1 == t2) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_exit(value_ptr: *mut c_void) -> ! {
+    exit_current_thread(value_ptr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_getattr_np(thread: pthread_t, attr: *mut pthread_attr_t) -> c_int {
+    let pthread = &*(thread as *const Pthread);
+    let sched = pthread.sched.lock();
+    let detachstate = if pthread.state.load(SeqCst) == DETACHED {
+        PTHREAD_CREATE_DETACHED
+    } else {
+        PTHREAD_CREATE_JOINABLE
+    };
+    *(attr as *mut RlctAttr) = RlctAttr {
+        detachstate: detachstate as c_uchar,
+        inheritsched: PTHREAD_EXPLICIT_SCHED as c_uchar,
+        schedpolicy: sched.0 as c_uchar,
+        scope: PTHREAD_SCOPE_SYSTEM as c_uchar,
+        guardsize: pthread.guardsize,
+        stacksize: pthread.stack_size,
+        stack: pthread.stack_base,
+        param: sched.1,
+    };
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn pthread_getconcurrency() -> c_int {
+    CONCURRENCY.load(SeqCst)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_getschedparam(
+    thread: pthread_t,
+    policy: *mut c_int,
+    param: *mut sched_param,
+) -> c_int {
+    let sched = (*(thread as *const Pthread)).sched.lock();
+    *policy = sched.0;
+    *param = sched.1;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_join(thread: pthread_t, retval: *mut *mut c_void) -> c_int {
+    let pthread = thread as *mut Pthread;
+    if pthread == SELF {
+        return EDEADLK;
+    }
+
+    if (*pthread).state.load(SeqCst) == DETACHED {
+        return EINVAL;
+    }
+    let point = enter_cancellation_point();
+    while (*pthread).wait_exited().is_err() {
+        // Nothing is taken from the thread before it has exited
+        pthread_testcancel();
+    }
+    leave_cancellation_point(point);
+
+    if !retval.is_null() {
+        *retval = (*pthread).retval;
+    }
+    stack::release(pthread);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_self() -> pthread_t {
+    SELF as pthread_t
+}
+
+#[no_mangle]
+pub extern "C" fn pthread_setconcurrency(new_level: c_int) -> c_int {
+    if new_level < 0 {
+        return EINVAL;
+    }
+    CONCURRENCY.store(new_level, SeqCst);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setschedparam(
+    thread: pthread_t,
+    policy: c_int,
+    param: *const sched_param,
+) -> c_int {
+    let pthread = &*(thread as *const Pthread);
+    let mut sched = pthread.sched.lock();
+    if Sys::sched_setscheduler(pthread.os_tid(), policy, param) < 0 {
+        return platform::errno;
+    }
+    *sched = (policy, *param);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setschedprio(thread: pthread_t, prio: c_int) -> c_int {
+    let pthread = &*(thread as *const Pthread);
+    let mut sched = pthread.sched.lock();
+    let param = sched_param {
+        sched_priority: prio,
+    };
+    if Sys::sched_setscheduler(pthread.os_tid(), sched.0, &param) < 0 {
+        return platform::errno;
+    }
+    sched.1 = param;
+    0
+}
@@ -0,0 +1,329 @@
+//! Mutexes and their attributes
+
+use core::{
+    mem, ptr,
+    sync::atomic::{AtomicU32, Ordering::Relaxed},
+};
+
+use super::*;
+
+pub const PTHREAD_MUTEX_NORMAL: c_int = 0;
+pub const PTHREAD_MUTEX_RECURSIVE: c_int = 1;
+pub const PTHREAD_MUTEX_ERRORCHECK: c_int = 2;
+pub const PTHREAD_MUTEX_DEFAULT: c_int = PTHREAD_MUTEX_NORMAL;
+
+pub const PTHREAD_MUTEX_STALLED: c_int = 0;
+pub const PTHREAD_MUTEX_ROBUST: c_int = 1;
+
+pub const PTHREAD_PRIO_NONE: c_int = 0;
+pub const PTHREAD_PRIO_INHERIT: c_int = 1;
+pub const PTHREAD_PRIO_PROTECT: c_int = 2;
+
+/// What pthread_mutex_t holds. All zeroes is an unlocked normal mutex.
+/// Threads share it through `&` references, so everything that changes after
+/// initialization is atomic.
+pub(crate) struct RlctMutex {
+    inner: Mutex<()>,
+    kind: c_int,
+    prioceiling: AtomicI32,
+    /// The kernel ID of the locking thread, for recursive and error checking
+    /// mutexes. Unlike the pthread_t, it is unique across processes too.
+    owner: AtomicI32,
+    /// How many more times a recursive mutex was locked by its owner, which
+    /// is the only thread to touch it
+    count: AtomicU32,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RlctMutexAttr {
+    kind: c_int,
+    pshared: c_int,
+    robust: c_int,
+    protocol: c_int,
+}
+
+impl Default for RlctMutexAttr {
+    fn default() -> Self {
+        Self {
+            kind: PTHREAD_MUTEX_DEFAULT,
+            pshared: PTHREAD_PROCESS_PRIVATE,
+            robust: PTHREAD_MUTEX_STALLED,
+            protocol: PTHREAD_PRIO_NONE,
+        }
+    }
+}
+
+// Fail to compile if the C types are too small
+const _: [(); 0] = [(); (mem::size_of::<RlctMutex>() > mem::size_of::<pthread_mutex_t>()) as usize];
+const _: [(); 0] =
+    [(); (mem::size_of::<RlctMutexAttr>() > mem::size_of::<pthread_mutexattr_t>()) as usize];
+
+impl RlctMutex {
+    fn new(attr: &RlctMutexAttr) -> Self {
+        Self {
+            inner: Mutex::new(()),
+            kind: attr.kind,
+            prioceiling: AtomicI32::new(0),
+            owner: AtomicI32::new(0),
+            count: AtomicU32::new(0),
+        }
+    }
+
+    /// Checks whether a recursive or error checking mutex is held by the
+    /// calling thread before locking. Returns Some with the result if
+    /// locking is already decided.
+    fn check_owner(&self) -> Option<c_int> {
+        if self.kind == PTHREAD_MUTEX_NORMAL || self.owner.load(SeqCst) != Sys::gettid() {
+            return None;
+        }
+        Some(match self.kind {
+            PTHREAD_MUTEX_RECURSIVE => match self.count.load(Relaxed).checked_add(1) {
+                Some(count) => {
+                    self.count.store(count, Relaxed);
+                    0
+                }
+                None => EAGAIN,
+            },
+            _ => EDEADLK,
+        })
+    }
+
+    fn locked(&self) -> c_int {
+        if self.kind != PTHREAD_MUTEX_NORMAL {
+            self.owner.store(Sys::gettid(), SeqCst);
+        }
+        0
+    }
+
+    pub(crate) unsafe fn lock(&self) -> c_int {
+        if let Some(ret) = self.check_owner() {
+            return ret;
+        }
+        self.inner.manual_lock();
+        self.locked()
+    }
+
+    pub(crate) unsafe fn try_lock(&self) -> c_int {
+        if let Some(ret) = self.check_owner() {
+            return if ret == EDEADLK { EBUSY } else { ret };
+        }
+        match self.inner.manual_try_lock() {
+            Ok(_) => self.locked(),
+            Err(_) => EBUSY,
+        }
+    }
+
+    pub(crate) unsafe fn timed_lock(&self, abstime: *const timespec) -> c_int {
+        if let Some(ret) = self.check_owner() {
+            return ret;
+        }
+        match self.inner.manual_lock_until(CLOCK_REALTIME, &*abstime) {
+            Ok(_) => self.locked(),
+            Err(err) => err,
+        }
+    }
+
+    pub(crate) unsafe fn unlock(&self) -> c_int {
+        if self.kind != PTHREAD_MUTEX_NORMAL {
+            if self.owner.load(SeqCst) != Sys::gettid() {
+                return EPERM;
+            }
+            let count = self.count.load(Relaxed);
+            if count > 0 {
+                self.count.store(count - 1, Relaxed);
+                return 0;
+            }
+            self.owner.store(0, SeqCst);
+        }
+        self.inner.manual_unlock();
+        0
+    }
+}
+
+unsafe fn mutex<'a>(mutex: *const pthread_mutex_t) -> &'a RlctMutex {
+    &*(mutex as *const RlctMutex)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_destroy(mutex: *mut pthread_mutex_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_getprioceiling(
+    mutex: *const pthread_mutex_t,
+    prioceiling: *mut c_int,
+) -> c_int {
+    *prioceiling = self::mutex(mutex).prioceiling.load(SeqCst);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_init(
+    mutex: *mut pthread_mutex_t,
+    attr: *const pthread_mutexattr_t,
+) -> c_int {
+    let attr = if attr.is_null() {
+        RlctMutexAttr::default()
+    } else {
+        *(attr as *const RlctMutexAttr)
+    };
+    ptr::write(mutex as *mut RlctMutex, RlctMutex::new(&attr));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut pthread_mutex_t) -> c_int {
+    self::mutex(mutex).lock()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_setprioceiling(
+    mutex: *mut pthread_mutex_t,
+    prioceiling: c_int,
+    old_prioceiling: *mut c_int,
+) -> c_int {
+    let mutex = self::mutex(mutex);
+    let ret = mutex.lock();
+    if ret != 0 {
+        return ret;
+    }
+    let old = mutex.prioceiling.swap(prioceiling, SeqCst);
+    if !old_prioceiling.is_null() {
+        *old_prioceiling = old;
+    }
+    mutex.unlock()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_timedlock(
+    mutex: *mut pthread_mutex_t,
+    abstime: *const timespec,
+) -> c_int {
+    self::mutex(mutex).timed_lock(abstime)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_trylock(mutex: *mut pthread_mutex_t) -> c_int {
+    self::mutex(mutex).try_lock()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_unlock(mutex: *mut pthread_mutex_t) -> c_int {
+    self::mutex(mutex).unlock()
+}
+
+unsafe fn mutexattr<'a>(attr: *const pthread_mutexattr_t) -> &'a RlctMutexAttr {
+    &*(attr as *const RlctMutexAttr)
+}
+
+unsafe fn mutexattr_mut<'a>(attr: *mut pthread_mutexattr_t) -> &'a mut RlctMutexAttr {
+    &mut *(attr as *mut RlctMutexAttr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_destroy(attr: *mut pthread_mutexattr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_getprotocol(
+    attr: *const pthread_mutexattr_t,
+    protocol: *mut c_int,
+) -> c_int {
+    *protocol = mutexattr(attr).protocol;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_getpshared(
+    attr: *const pthread_mutexattr_t,
+    pshared: *mut c_int,
+) -> c_int {
+    *pshared = mutexattr(attr).pshared;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_getrobust(
+    attr: *const pthread_mutexattr_t,
+    robust: *mut c_int,
+) -> c_int {
+    *robust = mutexattr(attr).robust;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_gettype(
+    attr: *const pthread_mutexattr_t,
+    kind: *mut c_int,
+) -> c_int {
+    *kind = mutexattr(attr).kind;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_init(attr: *mut pthread_mutexattr_t) -> c_int {
+    ptr::write(attr as *mut RlctMutexAttr, RlctMutexAttr::default());
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_setprotocol(
+    attr: *mut pthread_mutexattr_t,
+    protocol: c_int,
+) -> c_int {
+    match protocol {
+        PTHREAD_PRIO_NONE | PTHREAD_PRIO_INHERIT | PTHREAD_PRIO_PROTECT => {
+            mutexattr_mut(attr).protocol = protocol;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_setpshared(
+    attr: *mut pthread_mutexattr_t,
+    pshared: c_int,
+) -> c_int {
+    // Every mutex works across processes: the futex operations aren't
+    // private, and owners are kernel thread IDs
+    match pshared {
+        PTHREAD_PROCESS_PRIVATE | PTHREAD_PROCESS_SHARED => {
+            mutexattr_mut(attr).pshared = pshared;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_setrobust(
+    attr: *mut pthread_mutexattr_t,
+    robust: c_int,
+) -> c_int {
+    match robust {
+        PTHREAD_MUTEX_STALLED => {
+            mutexattr_mut(attr).robust = robust;
+            0
+        }
+        // A mutex whose owner dies stays locked
+        PTHREAD_MUTEX_ROBUST => ENOTSUP,
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutexattr_settype(
+    attr: *mut pthread_mutexattr_t,
+    kind: c_int,
+) -> c_int {
+    match kind {
+        PTHREAD_MUTEX_NORMAL | PTHREAD_MUTEX_RECURSIVE | PTHREAD_MUTEX_ERRORCHECK => {
+            mutexattr_mut(attr).kind = kind;
+            0
+        }
+        _ => EINVAL,
+    }
+}
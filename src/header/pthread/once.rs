@@ -0,0 +1,21 @@
+//! pthread_once, on top of sync::Once
+
+use core::mem;
+
+use super::*;
+use crate::sync::Once;
+
+pub const PTHREAD_ONCE_INIT: pthread_once_t = 0;
+
+// Once<()> is a single atomic, which starts out as 0
+const _: [(); 0] = [(); (mem::size_of::<Once<()>>() != mem::size_of::<pthread_once_t>()) as usize];
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_once(
+    once_control: *mut pthread_once_t,
+    init_routine: extern "C" fn(),
+) -> c_int {
+    let once = &*(once_control as *const Once<()>);
+    once.call_once(|| init_routine());
+    0
+}
@@ -0,0 +1,122 @@
+//! Read-write locks and their attributes
+
+use core::{mem, ptr};
+
+use super::*;
+use crate::sync::RwLock;
+
+#[derive(Clone, Copy)]
+pub(crate) struct RlctRwlockAttr {
+    pshared: c_int,
+}
+
+// Fail to compile if the C types are too small
+const _: [(); 0] =
+    [(); (mem::size_of::<RwLock<()>>() > mem::size_of::<pthread_rwlock_t>()) as usize];
+const _: [(); 0] =
+    [(); (mem::size_of::<RlctRwlockAttr>() > mem::size_of::<pthread_rwlockattr_t>()) as usize];
+
+unsafe fn rwlock<'a>(rwlock: *mut pthread_rwlock_t) -> &'a RwLock<()> {
+    &*(rwlock as *const RwLock<()>)
+}
+
+fn result<T>(result: Result<T, c_int>) -> c_int {
+    match result {
+        Ok(_) => 0,
+        Err(err) => err,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_destroy(rwlock: *mut pthread_rwlock_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_init(
+    rwlock: *mut pthread_rwlock_t,
+    attr: *const pthread_rwlockattr_t,
+) -> c_int {
+    ptr::write(rwlock as *mut RwLock<()>, RwLock::new(()));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_rdlock(rwlock: *mut pthread_rwlock_t) -> c_int {
+    result(self::rwlock(rwlock).manual_read(None))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_timedrdlock(
+    rwlock: *mut pthread_rwlock_t,
+    abstime: *const timespec,
+) -> c_int {
+    result(self::rwlock(rwlock).manual_read(Some((CLOCK_REALTIME, &*abstime))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_timedwrlock(
+    rwlock: *mut pthread_rwlock_t,
+    abstime: *const timespec,
+) -> c_int {
+    result(self::rwlock(rwlock).manual_write(Some((CLOCK_REALTIME, &*abstime))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_tryrdlock(rwlock: *mut pthread_rwlock_t) -> c_int {
+    result(self::rwlock(rwlock).manual_try_read())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_trywrlock(rwlock: *mut pthread_rwlock_t) -> c_int {
+    result(self::rwlock(rwlock).manual_try_write())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_unlock(rwlock: *mut pthread_rwlock_t) -> c_int {
+    result(self::rwlock(rwlock).manual_unlock())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_wrlock(rwlock: *mut pthread_rwlock_t) -> c_int {
+    result(self::rwlock(rwlock).manual_write(None))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlockattr_destroy(attr: *mut pthread_rwlockattr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlockattr_getpshared(
+    attr: *const pthread_rwlockattr_t,
+    pshared: *mut c_int,
+) -> c_int {
+    *pshared = (*(attr as *const RlctRwlockAttr)).pshared;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlockattr_init(attr: *mut pthread_rwlockattr_t) -> c_int {
+    ptr::write(
+        attr as *mut RlctRwlockAttr,
+        RlctRwlockAttr {
+            pshared: PTHREAD_PROCESS_PRIVATE,
+        },
+    );
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlockattr_setpshared(
+    attr: *mut pthread_rwlockattr_t,
+    pshared: c_int,
+) -> c_int {
+    match pshared {
+        PTHREAD_PROCESS_PRIVATE | PTHREAD_PROCESS_SHARED => {
+            (*(attr as *mut RlctRwlockAttr)).pshared = pshared;
+            0
+        }
+        _ => EINVAL,
+    }
+}
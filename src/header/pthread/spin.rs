@@ -0,0 +1,49 @@
+//! Spin locks
+
+use core::sync::atomic::{self, AtomicI32 as AtomicInt};
+
+use super::*;
+
+const UNLOCKED: c_int = 0;
+const LOCKED: c_int = 1;
+
+unsafe fn spinlock<'a>(lock: *mut pthread_spinlock_t) -> &'a AtomicInt {
+    &*(lock as *const AtomicInt)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_spin_destroy(lock: *mut pthread_spinlock_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_spin_init(lock: *mut pthread_spinlock_t, pshared: c_int) -> c_int {
+    spinlock(lock).store(UNLOCKED, SeqCst);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_spin_lock(lock: *mut pthread_spinlock_t) -> c_int {
+    let lock = spinlock(lock);
+    while lock
+        .compare_exchange_weak(UNLOCKED, LOCKED, SeqCst, SeqCst)
+        .is_err()
+    {
+        atomic::spin_loop_hint();
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_spin_trylock(lock: *mut pthread_spinlock_t) -> c_int {
+    match spinlock(lock).compare_exchange(UNLOCKED, LOCKED, SeqCst, SeqCst) {
+        Ok(_) => 0,
+        Err(_) => EBUSY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_spin_unlock(lock: *mut pthread_spinlock_t) -> c_int {
+    spinlock(lock).store(UNLOCKED, SeqCst);
+    0
+}
@@ -0,0 +1,121 @@
+//! Thread stacks
+//!
+//! Stacks are private mappings with a PROT_NONE guard region below them, so
+//! that overflowing one faults instead of running into the next mapping.
+//! Stacks of exited threads are kept in a small cache, which saves creating
+//! short lived threads a mmap, mprotect and munmap each.
+
+use alloc::vec::Vec;
+
+use super::*;
+
+/// Most bytes of stack, guards included, kept around for reuse
+const CACHE_MAX: usize = 16 * 1024 * 1024;
+
+struct Cache {
+    /// Exited or exiting threads, each owning its stack. A stack is only
+    /// reused once its thread is no longer alive.
+    threads: Vec<usize>,
+    size: usize,
+}
+
+static CACHE: Mutex<Cache> = Mutex::new(Cache {
+    threads: Vec::new(),
+    size: 0,
+});
+
+fn round_up(size: usize) -> usize {
+    (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+/// The whole mapping behind a thread's stack, guard included, or nothing if
+/// the stack was provided by the caller
+pub(super) fn mapping(pthread: &Pthread) -> (*mut c_void, usize) {
+    if pthread.own_stack {
+        (
+            (pthread.stack_base as usize - pthread.guardsize) as *mut c_void,
+            pthread.stack_size + pthread.guardsize,
+        )
+    } else {
+        (ptr::null_mut(), 0)
+    }
+}
+
+/// Returns the lowest usable address of a new stack, and the stack and guard
+/// sizes rounded up to whole pages
+pub(super) unsafe fn alloc(
+    stack_size: usize,
+    guardsize: usize,
+) -> Option<(*mut c_void, usize, usize)> {
+    let stack_size = round_up(stack_size);
+    let guardsize = round_up(guardsize);
+
+    {
+        let mut cache = CACHE.lock();
+        let found = cache.threads.iter().position(|&pthread| {
+            let pthread = &*(pthread as *const Pthread);
+            pthread.stack_size == stack_size
+                && pthread.guardsize == guardsize
+                && pthread.alive.load(SeqCst) == 0
+        });
+        if let Some(i) = found {
+            let pthread = Box::from_raw(cache.threads.swap_remove(i) as *mut Pthread);
+            cache.size -= mapping(&pthread).1;
+            return Some((pthread.stack_base, stack_size, guardsize));
+        }
+    }
+
+    let base = sys_mman::mmap(
+        ptr::null_mut(),
+        stack_size + guardsize,
+        sys_mman::PROT_READ | sys_mman::PROT_WRITE,
+        sys_mman::MAP_PRIVATE | sys_mman::MAP_ANONYMOUS,
+        -1,
+        0,
+    );
+    if base as usize == !0 {
+        return None;
+    }
+    if guardsize > 0 && sys_mman::mprotect(base, guardsize, sys_mman::PROT_NONE) < 0 {
+        sys_mman::munmap(base, stack_size + guardsize);
+        return None;
+    }
+    Some((
+        (base as usize + guardsize) as *mut c_void,
+        stack_size,
+        guardsize,
+    ))
+}
+
+/// Unmaps the stack of a thread, if it was not provided by the caller, and
+/// frees the thread. Must not be used on a thread still running.
+pub(super) unsafe fn free(pthread: *mut Pthread) {
+    let (base, size) = mapping(&*pthread);
+    drop(Box::from_raw(pthread));
+    if !base.is_null() {
+        sys_mman::munmap(base, size);
+    }
+}
+
+/// Puts a thread, which has exited or is about to, in the cache along with
+/// its stack. Returns false if there is no room, or the stack is not ours.
+pub(super) unsafe fn cache(pthread: *mut Pthread) -> bool {
+    let size = match mapping(&*pthread) {
+        (base, size) if !base.is_null() => size,
+        _ => return false,
+    };
+    let mut cache = CACHE.lock();
+    if cache.size + size > CACHE_MAX {
+        return false;
+    }
+    cache.threads.push(pthread as usize);
+    cache.size += size;
+    true
+}
+
+/// Disposes of a thread which has been joined, or detached after exiting
+pub(super) unsafe fn release(pthread: *mut Pthread) {
+    if !cache(pthread) {
+        free(pthread);
+    }
+}
@@ -0,0 +1,112 @@
+//! Thread-specific data keys
+
+use super::*;
+
+pub const PTHREAD_KEYS_MAX: c_uint = 128;
+pub const PTHREAD_DESTRUCTOR_ITERATIONS: c_int = 4;
+
+#[derive(Clone, Copy)]
+struct Key {
+    /// Odd while the key is in use, bumped by every create and delete so
+    /// values set before a delete are not returned after
+    seq: usize,
+    destructor: Option<extern "C" fn(value: *mut c_void)>,
+}
+
+#[derive(Clone, Copy)]
+struct Value {
+    seq: usize,
+    value: *mut c_void,
+}
+
+static KEYS: Mutex<[Key; PTHREAD_KEYS_MAX as usize]> = Mutex::new(
+    [Key {
+        seq: 0,
+        destructor: None,
+    }; PTHREAD_KEYS_MAX as usize],
+);
+
+#[thread_local]
+static mut VALUES: [Value; PTHREAD_KEYS_MAX as usize] = [Value {
+    seq: 0,
+    value: ptr::null_mut(),
+}; PTHREAD_KEYS_MAX as usize];
+
+/// Calls the destructors of the calling thread's values, from
+/// exit_current_thread
+pub(crate) unsafe fn run_destructors() {
+    for _ in 0..PTHREAD_DESTRUCTOR_ITERATIONS {
+        let mut called = false;
+        for (i, value) in VALUES.iter_mut().enumerate() {
+            let key = KEYS.lock()[i];
+            if value.value.is_null() || value.seq != key.seq || key.seq % 2 == 0 {
+                continue;
+            }
+            if let Some(destructor) = key.destructor {
+                let arg = value.value;
+                value.value = ptr::null_mut();
+                destructor(arg);
+                called = true;
+            }
+        }
+        if !called {
+            break;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_getspecific(key: pthread_key_t) -> *mut c_void {
+    let value = match VALUES.get(key as usize) {
+        Some(value) => *value,
+        None => return ptr::null_mut(),
+    };
+    if value.seq == KEYS.lock()[key as usize].seq {
+        value.value
+    } else {
+        ptr::null_mut()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_key_create(
+    key: *mut pthread_key_t,
+    destructor: Option<extern "C" fn(value: *mut c_void)>,
+) -> c_int {
+    let mut keys = KEYS.lock();
+    match keys.iter().position(|key| key.seq % 2 == 0) {
+        Some(i) => {
+            keys[i].seq += 1;
+            keys[i].destructor = destructor;
+            *key = i as pthread_key_t;
+            0
+        }
+        None => EAGAIN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_key_delete(key: pthread_key_t) -> c_int {
+    let mut keys = KEYS.lock();
+    match keys.get_mut(key as usize) {
+        Some(key) if key.seq % 2 == 1 => {
+            key.seq += 1;
+            key.destructor = None;
+            0
+        }
+        _ => EINVAL,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn pthread_setspecific(key: pthread_key_t, value: *const c_void) -> c_int {
+    let seq = match KEYS.lock().get(key as usize) {
+        Some(key) if key.seq % 2 == 1 => key.seq,
+        _ => return EINVAL,
+    };
+    VALUES[key as usize] = Value {
+        seq,
+        value: value as *mut c_void,
+    };
+    0
+}
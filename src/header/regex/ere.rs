@@ -0,0 +1,806 @@
+//! POSIX Extended Regular Expressions, compiled to a small program that runs
+//! every thread at once in one pass over the input. Matches follow the POSIX
+//! rules: the leftmost match wins, then the longest one, then subexpressions
+//! are leftmost-longest in order.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::mem;
+
+use crate::{header::ctype, platform::types::*};
+
+use super::{
+    REG_BADBR, REG_BADRPT, REG_EBRACE, REG_EBRACK, REG_ECOLLATE, REG_ECTYPE, REG_EESCAPE,
+    REG_EPAREN, REG_ERANGE, REG_ESPACE, REG_ESUBREG,
+};
+
+/// The largest count allowed in an interval, `_POSIX_RE_DUP_MAX`
+const DUP_MAX: usize = 255;
+/// Programs larger than this after expanding intervals are refused
+const MAX_PROGRAM: usize = 1 << 16;
+/// How many times one search may visit each instruction at each position on
+/// average, before failing with REG_ESPACE. Threads meeting at the same place
+/// are merged, so only back-references, which keep them apart, get anywhere
+/// near it.
+const VISITS: usize = 8;
+/// How many more instructions a search may run, for short inputs
+const STEP_BUDGET: usize = 1 << 20;
+
+type Set = [u32; 8];
+
+fn set_contains(set: &Set, b: u8) -> bool {
+    set[b as usize / 32] & (1 << (b % 32)) != 0
+}
+
+fn set_insert(set: &mut Set, b: u8) {
+    set[b as usize / 32] |= 1 << (b % 32);
+}
+
+enum Node {
+    Empty,
+    Byte(u8),
+    Any,
+    /// A bracket expression, and whether it is a non-matching list
+    Set(Box<Set>, bool),
+    Bol,
+    Eol,
+    Backref(usize),
+    Group(usize, Box<Node>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+#[derive(Clone, Copy)]
+enum Inst {
+    Byte(u8),
+    Any,
+    Set(usize, bool),
+    Bol,
+    Eol,
+    Backref(usize),
+    Save(usize),
+    /// Try the first branch, then the second
+    Split(usize, usize),
+    Jmp(usize),
+    /// Remember where an iteration of a loop started
+    Mark(usize),
+    /// Go back for another iteration, but only if the last one consumed
+    /// something, and otherwise leave the loop
+    Loop(usize, usize, usize),
+    Match,
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+    icase: bool,
+    nsub: usize,
+    /// Groups whose closing parenthesis has been seen, for back-references
+    closed: Vec<bool>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.pattern.get(self.pos + offset).copied()
+    }
+
+    fn alternation(&mut self, depth: usize) -> Result<Node, c_int> {
+        let mut branches = vec![self.concatenation(depth)?];
+        while self.peek() == Some(b'|') {
+            self.pos += 1;
+            branches.push(self.concatenation(depth)?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concatenation(&mut self, depth: usize) -> Result<Node, c_int> {
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(b'|') => break,
+                Some(b')') if depth > 0 => break,
+                _ => (),
+            }
+            let atom = self.atom(depth)?;
+            items.push(self.repetitions(atom)?);
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else if items.is_empty() {
+            Node::Empty
+        } else {
+            Node::Concat(items)
+        })
+    }
+
+    fn atom(&mut self, depth: usize) -> Result<Node, c_int> {
+        let b = self.peek().unwrap();
+        self.pos += 1;
+        Ok(match b {
+            b'(' => {
+                self.nsub += 1;
+                let index = self.nsub;
+                self.closed.push(false);
+                let inner = self.alternation(depth + 1)?;
+                if self.peek() != Some(b')') {
+                    return Err(REG_EPAREN);
+                }
+                self.pos += 1;
+                self.closed[index - 1] = true;
+                Node::Group(index, Box::new(inner))
+            }
+            b'*' | b'+' | b'?' | b'{' => return Err(REG_BADRPT),
+            b'.' => Node::Any,
+            b'^' => {
+                // An anchor can't be repeated
+                match self.peek() {
+                    Some(b'*') | Some(b'+') | Some(b'?') | Some(b'{') => return Err(REG_BADRPT),
+                    _ => Node::Bol,
+                }
+            }
+            b'$' => Node::Eol,
+            b'[' => self.bracket()?,
+            b'\\' => match self.peek() {
+                None => return Err(REG_EESCAPE),
+                Some(c @ b'1'..=b'9') => {
+                    self.pos += 1;
+                    let index = (c - b'0') as usize;
+                    if !self.closed.get(index - 1).copied().unwrap_or(false) {
+                        return Err(REG_ESUBREG);
+                    }
+                    Node::Backref(index)
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    self.literal(c)
+                }
+            },
+            c => self.literal(c),
+        })
+    }
+
+    fn literal(&self, c: u8) -> Node {
+        if self.icase && c.is_ascii_alphabetic() {
+            let mut set = [0; 8];
+            set_insert(&mut set, c.to_ascii_lowercase());
+            set_insert(&mut set, c.to_ascii_uppercase());
+            Node::Set(Box::new(set), false)
+        } else {
+            Node::Byte(c)
+        }
+    }
+
+    fn repetitions(&mut self, mut atom: Node) -> Result<Node, c_int> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some(b'*') => (0, None),
+                Some(b'+') => (1, None),
+                Some(b'?') => (0, Some(1)),
+                Some(b'{') => {
+                    self.pos += 1;
+                    let interval = self.interval()?;
+                    atom = Node::Repeat(Box::new(atom), interval.0, interval.1);
+                    continue;
+                }
+                _ => return Ok(atom),
+            };
+            self.pos += 1;
+            atom = Node::Repeat(Box::new(atom), min, max);
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        let mut n: usize = 0;
+        while let Some(c @ b'0'..=b'9') = self.peek() {
+            n = n.saturating_mul(10).saturating_add((c - b'0') as usize);
+            self.pos += 1;
+        }
+        if self.pos == start {
+            None
+        } else {
+            Some(n)
+        }
+    }
+
+    /// Parses the rest of `{min,max}`
+    fn interval(&mut self) -> Result<(usize, Option<usize>), c_int> {
+        let min = self.number();
+        let max = if self.peek() == Some(b',') {
+            self.pos += 1;
+            self.number()
+        } else {
+            match min {
+                Some(min) => Some(min),
+                None => return Err(self.interval_error()),
+            }
+        };
+        if self.peek() != Some(b'}') {
+            return Err(self.interval_error());
+        }
+        self.pos += 1;
+
+        let min = min.unwrap_or(0);
+        match max {
+            Some(max) if max < min || max > DUP_MAX => Err(REG_BADBR),
+            _ if min > DUP_MAX => Err(REG_BADBR),
+            _ => Ok((min, max)),
+        }
+    }
+
+    /// An unterminated interval is missing its brace, anything else has bad
+    /// contents
+    fn interval_error(&self) -> c_int {
+        if self.pattern[self.pos..].contains(&b'}') {
+            REG_BADBR
+        } else {
+            REG_EBRACE
+        }
+    }
+
+    /// Parses the rest of a bracket expression
+    fn bracket(&mut self) -> Result<Node, c_int> {
+        let mut set = [0; 8];
+        let negate = self.peek() == Some(b'^');
+        if negate {
+            self.pos += 1;
+        }
+
+        let mut first = true;
+        loop {
+            let start = match self.peek() {
+                None => return Err(REG_EBRACK),
+                Some(b']') if !first => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'[') if self.peek_at(1) == Some(b':') => {
+                    self.pos += 2;
+                    let name = self.bracket_name(b':')?;
+                    let class = class(name).ok_or(REG_ECTYPE)?;
+                    for c in 0..=255 {
+                        if class(c as c_int) != 0 {
+                            set_insert(&mut set, c);
+                        }
+                    }
+                    first = false;
+                    continue;
+                }
+                Some(b'[') if self.peek_at(1) == Some(b'=') => {
+                    // Every character is its own equivalence class
+                    self.pos += 2;
+                    let name = self.bracket_name(b'=')?;
+                    if name.len() != 1 {
+                        return Err(REG_ECOLLATE);
+                    }
+                    set_insert(&mut set, name[0]);
+                    first = false;
+                    continue;
+                }
+                _ => self.bracket_char()?,
+            };
+            first = false;
+
+            let end = if self.peek() == Some(b'-') && self.peek_at(1).map_or(false, |c| c != b']') {
+                self.pos += 1;
+                self.bracket_char()?
+            } else {
+                start
+            };
+            if end < start {
+                return Err(REG_ERANGE);
+            }
+            for c in start..=end {
+                set_insert(&mut set, c);
+            }
+        }
+
+        if self.icase {
+            for c in b'a'..=b'z' {
+                let upper = c.to_ascii_uppercase();
+                if set_contains(&set, c) || set_contains(&set, upper) {
+                    set_insert(&mut set, c);
+                    set_insert(&mut set, upper);
+                }
+            }
+        }
+        if negate {
+            for word in set.iter_mut() {
+                *word = !*word;
+            }
+        }
+        Ok(Node::Set(Box::new(set), negate))
+    }
+
+    /// A single character in a bracket expression, possibly as a collating
+    /// symbol
+    fn bracket_char(&mut self) -> Result<u8, c_int> {
+        if self.peek() == Some(b'[') && self.peek_at(1) == Some(b'.') {
+            self.pos += 2;
+            let name = self.bracket_name(b'.')?;
+            if name.len() != 1 {
+                return Err(REG_ECOLLATE);
+            }
+            Ok(name[0])
+        } else {
+            let c = self.peek().ok_or(REG_EBRACK)?;
+            self.pos += 1;
+            Ok(c)
+        }
+    }
+
+    /// The name in `[:name:]`, `[.name.]` or `[=name=]`
+    fn bracket_name(&mut self, delimiter: u8) -> Result<&'a [u8], c_int> {
+        let rest = &self.pattern[self.pos..];
+        let len = rest
+            .windows(2)
+            .position(|w| w[0] == delimiter && w[1] == b']')
+            .ok_or(REG_EBRACK)?;
+        self.pos += len + 2;
+        Ok(&rest[..len])
+    }
+}
+
+fn class(name: &[u8]) -> Option<extern "C" fn(c_int) -> c_int> {
+    Some(match name {
+        b"alnum" => ctype::isalnum,
+        b"alpha" => ctype::isalpha,
+        b"blank" => ctype::isblank,
+        b"cntrl" => ctype::iscntrl,
+        b"digit" => ctype::isdigit,
+        b"graph" => ctype::isgraph,
+        b"lower" => ctype::islower,
+        b"print" => ctype::isprint,
+        b"punct" => ctype::ispunct,
+        b"space" => ctype::isspace,
+        b"upper" => ctype::isupper,
+        b"xdigit" => ctype::isxdigit,
+        _ => return None,
+    })
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+    sets: Vec<Set>,
+    loops: usize,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, c_int> {
+        if self.prog.len() >= MAX_PROGRAM {
+            return Err(REG_ESPACE);
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), c_int> {
+        match *node {
+            Node::Empty => (),
+            Node::Byte(b) => {
+                self.push(Inst::Byte(b))?;
+            }
+            Node::Any => {
+                self.push(Inst::Any)?;
+            }
+            Node::Set(ref set, negated) => {
+                self.sets.push(**set);
+                self.push(Inst::Set(self.sets.len() - 1, negated))?;
+            }
+            Node::Bol => {
+                self.push(Inst::Bol)?;
+            }
+            Node::Eol => {
+                self.push(Inst::Eol)?;
+            }
+            Node::Backref(index) => {
+                self.push(Inst::Backref(index))?;
+            }
+            Node::Group(index, ref inner) => {
+                self.push(Inst::Save(index * 2))?;
+                self.node(inner)?;
+                self.push(Inst::Save(index * 2 + 1))?;
+            }
+            Node::Concat(ref items) => {
+                for item in items {
+                    self.node(item)?;
+                }
+            }
+            Node::Alt(ref branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.node(branch)?;
+                        jumps.push(self.push(Inst::Jmp(0))?);
+                        let next = self.prog.len();
+                        self.prog[split] = Inst::Split(split + 1, next);
+                    } else {
+                        self.node(branch)?;
+                    }
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat(ref inner, min, max) => {
+                for _ in 0..min {
+                    self.node(inner)?;
+                }
+                match max {
+                    None => {
+                        let reg = self.loops;
+                        self.loops += 1;
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.push(Inst::Mark(reg))?;
+                        self.node(inner)?;
+                        let end = self.prog.len() + 1;
+                        self.push(Inst::Loop(reg, split + 1, end))?;
+                        self.prog[split] = Inst::Split(split + 1, end);
+                    }
+                    Some(max) => {
+                        // Each optional copy is nested in the one before
+                        let mut splits = Vec::new();
+                        for _ in min..max {
+                            splits.push(self.push(Inst::Split(0, 0))?);
+                            self.node(inner)?;
+                        }
+                        let end = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Ere {
+    prog: Vec<Inst>,
+    sets: Vec<Set>,
+    loops: usize,
+    nsub: usize,
+    has_backrefs: bool,
+}
+
+pub struct Flags {
+    pub icase: bool,
+    pub newline: bool,
+    pub notbol: bool,
+    pub noteol: bool,
+}
+
+/// A match: the start and end of the whole match followed by those of each
+/// subexpression, or `!0` for subexpressions that didn't participate
+pub type Captures = Vec<usize>;
+
+struct Search<'a> {
+    ere: &'a Ere,
+    input: &'a [u8],
+    flags: &'a Flags,
+    steps: usize,
+    budget: usize,
+}
+
+/// One path through the program
+#[derive(Clone, PartialEq)]
+struct Thread {
+    pc: usize,
+    caps: Captures,
+    regs: Vec<usize>,
+    /// How much of a back-reference has been matched so far
+    skip: usize,
+}
+
+/// The threads at one position, along with where each instruction's thread
+/// is in the list
+struct Threads {
+    list: Vec<Thread>,
+    slots: Vec<usize>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            slots: vec![!0; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        for thread in self.list.drain(..) {
+            self.slots[thread.pc] = !0;
+        }
+    }
+}
+
+impl Ere {
+    pub fn compile(pattern: &[u8], icase: bool) -> Result<Self, c_int> {
+        let mut parser = Parser {
+            pattern,
+            pos: 0,
+            icase,
+            nsub: 0,
+            closed: Vec::new(),
+        };
+        let node = parser.alternation(0)?;
+
+        let mut compiler = Compiler {
+            prog: Vec::new(),
+            sets: Vec::new(),
+            loops: 0,
+        };
+        compiler.push(Inst::Save(0))?;
+        compiler.node(&node)?;
+        compiler.push(Inst::Save(1))?;
+        compiler.push(Inst::Match)?;
+
+        let has_backrefs = compiler
+            .prog
+            .iter()
+            .any(|inst| matches!(inst, Inst::Backref(_)));
+        Ok(Self {
+            prog: compiler.prog,
+            sets: compiler.sets,
+            loops: compiler.loops,
+            nsub: parser.nsub,
+            has_backrefs,
+        })
+    }
+
+    pub fn nsub(&self) -> usize {
+        self.nsub
+    }
+
+    /// Finds the POSIX match, or fails with `REG_ESPACE` if that takes too
+    /// long
+    pub fn exec(&self, input: &[u8], flags: &Flags) -> Result<Option<Captures>, c_int> {
+        let mut search = Search {
+            ere: self,
+            input,
+            flags,
+            steps: 0,
+            budget: (input.len() + 1)
+                .saturating_mul(self.prog.len())
+                .saturating_mul(VISITS)
+                .saturating_add(STEP_BUDGET),
+        };
+        search.run()
+    }
+}
+
+impl<'a> Search<'a> {
+    fn byte_matches(&self, inst: Inst, b: u8) -> bool {
+        match inst {
+            Inst::Byte(c) => b == c,
+            Inst::Any => !(self.flags.newline && b == b'\n'),
+            // Only a non-matching list leaves out newline with REG_NEWLINE
+            Inst::Set(i, negated) => {
+                set_contains(&self.ere.sets[i], b) && !(negated && self.flags.newline && b == b'\n')
+            }
+            _ => false,
+        }
+    }
+
+    fn at_bol(&self, pos: usize) -> bool {
+        (pos == 0 && !self.flags.notbol)
+            || (pos > 0 && self.flags.newline && self.input[pos - 1] == b'\n')
+    }
+
+    fn at_eol(&self, pos: usize) -> bool {
+        (pos == self.input.len() && !self.flags.noteol)
+            || (pos < self.input.len() && self.flags.newline && self.input[pos] == b'\n')
+    }
+
+    /// The subexpression a back-reference refers to, if it took part
+    fn group(&self, thread: &Thread, index: usize) -> Option<(usize, usize)> {
+        let (so, eo) = (thread.caps[index * 2], thread.caps[index * 2 + 1]);
+        if so == !0 || eo == !0 || eo < so {
+            None
+        } else {
+            Some((so, eo))
+        }
+    }
+
+    /// Adds `thread` to `threads`, following the instructions that don't
+    /// consume input. Two threads meeting at the same instruction have the
+    /// same future, so only the one the POSIX rules prefer is kept, unless
+    /// back-references make their futures differ.
+    fn add(&mut self, threads: &mut Threads, thread: Thread, pos: usize) -> Result<(), c_int> {
+        let mut stack = vec![thread];
+        while let Some(mut thread) = stack.pop() {
+            self.steps += 1;
+            if self.steps > self.budget {
+                return Err(REG_ESPACE);
+            }
+
+            let pc = thread.pc;
+            if self.ere.has_backrefs {
+                // Looking through every thread counts too
+                self.steps += threads.list.len();
+                if threads.list.contains(&thread) {
+                    continue;
+                }
+                threads.list.push(thread.clone());
+            } else {
+                let i = threads.slots[pc];
+                if i == !0 {
+                    threads.slots[pc] = threads.list.len();
+                    threads.list.push(thread.clone());
+                } else if preferred(&thread.caps, &threads.list[i].caps) {
+                    threads.list[i] = thread.clone();
+                } else {
+                    continue;
+                }
+            }
+
+            match self.ere.prog[pc] {
+                Inst::Bol if self.at_bol(pos) => thread.pc += 1,
+                Inst::Eol if self.at_eol(pos) => thread.pc += 1,
+                Inst::Backref(index) => match self.group(&thread, index) {
+                    Some((so, eo)) if so == eo => thread.pc += 1,
+                    _ => continue,
+                },
+                Inst::Save(slot) => {
+                    thread.caps[slot] = pos;
+                    thread.pc += 1;
+                }
+                Inst::Mark(reg) => {
+                    thread.regs[reg] = pos;
+                    thread.pc += 1;
+                }
+                Inst::Split(a, b) => {
+                    let mut other = thread.clone();
+                    other.pc = b;
+                    stack.push(other);
+                    thread.pc = a;
+                }
+                Inst::Jmp(a) => thread.pc = a,
+                Inst::Loop(reg, back, exit) => {
+                    // Another iteration has to consume something
+                    if thread.regs[reg] != pos {
+                        let mut other = thread.clone();
+                        other.pc = exit;
+                        stack.push(other);
+                        thread.pc = back;
+                    } else {
+                        thread.pc = exit;
+                    }
+                }
+                _ => continue,
+            }
+            stack.push(thread);
+        }
+        Ok(())
+    }
+
+    /// Moves every thread past the byte at `pos`
+    fn step(
+        &mut self,
+        threads: &Threads,
+        next: &mut Threads,
+        pos: usize,
+        best: &Option<Captures>,
+    ) -> Result<(), c_int> {
+        let b = self.input[pos];
+        for thread in &threads.list {
+            // Nothing starting after the best match can replace it
+            if best.as_ref().map_or(false, |best| thread.caps[0] > best[0]) {
+                continue;
+            }
+            let mut thread = thread.clone();
+            match self.ere.prog[thread.pc] {
+                inst @ Inst::Byte(_) | inst @ Inst::Any | inst @ Inst::Set(..) => {
+                    if !self.byte_matches(inst, b) {
+                        continue;
+                    }
+                    thread.pc += 1;
+                }
+                Inst::Backref(index) => {
+                    let (so, eo) = match self.group(&thread, index) {
+                        Some((so, eo)) if so < eo => (so, eo),
+                        _ => continue,
+                    };
+                    let c = self.input[so + thread.skip];
+                    if !(c == b
+                        || (self.flags.icase && c.to_ascii_lowercase() == b.to_ascii_lowercase()))
+                    {
+                        continue;
+                    }
+                    thread.skip += 1;
+                    if thread.skip == eo - so {
+                        thread.skip = 0;
+                        thread.pc += 1;
+                    }
+                }
+                _ => continue,
+            }
+            self.add(next, thread, pos + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Runs every thread at once over the input, starting a new one at each
+    /// position until something matches
+    fn run(&mut self) -> Result<Option<Captures>, c_int> {
+        let len = self.ere.prog.len();
+        let mut threads = Threads::new(len);
+        let mut next = Threads::new(len);
+        let mut best: Option<Captures> = None;
+
+        for pos in 0..=self.input.len() {
+            if best.is_none() {
+                let thread = Thread {
+                    pc: 0,
+                    caps: vec![!0; (self.ere.nsub + 1) * 2],
+                    regs: vec![!0; self.ere.loops],
+                    skip: 0,
+                };
+                self.add(&mut threads, thread, pos)?;
+            }
+            for thread in &threads.list {
+                if matches!(self.ere.prog[thread.pc], Inst::Match)
+                    && best
+                        .as_ref()
+                        .map_or(true, |best| preferred(&thread.caps, best))
+                {
+                    best = Some(thread.caps.clone());
+                }
+            }
+            if pos == self.input.len() || (threads.list.is_empty() && best.is_some()) {
+                break;
+            }
+
+            next.clear();
+            self.step(&threads, &mut next, pos, &best)?;
+            mem::swap(&mut threads, &mut next);
+        }
+        Ok(best)
+    }
+}
+
+/// Whether `new` is preferred over `old`, starting with the leftmost
+fn preferred(new: &[usize], old: &[usize]) -> bool {
+    if new[0] != old[0] {
+        return new[0] < old[0];
+    }
+    better(new, old)
+}
+
+/// Whether `new` is preferred over `old` by the POSIX rules, when both start
+/// at the same place
+fn better(new: &[usize], old: &[usize]) -> bool {
+    if new[1] != old[1] {
+        return new[1] > old[1];
+    }
+    for group in new[2..].chunks(2).zip(old[2..].chunks(2)) {
+        let ((new_so, new_eo), (old_so, old_eo)) =
+            ((group.0[0], group.0[1]), (group.1[0], group.1[1]));
+        if (new_so, new_eo) == (old_so, old_eo) {
+            continue;
+        }
+        // Taking part at all comes first, then starting early, then being long
+        if old_so == !0 {
+            return true;
+        }
+        if new_so == !0 {
+            return false;
+        }
+        if new_so != old_so {
+            return new_so < old_so;
+        }
+        return new_eo > old_eo;
+    }
+    false
+}
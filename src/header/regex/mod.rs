@@ -1,19 +1,23 @@
 //! regex.h implementation, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/regex.h.html
 
 use crate::{header::string::strlen, platform::types::*};
-use alloc::{borrow::Cow, vec::Vec};
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
 use core::{mem, ptr, slice};
 use posix_regex::{
     compile::{Error as CompileError, Range, Token},
     PosixRegex, PosixRegexBuilder,
 };
 
+use self::ere::Ere;
+
+mod ere;
+
 pub type regoff_t = size_t;
 
 #[repr(C)]
 pub struct regex_t {
     // Can't be a normal Vec<T> because then the struct size won't be known
-    // from C.
+    // from C. Extended expressions are a boxed Ere instead.
     ptr: *mut c_void,
     length: size_t,
     capacity: size_t,
@@ -52,11 +56,26 @@ pub const REG_BADRPT: c_int = 14;
 #[no_mangle]
 #[linkage = "weak"] // redefined in GIT
 pub unsafe extern "C" fn regcomp(out: *mut regex_t, pat: *const c_char, cflags: c_int) -> c_int {
+    let pat = slice::from_raw_parts(pat as *const u8, strlen(pat));
+
     if cflags & REG_EXTENDED == REG_EXTENDED {
-        return REG_ENOSYS;
+        return match Ere::compile(pat, cflags & REG_ICASE == REG_ICASE) {
+            Ok(ere) => {
+                let re_nsub = ere.nsub();
+                *out = regex_t {
+                    ptr: Box::into_raw(Box::new(ere)) as *mut c_void,
+                    length: 0,
+                    capacity: 0,
+
+                    cflags,
+                    re_nsub,
+                };
+                0
+            }
+            Err(code) => code,
+        };
     }
 
-    let pat = slice::from_raw_parts(pat as *const u8, strlen(pat));
     let res = PosixRegexBuilder::new(pat)
         .with_default_classes()
         .compile_tokens();
@@ -89,6 +108,11 @@ pub unsafe extern "C" fn regcomp(out: *mut regex_t, pat: *const c_char, cflags:
 #[no_mangle]
 #[linkage = "weak"] // redefined in GIT
 pub unsafe extern "C" fn regfree(regex: *mut regex_t) {
+    if (*regex).cflags & REG_EXTENDED == REG_EXTENDED {
+        drop(Box::from_raw((*regex).ptr as *mut Ere));
+        return;
+    }
+
     Vec::from_raw_parts(
         (*regex).ptr as *mut Vec<(Token, Range)>,
         (*regex).length,
@@ -105,10 +129,6 @@ pub unsafe extern "C" fn regexec(
     pmatch: *mut regmatch_t,
     eflags: c_int,
 ) -> c_int {
-    if eflags & REG_EXTENDED == REG_EXTENDED {
-        return REG_ENOSYS;
-    }
-
     let regex = &*regex;
 
     // Allow specifying a compiler argument to the executor and vise versa
@@ -116,6 +136,40 @@ pub unsafe extern "C" fn regexec(
     let flags = regex.cflags | eflags;
 
     let input = slice::from_raw_parts(input as *const u8, strlen(input));
+
+    // Only the compiler flags say what regex.ptr points to
+    if regex.cflags & REG_EXTENDED == REG_EXTENDED {
+        let ere = &*(regex.ptr as *const Ere);
+        let captures = ere.exec(
+            input,
+            &ere::Flags {
+                icase: flags & REG_ICASE == REG_ICASE,
+                newline: flags & REG_NEWLINE == REG_NEWLINE,
+                notbol: flags & REG_NOTBOL == REG_NOTBOL,
+                noteol: flags & REG_NOTEOL == REG_NOTEOL,
+            },
+        );
+        let captures = match captures {
+            Ok(Some(captures)) => captures,
+            Ok(None) => return REG_NOMATCH,
+            Err(err) => return err,
+        };
+
+        if flags & REG_NOSUB != REG_NOSUB && !pmatch.is_null() {
+            for i in 0..nmatch {
+                let (start, end) = match captures.get(i * 2..i * 2 + 2) {
+                    Some(&[start, end]) if start != !0 && end != !0 => (start, end),
+                    _ => (!0, !0),
+                };
+                *pmatch.add(i) = regmatch_t {
+                    rm_so: start,
+                    rm_eo: end,
+                };
+            }
+        }
+        return 0;
+    }
+
     let branches = slice::from_raw_parts(regex.ptr as *const Vec<(Token, Range)>, regex.length);
 
     let matches = PosixRegex::new(Cow::Borrowed(&branches))
@@ -0,0 +1,59 @@
+//! sched.h implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/sched.h.html
+
+use crate::{
+    header::errno::EINVAL,
+    platform::{self, types::*, Pal, Sys},
+};
+
+pub const SCHED_OTHER: c_int = 0;
+pub const SCHED_FIFO: c_int = 1;
+pub const SCHED_RR: c_int = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct sched_param {
+    pub sched_priority: c_int,
+}
+
+#[no_mangle]
+pub extern "C" fn sched_get_priority_max(policy: c_int) -> c_int {
+    match policy {
+        SCHED_FIFO | SCHED_RR => 99,
+        SCHED_OTHER => 0,
+        _ => {
+            unsafe { platform::errno = EINVAL };
+            -1
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn sched_get_priority_min(policy: c_int) -> c_int {
+    match policy {
+        SCHED_FIFO | SCHED_RR => 1,
+        SCHED_OTHER => 0,
+        _ => {
+            unsafe { platform::errno = EINVAL };
+            -1
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sched_setparam(pid: pid_t, param: *const sched_param) -> c_int {
+    Sys::sched_setparam(pid, param)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sched_setscheduler(
+    pid: pid_t,
+    policy: c_int,
+    param: *const sched_param,
+) -> c_int {
+    Sys::sched_setscheduler(pid, policy, param)
+}
+
+#[no_mangle]
+pub extern "C" fn sched_yield() -> c_int {
+    Sys::sched_yield()
+}
@@ -0,0 +1,164 @@
+//! hsearch and friends, on an open addressing hash table with linear probing
+
+use core::{mem, ptr};
+
+use crate::{
+    header::{
+        errno::{EINVAL, ENOMEM, ESRCH},
+        string::strcmp,
+    },
+    platform::{self, types::*},
+};
+
+pub type ACTION = c_int;
+
+pub const FIND: ACTION = 0;
+pub const ENTER: ACTION = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct entry {
+    pub key: *mut c_char,
+    pub data: *mut c_void,
+}
+
+pub type ENTRY = entry;
+
+#[repr(C)]
+pub struct hsearch_data {
+    table: *mut ENTRY,
+    size: c_uint,
+    filled: c_uint,
+}
+
+static mut HTAB: hsearch_data = hsearch_data {
+    table: ptr::null_mut(),
+    size: 0,
+    filled: 0,
+};
+
+/// FNV-1a
+unsafe fn hash(mut key: *const c_char) -> usize {
+    let mut hash: u32 = 0x811c_9dc5;
+    while *key != 0 {
+        hash ^= *key as u8 as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        key = key.add(1);
+    }
+    hash as usize
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hcreate_r(nel: size_t, htab: *mut hsearch_data) -> c_int {
+    if htab.is_null() {
+        platform::errno = EINVAL;
+        return 0;
+    }
+    let htab = &mut *htab;
+    if !htab.table.is_null() {
+        return 0;
+    }
+
+    // Keep the table at most three quarters full, and a power of two so
+    // that probing can wrap with a mask
+    let size = match nel
+        .checked_add(nel / 3 + 1)
+        .and_then(|size| size.max(8).checked_next_power_of_two())
+    {
+        Some(size) if size <= c_uint::max_value() as usize => size,
+        _ => {
+            platform::errno = ENOMEM;
+            return 0;
+        }
+    };
+
+    let table = platform::alloc(size * mem::size_of::<ENTRY>()) as *mut ENTRY;
+    if table.is_null() {
+        platform::errno = ENOMEM;
+        return 0;
+    }
+    for i in 0..size {
+        *table.add(i) = entry {
+            key: ptr::null_mut(),
+            data: ptr::null_mut(),
+        };
+    }
+
+    htab.table = table;
+    htab.size = size as c_uint;
+    htab.filled = 0;
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hdestroy_r(htab: *mut hsearch_data) {
+    if htab.is_null() {
+        platform::errno = EINVAL;
+        return;
+    }
+    let htab = &mut *htab;
+    platform::free(htab.table as *mut c_void);
+    htab.table = ptr::null_mut();
+    htab.size = 0;
+    htab.filled = 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hsearch_r(
+    item: ENTRY,
+    action: ACTION,
+    retval: *mut *mut ENTRY,
+    htab: *mut hsearch_data,
+) -> c_int {
+    let htab = &mut *htab;
+    *retval = ptr::null_mut();
+    if htab.table.is_null() {
+        platform::errno = ESRCH;
+        return 0;
+    }
+
+    let mask = htab.size as usize - 1;
+    let mut i = hash(item.key) & mask;
+    let slot = loop {
+        let slot = htab.table.add(i);
+        if (*slot).key.is_null() {
+            break slot;
+        }
+        if strcmp((*slot).key, item.key) == 0 {
+            *retval = slot;
+            return 1;
+        }
+        i = (i + 1) & mask;
+    };
+
+    if action != ENTER {
+        platform::errno = ESRCH;
+        return 0;
+    }
+    // There must always be an empty slot left to end a search
+    if htab.filled as usize + 1 >= htab.size as usize {
+        platform::errno = ENOMEM;
+        return 0;
+    }
+    *slot = item;
+    htab.filled += 1;
+    *retval = slot;
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hcreate(nel: size_t) -> c_int {
+    hcreate_r(nel, &mut HTAB)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hdestroy() {
+    hdestroy_r(&mut HTAB);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hsearch(item: ENTRY, action: ACTION) -> *mut ENTRY {
+    let mut retval = ptr::null_mut();
+    hsearch_r(item, action, &mut retval, &mut HTAB);
+    retval
+}
@@ -0,0 +1,99 @@
+//! search.h implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/search.h.html
+
+use core::ptr;
+
+use crate::platform::types::*;
+
+pub use self::hsearch::*;
+pub mod hsearch;
+
+pub use self::tsearch::*;
+pub mod tsearch;
+
+pub type VISIT = c_int;
+
+pub const preorder: VISIT = 0;
+pub const postorder: VISIT = 1;
+pub const endorder: VISIT = 2;
+pub const leaf: VISIT = 3;
+
+type Compare = Option<unsafe extern "C" fn(*const c_void, *const c_void) -> c_int>;
+
+// Any structure starting with these two pointers can be used with insque
+#[repr(C)]
+struct qelem {
+    q_forw: *mut qelem,
+    q_back: *mut qelem,
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn insque(element: *mut c_void, pred: *mut c_void) {
+    let element = element as *mut qelem;
+    let pred = pred as *mut qelem;
+
+    // A null predecessor starts a new list
+    if pred.is_null() {
+        (*element).q_forw = ptr::null_mut();
+        (*element).q_back = ptr::null_mut();
+        return;
+    }
+
+    (*element).q_forw = (*pred).q_forw;
+    (*element).q_back = pred;
+    if !(*pred).q_forw.is_null() {
+        (*(*pred).q_forw).q_back = element;
+    }
+    (*pred).q_forw = element;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn remque(element: *mut c_void) {
+    let element = element as *mut qelem;
+    if !(*element).q_forw.is_null() {
+        (*(*element).q_forw).q_back = (*element).q_back;
+    }
+    if !(*element).q_back.is_null() {
+        (*(*element).q_back).q_forw = (*element).q_forw;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lfind(
+    key: *const c_void,
+    base: *const c_void,
+    nelp: *mut size_t,
+    width: size_t,
+    compar: Compare,
+) -> *mut c_void {
+    let compar = match compar {
+        Some(compar) => compar,
+        None => return ptr::null_mut(),
+    };
+    for i in 0..*nelp {
+        let item = (base as *const u8).add(i * width) as *const c_void;
+        if compar(key, item) == 0 {
+            return item as *mut c_void;
+        }
+    }
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lsearch(
+    key: *const c_void,
+    base: *mut c_void,
+    nelp: *mut size_t,
+    width: size_t,
+    compar: Compare,
+) -> *mut c_void {
+    let item = lfind(key, base, nelp, width, compar);
+    if !item.is_null() || compar.is_none() {
+        return item;
+    }
+
+    // Not found, so add it to the end
+    let item = (base as *mut u8).add(*nelp * width);
+    ptr::copy_nonoverlapping(key as *const u8, item, width);
+    *nelp += 1;
+    item as *mut c_void
+}
@@ -0,0 +1,278 @@
+//! tsearch and friends, on binary search trees kept balanced as AVL trees
+
+use core::{cmp, mem, ptr};
+
+use crate::platform::{self, types::*};
+
+use super::{endorder, leaf, postorder, preorder, Compare, VISIT};
+
+// The key must come first, since callers read it through the returned node
+#[repr(C)]
+struct Node {
+    key: *const c_void,
+    left: *mut Node,
+    right: *mut Node,
+    height: c_int,
+}
+
+unsafe fn height(node: *mut Node) -> c_int {
+    if node.is_null() {
+        0
+    } else {
+        (*node).height
+    }
+}
+
+unsafe fn update(node: *mut Node) {
+    (*node).height = 1 + cmp::max(height((*node).left), height((*node).right));
+}
+
+unsafe fn rotate_left(node: *mut Node) -> *mut Node {
+    let right = (*node).right;
+    (*node).right = (*right).left;
+    (*right).left = node;
+    update(node);
+    update(right);
+    right
+}
+
+unsafe fn rotate_right(node: *mut Node) -> *mut Node {
+    let left = (*node).left;
+    (*node).left = (*left).right;
+    (*left).right = node;
+    update(node);
+    update(left);
+    left
+}
+
+/// Restores the balance of a subtree after one of its children changed
+/// height by one, returning its new root
+unsafe fn balance(node: *mut Node) -> *mut Node {
+    update(node);
+    let diff = height((*node).left) - height((*node).right);
+    if diff > 1 {
+        if height((*(*node).left).left) < height((*(*node).left).right) {
+            (*node).left = rotate_left((*node).left);
+        }
+        rotate_right(node)
+    } else if diff < -1 {
+        if height((*(*node).right).right) < height((*(*node).right).left) {
+            (*node).right = rotate_right((*node).right);
+        }
+        rotate_left(node)
+    } else {
+        node
+    }
+}
+
+/// Inserts `key` unless it is already there, setting `found` to its node.
+/// Nothing is changed if the allocation fails.
+unsafe fn insert(
+    node: *mut Node,
+    key: *const c_void,
+    compar: unsafe extern "C" fn(*const c_void, *const c_void) -> c_int,
+    found: &mut *mut Node,
+) -> Option<*mut Node> {
+    if node.is_null() {
+        let node = platform::alloc(mem::size_of::<Node>()) as *mut Node;
+        if node.is_null() {
+            return None;
+        }
+        *node = Node {
+            key,
+            left: ptr::null_mut(),
+            right: ptr::null_mut(),
+            height: 1,
+        };
+        *found = node;
+        return Some(node);
+    }
+
+    let diff = compar(key, (*node).key);
+    if diff == 0 {
+        *found = node;
+        return Some(node);
+    }
+    if diff < 0 {
+        (*node).left = insert((*node).left, key, compar, found)?;
+    } else {
+        (*node).right = insert((*node).right, key, compar, found)?;
+    }
+    Some(balance(node))
+}
+
+/// Unlinks the smallest node of a subtree, returning the new root of the
+/// subtree and the node
+unsafe fn remove_min(node: *mut Node) -> (*mut Node, *mut Node) {
+    if (*node).left.is_null() {
+        return ((*node).right, node);
+    }
+    let (left, min) = remove_min((*node).left);
+    (*node).left = left;
+    (balance(node), min)
+}
+
+/// Removes `key`, setting `parent` to the parent of the removed node
+unsafe fn remove(
+    node: *mut Node,
+    key: *const c_void,
+    compar: unsafe extern "C" fn(*const c_void, *const c_void) -> c_int,
+    up: *mut Node,
+    parent: &mut Option<*mut Node>,
+) -> *mut Node {
+    if node.is_null() {
+        return node;
+    }
+
+    let diff = compar(key, (*node).key);
+    if diff < 0 {
+        (*node).left = remove((*node).left, key, compar, node, parent);
+        return balance(node);
+    }
+    if diff > 0 {
+        (*node).right = remove((*node).right, key, compar, node, parent);
+        return balance(node);
+    }
+
+    *parent = Some(up);
+    let replacement = if (*node).left.is_null() {
+        (*node).right
+    } else if (*node).right.is_null() {
+        (*node).left
+    } else {
+        // The next node in order takes the place of the removed one
+        let (right, min) = remove_min((*node).right);
+        (*min).left = (*node).left;
+        (*min).right = right;
+        balance(min)
+    };
+    platform::free(node as *mut c_void);
+    replacement
+}
+
+unsafe fn walk(
+    node: *const Node,
+    action: unsafe extern "C" fn(*const c_void, VISIT, c_int),
+    depth: c_int,
+) {
+    if (*node).left.is_null() && (*node).right.is_null() {
+        action(node as *const c_void, leaf, depth);
+        return;
+    }
+
+    action(node as *const c_void, preorder, depth);
+    if !(*node).left.is_null() {
+        walk((*node).left, action, depth + 1);
+    }
+    action(node as *const c_void, postorder, depth);
+    if !(*node).right.is_null() {
+        walk((*node).right, action, depth + 1);
+    }
+    action(node as *const c_void, endorder, depth);
+}
+
+unsafe fn destroy(node: *mut Node, free_node: unsafe extern "C" fn(*mut c_void)) {
+    if node.is_null() {
+        return;
+    }
+    destroy((*node).left, free_node);
+    destroy((*node).right, free_node);
+    free_node((*node).key as *mut c_void);
+    platform::free(node as *mut c_void);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tsearch(
+    key: *const c_void,
+    rootp: *mut *mut c_void,
+    compar: Compare,
+) -> *mut c_void {
+    let compar = match compar {
+        Some(compar) if !rootp.is_null() => compar,
+        _ => return ptr::null_mut(),
+    };
+
+    let mut found = ptr::null_mut();
+    match insert(*rootp as *mut Node, key, compar, &mut found) {
+        Some(root) => {
+            *rootp = root as *mut c_void;
+            found as *mut c_void
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tfind(
+    key: *const c_void,
+    rootp: *const *mut c_void,
+    compar: Compare,
+) -> *mut c_void {
+    let compar = match compar {
+        Some(compar) if !rootp.is_null() => compar,
+        _ => return ptr::null_mut(),
+    };
+
+    let mut node = *rootp as *mut Node;
+    while !node.is_null() {
+        let diff = compar(key, (*node).key);
+        if diff == 0 {
+            return node as *mut c_void;
+        }
+        node = if diff < 0 {
+            (*node).left
+        } else {
+            (*node).right
+        };
+    }
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tdelete(
+    key: *const c_void,
+    rootp: *mut *mut c_void,
+    compar: Compare,
+) -> *mut c_void {
+    let compar = match compar {
+        Some(compar) if !rootp.is_null() => compar,
+        _ => return ptr::null_mut(),
+    };
+
+    let mut parent = None;
+    *rootp = remove(
+        *rootp as *mut Node,
+        key,
+        compar,
+        ptr::null_mut(),
+        &mut parent,
+    ) as *mut c_void;
+    match parent {
+        // The root has no parent, but success must still be non-null
+        Some(parent) if parent.is_null() => rootp as *mut c_void,
+        Some(parent) => parent as *mut c_void,
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn twalk(
+    root: *const c_void,
+    action: Option<unsafe extern "C" fn(*const c_void, VISIT, c_int)>,
+) {
+    if let Some(action) = action {
+        if !root.is_null() {
+            walk(root as *const Node, action, 0);
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tdestroy(
+    root: *mut c_void,
+    free_node: Option<unsafe extern "C" fn(*mut c_void)>,
+) {
+    unsafe extern "C" fn keep(_key: *mut c_void) {}
+
+    destroy(root as *mut Node, free_node.unwrap_or(keep));
+}
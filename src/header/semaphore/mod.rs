@@ -1,4 +1,24 @@
-use crate::platform::types::*;
+//! semaphore.h implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/semaphore.h.html
+
+use alloc::vec::Vec;
+use core::{mem, ptr, slice};
+
+use crate::{
+    c_str::CStr,
+    header::{
+        errno::{EEXIST, EINVAL, ENAMETOOLONG, ENOENT},
+        fcntl::{O_CLOEXEC, O_CREAT, O_EXCL, O_RDWR},
+        pthread::cancellation_point,
+        stdlib,
+        sys_mman::{MAP_SHARED, PROT_READ, PROT_WRITE, SHM_PATH},
+        sys_stat::stat,
+        time::{timespec, CLOCK_REALTIME},
+    },
+    platform::{self, types::*, Pal, Sys},
+    sync::{Mutex, Semaphore},
+};
+
+pub const SEM_VALUE_MAX: c_int = c_int::max_value();
 
 #[repr(C)]
 #[derive(Copy)]
@@ -12,50 +32,309 @@ impl Clone for sem_t {
         *self
     }
 }
-// #[no_mangle]
-pub extern "C" fn sem_init(sem: *mut sem_t, pshared: c_int, value: c_uint) -> c_int {
-    unimplemented!();
+
+// Fail to compile if the C type is too small
+const _: [(); 0] = [(); (mem::size_of::<Semaphore>() > mem::size_of::<sem_t>()) as usize];
+
+unsafe fn sem<'a>(sem: *mut sem_t) -> &'a Semaphore {
+    &*(sem as *const Semaphore)
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_destroy(sem: *mut sem_t) -> c_int {
-    unimplemented!();
+unsafe fn result(ret: Result<(), c_int>) -> c_int {
+    match ret {
+        Ok(()) => 0,
+        Err(err) => {
+            platform::errno = err;
+            -1
+        }
+    }
 }
 
-/*
- *#[no_mangle]
- *pub extern "C" fn sem_open(name: *const c_char,
- *                    oflag: c_int, ...) -> *mut sem_t {
- *    unimplemented!();
- *}
- */
+#[no_mangle]
+pub unsafe extern "C" fn sem_clockwait(
+    sem: *mut sem_t,
+    clock_id: clockid_t,
+    abstime: *const timespec,
+) -> c_int {
+    let sem = self::sem(sem);
+    cancellation_point(|| {
+        // The timeout is only looked at if the semaphore can't be taken
+        // right away
+        if sem.try_wait().is_ok() {
+            return 0;
+        }
+        if (*abstime).tv_nsec < 0 || (*abstime).tv_nsec >= 1_000_000_000 {
+            platform::errno = EINVAL;
+            return -1;
+        }
+        result(sem.wait(Some((clock_id, &*abstime))))
+    })
+}
 
-// #[no_mangle]
-pub extern "C" fn sem_close(sem: *mut sem_t) -> c_int {
-    unimplemented!();
+#[no_mangle]
+pub unsafe extern "C" fn sem_destroy(sem: *mut sem_t) -> c_int {
+    0
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_unlink(name: *const c_char) -> c_int {
-    unimplemented!();
+#[no_mangle]
+pub unsafe extern "C" fn sem_getvalue(sem: *mut sem_t, sval: *mut c_int) -> c_int {
+    *sval = self::sem(sem).value();
+    0
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_wait(sem: *mut sem_t) -> c_int {
-    unimplemented!();
+/// Since the semaphore is a futex word that is not process private, it
+/// works between processes as long as it is in shared memory, so `pshared`
+/// changes nothing
+#[no_mangle]
+pub unsafe extern "C" fn sem_init(sem: *mut sem_t, pshared: c_int, value: c_uint) -> c_int {
+    if value > SEM_VALUE_MAX as c_uint {
+        platform::errno = EINVAL;
+        return -1;
+    }
+    ptr::write(sem as *mut Semaphore, Semaphore::new(value as c_int));
+    0
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_trywait(sem: *mut sem_t) -> c_int {
-    unimplemented!();
+#[no_mangle]
+pub unsafe extern "C" fn sem_post(sem: *mut sem_t) -> c_int {
+    result(self::sem(sem).post())
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_post(sem: *mut sem_t) -> c_int {
-    unimplemented!();
+#[no_mangle]
+pub unsafe extern "C" fn sem_timedwait(sem: *mut sem_t, abstime: *const timespec) -> c_int {
+    sem_clockwait(sem, CLOCK_REALTIME, abstime)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sem_trywait(sem: *mut sem_t) -> c_int {
+    result(self::sem(sem).try_wait())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sem_wait(sem: *mut sem_t) -> c_int {
+    cancellation_point(|| result(self::sem(sem).wait(None)))
+}
+
+// Named semaphores are files in SEM_DIR_ENV, or the shared memory directory
+// by default, mapped into every process that opens them
+
+/// Also defined in bits/semaphore.h, as cbindgen can't export pointers
+const SEM_FAILED: *mut sem_t = ptr::null_mut();
+
+const SEM_DIR_ENV: &[u8] = b"RELIBC_SEM_DIR\0";
+const SEM_PREFIX: &[u8] = b"sem.";
+/// Where semaphores are written before being linked into place. Unlike the
+/// file of any semaphore it starts with a dot, so the two never collide.
+const SEM_TEMPLATE: &[u8] = b".sem.XXXXXX\0";
+const SEM_NAME_MAX: usize = 251;
+
+struct NamedSem {
+    dev: dev_t,
+    ino: ino_t,
+    sem: usize,
+    /// The number of sem_open calls not yet matched by sem_close
+    refs: usize,
+}
+
+/// Every semaphore mapped in by sem_open, so that opening one twice gives
+/// the same address and it is only unmapped by the last sem_close
+static NAMED: Mutex<Vec<NamedSem>> = Mutex::new(Vec::new());
+
+/// Returns the path of the file behind the semaphore `name`, plus the
+/// length of its directory part
+unsafe fn sem_path(name: *const c_char) -> Result<(Vec<u8>, usize), c_int> {
+    let mut name = CStr::from_ptr(name).to_bytes();
+    while let Some((b'/', rest)) = name.split_first() {
+        name = rest;
+    }
+    if name.is_empty() || name.contains(&b'/') || name == b"." || name == b".." {
+        return Err(EINVAL);
+    }
+    if name.len() > SEM_NAME_MAX {
+        return Err(ENAMETOOLONG);
+    }
+
+    let dir = stdlib::getenv(SEM_DIR_ENV.as_ptr() as *const c_char);
+    let mut path = if dir.is_null() || *dir == 0 {
+        SHM_PATH.to_vec()
+    } else {
+        let mut path = CStr::from_ptr(dir).to_bytes().to_vec();
+        if path.last() != Some(&b'/') {
+            path.push(b'/');
+        }
+        path
+    };
+    let dir_len = path.len();
+    path.extend_from_slice(SEM_PREFIX);
+    path.extend_from_slice(name);
+    Ok((path, dir_len))
+}
+
+/// Creates the file of a semaphore with the given initial value. It is
+/// written in full under a temporary name and then linked into place, so
+/// nobody can open it half initialized. Fails with EEXIST if it exists.
+unsafe fn create(path: &CStr, dir: &[u8], mode: mode_t, value: c_int) -> Result<c_int, c_int> {
+    let mut template = dir.to_vec();
+    template.extend_from_slice(SEM_TEMPLATE);
+    let fd = stdlib::mkostemps(template.as_mut_ptr() as *mut c_char, 0, O_CLOEXEC);
+    if fd < 0 {
+        return Err(platform::errno);
+    }
+    let template = CStr::from_ptr(template.as_ptr() as *const c_char);
+
+    let mut contents: sem_t = mem::zeroed();
+    ptr::write(
+        &mut contents as *mut sem_t as *mut Semaphore,
+        Semaphore::new(value),
+    );
+    let bytes = slice::from_raw_parts(
+        &contents as *const sem_t as *const u8,
+        mem::size_of::<sem_t>(),
+    );
+
+    let ret = if Sys::fchmod(fd, mode) < 0
+        || Sys::write(fd, bytes) != bytes.len() as ssize_t
+        || Sys::link(template, path) < 0
+    {
+        Err(platform::errno)
+    } else {
+        Ok(fd)
+    };
+    Sys::unlink(template);
+    if ret.is_err() {
+        Sys::close(fd);
+    }
+    ret
+}
+
+/// Maps in the semaphore file `fd`, or finds where it already is, and
+/// closes `fd`
+unsafe fn map(fd: c_int) -> Result<*mut sem_t, c_int> {
+    let mut st: stat = mem::zeroed();
+    let ret = if Sys::fstat(fd, &mut st) < 0 {
+        Err(platform::errno)
+    } else if st.st_size < mem::size_of::<sem_t>() as off_t {
+        Err(EINVAL)
+    } else {
+        let mut named = NAMED.lock();
+        if let Some(entry) = named
+            .iter_mut()
+            .find(|entry| entry.dev == st.st_dev && entry.ino == st.st_ino)
+        {
+            entry.refs += 1;
+            Ok(entry.sem as *mut sem_t)
+        } else {
+            let sem = Sys::mmap(
+                ptr::null_mut(),
+                mem::size_of::<sem_t>(),
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED,
+                fd,
+                0,
+            );
+            if sem as usize == !0
+            /* MAP_FAILED */
+            {
+                Err(platform::errno)
+            } else {
+                named.push(NamedSem {
+                    dev: st.st_dev,
+                    ino: st.st_ino,
+                    sem: sem as usize,
+                    refs: 1,
+                });
+                Ok(sem as *mut sem_t)
+            }
+        }
+    };
+    Sys::close(fd);
+    ret
+}
+
+/// The part of sem_open after the variadic arguments are read, see
+/// src/c/semaphore.c
+#[no_mangle]
+pub unsafe extern "C" fn sys_sem_open(
+    name: *const c_char,
+    oflag: c_int,
+    mode: mode_t,
+    value: c_uint,
+) -> *mut sem_t {
+    let (mut path, dir_len) = match sem_path(name) {
+        Ok(path) => path,
+        Err(err) => {
+            platform::errno = err;
+            return SEM_FAILED;
+        }
+    };
+    if oflag & O_CREAT == O_CREAT && value > SEM_VALUE_MAX as c_uint {
+        platform::errno = EINVAL;
+        return SEM_FAILED;
+    }
+    let dir = path[..dir_len].to_vec();
+    path.push(0);
+    let path = CStr::from_bytes_with_nul_unchecked(&path);
+
+    let fd = loop {
+        if oflag & (O_CREAT | O_EXCL) != O_CREAT | O_EXCL {
+            let fd = Sys::open(path, O_RDWR | O_CLOEXEC, 0);
+            if fd >= 0 {
+                break fd;
+            }
+            if platform::errno != ENOENT || oflag & O_CREAT != O_CREAT {
+                return SEM_FAILED;
+            }
+        }
+        match create(path, &dir, mode, value as c_int) {
+            Ok(fd) => break fd,
+            // Someone else created it first, so use theirs
+            Err(EEXIST) if oflag & O_EXCL != O_EXCL => continue,
+            Err(err) => {
+                platform::errno = err;
+                return SEM_FAILED;
+            }
+        }
+    };
+
+    match map(fd) {
+        Ok(sem) => sem,
+        Err(err) => {
+            platform::errno = err;
+            SEM_FAILED
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sem_close(sem: *mut sem_t) -> c_int {
+    let mut named = NAMED.lock();
+    let i = match named.iter().position(|entry| entry.sem == sem as usize) {
+        Some(i) => i,
+        None => {
+            platform::errno = EINVAL;
+            return -1;
+        }
+    };
+    named[i].refs -= 1;
+    if named[i].refs == 0 {
+        named.swap_remove(i);
+        Sys::munmap(sem as *mut c_void, mem::size_of::<sem_t>());
+    }
+    0
 }
 
-// #[no_mangle]
-pub extern "C" fn sem_getvalue(sem: *mut sem_t, sval: *mut c_int) -> c_int {
-    unimplemented!();
+/// Removes the name right away. Processes that have the semaphore open
+/// keep using it until they close it, and a new semaphore of the same name
+/// is a different one.
+#[no_mangle]
+pub unsafe extern "C" fn sem_unlink(name: *const c_char) -> c_int {
+    let mut path = match sem_path(name) {
+        Ok((path, _)) => path,
+        Err(err) => {
+            platform::errno = err;
+            return -1;
+        }
+    };
+    path.push(0);
+    Sys::unlink(CStr::from_bytes_with_nul_unchecked(&path))
 }
@@ -56,6 +56,9 @@ pub const SIGSYS: usize = 31;
 pub const SIGUNUSED: usize = SIGSYS;
 pub const NSIG: usize = 32;
 
+/// Reserved for pthread_cancel
+pub(crate) const SIGCANCEL: usize = 32;
+
 pub const SA_NOCLDSTOP: usize = 1;
 pub const SA_NOCLDWAIT: usize = 2;
 pub const SA_SIGINFO: usize = 4;
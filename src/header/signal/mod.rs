@@ -1,11 +1,11 @@
 //! signal implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/signal.h.html
 
-use core::mem;
+use core::{mem, ptr};
 
 use cbitset::BitSet;
 
 use crate::{
-    header::errno,
+    header::{errno, pthread::Pthread, sys_types::pthread_t},
     platform::{self, types::*, PalSignal, Sys},
 };
 
@@ -60,6 +60,16 @@ pub extern "C" fn killpg(pgrp: pid_t, sig: c_int) -> c_int {
     Sys::killpg(pgrp, sig)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn pthread_kill(thread: pthread_t, sig: c_int) -> c_int {
+    let os_tid = (*(thread as *const Pthread)).os_tid();
+    if Sys::rlct_kill(os_tid, sig) == 0 {
+        0
+    } else {
+        platform::errno
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn pthread_sigmask(
     how: c_int,
@@ -86,6 +96,15 @@ pub unsafe extern "C" fn sigaction(
     act: *const sigaction,
     oact: *mut sigaction,
 ) -> c_int {
+    if sig == SIGCANCEL as c_int {
+        platform::errno = errno::EINVAL;
+        return -1;
+    }
+    set_action(sig, act, oact)
+}
+
+/// sigaction without protecting the signals reserved for relibc
+pub(crate) unsafe fn set_action(sig: c_int, act: *const sigaction, oact: *mut sigaction) -> c_int {
     let act_opt = act.as_ref().map(|act| {
         let mut act_clone = act.clone();
         act_clone.sa_flags |= SA_RESTORER as c_ulong;
@@ -223,7 +242,14 @@ pub extern "C" fn sigpending(set: *mut sigset_t) -> c_int {
 
 #[no_mangle]
 pub extern "C" fn sigprocmask(how: c_int, set: *const sigset_t, oset: *mut sigset_t) -> c_int {
-    Sys::sigprocmask(how, set, oset)
+    // SIGCANCEL can't be blocked, or blocked threads couldn't be cancelled
+    let set = unsafe { set.as_ref() }.map(|set| *set & !(1 << (SIGCANCEL - 1)));
+    Sys::sigprocmask(
+        how,
+        set.as_ref()
+            .map_or(ptr::null(), |set| set as *const sigset_t),
+        oset,
+    )
 }
 
 // #[no_mangle]
@@ -53,6 +53,9 @@ pub const SIGPWR: usize = 30;
 pub const SIGSYS: usize = 31;
 pub const NSIG: usize = 32;
 
+/// Reserved for pthread_cancel
+pub(crate) const SIGCANCEL: usize = 32;
+
 pub const SA_NOCLDSTOP: usize = 0x00000001;
 pub const SA_NOCLDWAIT: usize = 0x00000002;
 pub const SA_SIGINFO: usize = 0x00000004;
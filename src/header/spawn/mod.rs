@@ -0,0 +1,593 @@
+//! spawn implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/spawn.h.html
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{mem, ptr};
+
+use crate::{
+    c_str::CStr,
+    header::{
+        errno::{EACCES, EBADF, EINTR, EINVAL, ENAMETOOLONG, ENOENT, ENOMEM, ENOTDIR},
+        fcntl::{FD_CLOEXEC, F_DUPFD, F_GETFD, F_SETFD, O_CLOEXEC},
+        limits::PATH_MAX,
+        sched::sched_param,
+        signal::{
+            sigaction, sigismember, sigset_t, NSIG, SIGKILL, SIGSTOP, SIG_DFL, SIG_IGN, SIG_SETMASK,
+        },
+        stdlib::getenv,
+    },
+    platform::{self, types::*, Pal, PalSignal, Sys},
+};
+
+pub const POSIX_SPAWN_RESETIDS: c_short = 0x01;
+pub const POSIX_SPAWN_SETPGROUP: c_short = 0x02;
+pub const POSIX_SPAWN_SETSIGDEF: c_short = 0x04;
+pub const POSIX_SPAWN_SETSIGMASK: c_short = 0x08;
+pub const POSIX_SPAWN_SETSCHEDPARAM: c_short = 0x10;
+pub const POSIX_SPAWN_SETSCHEDULER: c_short = 0x20;
+pub const POSIX_SPAWN_SETSID: c_short = 0x80;
+
+const POSIX_SPAWN_ALL: c_short = POSIX_SPAWN_RESETIDS
+    | POSIX_SPAWN_SETPGROUP
+    | POSIX_SPAWN_SETSIGDEF
+    | POSIX_SPAWN_SETSIGMASK
+    | POSIX_SPAWN_SETSCHEDPARAM
+    | POSIX_SPAWN_SETSCHEDULER
+    | POSIX_SPAWN_SETSID;
+
+// The child runs on its own stack until it execs, with room for a path
+const STACK_SIZE: usize = 32 * 1024 + PATH_MAX;
+
+#[repr(C)]
+pub struct posix_spawnattr_t {
+    flags: c_short,
+    pgroup: pid_t,
+    sigdefault: sigset_t,
+    sigmask: sigset_t,
+    schedpolicy: c_int,
+    schedparam: sched_param,
+}
+
+enum FileAction {
+    Open {
+        fd: c_int,
+        path: Vec<u8>,
+        oflag: c_int,
+        mode: mode_t,
+    },
+    Close(c_int),
+    Dup2(c_int, c_int),
+    Chdir(Vec<u8>),
+    Fchdir(c_int),
+}
+
+#[repr(C)]
+pub struct posix_spawn_file_actions_t {
+    // A Box<Vec<FileAction>>, allocated with the first action
+    actions: *mut c_void,
+}
+
+/// Everything the child needs, prepared by the parent so that the child
+/// doesn't have to allocate
+struct Args {
+    path: *const c_char,
+    search: bool,
+    actions: *const Vec<FileAction>,
+    attr: *const posix_spawnattr_t,
+    argv: *const *mut c_char,
+    envp: *const *mut c_char,
+    pipe: c_int,
+    oldmask: sigset_t,
+}
+
+unsafe fn check(ret: c_int) -> Result<c_int, c_int> {
+    if ret < 0 {
+        Err(platform::errno)
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Applies the attributes and file actions in the child
+unsafe fn setup(args: &Args, pipe: &mut c_int) -> Result<(), c_int> {
+    let attr = args.attr.as_ref();
+    let flags = attr.map_or(0, |attr| attr.flags);
+
+    // No handler may run in the child while it shares memory with the parent,
+    // and exec would reset them anyway
+    for sig in 1..=NSIG {
+        if sig == SIGKILL || sig == SIGSTOP {
+            continue;
+        }
+        let mut old: sigaction = mem::zeroed();
+        if sigaction(sig as c_int, ptr::null(), &mut old) < 0 {
+            continue;
+        }
+        let handler = old.sa_handler.map_or(SIG_DFL, |handler| handler as usize);
+        let default = match attr {
+            Some(attr) if flags & POSIX_SPAWN_SETSIGDEF == POSIX_SPAWN_SETSIGDEF => {
+                sigismember(&attr.sigdefault, sig as c_int) == 1
+            }
+            _ => false,
+        };
+        if default || (handler != SIG_DFL && handler != SIG_IGN) {
+            let mut act: sigaction = mem::zeroed();
+            act.sa_handler = None;
+            sigaction(sig as c_int, &act, ptr::null_mut());
+        }
+    }
+
+    if let Some(attr) = attr {
+        if flags & POSIX_SPAWN_SETSID == POSIX_SPAWN_SETSID {
+            check(Sys::setsid())?;
+        }
+        if flags & POSIX_SPAWN_SETPGROUP == POSIX_SPAWN_SETPGROUP {
+            check(Sys::setpgid(0, attr.pgroup))?;
+        }
+        if flags & POSIX_SPAWN_SETSCHEDULER == POSIX_SPAWN_SETSCHEDULER {
+            check(Sys::sched_setscheduler(
+                0,
+                attr.schedpolicy,
+                &attr.schedparam,
+            ))?;
+        } else if flags & POSIX_SPAWN_SETSCHEDPARAM == POSIX_SPAWN_SETSCHEDPARAM {
+            check(Sys::sched_setparam(0, &attr.schedparam))?;
+        }
+        if flags & POSIX_SPAWN_RESETIDS == POSIX_SPAWN_RESETIDS {
+            check(Sys::setregid(!0, Sys::getgid()))?;
+            check(Sys::setreuid(!0, Sys::getuid()))?;
+        }
+    }
+
+    if let Some(actions) = args.actions.as_ref() {
+        for action in actions {
+            let target = match *action {
+                FileAction::Open { fd, .. } | FileAction::Close(fd) | FileAction::Dup2(_, fd) => fd,
+                FileAction::Chdir(_) | FileAction::Fchdir(_) => -1,
+            };
+            if target == *pipe {
+                // Move the error pipe out of the way
+                let fd = check(Sys::fcntl(*pipe, F_DUPFD, 0))?;
+                Sys::fcntl(fd, F_SETFD, FD_CLOEXEC);
+                *pipe = fd;
+            }
+
+            match *action {
+                FileAction::Open {
+                    fd,
+                    ref path,
+                    oflag,
+                    mode,
+                } => {
+                    let new = check(Sys::open(
+                        CStr::from_bytes_with_nul_unchecked(path),
+                        oflag,
+                        mode,
+                    ))?;
+                    if new != fd {
+                        let ret = Sys::dup2(new, fd);
+                        Sys::close(new);
+                        check(ret)?;
+                    }
+                }
+                FileAction::Close(fd) => {
+                    Sys::close(fd);
+                }
+                FileAction::Dup2(fd, newfd) => {
+                    if fd == newfd {
+                        // Only the close-on-exec flag is cleared
+                        let flags = check(Sys::fcntl(fd, F_GETFD, 0))?;
+                        check(Sys::fcntl(fd, F_SETFD, flags & !FD_CLOEXEC))?;
+                    } else {
+                        check(Sys::dup2(fd, newfd))?;
+                    }
+                }
+                FileAction::Chdir(ref path) => {
+                    check(Sys::chdir(CStr::from_bytes_with_nul_unchecked(path)))?;
+                }
+                FileAction::Fchdir(fd) => {
+                    check(Sys::fchdir(fd))?;
+                }
+            }
+        }
+    }
+
+    // All signals were blocked by the parent around the clone
+    let mask = match attr {
+        Some(attr) if flags & POSIX_SPAWN_SETSIGMASK == POSIX_SPAWN_SETSIGMASK => attr.sigmask,
+        _ => args.oldmask,
+    };
+    Sys::sigprocmask(SIG_SETMASK, &mask, ptr::null_mut());
+    Ok(())
+}
+
+/// Execs the program, searching PATH like execvp for posix_spawnp, and
+/// returns the error if that fails
+unsafe fn exec(args: &Args) -> c_int {
+    let file = CStr::from_ptr(args.path);
+    let name = file.to_bytes();
+    if !args.search || name.contains(&b'/') {
+        Sys::execve(file, args.argv, args.envp);
+        return platform::errno;
+    }
+    if name.is_empty() {
+        return ENOENT;
+    }
+
+    let path = getenv(b"PATH\0".as_ptr() as *const c_char);
+    let dirs = if path.is_null() {
+        &b"/bin:/usr/bin"[..]
+    } else {
+        CStr::from_ptr(path).to_bytes()
+    };
+
+    let mut buf = [0; PATH_MAX];
+    let mut err = ENOENT;
+    for dir in dirs.split(|&c| c == b':') {
+        // An empty entry means the current directory
+        let dir = if dir.is_empty() { &b"."[..] } else { dir };
+        let len = dir.len() + 1 + name.len();
+        if len >= PATH_MAX {
+            err = ENAMETOOLONG;
+            continue;
+        }
+        buf[..dir.len()].copy_from_slice(dir);
+        buf[dir.len()] = b'/';
+        buf[dir.len() + 1..len].copy_from_slice(name);
+        buf[len] = 0;
+
+        Sys::execve(
+            CStr::from_bytes_with_nul_unchecked(&buf[..=len]),
+            args.argv,
+            args.envp,
+        );
+        match platform::errno {
+            EACCES => err = EACCES,
+            ENOENT | ENOTDIR => (),
+            errno => return errno,
+        }
+    }
+    err
+}
+
+unsafe extern "C" fn child(args: *mut c_void) -> c_int {
+    let args = &*(args as *const Args);
+    let mut pipe = args.pipe;
+    let err = match setup(args, &mut pipe) {
+        Ok(()) => exec(args),
+        Err(err) => err,
+    };
+
+    // The pipe is closed on exec, so anything read from it is an error
+    Sys::write(pipe, &err.to_ne_bytes());
+    Sys::exit(127)
+}
+
+unsafe fn spawn(
+    pid: *mut pid_t,
+    path: *const c_char,
+    file_actions: *const posix_spawn_file_actions_t,
+    attr: *const posix_spawnattr_t,
+    argv: *const *mut c_char,
+    envp: *const *mut c_char,
+    search: bool,
+) -> c_int {
+    let mut pipe = [0; 2];
+    if Sys::pipe2(&mut pipe, O_CLOEXEC) < 0 {
+        return platform::errno;
+    }
+    let stack = platform::alloc(STACK_SIZE) as *mut u8;
+    if stack.is_null() {
+        Sys::close(pipe[0]);
+        Sys::close(pipe[1]);
+        return ENOMEM;
+    }
+
+    let mut args = Args {
+        path,
+        search,
+        actions: match file_actions.as_ref() {
+            Some(file_actions) => file_actions.actions as *const Vec<FileAction>,
+            None => ptr::null(),
+        },
+        attr,
+        argv,
+        envp,
+        pipe: pipe[1],
+        oldmask: 0,
+    };
+
+    // Signal handlers of the parent must not run in the child
+    let all: sigset_t = !0;
+    Sys::sigprocmask(SIG_SETMASK, &all, &mut args.oldmask);
+
+    let mut sp = ((stack as usize + STACK_SIZE) & !0xF) as *mut usize;
+    {
+        let mut push = |value: usize| {
+            sp = sp.offset(-1);
+            *sp = value;
+        };
+
+        //WARNING: Stack must be 128-bit aligned for SSE
+        push(0);
+        push(0);
+        push(&mut args as *mut Args as usize);
+        push(child as usize);
+    }
+
+    let child_pid = Sys::spawn_clone(sp);
+    let mut err = if child_pid < 0 { platform::errno } else { 0 };
+
+    Sys::sigprocmask(SIG_SETMASK, &args.oldmask, ptr::null_mut());
+    Sys::close(pipe[1]);
+
+    if child_pid >= 0 {
+        let mut buf = [0; mem::size_of::<c_int>()];
+        let read = loop {
+            let read = Sys::read(pipe[0], &mut buf);
+            if read >= 0 || platform::errno != EINTR {
+                break read;
+            }
+        };
+        if read == buf.len() as ssize_t {
+            err = c_int::from_ne_bytes(buf);
+
+            // Reap the child that failed to exec
+            let mut status = 0;
+            while Sys::waitpid(child_pid, &mut status, 0) < 0 && platform::errno == EINTR {}
+        } else if !pid.is_null() {
+            *pid = child_pid;
+        }
+    }
+
+    Sys::close(pipe[0]);
+    platform::free(stack as *mut c_void);
+    err
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn(
+    pid: *mut pid_t,
+    path: *const c_char,
+    file_actions: *const posix_spawn_file_actions_t,
+    attrp: *const posix_spawnattr_t,
+    argv: *const *mut c_char,
+    envp: *const *mut c_char,
+) -> c_int {
+    spawn(pid, path, file_actions, attrp, argv, envp, false)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnp(
+    pid: *mut pid_t,
+    file: *const c_char,
+    file_actions: *const posix_spawn_file_actions_t,
+    attrp: *const posix_spawnattr_t,
+    argv: *const *mut c_char,
+    envp: *const *mut c_char,
+) -> c_int {
+    spawn(pid, file, file_actions, attrp, argv, envp, true)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_init(
+    file_actions: *mut posix_spawn_file_actions_t,
+) -> c_int {
+    (*file_actions).actions = ptr::null_mut();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_destroy(
+    file_actions: *mut posix_spawn_file_actions_t,
+) -> c_int {
+    let actions = (*file_actions).actions as *mut Vec<FileAction>;
+    if !actions.is_null() {
+        drop(Box::from_raw(actions));
+    }
+    (*file_actions).actions = ptr::null_mut();
+    0
+}
+
+unsafe fn add_action(file_actions: *mut posix_spawn_file_actions_t, action: FileAction) -> c_int {
+    let file_actions = &mut *file_actions;
+    if file_actions.actions.is_null() {
+        file_actions.actions = Box::into_raw(Box::new(Vec::<FileAction>::new())) as *mut c_void;
+    }
+    (*(file_actions.actions as *mut Vec<FileAction>)).push(action);
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_addopen(
+    file_actions: *mut posix_spawn_file_actions_t,
+    fildes: c_int,
+    path: *const c_char,
+    oflag: c_int,
+    mode: mode_t,
+) -> c_int {
+    if fildes < 0 {
+        return EBADF;
+    }
+    let path = CStr::from_ptr(path).to_bytes_with_nul().to_vec();
+    add_action(
+        file_actions,
+        FileAction::Open {
+            fd: fildes,
+            path,
+            oflag,
+            mode,
+        },
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_addclose(
+    file_actions: *mut posix_spawn_file_actions_t,
+    fildes: c_int,
+) -> c_int {
+    if fildes < 0 {
+        return EBADF;
+    }
+    add_action(file_actions, FileAction::Close(fildes))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_adddup2(
+    file_actions: *mut posix_spawn_file_actions_t,
+    fildes: c_int,
+    newfildes: c_int,
+) -> c_int {
+    if fildes < 0 || newfildes < 0 {
+        return EBADF;
+    }
+    add_action(file_actions, FileAction::Dup2(fildes, newfildes))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_addchdir_np(
+    file_actions: *mut posix_spawn_file_actions_t,
+    path: *const c_char,
+) -> c_int {
+    let path = CStr::from_ptr(path).to_bytes_with_nul().to_vec();
+    add_action(file_actions, FileAction::Chdir(path))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawn_file_actions_addfchdir_np(
+    file_actions: *mut posix_spawn_file_actions_t,
+    fildes: c_int,
+) -> c_int {
+    if fildes < 0 {
+        return EBADF;
+    }
+    add_action(file_actions, FileAction::Fchdir(fildes))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_init(attr: *mut posix_spawnattr_t) -> c_int {
+    *attr = posix_spawnattr_t {
+        flags: 0,
+        pgroup: 0,
+        sigdefault: 0,
+        sigmask: 0,
+        schedpolicy: 0,
+        schedparam: sched_param { sched_priority: 0 },
+    };
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_destroy(attr: *mut posix_spawnattr_t) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getflags(
+    attr: *const posix_spawnattr_t,
+    flags: *mut c_short,
+) -> c_int {
+    *flags = (*attr).flags;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setflags(
+    attr: *mut posix_spawnattr_t,
+    flags: c_short,
+) -> c_int {
+    if flags & !POSIX_SPAWN_ALL != 0 {
+        return EINVAL;
+    }
+    (*attr).flags = flags;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getpgroup(
+    attr: *const posix_spawnattr_t,
+    pgroup: *mut pid_t,
+) -> c_int {
+    *pgroup = (*attr).pgroup;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setpgroup(
+    attr: *mut posix_spawnattr_t,
+    pgroup: pid_t,
+) -> c_int {
+    (*attr).pgroup = pgroup;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getsigdefault(
+    attr: *const posix_spawnattr_t,
+    sigdefault: *mut sigset_t,
+) -> c_int {
+    *sigdefault = (*attr).sigdefault;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setsigdefault(
+    attr: *mut posix_spawnattr_t,
+    sigdefault: *const sigset_t,
+) -> c_int {
+    (*attr).sigdefault = *sigdefault;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getsigmask(
+    attr: *const posix_spawnattr_t,
+    sigmask: *mut sigset_t,
+) -> c_int {
+    *sigmask = (*attr).sigmask;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setsigmask(
+    attr: *mut posix_spawnattr_t,
+    sigmask: *const sigset_t,
+) -> c_int {
+    (*attr).sigmask = *sigmask;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getschedparam(
+    attr: *const posix_spawnattr_t,
+    schedparam: *mut sched_param,
+) -> c_int {
+    (*schedparam).sched_priority = (*attr).schedparam.sched_priority;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setschedparam(
+    attr: *mut posix_spawnattr_t,
+    schedparam: *const sched_param,
+) -> c_int {
+    (*attr).schedparam.sched_priority = (*schedparam).sched_priority;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_getschedpolicy(
+    attr: *const posix_spawnattr_t,
+    schedpolicy: *mut c_int,
+) -> c_int {
+    *schedpolicy = (*attr).schedpolicy;
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn posix_spawnattr_setschedpolicy(
+    attr: *mut posix_spawnattr_t,
+    schedpolicy: c_int,
+) -> c_int {
+    (*attr).schedpolicy = schedpolicy;
+    0
+}
@@ -20,7 +20,12 @@ use crate::{
     fs::File,
     header::{
         errno::{self, STR_ERROR},
-        fcntl, stdlib,
+        fcntl,
+        spawn::{
+            posix_spawn, posix_spawn_file_actions_adddup2, posix_spawn_file_actions_destroy,
+            posix_spawn_file_actions_init, posix_spawn_file_actions_t,
+        },
+        stdlib,
         string::{self, strlen},
         unistd,
     },
@@ -759,8 +764,6 @@ pub unsafe extern "C" fn perror(s: *const c_char) {
 
 #[no_mangle]
 pub unsafe extern "C" fn popen(command: *const c_char, mode: *const c_char) -> *mut FILE {
-    //TODO: share code with system
-
     let mode = CStr::from_ptr(mode);
 
     let mut cloexec = false;
@@ -786,60 +789,68 @@ pub unsafe extern "C" fn popen(command: *const c_char, mode: *const c_char) -> *
     };
 
     let mut pipes = [-1, -1];
-    if unistd::pipe(pipes.as_mut_ptr()) != 0 {
+    if unistd::pipe2(pipes.as_mut_ptr(), fcntl::O_CLOEXEC) != 0 {
         return ptr::null_mut();
     }
+    let (fd, child_fd, child_target) = if write {
+        (pipes[1], pipes[0], 0)
+    } else {
+        (pipes[0], pipes[1], 1)
+    };
 
-    let child_pid = unistd::fork();
-    if child_pid == 0 {
-        let command_nonnull = if command.is_null() {
-            "exit 0\0".as_ptr()
-        } else {
-            command as *const u8
-        };
-
-        let shell = "/bin/sh\0".as_ptr();
-
-        let args = [
-            "sh\0".as_ptr(),
-            "-c\0".as_ptr(),
-            command_nonnull,
-            ptr::null(),
-        ];
-
-        // Setup up stdin or stdout
-        //TODO: dup errors are ignored, should they be?
-        {
-            if write {
-                unistd::dup2(0, pipes[0]);
-            } else {
-                unistd::dup2(1, pipes[1]);
-            }
-
-            unistd::close(pipes[0]);
-            unistd::close(pipes[1]);
-        }
-
-        unistd::execv(shell as *const c_char, args.as_ptr() as *const *mut c_char);
-
-        stdlib::exit(127);
+    let command_nonnull = if command.is_null() {
+        c_str!("exit 0").as_ptr()
+    } else {
+        command
+    };
+    let args = [
+        c_str!("sh").as_ptr(),
+        c_str!("-c").as_ptr(),
+        command_nonnull,
+        ptr::null(),
+    ];
+
+    // Setup up stdin or stdout. dup2 clears close-on-exec, even if the pipe
+    // already is the right descriptor.
+    let mut file_actions: posix_spawn_file_actions_t = mem::zeroed();
+    posix_spawn_file_actions_init(&mut file_actions);
+    posix_spawn_file_actions_adddup2(&mut file_actions, child_fd, child_target);
+
+    let mut child_pid = 0;
+    let err = posix_spawn(
+        &mut child_pid,
+        c_str!("/bin/sh").as_ptr(),
+        &file_actions,
+        ptr::null(),
+        args.as_ptr() as *const *mut c_char,
+        platform::environ,
+    );
+    posix_spawn_file_actions_destroy(&mut file_actions);
+    unistd::close(child_fd);
+    if err != 0 {
+        unistd::close(fd);
+        errno = err;
+        return ptr::null_mut();
+    }
 
-        unreachable!();
-    } else if child_pid > 0 {
-        let (fd, fd_mode) = if write {
-            unistd::close(pipes[0]);
-            (pipes[1], if cloexec { c_str!("we") } else { c_str!("w") })
+    let fd_mode = if cloexec {
+        if write {
+            c_str!("we")
         } else {
-            unistd::close(pipes[1]);
-            (pipes[0], if cloexec { c_str!("re") } else { c_str!("r") })
-        };
-
-        if let Some(f) = helpers::_fdopen(fd, fd_mode.as_ptr()) {
-            (*f).pid = Some(child_pid);
-            f
+            c_str!("re")
+        }
+    } else {
+        fcntl::sys_fcntl(fd, fcntl::F_SETFD, 0);
+        if write {
+            c_str!("w")
         } else {
-            ptr::null_mut()
+            c_str!("r")
         }
+    };
+
+    if let Some(f) = helpers::_fdopen(fd, fd_mode.as_ptr()) {
+        (*f).pid = Some(child_pid);
+        f
     } else {
         ptr::null_mut()
     }
@@ -1,7 +1,10 @@
 use super::lookaheadreader::LookAheadReader;
-use crate::platform::types::*;
+use crate::{
+    header::{errno, stdlib::float},
+    platform::{self, types::*},
+};
 use alloc::{string::String, vec::Vec};
-use core::ffi::VaList as va_list;
+use core::{ffi::VaList as va_list, mem, ptr, str};
 
 #[derive(PartialEq, Eq)]
 enum IntKind {
@@ -10,6 +13,7 @@ enum IntKind {
     Int,
     Long,
     LongLong,
+    LongDouble,
     IntMax,
     PtrDiff,
     Size,
@@ -26,6 +30,61 @@ unsafe fn next_byte(string: &mut *const c_char) -> Result<u8, c_int> {
     }
 }
 
+/// Helper function for progressing a C string by one character, decoding
+/// UTF-8 when the conversion is a wide one
+unsafe fn next_char(string: &mut *const c_char, wide: bool) -> Result<u32, c_int> {
+    let c = next_byte(string)?;
+    let size = str::utf8_char_width(c);
+    if !wide || size <= 1 {
+        return Ok(c as u32);
+    }
+
+    let mut buf = [c, 0, 0, 0];
+    for i in 1..size {
+        buf[i] = next_byte(string)?;
+    }
+    match str::from_utf8(&buf[..size]) {
+        Ok(s) => Ok(s.chars().next().unwrap() as u32),
+        Err(_) => Err(-1),
+    }
+}
+
+/// Stores a double in the platform's `long double` format, for which Rust has
+/// no native type. Only the precision of a double is preserved.
+unsafe fn store_long_double(ptr: *mut u8, value: c_double) {
+    let bits = value.to_bits();
+    let sign = (bits >> 63) as u16;
+    let exp = ((bits >> 52) & 0x7FF) as i32;
+    let frac = bits & ((1 << 52) - 1);
+
+    // Biased 15-bit exponent and the significand with an explicit leading bit
+    let (exp, significand) = if exp == 0x7FF {
+        (0x7FFF, 1 << 63 | frac << 11)
+    } else if exp == 0 && frac == 0 {
+        (0, 0)
+    } else if exp == 0 {
+        let shift = frac.leading_zeros();
+        (16383 - 1022 - (shift as i32 - 11), frac << shift)
+    } else {
+        (exp - 1023 + 16383, 1 << 63 | frac << 11)
+    };
+    let top = sign << 15 | exp as u16;
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        // 80-bit x87 extended precision
+        ptr::write_unaligned(ptr as *mut u64, significand);
+        ptr::write_unaligned(ptr.add(8) as *mut u16, top);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        // IEEE 754 binary128
+        let fraction = (significand as u128 & !(1 << 63)) << 49;
+        ptr::write_unaligned(ptr as *mut u128, (top as u128) << 112 | fraction);
+    }
+}
+
 unsafe fn inner_scanf(
     mut r: LookAheadReader,
     mut format: *const c_char,
@@ -50,6 +109,15 @@ unsafe fn inner_scanf(
         }};
     }
 
+    macro_rules! input_failure {
+        () => {
+            match matched {
+                0 => return Ok(-1),
+                a => return Ok(a),
+            }
+        };
+    }
+
     macro_rules! maybe_read {
         () => {
             maybe_read!(inner false);
@@ -59,10 +127,7 @@ unsafe fn inner_scanf(
         };
         (inner $($placeholder:expr)*) => {
             if !skip_read && !read!() {
-                match matched {
-                    0 => return Ok(-1),
-                    a => return Ok(a),
-                }
+                input_failure!();
             }
             $(else {
                 // Hacky way of having this optional
@@ -79,7 +144,7 @@ unsafe fn inner_scanf(
 
             while (byte as char).is_whitespace() {
                 if !read!() {
-                    return Ok(matched);
+                    input_failure!();
                 }
             }
 
@@ -113,6 +178,14 @@ unsafe fn inner_scanf(
                 }
             };
 
+            // The assignment-allocation modifier, where the caller passes a
+            // pointer to a pointer that receives a malloc'd buffer
+            let mut alloc = false;
+            if c == b'm' {
+                alloc = true;
+                c = next_byte(&mut format)?;
+            }
+
             // When an EOF occurs, eof is set, stuff is marked matched
             // as usual, and finally it is returned
             let mut eof = false;
@@ -135,7 +208,8 @@ unsafe fn inner_scanf(
                             IntKind::Long
                         }
                     }
-                    b'q' | b'L' => IntKind::LongLong,
+                    b'q' => IntKind::LongLong,
+                    b'L' => IntKind::LongDouble,
                     b't' => IntKind::PtrDiff,
                     b'z' => IntKind::Size,
                     _ => break,
@@ -144,6 +218,9 @@ unsafe fn inner_scanf(
                 c = next_byte(&mut format)?;
             }
 
+            // %C and %S are the XSI spellings of %lc and %ls
+            let wide = kind == IntKind::Long || c == b'C' || c == b'S';
+
             if c != b'n' {
                 maybe_read!(noreset);
             }
@@ -151,7 +228,7 @@ unsafe fn inner_scanf(
                 b'%' => {
                     while (byte as char).is_whitespace() {
                         if !read!() {
-                            return Ok(matched);
+                            input_failure!();
                         }
                     }
 
@@ -161,18 +238,16 @@ unsafe fn inner_scanf(
                         return Ok(matched);
                     }
                 }
-                b'd' | b'i' | b'o' | b'u' | b'x' | b'X' | b'f' | b'e' | b'g' | b'E' | b'a'
-                | b'p' => {
+                b'd' | b'i' | b'o' | b'u' | b'x' | b'X' | b'p' => {
                     while (byte as char).is_whitespace() {
                         if !read!() {
-                            return Ok(matched);
+                            input_failure!();
                         }
                     }
 
                     let pointer = c == b'p';
                     // Pointers aren't automatic, but we do want to parse "0x"
                     let auto = c == b'i' || pointer;
-                    let float = c == b'f' || c == b'e' || c == b'g' || c == b'E' || c == b'a';
 
                     let mut radix = match c {
                         b'o' => 8,
@@ -181,93 +256,94 @@ unsafe fn inner_scanf(
                     };
 
                     let mut n = String::new();
-                    let mut dot = false;
+                    let mut negative = false;
+                    // A leading zero which was consumed as part of the prefix
+                    // still counts as a digit
+                    let mut prefix_zero = false;
+
+                    if (byte == b'-' || byte == b'+') && width.map(|w| w > 0).unwrap_or(true) {
+                        negative = byte == b'-';
+                        r.commit();
+                        width = width.map(|w| w - 1);
+                        if width.map(|w| w > 0).unwrap_or(true) && !read!() {
+                            eof = true;
+                        }
+                    }
 
-                    while width.map(|w| w > 0).unwrap_or(true)
+                    while !eof
+                        && width.map(|w| w > 0).unwrap_or(true)
                         && ((byte >= b'0' && byte <= b'7')
                             || (radix >= 10 && (byte >= b'8' && byte <= b'9'))
-                            || (float && !dot && byte == b'.')
                             || (radix == 16
                                 && ((byte >= b'a' && byte <= b'f')
                                     || (byte >= b'A' && byte <= b'F'))))
                     {
                         if auto
                             && n.is_empty()
+                            && !prefix_zero
                             && byte == b'0'
                             && width.map(|w| w > 0).unwrap_or(true)
                         {
+                            prefix_zero = true;
                             if !pointer {
                                 radix = 8;
                             }
+                            r.commit();
                             width = width.map(|w| w - 1);
-                            if !read!() {
-                                return Ok(matched);
+                            if width.map(|w| w > 0).unwrap_or(true) && !read!() {
+                                eof = true;
+                                break;
                             }
                             if width.map(|w| w > 0).unwrap_or(true)
                                 && (byte == b'x' || byte == b'X')
                             {
                                 radix = 16;
+                                r.commit();
                                 width = width.map(|w| w - 1);
                                 if width.map(|w| w > 0).unwrap_or(true) && !read!() {
-                                    return Ok(matched);
+                                    eof = true;
+                                    break;
                                 }
                             }
                             continue;
                         }
-                        if byte == b'.' {
-                            // Don't allow another dot
-                            dot = true;
-                        }
                         n.push(byte as char);
                         r.commit();
                         width = width.map(|w| w - 1);
                         if width.map(|w| w > 0).unwrap_or(true) && !read!() {
-                            return Ok(matched);
+                            eof = true;
+                            break;
                         }
                     }
 
+                    if n.is_empty() && !prefix_zero {
+                        // Matching failure, no digits were found
+                        return Ok(matched);
+                    }
+
+                    // Out of range values wrap like strtoul, rather than
+                    // failing the conversion
+                    let mut value: u64 = 0;
+                    for digit in n.chars() {
+                        value = value
+                            .wrapping_mul(radix)
+                            .wrapping_add(digit.to_digit(radix as u32).unwrap() as u64);
+                    }
+                    if negative {
+                        value = value.wrapping_neg();
+                    }
+
                     macro_rules! parse_type {
-                        (noformat $type:ident) => {{
-                            let n = if n.is_empty() {
-                                0 as $type
-                            } else {
-                                n.parse::<$type>().map_err(|_| 0)?
-                            };
-                            if !ignore {
-                                *ap.arg::<*mut $type>() = n;
-                                matched += 1;
-                            }
-                        }};
-                        (c_double) => {
-                            parse_type!(noformat c_double);
-                        };
-                        (c_float) => {
-                            parse_type!(noformat c_float);
-                        };
-                        ($type:ident) => {
-                            parse_type!($type, $type);
-                        };
-                        ($type:ident, $final:ty) => {{
-                            let n = if n.is_empty() {
-                                0 as $type
-                            } else {
-                                $type::from_str_radix(&n, radix).map_err(|_| 0)?
-                            };
+                        ($type:ty) => {{
                             if !ignore {
-                                *ap.arg::<*mut $final>() = n as $final;
+                                *ap.arg::<*mut $type>() = value as $type;
                                 matched += 1;
                             }
                         }};
                     }
 
-                    if float {
-                        if kind == IntKind::Long || kind == IntKind::LongLong {
-                            parse_type!(c_double);
-                        } else {
-                            parse_type!(c_float);
-                        }
-                    } else if c == b'p' {
-                        parse_type!(size_t, *mut c_void);
+                    if pointer {
+                        parse_type!(*mut c_void);
                     } else {
                         let unsigned = c == b'o' || c == b'u' || c == b'x' || c == b'X';
 
@@ -300,7 +376,7 @@ unsafe fn inner_scanf(
                                     parse_type!(c_long)
                                 }
                             }
-                            IntKind::LongLong => {
+                            IntKind::LongLong | IntKind::LongDouble => {
                                 if unsigned {
                                     parse_type!(c_ulonglong)
                                 } else {
@@ -325,117 +401,306 @@ unsafe fn inner_scanf(
                         }
                     }
                 }
-                b's' => {
+                b'a' | b'A' | b'e' | b'E' | b'f' | b'F' | b'g' | b'G' => {
                     while (byte as char).is_whitespace() {
                         if !read!() {
-                            return Ok(matched);
+                            input_failure!();
                         }
                     }
 
-                    let mut ptr: Option<*mut c_char> = if ignore { None } else { Some(ap.arg()) };
+                    let mut n = Vec::new();
+                    let decimal_point = float::decimal_point();
 
-                    while width.map(|w| w > 0).unwrap_or(true) && !(byte as char).is_whitespace() {
-                        if let Some(ref mut ptr) = ptr {
-                            **ptr = byte as c_char;
-                            *ptr = ptr.offset(1);
+                    // Takes the current byte as part of the number, then
+                    // reads the next one. Evaluates to false once the width
+                    // or the input has run out.
+                    macro_rules! accept {
+                        () => {{
+                            n.push(byte);
+                            r.commit();
+                            width = width.map(|w| w - 1);
+                            if width.map(|w| w > 0).unwrap_or(true) {
+                                if read!() {
+                                    true
+                                } else {
+                                    eof = true;
+                                    false
+                                }
+                            } else {
+                                false
+                            }
+                        }};
+                    }
+
+                    // Take the longest prefix of the input that could still
+                    // be the start of a number, and let the parser shared with
+                    // strtod decide how much of it is valid
+                    'number: loop {
+                        if (byte == b'-' || byte == b'+') && !accept!() {
+                            break 'number;
                         }
-                        width = width.map(|w| w - 1);
-                        if width.map(|w| w > 0).unwrap_or(true) && !read!() {
-                            eof = true;
-                            break;
+
+                        let lower = byte.to_ascii_lowercase();
+                        if lower == b'i' || lower == b'n' {
+                            let word: &[u8] = if lower == b'i' { b"infinity" } else { b"nan" };
+                            for &expected in word {
+                                if byte.to_ascii_lowercase() != expected || !accept!() {
+                                    break 'number;
+                                }
+                            }
+                            if word == b"nan" && byte == b'(' {
+                                while accept!() {
+                                    if byte == b')' {
+                                        accept!();
+                                        break 'number;
+                                    } else if !byte.is_ascii_alphanumeric() && byte != b'_' {
+                                        break 'number;
+                                    }
+                                }
+                            }
+                            break 'number;
                         }
-                    }
 
-                    if let Some(ptr) = ptr {
-                        *ptr = 0;
-                        matched += 1;
-                        r.commit();
-                    }
-                }
-                b'c' => {
-                    let ptr: Option<*mut c_char> = if ignore { None } else { Some(ap.arg()) };
+                        let mut hex = false;
+                        if byte == b'0' {
+                            if !accept!() {
+                                break 'number;
+                            }
+                            if byte == b'x' || byte == b'X' {
+                                hex = true;
+                                if !accept!() {
+                                    break 'number;
+                                }
+                            }
+                        }
 
-                    for i in 0..width.unwrap_or(1) {
-                        if let Some(ptr) = ptr {
-                            *ptr.add(i) = byte as c_char;
+                        let mut dot = false;
+                        loop {
+                            let digit = if hex {
+                                byte.is_ascii_hexdigit()
+                            } else {
+                                byte.is_ascii_digit()
+                            };
+                            if !digit && (byte != decimal_point || dot) {
+                                break;
+                            }
+                            dot |= byte == decimal_point;
+                            if !accept!() {
+                                break 'number;
+                            }
                         }
-                        width = width.map(|w| w - 1);
-                        if width.map(|w| w > 0).unwrap_or(true) && !read!() {
-                            eof = true;
-                            break;
+
+                        let exponent = if hex { b'p' } else { b'e' };
+                        if byte.to_ascii_lowercase() == exponent {
+                            if !accept!() {
+                                break 'number;
+                            }
+                            if (byte == b'-' || byte == b'+') && !accept!() {
+                                break 'number;
+                            }
+                            while byte.is_ascii_digit() {
+                                if !accept!() {
+                                    break 'number;
+                                }
+                            }
                         }
+                        break;
                     }
 
-                    if ptr.is_some() {
-                        matched += 1;
-                        r.commit();
+                    macro_rules! parse_type {
+                        ($type:ty) => {
+                            match float::parse::<$type>(&n, decimal_point) {
+                                Some((value, len)) if len == n.len() => value,
+                                // Matching failure, the input was only the
+                                // beginning of a number
+                                _ => return Ok(matched),
+                            }
+                        };
                     }
-                }
-                b'[' => {
-                    c = next_byte(&mut format)?;
 
-                    let mut matches = Vec::new();
-                    let invert = if c == b'^' {
-                        c = next_byte(&mut format)?;
-                        true
-                    } else {
-                        false
-                    };
+                    match kind {
+                        IntKind::Long => {
+                            let value = parse_type!(c_double);
+                            if !ignore {
+                                *ap.arg::<*mut c_double>() = value;
+                                matched += 1;
+                            }
+                        }
+                        IntKind::LongDouble => {
+                            let value = parse_type!(c_double);
+                            if !ignore {
+                                store_long_double(ap.arg::<*mut u8>(), value);
+                                matched += 1;
+                            }
+                        }
+                        _ => {
+                            let value = parse_type!(c_float);
+                            if !ignore {
+                                *ap.arg::<*mut c_float>() = value;
+                                matched += 1;
+                            }
+                        }
+                    }
+                }
+                b's' | b'S' | b'c' | b'C' | b'[' => {
+                    let string = c != b'c' && c != b'C';
+
+                    // The scanset, as inclusive ranges of characters
+                    let mut set = Vec::new();
+                    let mut invert = false;
+                    if c == b'[' {
+                        let mut c = next_char(&mut format, wide)?;
+                        if c == b'^' as u32 {
+                            invert = true;
+                            c = next_char(&mut format, wide)?;
+                        }
 
-                    let mut prev;
-                    loop {
-                        matches.push(c);
-                        prev = c;
-                        c = next_byte(&mut format)?;
-                        if c == b'-' {
-                            if prev == b']' {
+                        // A ']' right after the opening bracket is literal
+                        let mut first = true;
+                        while c != b']' as u32 || first {
+                            first = false;
+                            let start = c;
+                            c = next_char(&mut format, wide)?;
+                            if c != b'-' as u32 {
+                                set.push((start, start));
                                 continue;
                             }
-                            c = next_byte(&mut format)?;
-                            if c == b']' {
-                                matches.push(b'-');
+
+                            let end = next_char(&mut format, wide)?;
+                            if end == b']' as u32 {
+                                // A trailing '-' is literal
+                                set.push((start, start));
+                                set.push((c, c));
                                 break;
+                            } else if end >= start {
+                                set.push((start, end));
+                            } else {
+                                set.push((start, start));
+                                set.push((c, c));
+                                set.push((end, end));
                             }
-                            prev += 1;
-                            while prev < c {
-                                matches.push(prev);
-                                prev += 1;
+                            c = next_char(&mut format, wide)?;
+                        }
+                    } else if string {
+                        while (byte as char).is_whitespace() {
+                            if !read!() {
+                                input_failure!();
                             }
-                        } else if c == b']' {
-                            break;
                         }
                     }
 
-                    let mut ptr: Option<*mut c_char> = if ignore { None } else { Some(ap.arg()) };
+                    // %c reads exactly the width, defaulting to one
+                    if !string {
+                        width = Some(width.unwrap_or(1));
+                    }
 
-                    // While we haven't used up all the width, and it matches
-                    let mut data_stored = false;
-                    while width.map(|w| w > 0).unwrap_or(true) && !invert == matches.contains(&byte)
-                    {
-                        if let Some(ref mut ptr) = ptr {
-                            **ptr = byte as c_char;
-                            *ptr = ptr.offset(1);
-                            data_stored = true;
+                    let mut chars: Vec<u32> = Vec::new();
+                    while width.map(|w| w > 0).unwrap_or(true) {
+                        // Decode the multibyte sequence that starts at this byte
+                        let ch = if wide {
+                            let size = str::utf8_char_width(byte);
+                            let mut buf = [byte, 0, 0, 0];
+                            for i in 1..size {
+                                if !read!() {
+                                    input_failure!();
+                                }
+                                buf[i] = byte;
+                            }
+                            match str::from_utf8(&buf[..size.max(1)]) {
+                                Ok(s) if size > 0 => s.chars().next().unwrap() as u32,
+                                _ => {
+                                    platform::errno = errno::EILSEQ;
+                                    return Ok(matched);
+                                }
+                            }
+                        } else {
+                            byte as u32
+                        };
+
+                        let accepted = if c == b'[' {
+                            set.iter().any(|&(start, end)| start <= ch && ch <= end) != invert
+                        } else if string {
+                            !char::from_u32(ch).map_or(false, char::is_whitespace)
+                        } else {
+                            true
+                        };
+                        if !accepted {
+                            break;
                         }
+
+                        chars.push(ch);
                         r.commit();
                         // Decrease the width, and read a new character unless the width is 0
                         width = width.map(|w| w - 1);
                         if width.map(|w| w > 0).unwrap_or(true) && !read!() {
                             // Reading a new character has failed, return after
-                            // actually marking this as matched
+                            // actually storing this
                             eof = true;
                             break;
                         }
                     }
 
-                    if data_stored {
-                        *ptr.unwrap() = 0;
+                    if chars.is_empty() || (!string && width != Some(0)) {
+                        // Either nothing matched, or %c ran out of input
+                        if eof {
+                            input_failure!();
+                        }
+                        return Ok(matched);
+                    }
+
+                    if !ignore {
+                        let len = chars.len() + string as usize;
+                        let elem_size = if wide {
+                            mem::size_of::<wchar_t>()
+                        } else {
+                            mem::size_of::<c_char>()
+                        };
+
+                        let dest = if alloc {
+                            let buf = platform::alloc(len * elem_size);
+                            if buf.is_null() {
+                                platform::errno = errno::ENOMEM;
+                                return Err(-1);
+                            }
+                            *ap.arg::<*mut *mut c_void>() = buf;
+                            buf
+                        } else {
+                            ap.arg::<*mut c_void>()
+                        };
+
+                        if string {
+                            chars.push(0);
+                        }
+                        if wide {
+                            let dest = dest as *mut wchar_t;
+                            for (i, &ch) in chars.iter().enumerate() {
+                                *dest.add(i) = ch as wchar_t;
+                            }
+                        } else {
+                            let dest = dest as *mut c_char;
+                            for (i, &ch) in chars.iter().enumerate() {
+                                *dest.add(i) = ch as c_char;
+                            }
+                        }
                         matched += 1;
                     }
                 }
                 b'n' => {
                     if !ignore {
-                        *ap.arg::<*mut c_int>() = count as c_int;
+                        // A pending lookahead byte has not been consumed yet
+                        let count = count - skip_read as usize;
+                        match kind {
+                            IntKind::Byte => *ap.arg::<*mut c_char>() = count as c_char,
+                            IntKind::Short => *ap.arg::<*mut c_short>() = count as c_short,
+                            IntKind::Int => *ap.arg::<*mut c_int>() = count as c_int,
+                            IntKind::Long => *ap.arg::<*mut c_long>() = count as c_long,
+                            IntKind::LongLong | IntKind::LongDouble => {
+                                *ap.arg::<*mut c_longlong>() = count as c_longlong
+                            }
+                            IntKind::IntMax => *ap.arg::<*mut intmax_t>() = count as intmax_t,
+                            IntKind::PtrDiff => *ap.arg::<*mut ptrdiff_t>() = count as ptrdiff_t,
+                            IntKind::Size => *ap.arg::<*mut ssize_t>() = count as ssize_t,
+                        }
                     }
                 }
                 _ => return Err(-1),
@@ -445,10 +710,10 @@ unsafe fn inner_scanf(
                 return Ok(matched);
             }
 
-            if width != Some(0) && c != b'n' {
-                // It didn't hit the width, so an extra character was read and matched.
-                // But this character did not match so let's reuse it.
-                skip_read = true;
+            if c != b'n' {
+                // If it didn't hit the width, an extra character was read
+                // which did not match, so let's reuse it.
+                skip_read = width != Some(0);
             }
         }
     }
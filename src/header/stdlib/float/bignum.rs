@@ -0,0 +1,187 @@
+//! Arbitrary precision unsigned integers, just big enough to exactly convert
+//! the decimal numbers that the Eisel-Lemire fast path can't decide
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Little endian 32-bit limbs, without trailing zero limbs
+#[derive(Clone)]
+pub struct Bignum(Vec<u32>);
+
+impl Bignum {
+    pub fn from_u64(value: u64) -> Self {
+        let mut big = Bignum(vec![value as u32, (value >> 32) as u32]);
+        big.trim();
+        big
+    }
+
+    /// Builds a number from a list of decimal digit values
+    pub fn from_digits(digits: &[u8]) -> Self {
+        let mut big = Bignum(Vec::new());
+        for chunk in digits.chunks(9) {
+            let mut value = 0;
+            for digit in chunk {
+                value = value * 10 + *digit as u32;
+            }
+            big.mul_small(10u32.pow(chunk.len() as u32));
+            big.add_small(value);
+        }
+        big
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn bit_len(&self) -> usize {
+        match self.0.last() {
+            Some(top) => self.0.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0
+            .get(i / 32)
+            .map_or(false, |limb| limb >> (i % 32) & 1 == 1)
+    }
+
+    fn add_small(&mut self, value: u32) {
+        let mut carry = value as u64;
+        for limb in self.0.iter_mut() {
+            if carry == 0 {
+                break;
+            }
+            let sum = *limb as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn mul_small(&mut self, value: u32) {
+        let mut carry = 0;
+        for limb in self.0.iter_mut() {
+            let product = *limb as u64 * value as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+        self.trim();
+    }
+
+    pub fn mul_pow5(&mut self, mut exp: usize) {
+        // 5^13 is the largest power of five that fits in a limb
+        while exp >= 13 {
+            self.mul_small(1_220_703_125);
+            exp -= 13;
+        }
+        self.mul_small(5u32.pow(exp as u32));
+    }
+
+    pub fn shl(&mut self, bits: usize) {
+        if self.is_zero() {
+            return;
+        }
+        let limbs = bits / 32;
+        let bits = bits % 32;
+        if bits != 0 {
+            let mut carry = 0;
+            for limb in self.0.iter_mut() {
+                let shifted = *limb << bits | carry;
+                carry = *limb >> (32 - bits);
+                *limb = shifted;
+            }
+            if carry != 0 {
+                self.0.push(carry);
+            }
+        }
+        for _ in 0..limbs {
+            self.0.insert(0, 0);
+        }
+    }
+
+    /// Subtracts a smaller or equal number
+    fn sub(&mut self, other: &Bignum) {
+        let mut borrow = 0;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let rhs = *other.0.get(i).unwrap_or(&0) as u64 + borrow;
+            if (*limb as u64) < rhs {
+                *limb = ((1 << 32) + *limb as u64 - rhs) as u32;
+                borrow = 1;
+            } else {
+                *limb = (*limb as u64 - rhs) as u32;
+                borrow = 0;
+            }
+        }
+        self.trim();
+    }
+
+    /// Divides by `other`, returning a quotient of at most 128 bits and
+    /// whether there was a remainder
+    pub fn div_rem(mut self, other: &Bignum) -> (u128, bool) {
+        let mut quotient = 0;
+        if self.bit_len() >= other.bit_len() {
+            let shift = self.bit_len() - other.bit_len();
+            for i in (0..=shift).rev() {
+                let mut divisor = other.clone();
+                divisor.shl(i);
+                if self >= divisor {
+                    self.sub(&divisor);
+                    quotient |= 1 << i;
+                }
+            }
+        }
+        (quotient, !self.is_zero())
+    }
+
+    /// Returns the 64 most significant bits, whether any of the lower bits
+    /// are set, and the power of two that the 64 bits need to be scaled by
+    pub fn top64(&self) -> (u64, bool, i64) {
+        let len = self.bit_len();
+        if len <= 64 {
+            let mut value = 0;
+            for (i, limb) in self.0.iter().enumerate() {
+                value |= (*limb as u64) << (32 * i);
+            }
+            return (value, false, 0);
+        }
+
+        let shift = len - 64;
+        let mut value = 0;
+        for i in 0..64 {
+            if self.bit(shift + i) {
+                value |= 1 << i;
+            }
+        }
+        let sticky = (0..shift).any(|i| self.bit(i));
+        (value, sticky, shift as i64)
+    }
+}
+
+impl PartialEq for Bignum {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl PartialOrd for Bignum {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(
+            self.0
+                .len()
+                .cmp(&other.0.len())
+                .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev())),
+        )
+    }
+}
@@ -0,0 +1,111 @@
+//! The Eisel-Lemire algorithm, which correctly rounds `w * 10^q` for almost all
+//! inputs using a single 128-bit multiplication. See "Number Parsing at a
+//! Gigabyte per Second" by Daniel Lemire.
+
+use super::{
+    table::{LARGEST_POWER_OF_FIVE, POWER_OF_FIVE_128, SMALLEST_POWER_OF_FIVE},
+    Float,
+};
+
+/// Computes the biased exponent and the stored mantissa bits of `w * 10^q`.
+/// Returns None in the rare cases where the result can't be decided without
+/// looking at more digits.
+pub fn compute_float<T: Float>(q: i64, mut w: u64) -> Option<(i64, u64)> {
+    let mantissa_bits = T::MANTISSA_BITS as i64;
+    let infinite_power = 2 * T::BIAS + 1;
+
+    if w == 0 || q < T::SMALLEST_POWER_OF_TEN {
+        return Some((0, 0));
+    } else if q > T::LARGEST_POWER_OF_TEN {
+        return Some((infinite_power, 0));
+    }
+
+    // Normalize so that the most significant bit is set
+    let lz = w.leading_zeros() as i64;
+    w <<= lz;
+
+    let (lo, hi) = compute_product_approx(q, w, mantissa_bits as u32 + 3);
+    if lo == 0xFFFF_FFFF_FFFF_FFFF {
+        // The approximation of w * 5^q may be off by one, which could carry
+        // over the halfway point. Only small powers are known to be exact.
+        let inside_safe_exponent = q >= -27 && q <= 55;
+        if !inside_safe_exponent {
+            return None;
+        }
+    }
+
+    let upperbit = (hi >> 63) as i64;
+    let mut mantissa = hi >> (upperbit + 64 - mantissa_bits - 3);
+    let mut power2 = power(q) + upperbit - lz + T::BIAS;
+    if power2 <= 0 {
+        if -power2 + 1 >= 64 {
+            // More than 64 bits below the smallest exponent, so it's zero
+            return Some((0, 0));
+        }
+
+        // Subnormal, where rounding up may produce the smallest normal number
+        mantissa >>= -power2 + 1;
+        mantissa += mantissa & 1;
+        mantissa >>= 1;
+        power2 = (mantissa >= 1 << mantissa_bits) as i64;
+        return Some((power2, mantissa & ((1 << mantissa_bits) - 1)));
+    }
+
+    // Exactly halfway between two floats can only happen when the product
+    // is exact and every truncated bit is zero, in which case we must round
+    // to even instead of up
+    if lo <= 1
+        && q >= T::MIN_EXPONENT_ROUND_TO_EVEN
+        && q <= T::MAX_EXPONENT_ROUND_TO_EVEN
+        && mantissa & 3 == 1
+        && (mantissa << (upperbit + 64 - mantissa_bits - 3)) == hi
+    {
+        mantissa &= !1;
+    }
+
+    mantissa += mantissa & 1;
+    mantissa >>= 1;
+    if mantissa >= 2 << mantissa_bits {
+        // Rounding carried into a new bit
+        mantissa = 1 << mantissa_bits;
+        power2 += 1;
+    }
+    mantissa &= !(1 << mantissa_bits);
+
+    if power2 >= infinite_power {
+        return Some((infinite_power, 0));
+    }
+    Some((power2, mantissa))
+}
+
+/// Approximates `floor(log2(10^q))`
+fn power(q: i64) -> i64 {
+    ((q * (152_170 + 65536)) >> 16) + 63
+}
+
+fn full_multiplication(a: u64, b: u64) -> (u64, u64) {
+    let r = a as u128 * b as u128;
+    (r as u64, (r >> 64) as u64)
+}
+
+/// Computes the upper 128 bits of `w * 5^q`, with at least `precision`
+/// correct bits in the upper half
+fn compute_product_approx(q: i64, w: u64, precision: u32) -> (u64, u64) {
+    debug_assert!(q >= SMALLEST_POWER_OF_FIVE && q <= LARGEST_POWER_OF_FIVE);
+
+    let mask = 0xFFFF_FFFF_FFFF_FFFF_u64 >> precision;
+
+    let index = (q - SMALLEST_POWER_OF_FIVE) as usize;
+    let (hi5, lo5) = POWER_OF_FIVE_128[index];
+    let (mut first_lo, mut first_hi) = full_multiplication(w, hi5);
+    if first_hi & mask == mask {
+        // Not enough precision yet, so take the lower half of the power
+        // into account. This is exact for q < 55, since 5^55 < 2^128.
+        let (_, second_hi) = full_multiplication(w, lo5);
+        first_lo = first_lo.wrapping_add(second_hi);
+        if second_hi > first_lo {
+            first_hi += 1;
+        }
+    }
+    (first_lo, first_hi)
+}
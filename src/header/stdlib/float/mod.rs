@@ -0,0 +1,498 @@
+//! Correctly rounded floating point parsing shared by strtod, wcstod and scanf.
+//!
+//! Decimal numbers are converted with the Eisel-Lemire algorithm, falling back
+//! to exact big integer arithmetic for the rare inputs it can't decide.
+
+use alloc::vec::Vec;
+
+use crate::{
+    c_str::CStr,
+    header::{errno::ERANGE, locale},
+    platform,
+};
+
+use self::bignum::Bignum;
+
+mod bignum;
+mod lemire;
+mod table;
+
+/// Number of significant decimal digits that are kept when parsing. Digits
+/// after this can only affect rounding, which is preserved by a sticky digit.
+const MAX_DIGITS: usize = 800;
+
+/// Any exponent with a larger magnitude is out of range for every format
+const MAX_EXPONENT: i64 = 1_000_000;
+
+pub trait Float: Copy {
+    /// Number of explicitly stored mantissa bits
+    const MANTISSA_BITS: u32;
+    /// Exponent bias, which is also the largest unbiased exponent
+    const BIAS: i64;
+    /// Any decimal exponent below this rounds to zero for every 19 digit
+    /// mantissa, and any above the largest overflows
+    const SMALLEST_POWER_OF_TEN: i64;
+    const LARGEST_POWER_OF_TEN: i64;
+    /// Range of decimal exponents where a product can be exactly halfway
+    /// between two floats
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64;
+
+    fn from_raw(bits: u64) -> Self;
+    fn to_raw(self) -> u64;
+    fn negate(self) -> Self;
+
+    fn zero() -> Self {
+        Self::from_raw(0)
+    }
+    fn infinity() -> Self {
+        Self::from_raw(Self::exponent_mask())
+    }
+    fn nan(payload: u64) -> Self {
+        let quiet = 1 << (Self::MANTISSA_BITS - 1);
+        Self::from_raw(Self::exponent_mask() | quiet | payload & (quiet - 1))
+    }
+    fn exponent_mask() -> u64 {
+        ((2 * Self::BIAS + 1) as u64) << Self::MANTISSA_BITS
+    }
+}
+
+impl Float for f32 {
+    const MANTISSA_BITS: u32 = 23;
+    const BIAS: i64 = 127;
+    const SMALLEST_POWER_OF_TEN: i64 = -65;
+    const LARGEST_POWER_OF_TEN: i64 = 38;
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64 = -17;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64 = 10;
+
+    fn from_raw(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+    fn to_raw(self) -> u64 {
+        self.to_bits() as u64
+    }
+    fn negate(self) -> Self {
+        -self
+    }
+}
+
+impl Float for f64 {
+    const MANTISSA_BITS: u32 = 52;
+    const BIAS: i64 = 1023;
+    const SMALLEST_POWER_OF_TEN: i64 = -342;
+    const LARGEST_POWER_OF_TEN: i64 = 308;
+    const MIN_EXPONENT_ROUND_TO_EVEN: i64 = -4;
+    const MAX_EXPONENT_ROUND_TO_EVEN: i64 = 23;
+
+    fn from_raw(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+    fn to_raw(self) -> u64 {
+        self.to_bits()
+    }
+    fn negate(self) -> Self {
+        -self
+    }
+}
+
+enum Number {
+    /// `digits * 10^exp`, with each digit stored as its value
+    Decimal {
+        digits: Vec<u8>,
+        exp: i64,
+    },
+    /// `(mantissa + sticky) * 2^exp`, where sticky is an infinitesimal
+    Hex {
+        mantissa: u64,
+        sticky: bool,
+        exp: i64,
+    },
+    Infinity,
+    Nan(u64),
+}
+
+/// The decimal point of the current locale's LC_NUMERIC category
+pub fn decimal_point() -> u8 {
+    unsafe {
+        CStr::from_ptr(locale::current().numeric().decimal_point)
+            .to_bytes()
+            .get(0)
+            .cloned()
+            .unwrap_or(b'.')
+    }
+}
+
+fn starts_with_ignore_case(s: &[u8], prefix: &[u8]) -> bool {
+    s.len() >= prefix.len()
+        && s.iter()
+            .zip(prefix)
+            .all(|(a, b)| a.to_ascii_lowercase() == *b)
+}
+
+/// Parses an optionally signed exponent at the start of `s`, returning its
+/// value and length. Nothing is consumed unless at least one digit is found.
+fn scan_exponent(s: &[u8]) -> Option<(i64, usize)> {
+    let mut i = 0;
+    let negative = match s.get(0) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+
+    let start = i;
+    let mut exp: i64 = 0;
+    while let Some(&b) = s.get(i).filter(|b| b.is_ascii_digit()) {
+        exp = (exp * 10 + (b - b'0') as i64).min(MAX_EXPONENT);
+        i += 1;
+    }
+
+    if i == start {
+        None
+    } else if negative {
+        Some((-exp, i))
+    } else {
+        Some((exp, i))
+    }
+}
+
+fn scan_hex(s: &[u8], decimal_point: u8) -> Option<(Number, usize)> {
+    let mut i = 0;
+    let mut mantissa: u64 = 0;
+    let mut sticky = false;
+    let mut exp: i64 = 0;
+    let mut any_digits = false;
+    let mut after_point = false;
+
+    loop {
+        match s.get(i) {
+            Some(&b) if b == decimal_point && !after_point => after_point = true,
+            Some(&b) if b.is_ascii_hexdigit() => {
+                let digit = (b as char).to_digit(16).unwrap() as u64;
+                any_digits = true;
+                if mantissa >> 60 == 0 {
+                    mantissa = mantissa << 4 | digit;
+                    if after_point {
+                        exp -= 4;
+                    }
+                } else {
+                    sticky |= digit != 0;
+                    if !after_point {
+                        exp += 4;
+                    }
+                }
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+
+    if !any_digits {
+        return None;
+    }
+
+    if let Some(b'p') | Some(b'P') = s.get(i) {
+        if let Some((e, len)) = scan_exponent(&s[i + 1..]) {
+            exp += e;
+            i += 1 + len;
+        }
+    }
+
+    Some((
+        Number::Hex {
+            mantissa,
+            sticky,
+            exp,
+        },
+        i,
+    ))
+}
+
+fn scan_decimal(s: &[u8], decimal_point: u8) -> Option<(Number, usize)> {
+    let mut i = 0;
+    let mut digits = Vec::new();
+    let mut sticky = false;
+    let mut exp: i64 = 0;
+    let mut any_digits = false;
+    let mut after_point = false;
+
+    loop {
+        match s.get(i) {
+            Some(&b) if b == decimal_point && !after_point => after_point = true,
+            Some(&b) if b.is_ascii_digit() => {
+                let digit = b - b'0';
+                any_digits = true;
+                if digits.is_empty() && digit == 0 {
+                    // Leading zeros are not significant
+                    if after_point {
+                        exp -= 1;
+                    }
+                } else if digits.len() < MAX_DIGITS {
+                    digits.push(digit);
+                    if after_point {
+                        exp -= 1;
+                    }
+                } else {
+                    sticky |= digit != 0;
+                    if !after_point {
+                        exp += 1;
+                    }
+                }
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+
+    if !any_digits {
+        return None;
+    }
+
+    if sticky {
+        digits.push(1);
+        exp -= 1;
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+        exp += 1;
+    }
+
+    if let Some(b'e') | Some(b'E') = s.get(i) {
+        if let Some((e, len)) = scan_exponent(&s[i + 1..]) {
+            exp += e;
+            i += 1 + len;
+        }
+    }
+
+    Some((Number::Decimal { digits, exp }, i))
+}
+
+/// Interprets the n-char-sequence of "nan(...)" like strtoull with base 0
+fn nan_payload(s: &[u8]) -> u64 {
+    let (s, radix) = if s.len() > 2 && s[0] == b'0' && (s[1] == b'x' || s[1] == b'X') {
+        (&s[2..], 16)
+    } else if s.len() > 1 && s[0] == b'0' {
+        (&s[1..], 8)
+    } else {
+        (s, 10)
+    };
+
+    core::str::from_utf8(s)
+        .ok()
+        .and_then(|s| u64::from_str_radix(s, radix).ok())
+        .unwrap_or(0)
+}
+
+/// Recognizes the longest prefix of `s` that is a floating point number as
+/// accepted by strtod, excluding leading whitespace.
+fn scan(s: &[u8], decimal_point: u8) -> Option<(bool, Number, usize)> {
+    let mut i = 0;
+    let negative = match s.get(0) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    let s = &s[i..];
+
+    if starts_with_ignore_case(s, b"infinity") {
+        return Some((negative, Number::Infinity, i + 8));
+    }
+    if starts_with_ignore_case(s, b"inf") {
+        return Some((negative, Number::Infinity, i + 3));
+    }
+    if starts_with_ignore_case(s, b"nan") {
+        let mut len = 3;
+        let mut payload = 0;
+        if s.get(len) == Some(&b'(') {
+            let chars = s[len + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count();
+            if s.get(len + 1 + chars) == Some(&b')') {
+                payload = nan_payload(&s[len + 1..len + 1 + chars]);
+                len += chars + 2;
+            }
+        }
+        return Some((negative, Number::Nan(payload), i + len));
+    }
+
+    if s.len() >= 2 && s[0] == b'0' && (s[1] == b'x' || s[1] == b'X') {
+        if let Some((number, len)) = scan_hex(&s[2..], decimal_point) {
+            return Some((negative, number, i + 2 + len));
+        }
+        // Just a zero followed by garbage
+    }
+
+    scan_decimal(s, decimal_point).map(|(number, len)| (negative, number, i + len))
+}
+
+fn decimal_to_float<T: Float>(digits: &[u8], exp: i64) -> T {
+    if digits.is_empty() {
+        return T::zero();
+    }
+
+    // Position of the decimal point relative to the first significant digit,
+    // which also keeps the big integers used below to a sane size
+    let point = exp + digits.len() as i64;
+    if point > 400 {
+        return T::infinity();
+    }
+    if point < -400 {
+        return T::zero();
+    }
+
+    // The fast path only looks at the first 19 digits, which always fit in a
+    // u64. If there are more, the result is only known when rounding both
+    // the truncated mantissa and its successor agree.
+    let len = digits.len().min(19);
+    let mut w = 0;
+    for digit in &digits[..len] {
+        w = w * 10 + *digit as u64;
+    }
+    let q = exp + (digits.len() - len) as i64;
+
+    if let Some(fp) = lemire::compute_float::<T>(q, w) {
+        if len == digits.len() || lemire::compute_float::<T>(q, w + 1) == Some(fp) {
+            let (biased_exp, mantissa) = fp;
+            return T::from_raw((biased_exp as u64) << T::MANTISSA_BITS | mantissa);
+        }
+    }
+
+    bignum_to_float(digits, exp)
+}
+
+/// Converts `digits * 10^exp` exactly, using big integers
+fn bignum_to_float<T: Float>(digits: &[u8], exp: i64) -> T {
+    let mut num = Bignum::from_digits(digits);
+
+    if exp >= 0 {
+        num.mul_pow5(exp as usize);
+        num.shl(exp as usize);
+        let (mantissa, sticky, shift) = num.top64();
+        return binary_to_float(mantissa, sticky, shift);
+    }
+
+    // digits / 10^k is (digits * 2^scale / 5^k) * 2^(-scale - k), where the
+    // scale gives the quotient 66 or 67 significant bits
+    let k = -exp as usize;
+    let mut den = Bignum::from_u64(1);
+    den.mul_pow5(k);
+    let scale = den.bit_len() as i64 + 66 - num.bit_len() as i64;
+    if scale > 0 {
+        num.shl(scale as usize);
+    } else {
+        den.shl(-scale as usize);
+    }
+    let (quotient, remainder) = num.div_rem(&den);
+
+    let extra = (64 - quotient.leading_zeros() as i64).max(0);
+    let mantissa = (quotient >> extra) as u64;
+    let sticky = remainder || quotient & ((1 << extra) - 1) != 0;
+    binary_to_float(mantissa, sticky, extra - scale - k as i64)
+}
+
+/// Correctly rounds `(mantissa + sticky) * 2^exp`
+fn binary_to_float<T: Float>(mantissa: u64, sticky: bool, exp: i64) -> T {
+    if mantissa == 0 {
+        return T::zero();
+    }
+
+    // Normalize so that the mantissa has its top bit set
+    let shift = mantissa.leading_zeros();
+    let mantissa = mantissa << shift;
+    let exp = exp - shift as i64;
+
+    // Unbiased exponent of the leading bit
+    let mut e = exp + 63;
+    let min_exp = 1 - T::BIAS;
+    if e > T::BIAS {
+        return T::infinity();
+    }
+
+    // Bits of precision available at this exponent
+    let precision = T::MANTISSA_BITS as i64 + 1;
+    let keep = if e >= min_exp {
+        precision
+    } else {
+        precision - (min_exp - e)
+    };
+    if keep < 0 {
+        return T::zero();
+    }
+
+    let (mut kept, rest, half) = if keep == 0 {
+        (0, mantissa, 1 << 63)
+    } else {
+        let shift = 64 - keep as u32;
+        (
+            mantissa >> shift,
+            mantissa & ((1 << shift) - 1),
+            1 << (shift - 1),
+        )
+    };
+    if rest > half || (rest == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+        if kept == 1 << precision {
+            kept >>= 1;
+            e += 1;
+            if e > T::BIAS {
+                return T::infinity();
+            }
+        }
+    }
+
+    if e >= min_exp {
+        let biased = (e + T::BIAS) as u64;
+        T::from_raw(biased << T::MANTISSA_BITS | kept & ((1 << T::MANTISSA_BITS) - 1))
+    } else {
+        // Subnormal, where a carry into the exponent field yields the
+        // smallest normal number as required
+        T::from_raw(kept)
+    }
+}
+
+/// Parses the longest floating point number at the start of `s`, returning
+/// the correctly rounded result and the number of bytes consumed. Sets errno
+/// to ERANGE if the result overflows or underflows.
+pub fn parse<T: Float>(s: &[u8], decimal_point: u8) -> Option<(T, usize)> {
+    let (negative, number, len) = scan(s, decimal_point)?;
+
+    let (value, nonzero) = match number {
+        Number::Decimal { digits, exp } => {
+            let nonzero = !digits.is_empty();
+            (decimal_to_float(&digits, exp), nonzero)
+        }
+        Number::Hex {
+            mantissa,
+            sticky,
+            exp,
+        } => (binary_to_float(mantissa, sticky, exp), mantissa != 0),
+        Number::Infinity => (T::infinity(), false),
+        Number::Nan(payload) => (T::nan(payload), false),
+    };
+
+    // Overflow to infinity, or underflow to a subnormal or zero
+    let biased_exp = value.to_raw() >> T::MANTISSA_BITS;
+    if nonzero && (biased_exp == 0 || biased_exp == (2 * T::BIAS + 1) as u64) {
+        unsafe {
+            platform::errno = ERANGE;
+        }
+    }
+
+    if negative {
+        Some((value.negate(), len))
+    } else {
+        Some((value, len))
+    }
+}
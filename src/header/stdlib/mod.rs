@@ -16,6 +16,15 @@ use crate::{
         errno::{self, *},
         fcntl::*,
         limits,
+        signal::{
+            self, sigaction, sigaddset, sigemptyset, sigprocmask, SIGCHLD, SIGINT, SIGQUIT,
+            SIG_BLOCK, SIG_DFL, SIG_IGN, SIG_SETMASK,
+        },
+        spawn::{
+            posix_spawn, posix_spawnattr_destroy, posix_spawnattr_init, posix_spawnattr_setflags,
+            posix_spawnattr_setsigdefault, posix_spawnattr_setsigmask, posix_spawnattr_t,
+            POSIX_SPAWN_SETSIGDEF, POSIX_SPAWN_SETSIGMASK,
+        },
         string::*,
         time::constants::CLOCK_MONOTONIC,
         unistd::{self, sysconf, _SC_PAGESIZE},
@@ -24,6 +33,7 @@ use crate::{
     platform::{self, types::*, Pal, Sys},
 };
 
+pub mod float;
 mod rand48;
 mod random;
 mod sort;
@@ -271,7 +281,6 @@ pub unsafe extern "C" fn exit(status: c_int) {
         static __fini_array_start: extern "C" fn();
         static __fini_array_end: extern "C" fn();
 
-        fn pthread_terminate();
         fn _fini();
     }
 
@@ -291,8 +300,6 @@ pub unsafe extern "C" fn exit(status: c_int) {
 
     _fini();
 
-    pthread_terminate();
-
     Sys::exit(status);
 }
 
@@ -525,8 +532,13 @@ pub unsafe extern "C" fn memalign(alignment: size_t, size: size_t) -> *mut c_voi
 
 #[no_mangle]
 pub unsafe extern "C" fn mblen(s: *const c_char, n: size_t) -> c_int {
+    // UTF-8 has no shift states
+    if s.is_null() {
+        return 0;
+    }
+
     let mut wc: wchar_t = 0;
-    let mut state: mbstate_t = mbstate_t {};
+    let mut state = mbstate_t::new();
     let result: usize = mbrtowc(&mut wc, s, n, &mut state);
 
     if result == -1isize as usize {
@@ -541,14 +553,24 @@ pub unsafe extern "C" fn mblen(s: *const c_char, n: size_t) -> c_int {
 
 #[no_mangle]
 pub unsafe extern "C" fn mbstowcs(pwcs: *mut wchar_t, mut s: *const c_char, n: size_t) -> size_t {
-    let mut state: mbstate_t = mbstate_t {};
+    let mut state = mbstate_t::new();
     mbsrtowcs(pwcs, &mut s, n, &mut state)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn mbtowc(pwc: *mut wchar_t, s: *const c_char, n: size_t) -> c_int {
-    let mut state: mbstate_t = mbstate_t {};
-    mbrtowc(pwc, s, n, &mut state) as c_int
+    if s.is_null() {
+        return 0;
+    }
+
+    let mut state = mbstate_t::new();
+    let result: usize = mbrtowc(pwc, s, n, &mut state);
+
+    if result == -1isize as usize || result == -2isize as usize {
+        return -1;
+    }
+
+    result as c_int
 }
 
 fn inner_mktemp<T, F>(name: *mut c_char, suffix_len: c_int, mut attempt: F) -> Option<T>
@@ -952,13 +974,38 @@ pub unsafe extern "C" fn srandom(seed: c_uint) {
     // TODO: unlock?
 }
 
+unsafe fn strto_float<T: float::Float>(s: *const c_char, endptr: *mut *mut c_char) -> T {
+    let mut idx = 0;
+    while ctype::isspace(*s.offset(idx) as c_int) != 0 {
+        idx += 1;
+    }
+
+    let bytes = CStr::from_ptr(s.offset(idx)).to_bytes();
+    let (value, end) = match float::parse::<T>(bytes, float::decimal_point()) {
+        Some((value, len)) => (value, s.offset(idx).add(len)),
+        // No conversion could be performed
+        None => (T::zero(), s),
+    };
+
+    if !endptr.is_null() {
+        // This is stupid, but apparently strto* functions want
+        // const input but mut output, yet the man page says
+        // "stores the address of the first invalid character in *endptr"
+        // so obviously it doesn't want us to clone it.
+        *endptr = end as *mut _;
+    }
+
+    value
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn strtod(s: *const c_char, endptr: *mut *mut c_char) -> c_double {
-    strto_float_impl!(c_double, s, endptr)
+    strto_float(s, endptr)
 }
+
 #[no_mangle]
 pub unsafe extern "C" fn strtof(s: *const c_char, endptr: *mut *mut c_char) -> c_float {
-    strto_float_impl!(c_float, s, endptr)
+    strto_float(s, endptr)
 }
 
 pub fn is_positive(ch: c_char) -> Option<(bool, isize)> {
@@ -1118,40 +1165,85 @@ pub unsafe extern "C" fn strtoll(
 
 #[no_mangle]
 pub unsafe extern "C" fn system(command: *const c_char) -> c_int {
-    //TODO: share code with popen
-
-    let child_pid = unistd::fork();
-    if child_pid == 0 {
-        let command_nonnull = if command.is_null() {
-            "exit 0\0".as_ptr()
-        } else {
-            command as *const u8
-        };
-
-        let shell = "/bin/sh\0".as_ptr();
-
-        let args = [
-            "sh\0".as_ptr(),
-            "-c\0".as_ptr(),
-            command_nonnull,
-            ptr::null(),
-        ];
-
-        unistd::execv(shell as *const c_char, args.as_ptr() as *const *mut c_char);
+    // A null command asks whether a shell is available
+    if command.is_null() {
+        return (system(c_str!("exit 0").as_ptr()) == 0) as c_int;
+    }
 
-        exit(127);
+    // Interrupts go to the shell only, and the caller's SIGCHLD handler must
+    // not reap it
+    let mut ignore: sigaction = mem::zeroed();
+    ignore.sa_handler = mem::transmute(SIG_IGN);
+    let mut old_int: sigaction = mem::zeroed();
+    let mut old_quit: sigaction = mem::zeroed();
+    signal::sigaction(SIGINT as c_int, &ignore, &mut old_int);
+    signal::sigaction(SIGQUIT as c_int, &ignore, &mut old_quit);
+
+    let mut mask = 0;
+    let mut old_mask = 0;
+    sigemptyset(&mut mask);
+    sigaddset(&mut mask, SIGCHLD as c_int);
+    sigprocmask(SIG_BLOCK, &mask, &mut old_mask);
+
+    // The shell gets back whatever the caller had
+    let mut sigdefault = 0;
+    sigemptyset(&mut sigdefault);
+    if old_int
+        .sa_handler
+        .map_or(SIG_DFL, |handler| handler as usize)
+        != SIG_IGN
+    {
+        sigaddset(&mut sigdefault, SIGINT as c_int);
+    }
+    if old_quit
+        .sa_handler
+        .map_or(SIG_DFL, |handler| handler as usize)
+        != SIG_IGN
+    {
+        sigaddset(&mut sigdefault, SIGQUIT as c_int);
+    }
 
-        unreachable!();
-    } else if child_pid > 0 {
-        let mut wstatus = 0;
-        if Sys::waitpid(child_pid, &mut wstatus, 0) == !0 {
-            return -1;
+    let mut attr: posix_spawnattr_t = mem::zeroed();
+    posix_spawnattr_init(&mut attr);
+    posix_spawnattr_setsigdefault(&mut attr, &sigdefault);
+    posix_spawnattr_setsigmask(&mut attr, &old_mask);
+    posix_spawnattr_setflags(&mut attr, POSIX_SPAWN_SETSIGDEF | POSIX_SPAWN_SETSIGMASK);
+
+    let args = [
+        c_str!("sh").as_ptr(),
+        c_str!("-c").as_ptr(),
+        command,
+        ptr::null(),
+    ];
+    let mut pid = 0;
+    let err = posix_spawn(
+        &mut pid,
+        c_str!("/bin/sh").as_ptr(),
+        ptr::null(),
+        &attr,
+        args.as_ptr() as *const *mut c_char,
+        platform::environ,
+    );
+    posix_spawnattr_destroy(&mut attr);
+
+    let mut wstatus = 0;
+    if err == 0 {
+        while Sys::waitpid(pid, &mut wstatus, 0) == !0 {
+            if platform::errno != EINTR {
+                wstatus = -1;
+                break;
+            }
         }
-
-        wstatus
     } else {
-        -1
+        // Failing to run the shell looks like the shell exiting with 127
+        wstatus = 127 << 8;
     }
+
+    signal::sigaction(SIGINT as c_int, &old_int, ptr::null_mut());
+    signal::sigaction(SIGQUIT as c_int, &old_quit, ptr::null_mut());
+    sigprocmask(SIG_SETMASK, &old_mask, ptr::null_mut());
+
+    wstatus
 }
 
 // #[no_mangle]
@@ -1196,14 +1288,18 @@ pub unsafe extern "C" fn valloc(size: size_t) -> *mut c_void {
 }
 
 #[no_mangle]
-pub extern "C" fn wcstombs(s: *mut c_char, pwcs: *mut *const wchar_t, n: size_t) -> size_t {
-    let mut state: mbstate_t = mbstate_t {};
-    wcsrtombs(s, pwcs, n, &mut state)
+pub unsafe extern "C" fn wcstombs(s: *mut c_char, mut pwcs: *const wchar_t, n: size_t) -> size_t {
+    let mut state = mbstate_t::new();
+    wcsrtombs(s, &mut pwcs, n, &mut state)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wctomb(s: *mut c_char, wc: wchar_t) -> c_int {
-    let mut state: mbstate_t = mbstate_t {};
+    if s.is_null() {
+        return 0;
+    }
+
+    let mut state = mbstate_t::new();
     let result: usize = wcrtomb(s, wc, &mut state);
 
     if result == -1isize as usize {
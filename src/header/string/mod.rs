@@ -5,7 +5,7 @@ use core::{mem, ptr, slice, usize};
 use cbitset::BitSet256;
 
 use crate::{
-    header::{errno::*, signal},
+    header::{errno::*, locale::locale_t, signal},
     platform::{self, types::*},
 };
 
@@ -148,10 +148,15 @@ pub unsafe extern "C" fn strcmp(s1: *const c_char, s2: *const c_char) -> c_int {
 
 #[no_mangle]
 pub unsafe extern "C" fn strcoll(s1: *const c_char, s2: *const c_char) -> c_int {
-    // relibc has no locale stuff (yet)
+    // All supported locales collate in byte order
     strcmp(s1, s2)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn strcoll_l(s1: *const c_char, s2: *const c_char, _loc: locale_t) -> c_int {
+    strcoll(s1, s2)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn strcpy(dst: *mut c_char, src: *const c_char) -> *mut c_char {
     let mut i = 0;
@@ -243,6 +248,11 @@ pub unsafe extern "C" fn strerror(errnum: c_int) -> *mut c_char {
     strerror_buf.as_mut_ptr() as *mut c_char
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn strerror_l(errnum: c_int, _loc: locale_t) -> *mut c_char {
+    strerror(errnum)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn strerror_r(errnum: c_int, buf: *mut c_char, buflen: size_t) -> c_int {
     let msg = strerror(errnum);
@@ -454,10 +464,20 @@ pub unsafe extern "C" fn strtok_r(
 
 #[no_mangle]
 pub unsafe extern "C" fn strxfrm(s1: *mut c_char, s2: *const c_char, n: size_t) -> size_t {
-    // relibc has no locale stuff (yet)
+    // All supported locales collate in byte order
     let len = strlen(s2);
     if len < n {
         strcpy(s1, s2);
     }
     len
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn strxfrm_l(
+    s1: *mut c_char,
+    s2: *const c_char,
+    n: size_t,
+    _loc: locale_t,
+) -> size_t {
+    strxfrm(s1, s2, n)
+}
@@ -1,6 +1,9 @@
 //! sys/auxv.h implementation
 
-use crate::platform::types::*;
+use crate::{
+    header::errno::ENOENT,
+    platform::{self, types::*},
+};
 
 pub const AT_NULL: usize = 0; /* End of vector */
 pub const AT_IGNORE: usize = 1; /* Entry should be ignored */
@@ -30,7 +33,31 @@ pub const AT_RANDOM: usize = 25; /* Address of 16 random bytes.  */
 pub const AT_HWCAP2: usize = 26; /* More machine-dependent hints about*/
 pub const AT_EXECFN: usize = 31; /* Filename of executable.  */
 
+/// Looks up an entry of the auxiliary vector. Unlike getauxval, this leaves
+/// errno alone, so it works before thread local storage is set up.
+pub(crate) fn auxval(kind: usize) -> Option<usize> {
+    unsafe {
+        let mut entry = platform::auxv;
+        if entry.is_null() {
+            return None;
+        }
+        while (*entry).0 != AT_NULL {
+            if (*entry).0 == kind {
+                return Some((*entry).1);
+            }
+            entry = entry.add(1);
+        }
+        None
+    }
+}
+
 #[no_mangle]
-pub extern "C" fn getauxval(_t: c_ulong) -> c_ulong {
-    0
+pub unsafe extern "C" fn getauxval(t: c_ulong) -> c_ulong {
+    match auxval(t as usize) {
+        Some(value) => value as c_ulong,
+        None => {
+            platform::errno = ENOENT;
+            0
+        }
+    }
 }
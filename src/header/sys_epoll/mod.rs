@@ -3,8 +3,11 @@
 use core::ptr;
 
 use crate::{
-    header::signal::sigset_t,
-    platform::{types::*, PalEpoll, Sys},
+    header::{
+        pthread::{cancellation_point, with_cleanup},
+        signal::sigset_t,
+    },
+    platform::{types::*, Pal, PalEpoll, Sys},
 };
 
 pub use self::sys::*;
@@ -90,7 +93,7 @@ pub extern "C" fn epoll_pwait(
     sigmask: *const sigset_t,
 ) -> c_int {
     trace_expr!(
-        Sys::epoll_pwait(epfd, events, maxevents, timeout, sigmask),
+        cancellation_point(|| Sys::epoll_pwait(epfd, events, maxevents, timeout, sigmask)),
         "epoll_pwait({}, {:p}, {}, {}, {:p})",
         epfd,
         events,
@@ -99,3 +102,19 @@ pub extern "C" fn epoll_pwait(
         sigmask
     )
 }
+
+extern "C" fn close_epoll(epfd: *mut c_void) {
+    Sys::close(epfd as usize as c_int);
+}
+/// epoll_wait on an instance created just for this wait, as by poll and
+/// select, which closes it if the thread is cancelled while it waits
+pub(crate) fn epoll_wait_once(
+    epfd: c_int,
+    events: *mut epoll_event,
+    maxevents: c_int,
+    timeout: c_int,
+) -> c_int {
+    with_cleanup(close_epoll, epfd as usize as *mut c_void, || {
+        epoll_wait(epfd, events, maxevents, timeout)
+    })
+}
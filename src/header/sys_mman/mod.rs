@@ -1,6 +1,6 @@
 use crate::{
     c_str::{CStr, CString},
-    header::{fcntl, unistd},
+    header::{fcntl, pthread::cancellation_point, unistd},
     platform::{types::*, Pal, Sys},
 };
 
@@ -53,7 +53,7 @@ pub unsafe extern "C" fn mprotect(addr: *mut c_void, len: size_t, prot: c_int) -
 
 #[no_mangle]
 pub unsafe extern "C" fn msync(addr: *mut c_void, len: size_t, flags: c_int) -> c_int {
-    Sys::msync(addr, len, flags)
+    cancellation_point(|| Sys::msync(addr, len, flags))
 }
 
 // #[no_mangle]
@@ -72,10 +72,10 @@ pub unsafe extern "C" fn munmap(addr: *mut c_void, len: size_t) -> c_int {
 }
 
 #[cfg(target_os = "linux")]
-static SHM_PATH: &'static [u8] = b"/dev/shm/";
+pub(crate) static SHM_PATH: &'static [u8] = b"/dev/shm/";
 
 #[cfg(target_os = "redox")]
-static SHM_PATH: &'static [u8] = b"shm:";
+pub(crate) static SHM_PATH: &'static [u8] = b"shm:";
 
 unsafe fn shm_path(name: *const c_char) -> CString {
     let name_c = CStr::from_ptr(name);
@@ -9,7 +9,7 @@ use crate::{
     header::{
         errno,
         sys_epoll::{
-            epoll_create1, epoll_ctl, epoll_data, epoll_event, epoll_wait, EPOLLERR, EPOLLIN,
+            epoll_create1, epoll_ctl, epoll_data, epoll_event, epoll_wait_once, EPOLLERR, EPOLLIN,
             EPOLLOUT, EPOLL_CLOEXEC, EPOLL_CTL_ADD,
         },
         sys_time::timeval,
@@ -121,7 +121,7 @@ pub fn select_epoll(
             None => -1,
         }
     };
-    let res = epoll_wait(
+    let res = epoll_wait_once(
         *ep,
         events.as_mut_ptr(),
         events.len() as c_int,
@@ -1,2 +1,74 @@
 //! sys/types.h
 use crate::platform::types::*;
+
+// The pthread types are opaque to C, header::pthread casts them to its own
+// structures. An all-zero value of the mutex, cond and rwlock types must be
+// valid, since it is what their static initializers produce.
+
+pub type pthread_t = *mut c_void;
+pub type pthread_key_t = c_uint;
+pub type pthread_once_t = c_int;
+pub type pthread_spinlock_t = c_int;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_attr_t {
+    pub size: [c_uchar; 56],
+    pub align: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_mutex_t {
+    pub size: [c_uchar; 40],
+    pub align: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_mutexattr_t {
+    pub size: [c_uchar; 16],
+    pub align: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_cond_t {
+    pub size: [c_uchar; 48],
+    pub align: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_condattr_t {
+    pub size: [c_uchar; 8],
+    pub align: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_rwlock_t {
+    pub size: [c_uchar; 56],
+    pub align: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_rwlockattr_t {
+    pub size: [c_uchar; 8],
+    pub align: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_barrier_t {
+    pub size: [c_uchar; 32],
+    pub align: c_long,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union pthread_barrierattr_t {
+    pub size: [c_uchar; 4],
+    pub align: c_int,
+}
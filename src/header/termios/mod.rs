@@ -1,7 +1,7 @@
 //! termios implementation, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/termios.h.html
 
 use crate::{
-    header::{errno, sys_ioctl},
+    header::{errno, pthread::cancellation_point, sys_ioctl},
     platform::{self, types::*},
 };
 
@@ -118,7 +118,7 @@ pub unsafe extern "C" fn tcflush(fd: c_int, queue: c_int) -> c_int {
 
 #[no_mangle]
 pub unsafe extern "C" fn tcdrain(fd: c_int) -> c_int {
-    sys_ioctl::ioctl(fd, sys_ioctl::TCSBRK, 1 as *mut _)
+    cancellation_point(|| sys_ioctl::ioctl(fd, sys_ioctl::TCSBRK, 1 as *mut _))
 }
 
 #[no_mangle]
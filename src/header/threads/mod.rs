@@ -0,0 +1,229 @@
+//! threads.h implementation, following the C11 standard. Everything is a thin
+//! layer over the pthread functions.
+
+use alloc::boxed::Box;
+use core::{mem, ptr};
+
+use crate::{
+    header::{
+        errno::*,
+        pthread::*,
+        sys_types::*,
+        time::{nanosleep, timespec},
+    },
+    platform::{self, types::*, Pal, Sys},
+};
+
+pub type thrd_t = pthread_t;
+pub type thrd_start_t = extern "C" fn(arg: *mut c_void) -> c_int;
+pub type mtx_t = pthread_mutex_t;
+pub type cnd_t = pthread_cond_t;
+pub type tss_t = pthread_key_t;
+pub type tss_dtor_t = Option<extern "C" fn(value: *mut c_void)>;
+pub type once_flag = pthread_once_t;
+
+pub const thrd_success: c_int = 0;
+pub const thrd_busy: c_int = 1;
+pub const thrd_error: c_int = 2;
+pub const thrd_nomem: c_int = 3;
+pub const thrd_timedout: c_int = 4;
+
+pub const mtx_plain: c_int = 0;
+pub const mtx_recursive: c_int = 1;
+pub const mtx_timed: c_int = 2;
+
+pub const TSS_DTOR_ITERATIONS: c_int = PTHREAD_DESTRUCTOR_ITERATIONS;
+
+/// Turns the error number returned by a pthread function into a thrd_*
+/// result
+fn result(err: c_int) -> c_int {
+    match err {
+        0 => thrd_success,
+        EBUSY => thrd_busy,
+        ENOMEM => thrd_nomem,
+        ETIMEDOUT => thrd_timedout,
+        _ => thrd_error,
+    }
+}
+
+/// A thread entry point and its argument, freed by the new thread
+struct Start {
+    func: thrd_start_t,
+    arg: *mut c_void,
+}
+
+/// Runs a C11 thread function, which returns an int rather than a pointer
+extern "C" fn start(arg: *mut c_void) -> *mut c_void {
+    let start = unsafe { *Box::from_raw(arg as *mut Start) };
+    (start.func)(start.arg) as isize as *mut c_void
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn call_once(flag: *mut once_flag, func: extern "C" fn()) {
+    pthread_once(flag, func);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cnd_broadcast(cond: *mut cnd_t) -> c_int {
+    result(pthread_cond_broadcast(cond))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cnd_destroy(cond: *mut cnd_t) {
+    pthread_cond_destroy(cond);
+}
+
+/// The condition uses the default clock, CLOCK_REALTIME, which is TIME_UTC
+#[no_mangle]
+pub unsafe extern "C" fn cnd_init(cond: *mut cnd_t) -> c_int {
+    result(pthread_cond_init(cond, ptr::null()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cnd_signal(cond: *mut cnd_t) -> c_int {
+    result(pthread_cond_signal(cond))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cnd_timedwait(
+    cond: *mut cnd_t,
+    mtx: *mut mtx_t,
+    time_point: *const timespec,
+) -> c_int {
+    result(pthread_cond_timedwait(cond, mtx, time_point))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cnd_wait(cond: *mut cnd_t, mtx: *mut mtx_t) -> c_int {
+    result(pthread_cond_wait(cond, mtx))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mtx_destroy(mtx: *mut mtx_t) {
+    pthread_mutex_destroy(mtx);
+}
+
+/// Any mutex can be locked with a timeout, so mtx_timed changes nothing
+#[no_mangle]
+pub unsafe extern "C" fn mtx_init(mtx: *mut mtx_t, type_: c_int) -> c_int {
+    let kind = match type_ {
+        mtx_plain | mtx_timed => PTHREAD_MUTEX_NORMAL,
+        _ if type_ & !mtx_timed == mtx_recursive => PTHREAD_MUTEX_RECURSIVE,
+        _ => return thrd_error,
+    };
+    let mut attr: pthread_mutexattr_t = mem::zeroed();
+    pthread_mutexattr_init(&mut attr);
+    pthread_mutexattr_settype(&mut attr, kind);
+    let ret = pthread_mutex_init(mtx, &attr);
+    pthread_mutexattr_destroy(&mut attr);
+    result(ret)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mtx_lock(mtx: *mut mtx_t) -> c_int {
+    result(pthread_mutex_lock(mtx))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mtx_timedlock(mtx: *mut mtx_t, time_point: *const timespec) -> c_int {
+    result(pthread_mutex_timedlock(mtx, time_point))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mtx_trylock(mtx: *mut mtx_t) -> c_int {
+    result(pthread_mutex_trylock(mtx))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mtx_unlock(mtx: *mut mtx_t) -> c_int {
+    result(pthread_mutex_unlock(mtx))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn thrd_create(
+    thr: *mut thrd_t,
+    func: thrd_start_t,
+    arg: *mut c_void,
+) -> c_int {
+    let args = Box::into_raw(Box::new(Start { func, arg }));
+    match pthread_create(thr, ptr::null(), start, args as *mut c_void) {
+        0 => thrd_success,
+        err => {
+            drop(Box::from_raw(args));
+            // Thread stacks are allocated memory too
+            if err == EAGAIN {
+                thrd_nomem
+            } else {
+                result(err)
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn thrd_current() -> thrd_t {
+    pthread_self()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn thrd_detach(thr: thrd_t) -> c_int {
+    result(pthread_detach(thr))
+}
+
+#[no_mangle]
+pub extern "C" fn thrd_equal(thr0: thrd_t, thr1: thrd_t) -> c_int {
+    pthread_equal(thr0, thr1)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn thrd_exit(res: c_int) -> ! {
+    pthread_exit(res as isize as *mut c_void)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn thrd_join(thr: thrd_t, res: *mut c_int) -> c_int {
+    let mut retval = ptr::null_mut();
+    let ret = pthread_join(thr, &mut retval);
+    if ret == 0 && !res.is_null() {
+        *res = retval as isize as c_int;
+    }
+    result(ret)
+}
+
+/// Returns 0 once the time has passed, -1 if interrupted by a signal, and
+/// another negative value on any other error
+#[no_mangle]
+pub unsafe extern "C" fn thrd_sleep(duration: *const timespec, remaining: *mut timespec) -> c_int {
+    if nanosleep(duration, remaining) == 0 {
+        0
+    } else if platform::errno == EINTR {
+        -1
+    } else {
+        -2
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn thrd_yield() {
+    Sys::sched_yield();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tss_create(key: *mut tss_t, dtor: tss_dtor_t) -> c_int {
+    result(pthread_key_create(key, dtor))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tss_delete(key: tss_t) {
+    pthread_key_delete(key);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tss_get(key: tss_t) -> *mut c_void {
+    pthread_getspecific(key)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tss_set(key: tss_t, val: *mut c_void) -> c_int {
+    result(pthread_setspecific(key, val))
+}
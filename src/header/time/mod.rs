@@ -1,7 +1,7 @@
 //! time implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/time.h.html
 
 use crate::{
-    header::errno::EIO,
+    header::{errno::EIO, pthread::cancellation_point},
     platform::{self, types::*, Pal, Sys},
 };
 
@@ -319,7 +319,7 @@ pub unsafe extern "C" fn mktime(t: *mut tm) -> time_t {
 
 #[no_mangle]
 pub extern "C" fn nanosleep(rqtp: *const timespec, rmtp: *mut timespec) -> c_int {
-    Sys::nanosleep(rqtp, rmtp)
+    cancellation_point(|| Sys::nanosleep(rqtp, rmtp))
 }
 
 #[no_mangle]
@@ -1,14 +1,21 @@
 use alloc::string::String;
 
-use crate::platform::{self, types::*, WriteByte};
+use crate::{
+    c_str::CStr,
+    header::locale,
+    platform::{self, types::*, WriteByte},
+};
 
 use super::tm;
 
 pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const tm) -> size_t {
+    /// `nested` is set within the formats of the locale, which are not
+    /// trusted to leave each other out
     pub unsafe fn inner_strftime<W: WriteByte>(
         w: &mut W,
         mut format: *const c_char,
         t: *const tm,
+        nested: bool,
     ) -> bool {
         macro_rules! w {
             (byte $b:expr) => {{
@@ -26,7 +33,19 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
                 fmt.push_str($fmt);
                 fmt.push('\0');
 
-                if !inner_strftime(w, fmt.as_ptr() as *mut c_char, t) {
+                if !inner_strftime(w, fmt.as_ptr() as *mut c_char, t, nested) {
+                    return false;
+                }
+            }};
+            (cstr $ptr:expr) => {{
+                for &b in CStr::from_ptr($ptr).to_bytes() {
+                    w!(byte b);
+                }
+            }};
+            (format $fmt:expr) => {{
+                // A locale format referring to another one could recurse
+                // forever
+                if nested || !inner_strftime(w, $fmt, t, true) {
                     return false;
                 }
             }};
@@ -42,29 +61,7 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
                 }
             }};
         }
-        const WDAYS: [&str; 7] = [
-            "Sunday",
-            "Monday",
-            "Tuesday",
-            "Wednesday",
-            "Thursday",
-            "Friday",
-            "Saturday",
-        ];
-        const MONTHS: [&str; 12] = [
-            "January",
-            "Febuary",
-            "March",
-            "April",
-            "May",
-            "June",
-            "July",
-            "August",
-            "September",
-            "October",
-            "November",
-            "December",
-        ];
+        let time = locale::current().time();
 
         while *format != 0 {
             if *format as u8 != b'%' {
@@ -76,7 +73,7 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
             format = format.offset(1);
 
             if *format as u8 == b'E' || *format as u8 == b'O' {
-                // Ignore because these do nothing without locale
+                // Ignore because no supported locale has alternative formats
                 format = format.offset(1);
             }
 
@@ -84,10 +81,11 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
                 b'%' => w!(byte b'%'),
                 b'n' => w!(byte b'\n'),
                 b't' => w!(byte b'\t'),
-                b'a' => w!(&WDAYS[(*t).tm_wday as usize][..3]),
-                b'A' => w!(WDAYS[(*t).tm_wday as usize]),
-                b'b' | b'h' => w!(&MONTHS[(*t).tm_mon as usize][..3]),
-                b'B' => w!(MONTHS[(*t).tm_mon as usize]),
+                b'a' => w!(cstr time.abday[(*t).tm_wday as usize]),
+                b'A' => w!(cstr time.day[(*t).tm_wday as usize]),
+                b'b' | b'h' => w!(cstr time.abmon[(*t).tm_mon as usize]),
+                b'B' => w!(cstr time.mon[(*t).tm_mon as usize]),
+                b'c' => w!(format time.d_t_fmt),
                 b'C' => {
                     let mut year = (*t).tm_year / 100;
                     // Round up
@@ -107,9 +105,13 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
                 b'l' => w!("{:2}", ((*t).tm_hour + 12 - 1) % 12 + 1),
                 b'm' => w!("{:02}", (*t).tm_mon + 1),
                 b'M' => w!("{:02}", (*t).tm_min),
-                b'p' => w!(if (*t).tm_hour < 12 { "AM" } else { "PM" }),
-                b'P' => w!(if (*t).tm_hour < 12 { "am" } else { "pm" }),
-                b'r' => w!(recurse "%I:%M:%S %p"),
+                b'p' => w!(cstr time.am_pm[((*t).tm_hour >= 12) as usize]),
+                b'P' => {
+                    for &b in CStr::from_ptr(time.am_pm[((*t).tm_hour >= 12) as usize]).to_bytes() {
+                        w!(byte b.to_ascii_lowercase());
+                    }
+                }
+                b'r' => w!(format time.t_fmt_ampm),
                 b'R' => w!(recurse "%H:%M"),
                 // Nothing is modified in mktime, but the C standard of course requires a mutable pointer ._.
                 b's' => w!("{}", super::mktime(t as *mut tm)),
@@ -119,6 +121,8 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
                 b'U' => w!("{}", ((*t).tm_yday + 7 - (*t).tm_wday) / 7),
                 b'w' => w!("{}", (*t).tm_wday),
                 b'W' => w!("{}", ((*t).tm_yday + 7 - ((*t).tm_wday + 6) % 7) / 7),
+                b'x' => w!(format time.d_fmt),
+                b'X' => w!(format time.t_fmt),
                 b'y' => w!("{:02}", (*t).tm_year % 100),
                 b'Y' => w!("{}", (*t).tm_year + 1900),
                 b'z' => w!("+0000"), // TODO
@@ -133,7 +137,7 @@ pub unsafe fn strftime<W: WriteByte>(w: &mut W, format: *const c_char, t: *const
     }
 
     let mut w = platform::CountingWriter::new(w);
-    if !inner_strftime(&mut w, format, t) {
+    if !inner_strftime(&mut w, format, t, false) {
         return 0;
     }
 
@@ -0,0 +1,132 @@
+//! uchar implementation for Redox, following the C11 standard
+
+use core::ptr;
+
+use crate::{
+    header::{
+        errno::EILSEQ,
+        wchar::{mbrtowc, mbstate_t, wcrtomb},
+    },
+    platform::{self, types::*},
+};
+
+fn invalid(ps: &mut mbstate_t) -> size_t {
+    *ps = mbstate_t::new();
+    unsafe {
+        platform::errno = EILSEQ;
+    }
+    -1isize as size_t
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn c16rtomb(s: *mut c_char, c16: char16_t, ps: *mut mbstate_t) -> size_t {
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL
+    } else {
+        &mut *ps
+    };
+    if s.is_null() {
+        *ps = mbstate_t::new();
+        return 1;
+    }
+
+    // The first half of a surrogate pair is kept until the second arrives
+    let c = c16 as c_uint;
+    let high = ps.surrogate();
+    if c >= 0xD800 && c < 0xDC00 {
+        if high != 0 {
+            return invalid(ps);
+        }
+        ps.set_surrogate(c);
+        return 0;
+    }
+
+    let wc = if c >= 0xDC00 && c < 0xE000 {
+        if high == 0 {
+            return invalid(ps);
+        }
+        ps.set_surrogate(0);
+        0x10000 + ((high - 0xD800) << 10) + (c - 0xDC00)
+    } else if high != 0 {
+        return invalid(ps);
+    } else {
+        c
+    };
+    wcrtomb(s, wc as wchar_t, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn c32rtomb(s: *mut c_char, c32: char32_t, ps: *mut mbstate_t) -> size_t {
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL
+    } else {
+        &mut *ps
+    };
+    wcrtomb(s, c32 as wchar_t, ps)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mbrtoc16(
+    pc16: *mut char16_t,
+    s: *const c_char,
+    n: size_t,
+    ps: *mut mbstate_t,
+) -> size_t {
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL
+    } else {
+        &mut *ps
+    };
+    let pc16 = if s.is_null() { ptr::null_mut() } else { pc16 };
+
+    // The second half of a surrogate pair is returned without reading input
+    let low = ps.surrogate();
+    if low != 0 {
+        ps.set_surrogate(0);
+        if !pc16.is_null() {
+            *pc16 = low as char16_t;
+        }
+        return -3isize as size_t;
+    }
+
+    let mut wc: wchar_t = 0;
+    let result = mbrtowc(&mut wc, s, n, ps);
+    if result >= -2isize as size_t {
+        return result;
+    }
+
+    let c = wc as c_uint;
+    let c16 = if c >= 0x10000 {
+        ps.set_surrogate(0xDC00 | ((c - 0x10000) & 0x3FF));
+        0xD800 | ((c - 0x10000) >> 10)
+    } else {
+        c
+    };
+    if !pc16.is_null() {
+        *pc16 = c16 as char16_t;
+    }
+    result
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mbrtoc32(
+    pc32: *mut char32_t,
+    s: *const c_char,
+    n: size_t,
+    ps: *mut mbstate_t,
+) -> size_t {
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL
+    } else {
+        &mut *ps
+    };
+    mbrtowc(pc32 as *mut wchar_t, s, n, ps)
+}
@@ -1,12 +1,12 @@
 //! unistd implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/unistd.h.html
 
-use core::{convert::TryFrom, mem, ptr, slice};
+use core::{convert::TryFrom, intrinsics, mem, ptr, slice};
 
 use crate::{
     c_str::CStr,
     header::{
-        errno, fcntl, limits, stdlib::getenv, sys_ioctl, sys_time, sys_utsname, termios,
-        time::timespec,
+        errno, fcntl, limits, pthread::cancellation_point, stdlib::getenv, sys_ioctl, sys_time,
+        sys_utsname, termios, time::timespec,
     },
     platform::{self, types::*, Pal, Sys},
 };
@@ -101,7 +101,7 @@ pub unsafe extern "C" fn chown(path: *const c_char, owner: uid_t, group: gid_t)
 
 #[no_mangle]
 pub extern "C" fn close(fildes: c_int) -> c_int {
-    Sys::close(fildes)
+    cancellation_point(|| Sys::close(fildes))
 }
 
 // #[no_mangle]
@@ -240,7 +240,7 @@ pub extern "C" fn fork() -> pid_t {
 
 #[no_mangle]
 pub extern "C" fn fsync(fildes: c_int) -> c_int {
-    Sys::fsync(fildes)
+    cancellation_point(|| Sys::fsync(fildes))
 }
 
 #[no_mangle]
@@ -534,7 +534,7 @@ pub extern "C" fn pwrite(
 pub extern "C" fn read(fildes: c_int, buf: *const c_void, nbyte: size_t) -> ssize_t {
     let buf = unsafe { slice::from_raw_parts_mut(buf as *mut u8, nbyte as usize) };
     trace_expr!(
-        Sys::read(fildes, buf),
+        cancellation_point(|| Sys::read(fildes, buf)),
         "read({}, {:p}, {})",
         fildes,
         buf,
@@ -584,9 +584,9 @@ pub extern "C" fn setreuid(ruid: uid_t, euid: uid_t) -> c_int {
     Sys::setreuid(ruid, euid)
 }
 
-// #[no_mangle]
+#[no_mangle]
 pub extern "C" fn setsid() -> pid_t {
-    unimplemented!();
+    Sys::setsid()
 }
 
 #[no_mangle]
@@ -601,7 +601,7 @@ pub extern "C" fn sleep(seconds: c_uint) -> c_uint {
         tv_nsec: 0,
     };
     let rmtp = ptr::null_mut();
-    Sys::nanosleep(&rqtp, rmtp);
+    cancellation_point(|| Sys::nanosleep(&rqtp, rmtp));
     0
 }
 
@@ -729,16 +729,27 @@ pub extern "C" fn usleep(useconds: useconds_t) -> c_int {
         tv_nsec: ((useconds % 1_000_000) * 1000) as i64,
     };
     let rmtp = ptr::null_mut();
-    Sys::nanosleep(&rqtp, rmtp)
+    cancellation_point(|| Sys::nanosleep(&rqtp, rmtp))
 }
 
-// #[no_mangle]
-pub extern "C" fn vfork() -> pid_t {
-    unimplemented!();
+/// Jumps straight to Sys::vfork, since the child returns on the stack of the
+/// parent, and a frame of this function would be popped by the child before
+/// the parent gets to return through it
+#[cfg(target_arch = "x86_64")]
+#[no_mangle]
+#[naked]
+pub unsafe extern "C" fn vfork() -> pid_t {
+    llvm_asm!("jmp ${0:P}"
+        :
+        : "s"(Sys::vfork as unsafe extern "C" fn() -> pid_t)
+        :
+        : "volatile"
+    );
+    intrinsics::unreachable()
 }
 
 #[no_mangle]
 pub extern "C" fn write(fildes: c_int, buf: *const c_void, nbyte: size_t) -> ssize_t {
     let buf = unsafe { slice::from_raw_parts(buf as *const u8, nbyte as usize) };
-    Sys::write(fildes, buf)
+    cancellation_point(|| Sys::write(fildes, buf))
 }
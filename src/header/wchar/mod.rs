@@ -1,18 +1,51 @@
 //! wchar implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/wchar.h.html
 
+use alloc::vec::Vec;
 use core::{char, ffi::VaList as va_list, mem, ptr, slice, usize};
 
 use crate::{
     header::{
-        ctype::isspace, errno::ERANGE, stdio::*, stdlib::MB_CUR_MAX, string, time::*, wctype::*,
+        ctype::isspace,
+        errno::ERANGE,
+        stdio::*,
+        stdlib::{float, MB_CUR_MAX},
+        string,
+        time::*,
+        wctype::*,
     },
     platform::{self, types::*},
 };
 
 mod utf8;
+
+/// The state of a multibyte conversion, which is all zeroes initially
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct mbstate_t;
+pub struct mbstate_t {
+    /// A UTF-16 surrogate left over by mbrtoc16 or c16rtomb
+    surrogate: c_uint,
+    /// The leading bytes of an incomplete UTF-8 sequence
+    pending: [c_uchar; 3],
+    pending_len: c_uchar,
+}
+
+impl mbstate_t {
+    pub const fn new() -> Self {
+        Self {
+            surrogate: 0,
+            pending: [0; 3],
+            pending_len: 0,
+        }
+    }
+
+    pub fn surrogate(&self) -> c_uint {
+        self.surrogate
+    }
+
+    pub fn set_surrogate(&mut self, surrogate: c_uint) {
+        self.surrogate = surrogate;
+    }
+}
 
 #[no_mangle]
 pub unsafe extern "C" fn btowc(c: c_int) -> wint_t {
@@ -23,7 +56,7 @@ pub unsafe extern "C" fn btowc(c: c_int) -> wint_t {
 
     let uc = c as u8;
     let c = uc as c_char;
-    let mut ps: mbstate_t = mbstate_t;
+    let mut ps = mbstate_t::new();
     let mut wc: wchar_t = 0;
     let saved_errno = platform::errno;
     let status = mbrtowc(&mut wc, &c as *const c_char, 1, &mut ps);
@@ -62,10 +95,13 @@ pub unsafe extern "C" fn fgetws(ws: *mut wchar_t, n: c_int, stream: *mut FILE) -
 #[no_mangle]
 pub unsafe extern "C" fn fputwc(wc: wchar_t, stream: *mut FILE) -> wint_t {
     //Convert wchar_t to multibytes first
-    static mut INTERNAL: mbstate_t = mbstate_t;
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
     let mut bytes: [c_char; MB_CUR_MAX as usize] = [0; MB_CUR_MAX as usize];
 
     let amount = wcrtomb(bytes.as_mut_ptr(), wc, &mut INTERNAL);
+    if amount == -1isize as usize {
+        return WEOF;
+    }
 
     for i in 0..amount {
         fputc(bytes[i] as c_int, &mut *stream);
@@ -106,18 +142,18 @@ pub unsafe extern "C" fn getwchar() -> wint_t {
 
 #[no_mangle]
 pub unsafe extern "C" fn mbsinit(ps: *const mbstate_t) -> c_int {
-    //Add a check for the state maybe
-    if ps.is_null() {
-        1
-    } else {
-        0
-    }
+    c_int::from(ps.is_null() || ((*ps).pending_len == 0 && (*ps).surrogate == 0))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn mbrlen(s: *const c_char, n: size_t, ps: *mut mbstate_t) -> size_t {
-    static mut INTERNAL: mbstate_t = mbstate_t;
-    mbrtowc(ptr::null_mut(), s, n, &mut INTERNAL)
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+    let ps = if ps.is_null() {
+        &mut INTERNAL as *mut _
+    } else {
+        ps
+    };
+    mbrtowc(ptr::null_mut(), s, n, ps)
 }
 
 //Only works for UTF8 at the moment
@@ -128,14 +164,16 @@ pub unsafe extern "C" fn mbrtowc(
     n: size_t,
     ps: *mut mbstate_t,
 ) -> size_t {
-    static mut INTERNAL: mbstate_t = mbstate_t;
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
 
-    if ps.is_null() {
-        let ps = &mut INTERNAL;
-    }
+    let ps = if ps.is_null() {
+        &mut INTERNAL as *mut _
+    } else {
+        ps
+    };
     if s.is_null() {
         let xs: [c_char; 1] = [0];
-        utf8::mbrtowc(pwc, &xs[0] as *const c_char, 1, ps)
+        utf8::mbrtowc(ptr::null_mut(), &xs[0] as *const c_char, 1, ps)
     } else {
         utf8::mbrtowc(pwc, s, n, ps)
     }
@@ -151,11 +189,20 @@ pub unsafe extern "C" fn mbsnrtowcs(
     dst_len: size_t,
     ps: *mut mbstate_t,
 ) -> size_t {
-    static mut INTERNAL: mbstate_t = mbstate_t;
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
 
-    if ps.is_null() {
-        let ps = &mut INTERNAL;
-    }
+    let ps = if ps.is_null() {
+        &mut INTERNAL
+    } else {
+        &mut *ps
+    };
+    // Only counting the characters mustn't change the state
+    let mut count_state = *ps;
+    let ps = if dst_ptr.is_null() {
+        &mut count_state
+    } else {
+        ps
+    };
 
     let mut src = *src_ptr;
 
@@ -163,19 +210,21 @@ pub unsafe extern "C" fn mbsnrtowcs(
     let mut src_offset: usize = 0;
 
     while (dst_ptr.is_null() || dst_offset < dst_len) && src_offset < src_len {
-        let ps_copy = *ps;
         let mut wc: wchar_t = 0;
-        let amount = mbrtowc(&mut wc, src.add(src_offset), src_len - src_offset, ps);
+        let amount = utf8::mbrtowc(&mut wc, src.add(src_offset), src_len - src_offset, ps);
 
         // Stop in the event a decoding error occured.
         if amount == -1isize as usize {
-            *src_ptr = src.add(src_offset);
-            return 1isize as usize;
+            if !dst_ptr.is_null() {
+                *src_ptr = src.add(src_offset);
+            }
+            return -1isize as usize;
         }
 
-        // Stop decoding early in the event we encountered a partial character.
+        // The rest of the input is the start of a character, which is kept in
+        // the state for the next call.
         if amount == -2isize as usize {
-            *ps = ps_copy;
+            src_offset = src_len;
             break;
         }
 
@@ -197,7 +246,9 @@ pub unsafe extern "C" fn mbsnrtowcs(
         src_offset += amount;
     }
 
-    *src_ptr = src.add(src_offset);
+    if !dst_ptr.is_null() {
+        *src_ptr = src.add(src_offset);
+    }
     dst_offset
 }
 
@@ -265,6 +316,13 @@ pub extern "C" fn vswprintf(
 //widechar to multibyte
 #[no_mangle]
 pub unsafe extern "C" fn wcrtomb(s: *mut c_char, wc: wchar_t, ps: *mut mbstate_t) -> size_t {
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL as *mut _
+    } else {
+        ps
+    };
     let mut buffer: [c_char; MB_CUR_MAX as usize] = [0; MB_CUR_MAX as usize];
     let (s_cpy, wc_cpy) = if s.is_null() {
         (buffer.as_mut_ptr(), 0)
@@ -436,14 +494,71 @@ pub unsafe extern "C" fn wcsrchr(ws1: *const wchar_t, wc: wchar_t) -> *mut wchar
     last_matching_wc as *mut wchar_t
 }
 
-// #[no_mangle]
-pub extern "C" fn wcsrtombs(
+//Convert a wide string to a multibyte string with a limited amount of characters
+//Required for in POSIX.1-2008
+#[no_mangle]
+pub unsafe extern "C" fn wcsnrtombs(
     dst: *mut c_char,
     src: *mut *const wchar_t,
+    nwc: size_t,
     len: size_t,
     ps: *mut mbstate_t,
 ) -> size_t {
-    unimplemented!();
+    static mut INTERNAL: mbstate_t = mbstate_t::new();
+
+    let ps = if ps.is_null() {
+        &mut INTERNAL as *mut _
+    } else {
+        ps
+    };
+    let wcs = *src;
+
+    let mut written = 0;
+    let mut read = 0;
+    while read < nwc {
+        let wc = *wcs.add(read);
+        let mut buffer: [c_char; MB_CUR_MAX as usize] = [0; MB_CUR_MAX as usize];
+        let amount = utf8::wcrtomb(buffer.as_mut_ptr(), wc, ps);
+        if amount == -1isize as usize {
+            if !dst.is_null() {
+                *src = wcs.add(read);
+            }
+            return -1isize as usize;
+        }
+
+        if !dst.is_null() {
+            // Characters are never split across calls
+            if written + amount > len {
+                break;
+            }
+            ptr::copy_nonoverlapping(buffer.as_ptr(), dst.add(written), amount);
+        }
+
+        if wc == 0 {
+            if !dst.is_null() {
+                *src = ptr::null();
+            }
+            return written;
+        }
+
+        written += amount;
+        read += 1;
+    }
+
+    if !dst.is_null() {
+        *src = wcs.add(read);
+    }
+    written
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wcsrtombs(
+    dst: *mut c_char,
+    src: *mut *const wchar_t,
+    len: size_t,
+    ps: *mut mbstate_t,
+) -> size_t {
+    wcsnrtombs(dst, src, size_t::max_value(), len, ps)
 }
 
 #[no_mangle]
@@ -487,46 +602,47 @@ macro_rules! skipws {
     };
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn wcstod(mut ptr: *const wchar_t, end: *mut *mut wchar_t) -> c_double {
-    const RADIX: u32 = 10;
-
+unsafe fn wcsto_float<T: float::Float>(mut ptr: *const wchar_t, end: *mut *mut wchar_t) -> T {
+    let start = ptr;
     skipws!(ptr);
-    let negative = *ptr == '-' as wchar_t;
-    if negative {
-        ptr = ptr.add(1);
-    }
 
-    let mut result: c_double = 0.0;
-    while let Some(digit) = char::from_u32(*ptr as _).and_then(|c| c.to_digit(RADIX)) {
-        result *= 10.0;
-        if negative {
-            result -= digit as c_double;
-        } else {
-            result += digit as c_double;
-        }
-        ptr = ptr.add(1);
-    }
-    if *ptr == '.' as wchar_t {
-        ptr = ptr.add(1);
-
-        let mut scale = 1.0;
-        while let Some(digit) = char::from_u32(*ptr as _).and_then(|c| c.to_digit(RADIX)) {
-            scale /= 10.0;
-            if negative {
-                result -= digit as c_double * scale;
-            } else {
-                result += digit as c_double * scale;
-            }
-            ptr = ptr.add(1);
+    // Numbers are always spelled in ASCII, so narrow every character that
+    // could be part of one and let the parser shared with strtod decide
+    let decimal_point = float::decimal_point();
+    let mut bytes = Vec::new();
+    loop {
+        let wc = *ptr.add(bytes.len());
+        if wc <= 0 || wc > 0x7F {
+            break;
+        }
+        let b = wc as u8;
+        if !b.is_ascii_alphanumeric() && !b"+-()_".contains(&b) && b != decimal_point {
+            break;
         }
+        bytes.push(b);
     }
+
+    let (result, ptr) = match float::parse::<T>(&bytes, decimal_point) {
+        Some((result, len)) => (result, ptr.add(len)),
+        // No conversion could be performed
+        None => (T::zero(), start),
+    };
     if !end.is_null() {
         *end = ptr as *mut _;
     }
     result
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn wcstod(ptr: *const wchar_t, end: *mut *mut wchar_t) -> c_double {
+    wcsto_float(ptr, end)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wcstof(ptr: *const wchar_t, end: *mut *mut wchar_t) -> c_float {
+    wcsto_float(ptr, end)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wcstok(
     mut wcs: *mut wchar_t,
@@ -637,9 +753,21 @@ pub extern "C" fn wcswcs(ws1: *const wchar_t, ws2: *const wchar_t) -> *mut wchar
     unimplemented!();
 }
 
-// #[no_mangle]
-pub extern "C" fn wcswidth(pwcs: *const wchar_t, n: size_t) -> c_int {
-    unimplemented!();
+#[no_mangle]
+pub unsafe extern "C" fn wcswidth(pwcs: *const wchar_t, n: size_t) -> c_int {
+    let mut total = 0;
+    for i in 0..n {
+        let wc = *pwcs.add(i);
+        if wc == 0 {
+            break;
+        }
+        let width = wcwidth(wc);
+        if width < 0 {
+            return -1;
+        }
+        total += width;
+    }
+    total
 }
 
 // #[no_mangle]
@@ -656,9 +784,9 @@ pub extern "C" fn wctob(c: wint_t) -> c_int {
     }
 }
 
-// #[no_mangle]
+#[no_mangle]
 pub extern "C" fn wcwidth(wc: wchar_t) -> c_int {
-    unimplemented!();
+    width(wc as wint_t)
 }
 
 #[no_mangle]
@@ -1,7 +1,7 @@
 //UTF implementation parts for wchar.h.
 //Partially ported from the Sortix libc
 
-use core::{char, slice, str, usize};
+use core::{char, slice, str};
 
 use crate::{
     header::errno,
@@ -10,38 +10,82 @@ use crate::{
 
 use super::mbstate_t;
 
-//It's guaranteed that we don't have any nullpointers here
-pub unsafe fn mbrtowc(pwc: *mut wchar_t, s: *const c_char, n: usize, ps: *mut mbstate_t) -> usize {
-    let size = str::utf8_char_width(*s as u8);
-    if size > n {
-        platform::errno = errno::EILSEQ;
-        return -2isize as usize;
-    }
-    if size == 0 {
-        platform::errno = errno::EILSEQ;
-        return -1isize as usize;
+/// The length of the sequence started by `b`, or 0 if it can't start one
+fn sequence_len(b: u8) -> usize {
+    match b {
+        0x00..=0x7F => 1,
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => 0,
     }
+}
 
-    let slice = slice::from_raw_parts(s as *const u8, size);
-    let decoded = str::from_utf8(slice);
-    if decoded.is_err() {
-        platform::errno = errno::EILSEQ;
-        return -1isize as usize;
+/// Whether `b` can follow `first` as the second byte of a sequence, which
+/// rules out overlong encodings, surrogates and values above U+10FFFF early
+fn valid_second(first: u8, b: u8) -> bool {
+    match first {
+        0xE0 => b >= 0xA0 && b <= 0xBF,
+        0xED => b >= 0x80 && b <= 0x9F,
+        0xF0 => b >= 0x90 && b <= 0xBF,
+        0xF4 => b >= 0x80 && b <= 0x8F,
+        _ => b >= 0x80 && b <= 0xBF,
     }
+}
 
-    let wc = decoded.unwrap();
+//It's guaranteed that we don't have any nullpointers here
+pub unsafe fn mbrtowc(pwc: *mut wchar_t, s: *const c_char, n: usize, ps: *mut mbstate_t) -> usize {
+    let ps = &mut *ps;
 
-    let result: wchar_t = wc.chars().next().unwrap() as wchar_t;
+    // Continue from the bytes of an incomplete character seen last time
+    let mut buf = [0; 4];
+    let mut len = ps.pending_len as usize;
+    buf[..len].copy_from_slice(&ps.pending[..len]);
 
-    if !pwc.is_null() {
-        *pwc = result;
-    }
+    let mut used = 0;
+    loop {
+        let size = if len == 0 {
+            if used == n {
+                return -2isize as usize;
+            }
+            sequence_len(*s as u8)
+        } else {
+            sequence_len(buf[0])
+        };
+        if size == 0 {
+            break;
+        }
+        if len == size {
+            let c = str::from_utf8(&buf[..size])
+                .unwrap()
+                .chars()
+                .next()
+                .unwrap();
+            *ps = mbstate_t::new();
+            if !pwc.is_null() {
+                *pwc = c as wchar_t;
+            }
+            return if c == '\0' { 0 } else { used };
+        }
+        if used == n {
+            // Save what we have for the next call
+            ps.pending[..len].copy_from_slice(&buf[..len]);
+            ps.pending_len = len as c_uchar;
+            return -2isize as usize;
+        }
 
-    if result != 0 {
-        size
-    } else {
-        0
+        let b = *s.add(used) as u8;
+        if (len == 1 && !valid_second(buf[0], b)) || (len > 1 && b & 0xC0 != 0x80) {
+            break;
+        }
+        buf[len] = b;
+        len += 1;
+        used += 1;
     }
+
+    *ps = mbstate_t::new();
+    platform::errno = errno::EILSEQ;
+    -1isize as usize
 }
 
 //It's guaranteed that we don't have any nullpointers here
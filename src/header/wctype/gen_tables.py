@@ -0,0 +1,116 @@
+#!/usr/bin/env python3
+"""Generates tables.rs, the Unicode character properties used by wctype.h and
+wcwidth.
+
+The data comes from the unicodedata module, which is pinned to the UCD version
+below so that regenerating the tables on a different Python does not silently
+change them. Run from this directory:
+
+    python3 gen_tables.py > tables.rs
+"""
+
+import sys
+import unicodedata
+
+UCD_VERSION = "14.0.0"
+
+if unicodedata.unidata_version != UCD_VERSION:
+    sys.exit(
+        "unicodedata has UCD %s, expected %s"
+        % (unicodedata.unidata_version, UCD_VERSION)
+    )
+
+# Each table is a bitmap split into blocks of 256 code points, with an index
+# of the (deduplicated) block used for every part of the code space
+BLOCK = 256
+MAX = 0x110000
+
+
+def category(c):
+    return unicodedata.category(chr(c))
+
+
+def is_alpha(c):
+    cat = category(c)
+    # Decimal digits other than 0-9 are letters as far as C is concerned,
+    # since iswdigit only accepts ASCII digits
+    return (
+        cat[0] == "L"
+        or cat in ("Nl", "Mc")
+        or (cat == "Nd" and c > 0x7F)
+    )
+
+
+def is_lower(c):
+    return chr(c).islower()
+
+
+def is_upper(c):
+    return chr(c).isupper()
+
+
+def is_print(c):
+    # Unassigned, surrogates, controls and line separators
+    return category(c) not in ("Cn", "Cs", "Cc", "Zl", "Zp")
+
+
+def is_wide(c):
+    return unicodedata.east_asian_width(chr(c)) in ("W", "F")
+
+
+PREPENDED_CONCATENATION_MARKS = set(
+    list(range(0x600, 0x606)) + [0x6DD, 0x70F, 0x890, 0x891, 0x8E2, 0x110BD, 0x110CD]
+)
+
+
+def is_zero_width(c):
+    if c == 0xAD or c in PREPENDED_CONCATENATION_MARKS:
+        # SOFT HYPHEN is traditionally displayed, and the marks that are
+        # written before a number are visible
+        return False
+    if 0x1160 <= c <= 0x11FF or c == 0x200B:
+        # Hangul medial vowels and final consonants join the previous syllable
+        return True
+    return category(c) in ("Mn", "Me", "Cf")
+
+
+def table(name, prop):
+    blocks = []
+    index = []
+    for start in range(0, MAX, BLOCK):
+        bits = [0] * (BLOCK // 8)
+        for c in range(start, start + BLOCK):
+            if prop(c):
+                bits[(c - start) // 8] |= 1 << (c % 8)
+        bits = tuple(bits)
+        if bits not in blocks:
+            blocks.append(bits)
+        index.append(blocks.index(bits))
+    assert len(blocks) <= 256
+
+    print()
+    print("#[rustfmt::skip]")
+    print("pub static %s: Table = Table {" % name)
+    print("    index: &[")
+    for i in range(0, len(index), 32):
+        print("        " + " ".join("%d," % i for i in index[i : i + 32]))
+    print("    ],")
+    print("    blocks: &[")
+    for bits in blocks:
+        print("        [")
+        for i in range(0, len(bits), 16):
+            print("            " + " ".join("0x%02x," % b for b in bits[i : i + 16]))
+        print("        ],")
+    print("    ],")
+    print("};")
+
+
+print("//! Unicode %s character properties, generated by gen_tables.py" % UCD_VERSION)
+print()
+print("use super::Table;")
+table("ALPHA", is_alpha)
+table("LOWER", is_lower)
+table("PRINT", is_print)
+table("UPPER", is_upper)
+table("WIDE", is_wide)
+table("ZERO_WIDTH", is_zero_width)
@@ -1,11 +1,163 @@
-//! wchar implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/wchar.h.html
+//! wctype implementation for Redox, following http://pubs.opengroup.org/onlinepubs/7908799/xsh/wctype.h.html
 
-use crate::platform::types::*;
+use crate::{c_str::CStr, header::locale::locale_t, platform::types::*};
 
 mod casecmp;
+mod tables;
 use casecmp::casemap;
 pub const WEOF: wint_t = 0xFFFF_FFFFu32;
 
+pub type wctrans_t = *const i32;
+
+const WCTYPE_ALNUM: wctype_t = 1;
+const WCTYPE_ALPHA: wctype_t = 2;
+const WCTYPE_BLANK: wctype_t = 3;
+const WCTYPE_CNTRL: wctype_t = 4;
+const WCTYPE_DIGIT: wctype_t = 5;
+const WCTYPE_GRAPH: wctype_t = 6;
+const WCTYPE_LOWER: wctype_t = 7;
+const WCTYPE_PRINT: wctype_t = 8;
+const WCTYPE_PUNCT: wctype_t = 9;
+const WCTYPE_SPACE: wctype_t = 10;
+const WCTYPE_UPPER: wctype_t = 11;
+const WCTYPE_XDIGIT: wctype_t = 12;
+
+const WCTRANS_TOLOWER: wctrans_t = 1 as wctrans_t;
+const WCTRANS_TOUPPER: wctrans_t = 2 as wctrans_t;
+
+/// A set of characters stored as bitmaps of 256 characters each, many of
+/// which are shared
+pub struct Table {
+    index: &'static [u8],
+    blocks: &'static [[u8; 32]],
+}
+
+impl Table {
+    pub fn contains(&self, wc: wint_t) -> bool {
+        let block = match self.index.get(wc as usize / 256) {
+            Some(&block) => &self.blocks[block as usize],
+            None => return false,
+        };
+        let bit = wc as usize % 256;
+        block[bit / 8] & (1 << (bit % 8)) != 0
+    }
+}
+
+/// The display width of a character: -1 if it is not printable, 0 if it
+/// combines with the previous character and 2 if it is wide
+pub fn width(wc: wint_t) -> c_int {
+    if wc == 0 {
+        0
+    } else if !tables::PRINT.contains(wc) {
+        -1
+    } else if tables::ZERO_WIDTH.contains(wc) {
+        0
+    } else if tables::WIDE.contains(wc) {
+        2
+    } else {
+        1
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn iswalnum(wc: wint_t) -> c_int {
+    c_int::from(iswdigit(wc) != 0 || iswalpha(wc) != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn iswalpha(wc: wint_t) -> c_int {
+    c_int::from(tables::ALPHA.contains(wc))
+}
+
+#[no_mangle]
+pub extern "C" fn iswblank(wc: wint_t) -> c_int {
+    c_int::from(wc == '\t' as wint_t || (iswspace(wc) != 0 && iswcntrl(wc) == 0))
+}
+
+#[no_mangle]
+pub extern "C" fn iswcntrl(wc: wint_t) -> c_int {
+    c_int::from(wc < 0x20 || (wc >= 0x7F && wc < 0xA0) || wc == 0x2028 || wc == 0x2029)
+}
+
+#[no_mangle]
+pub extern "C" fn iswctype(wc: wint_t, desc: wctype_t) -> c_int {
+    match desc {
+        WCTYPE_ALNUM => iswalnum(wc),
+        WCTYPE_ALPHA => iswalpha(wc),
+        WCTYPE_BLANK => iswblank(wc),
+        WCTYPE_CNTRL => iswcntrl(wc),
+        WCTYPE_DIGIT => iswdigit(wc),
+        WCTYPE_GRAPH => iswgraph(wc),
+        WCTYPE_LOWER => iswlower(wc),
+        WCTYPE_PRINT => iswprint(wc),
+        WCTYPE_PUNCT => iswpunct(wc),
+        WCTYPE_SPACE => iswspace(wc),
+        WCTYPE_UPPER => iswupper(wc),
+        WCTYPE_XDIGIT => iswxdigit(wc),
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn iswdigit(wc: wint_t) -> c_int {
+    c_int::from(wc >= '0' as wint_t && wc <= '9' as wint_t)
+}
+
+#[no_mangle]
+pub extern "C" fn iswgraph(wc: wint_t) -> c_int {
+    c_int::from(iswprint(wc) != 0 && iswspace(wc) == 0)
+}
+
+#[no_mangle]
+pub extern "C" fn iswlower(wc: wint_t) -> c_int {
+    c_int::from(tables::LOWER.contains(wc))
+}
+
+#[no_mangle]
+pub extern "C" fn iswprint(wc: wint_t) -> c_int {
+    c_int::from(tables::PRINT.contains(wc))
+}
+
+#[no_mangle]
+pub extern "C" fn iswpunct(wc: wint_t) -> c_int {
+    c_int::from(iswgraph(wc) != 0 && iswalnum(wc) == 0)
+}
+
+#[no_mangle]
+pub extern "C" fn iswspace(wc: wint_t) -> c_int {
+    // White_Space, except for the no-break spaces
+    c_int::from(match wc {
+        0x09..=0x0D | 0x20 | 0x1680 | 0x2000..=0x2006 | 0x2008..=0x200A => true,
+        0x2028 | 0x2029 | 0x205F | 0x3000 => true,
+        _ => false,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn iswupper(wc: wint_t) -> c_int {
+    c_int::from(tables::UPPER.contains(wc))
+}
+
+#[no_mangle]
+pub extern "C" fn iswxdigit(wc: wint_t) -> c_int {
+    c_int::from(
+        iswdigit(wc) != 0
+            || (wc >= 'a' as wint_t && wc <= 'f' as wint_t)
+            || (wc >= 'A' as wint_t && wc <= 'F' as wint_t),
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn towctrans(wc: wint_t, desc: wctrans_t) -> wint_t {
+    if desc == WCTRANS_TOLOWER {
+        towlower(wc)
+    } else if desc == WCTRANS_TOUPPER {
+        towupper(wc)
+    } else {
+        wc
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn towlower(wc: wint_t) -> wint_t {
     casemap(wc, 0)
@@ -15,3 +167,121 @@ pub extern "C" fn towlower(wc: wint_t) -> wint_t {
 pub extern "C" fn towupper(wc: wint_t) -> wint_t {
     casemap(wc, 1)
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn wctrans(property: *const c_char) -> wctrans_t {
+    match CStr::from_ptr(property).to_bytes() {
+        b"tolower" => WCTRANS_TOLOWER,
+        b"toupper" => WCTRANS_TOUPPER,
+        _ => 0 as wctrans_t,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wctype(property: *const c_char) -> wctype_t {
+    match CStr::from_ptr(property).to_bytes() {
+        b"alnum" => WCTYPE_ALNUM,
+        b"alpha" => WCTYPE_ALPHA,
+        b"blank" => WCTYPE_BLANK,
+        b"cntrl" => WCTYPE_CNTRL,
+        b"digit" => WCTYPE_DIGIT,
+        b"graph" => WCTYPE_GRAPH,
+        b"lower" => WCTYPE_LOWER,
+        b"print" => WCTYPE_PRINT,
+        b"punct" => WCTYPE_PUNCT,
+        b"space" => WCTYPE_SPACE,
+        b"upper" => WCTYPE_UPPER,
+        b"xdigit" => WCTYPE_XDIGIT,
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn iswalnum_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswalnum(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswalpha_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswalpha(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswblank_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswblank(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswcntrl_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswcntrl(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswctype_l(wc: wint_t, desc: wctype_t, _loc: locale_t) -> c_int {
+    iswctype(wc, desc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswdigit_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswdigit(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswgraph_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswgraph(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswlower_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswlower(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswprint_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswprint(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswpunct_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswpunct(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswspace_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswspace(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswupper_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswupper(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn iswxdigit_l(wc: wint_t, _loc: locale_t) -> c_int {
+    iswxdigit(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn towctrans_l(wc: wint_t, desc: wctrans_t, _loc: locale_t) -> wint_t {
+    towctrans(wc, desc)
+}
+
+#[no_mangle]
+pub extern "C" fn towlower_l(wc: wint_t, _loc: locale_t) -> wint_t {
+    towlower(wc)
+}
+
+#[no_mangle]
+pub extern "C" fn towupper_l(wc: wint_t, _loc: locale_t) -> wint_t {
+    towupper(wc)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wctrans_l(property: *const c_char, _loc: locale_t) -> wctrans_t {
+    wctrans(property)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wctype_l(property: *const c_char, _loc: locale_t) -> wctype_t {
+    wctype(property)
+}
@@ -0,0 +1,602 @@
+//! wordexp implementation, following https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/wordexp.h.html
+
+use alloc::{string::ToString, vec::Vec};
+use core::{mem, ptr, slice};
+
+use crate::{
+    header::{
+        fnmatch::fnmatch,
+        glob::{glob, glob_t, globfree},
+        pwd::{getpwnam, getpwuid},
+        stdio::{fread, pclose, popen},
+        stdlib::{getenv, setenv},
+        string::strlen,
+        unistd::{getpid, getuid},
+    },
+    platform::{self, types::*},
+};
+
+pub const WRDE_DOOFFS: c_int = 1 << 0;
+pub const WRDE_APPEND: c_int = 1 << 1;
+pub const WRDE_NOCMD: c_int = 1 << 2;
+pub const WRDE_REUSE: c_int = 1 << 3;
+pub const WRDE_SHOWERR: c_int = 1 << 4;
+pub const WRDE_UNDEF: c_int = 1 << 5;
+
+pub const WRDE_NOSPACE: c_int = 1;
+pub const WRDE_BADCHAR: c_int = 2;
+pub const WRDE_BADVAL: c_int = 3;
+pub const WRDE_CMDSUB: c_int = 4;
+pub const WRDE_SYNTAX: c_int = 5;
+
+#[repr(C)]
+pub struct wordexp_t {
+    pub we_wordc: size_t,
+    pub we_wordv: *mut *mut c_char,
+    pub we_offs: size_t,
+}
+
+fn with_nul(s: &[u8]) -> Vec<u8> {
+    let mut s = s.to_vec();
+    s.push(0);
+    s
+}
+
+fn is_name(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Returns the index of the `"` that closes a double-quoted string starting
+/// at `i`
+fn dquote_end(word: &[u8], mut i: usize) -> Option<usize> {
+    while i < word.len() {
+        match word[i] {
+            b'\\' => i += 1,
+            b'"' => return Some(i),
+            b'`' => i = backquote_end(word, i + 1)?,
+            b'$' if word.get(i + 1) == Some(&b'(') => i = matching(word, i + 2, b'(', b')')?,
+            b'$' if word.get(i + 1) == Some(&b'{') => i = matching(word, i + 2, b'{', b'}')?,
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the index of the backquote that ends a command starting at `i`
+fn backquote_end(word: &[u8], mut i: usize) -> Option<usize> {
+    while i < word.len() {
+        match word[i] {
+            b'\\' => i += 1,
+            b'`' => return Some(i),
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the index of the `close` that ends a `${` or `$(` whose contents
+/// start at `i`, skipping over quotes and nested pairs
+fn matching(word: &[u8], mut i: usize, open: u8, close: u8) -> Option<usize> {
+    let mut depth = 0;
+    while i < word.len() {
+        match word[i] {
+            b'\\' => i += 1,
+            b'\'' => i += word[i + 1..].iter().position(|&c| c == b'\'')? + 1,
+            b'"' => i = dquote_end(word, i + 1)?,
+            b'`' => i = backquote_end(word, i + 1)?,
+            c if c == close && depth == 0 => return Some(i),
+            c if c == close => depth -= 1,
+            c if c == open => depth += 1,
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+#[derive(Default)]
+struct Field {
+    /// The field after quote removal
+    literal: Vec<u8>,
+    /// The field as a glob pattern, with quoted wildcards escaped
+    pattern: Vec<u8>,
+    /// Whether the field has unquoted wildcards
+    magic: bool,
+    /// Whether the field is kept even when empty, because it was quoted
+    exists: bool,
+}
+
+struct WordExp {
+    flags: c_int,
+    ifs: Vec<u8>,
+    /// Whether this expands a single string, like the value of `${VAR=word}`,
+    /// without field splitting or pathname expansion
+    nested: bool,
+    field: Field,
+    words: Vec<Vec<u8>>,
+}
+
+impl WordExp {
+    fn new(flags: c_int, ifs: Vec<u8>, nested: bool) -> Self {
+        Self {
+            flags,
+            ifs,
+            nested,
+            field: Field::default(),
+            words: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, c: u8, quoted: bool) {
+        let field = &mut self.field;
+        field.exists = true;
+        field.literal.push(c);
+        // A backslash can only come from quotes or an expansion, and is
+        // never an escape in the pattern
+        let wildcard = c == b'*' || c == b'?' || c == b'[';
+        if c == b'\\' || (quoted && wildcard) {
+            field.pattern.push(b'\\');
+        } else if wildcard {
+            field.magic = true;
+        }
+        field.pattern.push(c);
+    }
+
+    /// Ends the current field, expanding it as a pathname pattern
+    unsafe fn split(&mut self) {
+        let field = mem::take(&mut self.field);
+        if !field.exists {
+            return;
+        }
+        if field.magic {
+            let mut pglob: glob_t = mem::zeroed();
+            let pattern = with_nul(&field.pattern);
+            if glob(pattern.as_ptr() as *const c_char, 0, None, &mut pglob) == 0 {
+                for i in 0..pglob.gl_pathc {
+                    let path = *pglob.gl_pathv.add(i);
+                    self.words
+                        .push(slice::from_raw_parts(path as *const u8, strlen(path)).to_vec());
+                }
+                globfree(&mut pglob);
+                return;
+            }
+        }
+        self.words.push(field.literal);
+    }
+
+    /// Adds the result of an expansion, splitting it into fields on IFS
+    /// unless it was quoted
+    unsafe fn expansion(&mut self, value: &[u8], quoted: bool) {
+        if quoted || self.nested {
+            self.field.exists |= quoted;
+            for &c in value {
+                self.push(c, true);
+            }
+            return;
+        }
+
+        let mut after_space = false;
+        for &c in value {
+            if !self.ifs.contains(&c) {
+                self.push(c, false);
+                after_space = false;
+            } else if c == b' ' || c == b'\t' || c == b'\n' {
+                self.split();
+                after_space = true;
+            } else {
+                // Other IFS characters delimit a field even if it is empty
+                if !after_space {
+                    self.field.exists = true;
+                }
+                self.split();
+                after_space = false;
+            }
+        }
+    }
+
+    /// Expands a word on its own, for the value of an assignment or a
+    /// pattern
+    unsafe fn string(&self, word: &[u8], dquote: bool) -> Result<Field, c_int> {
+        let mut nested = WordExp::new(self.flags, self.ifs.clone(), true);
+        nested.parse(word, dquote)?;
+        Ok(nested.field)
+    }
+
+    unsafe fn variable(&self, name: &[u8]) -> Option<Vec<u8>> {
+        if name == b"$" {
+            return Some(getpid().to_string().into_bytes());
+        }
+        if name == b"#" {
+            return Some(b"0".to_vec());
+        }
+        if name.iter().all(u8::is_ascii_digit) {
+            // There are no positional parameters
+            return None;
+        }
+        let value = getenv(with_nul(name).as_ptr() as *const c_char);
+        if value.is_null() {
+            None
+        } else {
+            Some(slice::from_raw_parts(value as *const u8, strlen(value)).to_vec())
+        }
+    }
+
+    /// Expands the contents of `${...}`
+    unsafe fn parameter(&mut self, expr: &[u8], dquote: bool) -> Result<(), c_int> {
+        if expr.len() > 1 && expr[0] == b'#' {
+            let value = match self.variable(&expr[1..]) {
+                Some(value) => value,
+                None if self.flags & WRDE_UNDEF == WRDE_UNDEF => return Err(WRDE_BADVAL),
+                None => Vec::new(),
+            };
+            self.expansion(value.len().to_string().as_bytes(), dquote);
+            return Ok(());
+        }
+
+        let len = match expr.first() {
+            Some(&c) if is_name(c) => expr.iter().take_while(|&&c| is_name(c)).count(),
+            Some(b'$') | Some(b'#') => 1,
+            _ => return Err(WRDE_SYNTAX),
+        };
+        let (name, rest) = expr.split_at(len);
+        let value = self.variable(name);
+
+        let (colon, rest) = match rest.first() {
+            Some(b':') => (true, &rest[1..]),
+            _ => (false, rest),
+        };
+        // With a colon, an empty value counts as unset
+        let set = match value {
+            Some(ref value) => !colon || !value.is_empty(),
+            None => false,
+        };
+
+        let word = rest.get(1..).unwrap_or(&[]);
+        match rest.first() {
+            None if !colon => match value {
+                Some(value) => self.expansion(&value, dquote),
+                None if self.flags & WRDE_UNDEF == WRDE_UNDEF => return Err(WRDE_BADVAL),
+                None => (),
+            },
+            Some(b'-') => {
+                if set {
+                    self.expansion(&value.unwrap(), dquote);
+                } else {
+                    self.parse(word, dquote)?;
+                }
+            }
+            Some(b'=') => {
+                if set {
+                    self.expansion(&value.unwrap(), dquote);
+                } else {
+                    let value = self.string(word, false)?.literal;
+                    setenv(
+                        with_nul(name).as_ptr() as *const c_char,
+                        with_nul(&value).as_ptr() as *const c_char,
+                        1,
+                    );
+                    self.expansion(&value, dquote);
+                }
+            }
+            Some(b'+') => {
+                if set {
+                    self.parse(word, dquote)?;
+                } else {
+                    self.field.exists |= dquote;
+                }
+            }
+            Some(b'?') => {
+                if !set {
+                    if self.flags & WRDE_SHOWERR == WRDE_SHOWERR {
+                        let message = self.string(word, false)?.literal;
+                        eprintln!(
+                            "{}: {}",
+                            core::str::from_utf8(name).unwrap_or("?"),
+                            if message.is_empty() {
+                                "parameter null or not set"
+                            } else {
+                                core::str::from_utf8(&message).unwrap_or("?")
+                            }
+                        );
+                    }
+                    return Err(WRDE_BADVAL);
+                }
+                self.expansion(&value.unwrap(), dquote);
+            }
+            Some(&op) if (op == b'%' || op == b'#') && !colon => {
+                let suffix = op == b'%';
+                let (longest, word) = match word.first() {
+                    Some(&c) if c == op => (true, &word[1..]),
+                    _ => (false, word),
+                };
+                let value = match value {
+                    Some(value) => value,
+                    None if self.flags & WRDE_UNDEF == WRDE_UNDEF => return Err(WRDE_BADVAL),
+                    None => Vec::new(),
+                };
+                let pattern = with_nul(&self.string(word, false)?.pattern);
+
+                // Try the shortest or the longest part of the value first
+                let mut cuts: Vec<usize> = (0..=value.len()).collect();
+                if suffix != longest {
+                    cuts.reverse();
+                }
+                let mut result = &value[..];
+                for cut in cuts {
+                    let part = if suffix { &value[cut..] } else { &value[..cut] };
+                    let part = with_nul(part);
+                    if fnmatch(
+                        pattern.as_ptr() as *const c_char,
+                        part.as_ptr() as *const c_char,
+                        0,
+                    ) == 0
+                    {
+                        result = if suffix { &value[..cut] } else { &value[cut..] };
+                        break;
+                    }
+                }
+                self.expansion(result, dquote);
+            }
+            _ => return Err(WRDE_SYNTAX),
+        }
+        Ok(())
+    }
+
+    /// Runs a command and expands to its output
+    unsafe fn command(&mut self, command: &[u8], dquote: bool) -> Result<(), c_int> {
+        if self.flags & WRDE_NOCMD == WRDE_NOCMD {
+            return Err(WRDE_CMDSUB);
+        }
+
+        let mut script = Vec::new();
+        if self.flags & WRDE_SHOWERR != WRDE_SHOWERR {
+            script.extend_from_slice(b"exec 2>/dev/null\n");
+        }
+        script.extend_from_slice(command);
+        script.push(0);
+
+        let stream = popen(
+            script.as_ptr() as *const c_char,
+            b"r\0".as_ptr() as *const c_char,
+        );
+        if stream.is_null() {
+            return Err(WRDE_NOSPACE);
+        }
+        let mut output = Vec::new();
+        let mut buf = [0u8; 256];
+        loop {
+            let read = fread(buf.as_mut_ptr() as *mut c_void, 1, buf.len(), stream);
+            if read == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..read]);
+        }
+        pclose(stream);
+
+        while output.last() == Some(&b'\n') {
+            output.pop();
+        }
+        self.expansion(&output, dquote);
+        Ok(())
+    }
+
+    /// Expands a `$` at `i`, returning the index after the expansion
+    unsafe fn dollar(&mut self, word: &[u8], i: usize, dquote: bool) -> Result<usize, c_int> {
+        match word.get(i + 1) {
+            Some(b'(') => {
+                let end = matching(word, i + 2, b'(', b')').ok_or(WRDE_SYNTAX)?;
+                self.command(&word[i + 2..end], dquote)?;
+                Ok(end + 1)
+            }
+            Some(b'{') => {
+                let end = matching(word, i + 2, b'{', b'}').ok_or(WRDE_SYNTAX)?;
+                self.parameter(&word[i + 2..end], dquote)?;
+                Ok(end + 1)
+            }
+            Some(&c) if is_name(c) && !c.is_ascii_digit() => {
+                let len = word[i + 1..].iter().take_while(|&&c| is_name(c)).count();
+                self.parameter(&word[i + 1..i + 1 + len], dquote)?;
+                Ok(i + 1 + len)
+            }
+            Some(&c) if c.is_ascii_digit() || c == b'$' || c == b'#' => {
+                self.parameter(&word[i + 1..i + 2], dquote)?;
+                Ok(i + 2)
+            }
+            _ => {
+                self.push(b'$', dquote);
+                Ok(i + 1)
+            }
+        }
+    }
+
+    /// Expands `~` or `~user` at the start of a word, returning the index
+    /// after it
+    unsafe fn tilde(&mut self, word: &[u8], i: usize) -> usize {
+        let len = word[i + 1..]
+            .iter()
+            .take_while(|&&c| c != b'/' && c != b' ' && c != b'\t')
+            .count();
+        let name = &word[i + 1..i + 1 + len];
+        if name.iter().any(|&c| !is_name(c) && c != b'.' && c != b'-') {
+            self.push(b'~', false);
+            return i + 1;
+        }
+
+        let mut home = ptr::null();
+        if name.is_empty() {
+            home = getenv(b"HOME\0".as_ptr() as *const c_char);
+            if home.is_null() {
+                let pwd = getpwuid(getuid());
+                if !pwd.is_null() {
+                    home = (*pwd).pw_dir;
+                }
+            }
+        } else {
+            let pwd = getpwnam(with_nul(name).as_ptr() as *const c_char);
+            if !pwd.is_null() {
+                home = (*pwd).pw_dir;
+            }
+        }
+
+        if home.is_null() {
+            self.push(b'~', false);
+            return i + 1;
+        }
+        for &c in slice::from_raw_parts(home as *const u8, strlen(home)) {
+            self.push(c, true);
+        }
+        i + 1 + len
+    }
+
+    unsafe fn parse(&mut self, word: &[u8], dquote: bool) -> Result<(), c_int> {
+        let mut i = 0;
+        while i < word.len() {
+            let c = word[i];
+            match c {
+                b'\\' => match word.get(i + 1) {
+                    // Inside double quotes, only some characters are escaped
+                    Some(&c) if dquote && !b"$`\"\\\n".contains(&c) => {
+                        self.push(b'\\', true);
+                        i += 1;
+                    }
+                    Some(b'\n') => i += 2,
+                    Some(&c) => {
+                        self.push(c, true);
+                        i += 2;
+                    }
+                    None => {
+                        self.push(b'\\', true);
+                        i += 1;
+                    }
+                },
+                b'\'' if !dquote => {
+                    let len = word[i + 1..]
+                        .iter()
+                        .position(|&c| c == b'\'')
+                        .ok_or(WRDE_SYNTAX)?;
+                    self.field.exists = true;
+                    for &c in &word[i + 1..i + 1 + len] {
+                        self.push(c, true);
+                    }
+                    i += len + 2;
+                }
+                b'"' if !dquote => {
+                    let end = dquote_end(word, i + 1).ok_or(WRDE_SYNTAX)?;
+                    self.field.exists = true;
+                    self.parse(&word[i + 1..end], true)?;
+                    i = end + 1;
+                }
+                b'$' => i = self.dollar(word, i, dquote)?,
+                b'`' => {
+                    let end = backquote_end(word, i + 1).ok_or(WRDE_SYNTAX)?;
+                    let mut command = Vec::new();
+                    let mut j = i + 1;
+                    while j < end {
+                        if word[j] == b'\\' && b"$`\\".contains(&word[j + 1]) {
+                            j += 1;
+                        }
+                        command.push(word[j]);
+                        j += 1;
+                    }
+                    self.command(&command, dquote)?;
+                    i = end + 1;
+                }
+                b'~' if !dquote && !self.field.exists => i = self.tilde(word, i),
+                b' ' | b'\t' if !dquote && !self.nested => {
+                    self.split();
+                    i += 1;
+                }
+                b'\n' | b'|' | b'&' | b';' | b'<' | b'>' | b'(' | b')' | b'{' | b'}' if !dquote => {
+                    return Err(WRDE_BADCHAR);
+                }
+                _ => {
+                    self.push(c, dquote);
+                    i += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wordexp(
+    words: *const c_char,
+    pwordexp: *mut wordexp_t,
+    flags: c_int,
+) -> c_int {
+    let pwordexp = &mut *pwordexp;
+    if flags & WRDE_REUSE == WRDE_REUSE && flags & WRDE_APPEND != WRDE_APPEND {
+        wordfree(pwordexp);
+    }
+    if flags & WRDE_APPEND != WRDE_APPEND {
+        pwordexp.we_wordc = 0;
+        pwordexp.we_wordv = ptr::null_mut();
+        if flags & WRDE_DOOFFS != WRDE_DOOFFS {
+            pwordexp.we_offs = 0;
+        }
+    }
+
+    let ifs = getenv(b"IFS\0".as_ptr() as *const c_char);
+    let ifs = if ifs.is_null() {
+        b" \t\n".to_vec()
+    } else {
+        slice::from_raw_parts(ifs as *const u8, strlen(ifs)).to_vec()
+    };
+
+    let mut state = WordExp::new(flags, ifs, false);
+    let words = slice::from_raw_parts(words as *const u8, strlen(words));
+    if let Err(err) = state.parse(words, false) {
+        return err;
+    }
+    state.split();
+
+    // Copy the new words onto the end of we_wordv
+    let offs = pwordexp.we_offs;
+    let old = pwordexp.we_wordc;
+    let len = offs + old + state.words.len() + 1;
+    let wordv = platform::realloc(
+        pwordexp.we_wordv as *mut c_void,
+        len * mem::size_of::<*mut c_char>(),
+    ) as *mut *mut c_char;
+    if wordv.is_null() {
+        return WRDE_NOSPACE;
+    }
+    if pwordexp.we_wordv.is_null() {
+        for i in 0..offs {
+            *wordv.add(i) = ptr::null_mut();
+        }
+    }
+    pwordexp.we_wordv = wordv;
+
+    for word in &state.words {
+        let copy = platform::alloc(word.len() + 1) as *mut c_char;
+        if copy.is_null() {
+            *wordv.add(offs + pwordexp.we_wordc) = ptr::null_mut();
+            return WRDE_NOSPACE;
+        }
+        ptr::copy_nonoverlapping(word.as_ptr() as *const c_char, copy, word.len());
+        *copy.add(word.len()) = 0;
+        *wordv.add(offs + pwordexp.we_wordc) = copy;
+        pwordexp.we_wordc += 1;
+    }
+    *wordv.add(offs + pwordexp.we_wordc) = ptr::null_mut();
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wordfree(pwordexp: *mut wordexp_t) {
+    let pwordexp = &mut *pwordexp;
+    if pwordexp.we_wordv.is_null() {
+        return;
+    }
+    for i in 0..pwordexp.we_wordc {
+        platform::free(*pwordexp.we_wordv.add(pwordexp.we_offs + i) as *mut c_void);
+    }
+    platform::free(pwordexp.we_wordv as *mut c_void);
+
+    pwordexp.we_wordc = 0;
+    pwordexp.we_wordv = ptr::null_mut();
+}
@@ -5,8 +5,8 @@ use alloc::{borrow::ToOwned, boxed::Box, collections::BTreeMap, string::String,
 use crate::{
     c_str::CStr,
     header::{unistd, sys_auxv::AT_NULL},
-    platform::{new_mspace, types::c_char},
-    start::Stack,
+    platform::{self, new_mspace, types::c_char},
+    start::{init_auxv, Stack},
     sync::mutex::Mutex,
     ALLOCATOR
 };
@@ -121,7 +121,9 @@ unsafe fn adjust_stack(sp: &'static mut Stack) {
 }
 #[no_mangle]
 pub extern "C" fn relibc_ld_so_start(sp: &'static mut Stack, ld_entry: usize) -> usize {
-    // First thing we initialize the mspace
+    // First thing we keep the auxiliary vector, which seeds what has to be
+    // seeded before anything is allocated, and initialize the mspace
+    unsafe { init_auxv(sp.auxv()) };
     ALLOCATOR.set_book_keeper(new_mspace());
     // next we get the arguments, the environment, and the auxilary vector
     let (argv, envs, auxv) = unsafe {
@@ -160,11 +162,16 @@ pub extern "C" fn relibc_ld_so_start(sp: &'static mut Stack, ld_entry: usize) ->
             unistd::_exit(1);
             loop {}
         }
-        unsafe { adjust_stack(sp) };
+        unsafe {
+            adjust_stack(sp);
+            // The auxiliary vector moved with the stack
+            platform::auxv = sp.auxv();
+        }
         &argv[1]
     } else {
         &argv[0]
     };
+
     // if we are not running in manual mode, then the main program is already
     // loaded by the linux kernel and we want to use it. on redox, we treat it
     // the same.
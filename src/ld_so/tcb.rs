@@ -2,7 +2,7 @@ use alloc::boxed::Box;
 use core::{mem, ops::Range, ptr, slice};
 use goblin::error::{Error, Result};
 
-use crate::{header::sys_mman, ld_so::linker::Linker, sync::mutex::Mutex};
+use crate::{header::sys_mman, ld_so::linker::Linker, platform::types::c_void, sync::mutex::Mutex};
 
 use super::PAGE_SIZE;
 
@@ -147,6 +147,23 @@ impl Tcb {
         Self::os_arch_activate(self.tcb_ptr as usize);
     }
 
+    /// Unmap the TLS and TCB created by `new`, after which thread local variables of the
+    /// current thread are invalid - Linux
+    #[cfg(target_os = "linux")]
+    pub unsafe fn unmap(&mut self) {
+        // The TCB page directly follows the TLS
+        let tls = self.tls_end.offset(-(self.tls_len as isize));
+        sys_mman::munmap(tls as *mut c_void, self.tls_len + self.tcb_len);
+    }
+
+    /// Unmap the TLS created by `new` - Redox
+    #[cfg(target_os = "redox")]
+    pub unsafe fn unmap(&mut self) {
+        // The TCB is at a fixed address per context, only the TLS was mapped
+        let tls = self.tls_end.offset(-(self.tls_len as isize));
+        sys_mman::munmap(tls as *mut c_void, self.tls_len);
+    }
+
     /// Mapping with correct flags for TCB and TLS
     unsafe fn map(size: usize) -> Result<&'static mut [u8]> {
         let ptr = sys_mman::mmap(
@@ -14,6 +14,7 @@
 #![feature(linkage)]
 #![feature(llvm_asm)]
 #![feature(maybe_uninit_extra)]
+#![feature(naked_functions)]
 #![feature(stmt_expr_attributes)]
 #![feature(str_internals)]
 #![feature(thread_local)]
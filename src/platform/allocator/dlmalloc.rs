@@ -1,4 +1,7 @@
-use crate::ALLOCATOR;
+use crate::{
+    header::malloc::{mallinfo2, M_ARENA_MAX, M_ARENA_TEST, M_MMAP_THRESHOLD, M_TRIM_THRESHOLD},
+    ALLOCATOR,
+};
 use core::{
     alloc::{GlobalAlloc, Layout},
     sync::atomic::{AtomicUsize, Ordering},
@@ -6,12 +9,30 @@ use core::{
 
 use super::types::*;
 
+#[cfg(feature = "hardened_malloc")]
+#[path = "hardened.rs"]
+mod hardened;
+
+#[cfg(feature = "hardened_malloc")]
+pub use self::hardened::{alloc, alloc_align, free, init_secret, realloc, usable_size};
+
 extern "C" {
     fn create_mspace(capacity: size_t, locked: c_int) -> usize;
     fn mspace_malloc(msp: usize, bytes: size_t) -> *mut c_void;
     fn mspace_memalign(msp: usize, alignment: size_t, bytes: size_t) -> *mut c_void;
+    #[cfg(not(feature = "hardened_malloc"))]
     fn mspace_realloc(msp: usize, oldmem: *mut c_void, bytes: size_t) -> *mut c_void;
     fn mspace_free(msp: usize, mem: *mut c_void);
+    #[cfg(not(feature = "hardened_malloc"))]
+    fn mspace_usable_size(mem: *const c_void) -> size_t;
+    // The struct mallinfo of dlmalloc has size_t fields, like mallinfo2
+    fn mspace_mallinfo(msp: usize) -> mallinfo2;
+    fn mspace_trim(msp: usize, pad: size_t) -> c_int;
+    fn mspace_mallopt(param_number: c_int, value: c_int) -> c_int;
+    #[cfg(feature = "hardened_malloc")]
+    fn mspace_track_large_chunks(msp: usize, enable: c_int) -> c_int;
+    #[cfg(feature = "hardened_malloc")]
+    fn mspace_contains(msp: usize, mem: *const c_void) -> c_int;
 //fn dlmalloc(bytes: size_t) -> *mut c_void;
 //fn dlmemalign(alignment: size_t, bytes: size_t) -> *mut c_void;
 //fn dlrealloc(oldmem: *mut c_void, bytes: size_t) -> *mut c_void;
@@ -44,22 +65,62 @@ unsafe impl<'a> GlobalAlloc for Allocator {
     }
 }
 
+#[cfg(not(feature = "hardened_malloc"))]
 pub unsafe fn alloc(size: usize) -> *mut c_void {
     mspace_malloc(ALLOCATOR.get_book_keeper(), size)
 }
 
+#[cfg(not(feature = "hardened_malloc"))]
 pub unsafe fn alloc_align(size: usize, alignment: usize) -> *mut c_void {
     mspace_memalign(ALLOCATOR.get_book_keeper(), alignment, size)
 }
 
+#[cfg(not(feature = "hardened_malloc"))]
 pub unsafe fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void {
     mspace_realloc(ALLOCATOR.get_book_keeper(), ptr, size)
 }
 
+#[cfg(not(feature = "hardened_malloc"))]
 pub unsafe fn free(ptr: *mut c_void) {
     mspace_free(ALLOCATOR.get_book_keeper(), ptr)
 }
 
+#[cfg(not(feature = "hardened_malloc"))]
+pub unsafe fn usable_size(ptr: *mut c_void) -> size_t {
+    mspace_usable_size(ptr)
+}
+
+pub unsafe fn heap_info() -> mallinfo2 {
+    mspace_mallinfo(ALLOCATOR.get_book_keeper())
+}
+
+pub unsafe fn heap_trim(pad: size_t) -> c_int {
+    #[cfg(feature = "hardened_malloc")]
+    hardened::flush_quarantine();
+    mspace_trim(ALLOCATOR.get_book_keeper(), pad)
+}
+
+pub unsafe fn set_heap_param(param: c_int, value: c_int) -> c_int {
+    match param {
+        // dlmalloc numbers these two the same way
+        M_TRIM_THRESHOLD | M_MMAP_THRESHOLD => mspace_mallopt(param, value),
+        // There is only ever one arena
+        M_ARENA_TEST | M_ARENA_MAX => 1,
+        #[cfg(feature = "hardened_malloc")]
+        _ => hardened::set_param(param, value),
+        #[cfg(not(feature = "hardened_malloc"))]
+        _ => 0,
+    }
+}
+
 pub fn new_mspace() -> usize {
-    unsafe { create_mspace(0, 0) }
+    // Locked, since threads share the mspace
+    let msp = unsafe { create_mspace(0, 1) };
+    // Keeping every chunk in a segment lets free check that a pointer is
+    // in the heap before reading its header
+    #[cfg(feature = "hardened_malloc")]
+    unsafe {
+        mspace_track_large_chunks(msp, 1);
+    }
+    msp
 }
@@ -0,0 +1,295 @@
+//! Hardened allocation on top of the mspace, enabled by the hardened_malloc
+//! feature
+//!
+//! Every block has a header in front, and a canary right after it, both
+//! keyed with a secret from AT_RANDOM. They are checked when the block is
+//! freed, and a mismatch aborts instead of letting a corrupted heap carry
+//! on. Freed blocks are filled with junk and wait in a quarantine before
+//! going back to the mspace, so a dangling pointer does not see the next
+//! block handed out there, and freeing it again finds it marked as freed.
+//! Large blocks end in a guard page. The mspace keeps every chunk in one of
+//! its segments, so free can check that a pointer is in the heap before
+//! reading its header, and a pointer that never came from malloc aborts too.
+
+use core::{
+    cmp, intrinsics, mem, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{mspace_contains, mspace_free, mspace_malloc, mspace_memalign};
+use crate::{
+    header::{
+        malloc::{M_GUARD_THRESHOLD, M_QUARANTINE_MAX},
+        sys_mman::{PROT_NONE, PROT_READ, PROT_WRITE},
+    },
+    ld_so::PAGE_SIZE,
+    platform::{types::*, Pal, Sys},
+    sync::Mutex,
+    ALLOCATOR,
+};
+
+#[repr(C, align(16))]
+struct Header {
+    /// Start of the mspace chunk the block is in
+    base: usize,
+    /// The size asked for, which the tail canary follows
+    size: usize,
+    flags: usize,
+    /// Covers the fields above and the address of the header. It comes
+    /// last, so an underflow of the block hits it first.
+    canary: usize,
+}
+
+const HEADER: usize = mem::size_of::<Header>();
+const TAIL: usize = mem::size_of::<usize>();
+
+// Bits of Header::flags
+const FREED: usize = 1;
+const GUARDED: usize = 2;
+
+/// What freed blocks are filled with
+const JUNK: u8 = 0xdf;
+
+/// How many freed blocks the quarantine holds at most
+const QUARANTINE_LEN: usize = 256;
+
+/// Blocks of at least this many bytes get a guard page. Blocks this large
+/// take whole pages anyway, so this costs a page.
+static GUARD_THRESHOLD: AtomicUsize = AtomicUsize::new(256 * 1024);
+/// Most bytes of freed blocks kept in the quarantine
+static QUARANTINE_MAX: AtomicUsize = AtomicUsize::new(1024 * 1024);
+
+static SECRET: AtomicUsize = AtomicUsize::new(0);
+
+struct Quarantine {
+    /// Headers of freed blocks, the oldest at `head`
+    blocks: [usize; QUARANTINE_LEN],
+    head: usize,
+    len: usize,
+    size: usize,
+}
+
+static QUARANTINE: Mutex<Quarantine> = Mutex::new(Quarantine {
+    blocks: [0; QUARANTINE_LEN],
+    head: 0,
+    len: 0,
+    size: 0,
+});
+
+fn fail(msg: &[u8]) -> ! {
+    Sys::write(2, msg);
+    intrinsics::abort()
+}
+
+fn round_up(size: usize) -> usize {
+    (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+fn mix(hash: usize, value: usize) -> usize {
+    let hash = (hash ^ value).wrapping_mul(0x9e37_79b9_7f4a_7c15_u64 as usize);
+    hash ^ (hash >> 29)
+}
+
+/// Keys headers and canaries with `random`. Called by init_auxv, before
+/// anything is allocated.
+pub fn init_secret(random: usize) {
+    SECRET.store(mix(random, 0) | 1, Ordering::Relaxed);
+}
+
+fn secret() -> usize {
+    SECRET.load(Ordering::Relaxed)
+}
+
+unsafe fn canary(header: *const Header) -> usize {
+    let mut hash = mix(secret(), header as usize);
+    hash = mix(hash, (*header).base);
+    hash = mix(hash, (*header).size);
+    mix(hash, (*header).flags)
+}
+
+/// Writes the header and the tail canary of a block
+unsafe fn init(block: usize, base: usize, size: usize, flags: usize) -> *mut c_void {
+    let header = (block - HEADER) as *mut Header;
+    ptr::write(
+        header,
+        Header {
+            base,
+            size,
+            flags,
+            canary: 0,
+        },
+    );
+    (*header).canary = canary(header);
+    ptr::write_unaligned((block + size) as *mut usize, secret() ^ block);
+    block as *mut c_void
+}
+
+/// Finds the header of a block, aborting if the block was freed already or
+/// either canary has been overwritten
+unsafe fn header(ptr: *mut c_void) -> *mut Header {
+    let block = ptr as usize;
+    // Anything outside the segments of the mspace may not even be mapped
+    let msp = ALLOCATOR.get_book_keeper();
+    if block % mem::align_of::<Header>() != 0
+        || block < HEADER
+        || mspace_contains(msp, (block - HEADER) as *const c_void) == 0
+        || mspace_contains(msp, (block - 1) as *const c_void) == 0
+    {
+        fail(b"*** invalid free detected ***: terminated\n");
+    }
+    let header = (block - HEADER) as *mut Header;
+    if (*header).canary != canary(header) {
+        fail(b"*** invalid free or heap corruption detected ***: terminated\n");
+    }
+    if (*header).flags & FREED != 0 {
+        fail(b"*** double free detected ***: terminated\n");
+    }
+    if ptr::read_unaligned((block + (*header).size) as *const usize) != secret() ^ block {
+        fail(b"*** heap buffer overflow detected ***: terminated\n");
+    }
+    header
+}
+
+/// Allocates pages for a block so that it ends right before a guard page,
+/// give or take its alignment and the tail canary
+unsafe fn alloc_guarded(size: usize, alignment: usize) -> *mut c_void {
+    // The block and its header rounded up to pages, plus the guard page
+    let len = match size.checked_add(alignment + HEADER + TAIL + 2 * PAGE_SIZE - 1) {
+        Some(len) => len & !(PAGE_SIZE - 1),
+        None => return ptr::null_mut(),
+    };
+    let base = mspace_memalign(ALLOCATOR.get_book_keeper(), PAGE_SIZE, len);
+    if base.is_null() {
+        return ptr::null_mut();
+    }
+    // The guard page is inside the chunk, so the mspace never touches it
+    // while the block is in use
+    let guard = base as usize + len - PAGE_SIZE;
+    if Sys::mprotect(guard as *mut c_void, PAGE_SIZE, PROT_NONE) < 0 {
+        mspace_free(ALLOCATOR.get_book_keeper(), base);
+        return ptr::null_mut();
+    }
+
+    let block = (guard - TAIL - size) & !(alignment - 1);
+    init(block, base as usize, size, GUARDED)
+}
+
+/// Gives a freed block back to the mspace
+unsafe fn release(header: *mut Header) {
+    let Header {
+        base, size, flags, ..
+    } = ptr::read(header);
+    if flags & GUARDED != 0 {
+        // Guarded blocks are never aligned to more than a page, so the
+        // guard page is the first one after the tail canary
+        let guard = round_up(header as usize + HEADER + size + TAIL);
+        Sys::mprotect(guard as *mut c_void, PAGE_SIZE, PROT_READ | PROT_WRITE);
+    }
+    mspace_free(ALLOCATOR.get_book_keeper(), base as *mut c_void);
+}
+
+pub unsafe fn alloc(size: usize) -> *mut c_void {
+    alloc_align(size, mem::align_of::<Header>())
+}
+
+pub unsafe fn alloc_align(size: usize, alignment: usize) -> *mut c_void {
+    // Aligning the block to at least the header's alignment keeps the header
+    // aligned too
+    let alignment = cmp::max(alignment, mem::align_of::<Header>());
+    if size >= GUARD_THRESHOLD.load(Ordering::Relaxed) && alignment <= PAGE_SIZE {
+        return alloc_guarded(size, alignment);
+    }
+
+    let offset = cmp::max(HEADER, alignment);
+    let total = match size.checked_add(offset + TAIL) {
+        Some(total) => total,
+        None => return ptr::null_mut(),
+    };
+    let base = if alignment == mem::align_of::<Header>() {
+        mspace_malloc(ALLOCATOR.get_book_keeper(), total)
+    } else {
+        mspace_memalign(ALLOCATOR.get_book_keeper(), alignment, total)
+    };
+    if base.is_null() {
+        return ptr::null_mut();
+    }
+    init(base as usize + offset, base as usize, size, 0)
+}
+
+pub unsafe fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void {
+    if ptr.is_null() {
+        return alloc(size);
+    }
+    // Moving every time keeps the old block in the quarantine, like any
+    // other freed block
+    let old_size = (*header(ptr)).size;
+    let new = alloc(size);
+    if !new.is_null() {
+        ptr::copy_nonoverlapping(ptr as *const u8, new as *mut u8, cmp::min(old_size, size));
+        free(ptr);
+    }
+    new
+}
+
+pub unsafe fn free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let header = header(ptr);
+    let size = (*header).size;
+    (*header).flags |= FREED;
+    (*header).canary = canary(header);
+
+    // Blocks too large for the quarantine go back right away, so freeing
+    // one again is only caught until its memory is reused or unmapped
+    let max = QUARANTINE_MAX.load(Ordering::Relaxed);
+    if size > max {
+        release(header);
+        return;
+    }
+    ptr::write_bytes(ptr as *mut u8, JUNK, size);
+
+    let mut quarantine = QUARANTINE.lock();
+    while quarantine.len == QUARANTINE_LEN || quarantine.size + size > max {
+        let oldest = quarantine.blocks[quarantine.head] as *mut Header;
+        quarantine.head = (quarantine.head + 1) % QUARANTINE_LEN;
+        quarantine.len -= 1;
+        quarantine.size -= (*oldest).size;
+        release(oldest);
+    }
+    let i = (quarantine.head + quarantine.len) % QUARANTINE_LEN;
+    quarantine.blocks[i] = header as usize;
+    quarantine.len += 1;
+    quarantine.size += size;
+}
+
+pub unsafe fn usable_size(ptr: *mut c_void) -> size_t {
+    // Anything past the size asked for is the tail canary
+    (*header(ptr)).size
+}
+
+/// Releases every block in the quarantine
+pub unsafe fn flush_quarantine() {
+    let mut quarantine = QUARANTINE.lock();
+    while quarantine.len > 0 {
+        let oldest = quarantine.blocks[quarantine.head] as *mut Header;
+        quarantine.head = (quarantine.head + 1) % QUARANTINE_LEN;
+        quarantine.len -= 1;
+        release(oldest);
+    }
+    quarantine.size = 0;
+}
+
+pub fn set_param(param: c_int, value: c_int) -> c_int {
+    let value = match value {
+        -1 => usize::max_value(),
+        value if value >= 0 => value as usize,
+        _ => return 0,
+    };
+    match param {
+        M_GUARD_THRESHOLD => GUARD_THRESHOLD.store(value, Ordering::Relaxed),
+        M_QUARANTINE_MAX => QUARANTINE_MAX.store(value, Ordering::Relaxed),
+        _ => return 0,
+    }
+    1
+}
@@ -1,5 +1,8 @@
 extern crate ralloc;
 
+use super::types::*;
+use crate::header::malloc::mallinfo2;
+
 pub use ralloc::Allocator;
 
 unsafe fn alloc_inner(size: usize, offset: usize, align: usize) -> *mut c_void {
@@ -46,3 +49,36 @@ pub unsafe fn free(ptr: *mut c_void) {
     let _align = *(ptr as *mut u64).offset(1);
     ralloc::free(ptr, size as usize);
 }
+
+pub unsafe fn usable_size(ptr: *mut c_void) -> size_t {
+    let ptr = (ptr as *mut u8).offset(-16);
+    let size = *(ptr as *mut u64);
+    let align = *(ptr as *mut u64).offset(1);
+    // See alloc and alloc_align for the offsets
+    let offset = if align == 8 { 16 } else { align / 2 };
+    (size - offset) as size_t
+}
+
+/// ralloc keeps no statistics
+pub unsafe fn heap_info() -> mallinfo2 {
+    mallinfo2 {
+        arena: 0,
+        ordblks: 0,
+        smblks: 0,
+        hblks: 0,
+        hblkhd: 0,
+        usmblks: 0,
+        fsmblks: 0,
+        uordblks: 0,
+        fordblks: 0,
+        keepcost: 0,
+    }
+}
+
+pub unsafe fn heap_trim(_pad: size_t) -> c_int {
+    0
+}
+
+pub unsafe fn set_heap_param(_param: c_int, _value: c_int) -> c_int {
+    0
+}
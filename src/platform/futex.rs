@@ -0,0 +1,56 @@
+//! Operations and flags for Pal::futex, numbered as on Linux. See futex(2)
+//! for what they do.
+
+use super::types::*;
+
+/// Sleep if the futex holds `val`, until woken or the relative timeout in
+/// `val2` has passed
+pub const FUTEX_WAIT: c_int = 0;
+/// Wake at most `val` waiters
+pub const FUTEX_WAKE: c_int = 1;
+/// Wake at most `val` waiters and move at most `val2` more to `addr2`
+pub const FUTEX_REQUEUE: c_int = 3;
+/// Like FUTEX_REQUEUE, but fail with EAGAIN if the futex does not hold
+/// `val3`
+pub const FUTEX_CMP_REQUEUE: c_int = 4;
+/// Apply the operation encoded in `val3` to `addr2`, wake at most `val`
+/// waiters, and at most `val2` waiters of `addr2` if its old value passes
+/// the comparison encoded in `val3`
+pub const FUTEX_WAKE_OP: c_int = 5;
+/// Like FUTEX_WAIT, but the timeout is absolute and waiters are tagged
+/// with the bitset `val3`
+pub const FUTEX_WAIT_BITSET: c_int = 9;
+/// Like FUTEX_WAKE, but only waiters whose bitset intersects `val3`
+pub const FUTEX_WAKE_BITSET: c_int = 10;
+
+/// The futex is only used within this process
+pub const FUTEX_PRIVATE_FLAG: c_int = 128;
+/// Timeouts of FUTEX_WAIT_BITSET are on CLOCK_REALTIME instead of
+/// CLOCK_MONOTONIC
+pub const FUTEX_CLOCK_REALTIME: c_int = 256;
+pub const FUTEX_CMD_MASK: c_int = !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+
+pub const FUTEX_BITSET_MATCH_ANY: c_int = !0;
+
+// Operations of FUTEX_WAKE_OP, with FUTEX_OP_OPARG_SHIFT to use 1 << oparg
+// as the argument
+pub const FUTEX_OP_SET: c_int = 0;
+pub const FUTEX_OP_ADD: c_int = 1;
+pub const FUTEX_OP_OR: c_int = 2;
+pub const FUTEX_OP_ANDN: c_int = 3;
+pub const FUTEX_OP_XOR: c_int = 4;
+pub const FUTEX_OP_OPARG_SHIFT: c_int = 8;
+
+// Comparisons of FUTEX_WAKE_OP
+pub const FUTEX_OP_CMP_EQ: c_int = 0;
+pub const FUTEX_OP_CMP_NE: c_int = 1;
+pub const FUTEX_OP_CMP_LT: c_int = 2;
+pub const FUTEX_OP_CMP_LE: c_int = 3;
+pub const FUTEX_OP_CMP_GT: c_int = 4;
+pub const FUTEX_OP_CMP_GE: c_int = 5;
+
+/// Encodes the `val3` of FUTEX_WAKE_OP: `*addr2 = *addr2 op oparg`, then
+/// wake waiters of `addr2` if `old cmp cmparg`
+pub const fn futex_op(op: c_int, oparg: c_int, cmp: c_int, cmparg: c_int) -> c_int {
+    ((op & 0xf) << 28) | ((cmp & 0xf) << 24) | ((oparg & 0xfff) << 12) | (cmparg & 0xfff)
+}
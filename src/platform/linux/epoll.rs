@@ -21,7 +21,7 @@ impl PalEpoll for Sys {
         sigmask: *const sigset_t,
     ) -> c_int {
         unsafe {
-            e(syscall!(
+            e(syscall_cp!(
                 EPOLL_PWAIT,
                 epfd,
                 events,
@@ -1,10 +1,10 @@
-use core::ptr;
+use core::{intrinsics, ptr};
 use core_io::Write;
 
 use super::{errno, types::*, Pal};
 use crate::{
     c_str::CStr,
-    header::{dirent::dirent, signal::SIGCHLD, sys_stat::S_IFIFO},
+    header::{dirent::dirent, sched::sched_param, signal::SIGCHLD, sys_stat::S_IFIFO},
 };
 // use header::sys_resource::rusage;
 use crate::header::{
@@ -16,6 +16,39 @@ use crate::header::{
 // use header::sys_times::tms;
 use crate::header::{sys_utsname::utsname, time::timespec};
 
+/// Like syscall!, for the system calls of cancellation points, see
+/// header::pthread::cancel
+macro_rules! syscall_cp {
+    ($nr:ident, $a:expr) => {
+        syscall_cp!($nr, $a, 0, 0, 0, 0, 0)
+    };
+    ($nr:ident, $a:expr, $b:expr) => {
+        syscall_cp!($nr, $a, $b, 0, 0, 0, 0)
+    };
+    ($nr:ident, $a:expr, $b:expr, $c:expr) => {
+        syscall_cp!($nr, $a, $b, $c, 0, 0, 0)
+    };
+    ($nr:ident, $a:expr, $b:expr, $c:expr, $d:expr) => {
+        syscall_cp!($nr, $a, $b, $c, $d, 0, 0)
+    };
+    ($nr:ident, $a:expr, $b:expr, $c:expr, $d:expr, $e:expr) => {
+        syscall_cp!($nr, $a, $b, $c, $d, $e, 0)
+    };
+    ($nr:ident, $a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr) => {
+        crate::header::pthread::syscall_cp(
+            sc::nr::$nr,
+            [
+                $a as usize,
+                $b as usize,
+                $c as usize,
+                $d as usize,
+                $e as usize,
+                $f as usize,
+            ],
+        )
+    };
+}
+
 mod epoll;
 mod ptrace;
 mod signal;
@@ -30,6 +63,10 @@ const CLONE_VM: usize = 0x0100;
 const CLONE_FS: usize = 0x0200;
 const CLONE_FILES: usize = 0x0400;
 const CLONE_SIGHAND: usize = 0x0800;
+const CLONE_VFORK: usize = 0x4000;
+const CLONE_THREAD: usize = 0x10000;
+const CLONE_SYSVSEM: usize = 0x40000;
+const CLONE_PARENT_SETTID: usize = 0x100000;
 
 #[repr(C)]
 #[derive(Default)]
@@ -59,6 +96,12 @@ pub fn e(sys: usize) -> usize {
     }
 }
 
+/// Sets errno for Sys::vfork
+#[no_mangle]
+unsafe extern "C" fn __relibc_vfork_ret(ret: usize) -> pid_t {
+    e(ret) as pid_t
+}
+
 pub struct Sys;
 
 impl Sys {
@@ -68,7 +111,7 @@ impl Sys {
 
     pub unsafe fn ioctl(fd: c_int, request: c_ulong, out: *mut c_void) -> c_int {
         // TODO: Somehow support varargs to syscall??
-        e(syscall!(IOCTL, fd, request, out)) as c_int
+        e(syscall_cp!(IOCTL, fd, request, out)) as c_int
     }
 
     // fn times(out: *mut tms) -> clock_t {
@@ -110,7 +153,7 @@ impl Pal for Sys {
     }
 
     fn close(fildes: c_int) -> c_int {
-        e(unsafe { syscall!(CLOSE, fildes) }) as c_int
+        e(unsafe { syscall_cp!(CLOSE, fildes) }) as c_int
     }
 
     fn dup(fildes: c_int) -> c_int {
@@ -127,11 +170,47 @@ impl Pal for Sys {
 
     fn exit(status: c_int) -> ! {
         unsafe {
-            syscall!(EXIT, status);
+            syscall!(EXIT_GROUP, status);
         }
         loop {}
     }
 
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn exit_thread(stack: *mut c_void, stack_size: usize, alive: *mut c_int) -> ! {
+        // Nothing may touch the stack once it is unmapped. If stack_size is
+        // zero munmap fails, which leaves a stack we do not own alone
+        llvm_asm!("
+            # Unmap the stack
+            mov rax, 11
+            syscall
+
+            # Clear alive and wake everyone waiting on it
+            test r8, r8
+            jz .exit_thread_exit
+            mov dword ptr [r8], 0
+            mov rax, 202
+            mov rdi, r8
+            mov rsi, 1
+            mov rdx, 0x7fffffff
+            syscall
+
+            # Exit the thread
+            .exit_thread_exit:
+            mov rax, 60
+            xor rdi, rdi
+            syscall
+
+            # Invalid instruction on failure to exit
+            ud2
+            "
+            :
+            : "{rdi}"(stack), "{rsi}"(stack_size), "{r8}"(alive)
+            : "memory"
+            : "intel", "volatile"
+        );
+        unreachable!()
+    }
+
     fn fchdir(fildes: c_int) -> c_int {
         e(unsafe { syscall!(FCHDIR, fildes) }) as c_int
     }
@@ -183,7 +262,7 @@ impl Pal for Sys {
     }
 
     fn fcntl(fildes: c_int, cmd: c_int, arg: c_int) -> c_int {
-        e(unsafe { syscall!(FCNTL, fildes, cmd, arg) }) as c_int
+        e(unsafe { syscall_cp!(FCNTL, fildes, cmd, arg) }) as c_int
     }
 
     fn fork() -> pid_t {
@@ -199,15 +278,22 @@ impl Pal for Sys {
     }
 
     fn fsync(fildes: c_int) -> c_int {
-        e(unsafe { syscall!(FSYNC, fildes) }) as c_int
+        e(unsafe { syscall_cp!(FSYNC, fildes) }) as c_int
     }
 
     fn ftruncate(fildes: c_int, length: off_t) -> c_int {
         e(unsafe { syscall!(FTRUNCATE, fildes, length) }) as c_int
     }
 
-    fn futex(addr: *mut c_int, op: c_int, val: c_int) -> c_int {
-        unsafe { syscall!(FUTEX, addr, op, val, 0, 0, 0) as c_int }
+    fn futex(
+        addr: *mut c_int,
+        op: c_int,
+        val: c_int,
+        val2: usize,
+        addr2: *mut c_int,
+        val3: c_int,
+    ) -> c_int {
+        unsafe { syscall_cp!(FUTEX, addr, op, val, val2, addr2, val3) as c_int }
     }
 
     fn futimens(fd: c_int, times: *const timespec) -> c_int {
@@ -315,7 +401,7 @@ impl Pal for Sys {
     }
 
     unsafe fn msync(addr: *mut c_void, len: usize, flags: c_int) -> c_int {
-        e(syscall!(MSYNC, addr, len, flags)) as c_int
+        e(syscall_cp!(MSYNC, addr, len, flags)) as c_int
     }
 
     unsafe fn munmap(addr: *mut c_void, len: usize) -> c_int {
@@ -323,20 +409,49 @@ impl Pal for Sys {
     }
 
     fn nanosleep(rqtp: *const timespec, rmtp: *mut timespec) -> c_int {
-        e(unsafe { syscall!(NANOSLEEP, rqtp, rmtp) }) as c_int
+        e(unsafe { syscall_cp!(NANOSLEEP, rqtp, rmtp) }) as c_int
     }
 
     fn open(path: &CStr, oflag: c_int, mode: mode_t) -> c_int {
-        e(unsafe { syscall!(OPENAT, AT_FDCWD, path.as_ptr(), oflag, mode) }) as c_int
+        e(unsafe { syscall_cp!(OPENAT, AT_FDCWD, path.as_ptr(), oflag, mode) }) as c_int
     }
 
     fn pipe2(fildes: &mut [c_int], flags: c_int) -> c_int {
         e(unsafe { syscall!(PIPE2, fildes.as_mut_ptr(), flags) }) as c_int
     }
 
+    fn read(fildes: c_int, buf: &mut [u8]) -> ssize_t {
+        e(unsafe { syscall_cp!(READ, fildes, buf.as_mut_ptr(), buf.len()) }) as ssize_t
+    }
+
+    fn readlink(pathname: &CStr, out: &mut [u8]) -> ssize_t {
+        e(unsafe {
+            syscall!(
+                READLINKAT,
+                AT_FDCWD,
+                pathname.as_ptr(),
+                out.as_mut_ptr(),
+                out.len()
+            )
+        }) as ssize_t
+    }
+
+    fn rename(old: &CStr, new: &CStr) -> c_int {
+        e(unsafe { syscall!(RENAMEAT, AT_FDCWD, old.as_ptr(), AT_FDCWD, new.as_ptr()) }) as c_int
+    }
+
     #[cfg(target_arch = "x86_64")]
-    unsafe fn pte_clone(stack: *mut usize) -> pid_t {
-        let flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND;
+    unsafe fn rlct_clone(stack: *mut usize, tid: *mut pid_t) -> pid_t {
+        // The kernel stores the ID at `tid` before the thread runs.
+        // CLONE_CHILD_CLEARTID is left out, as a detached thread may free the
+        // memory at `tid` before the kernel would clear it on exit.
+        let flags = CLONE_VM
+            | CLONE_FS
+            | CLONE_FILES
+            | CLONE_SIGHAND
+            | CLONE_THREAD
+            | CLONE_SYSVSEM
+            | CLONE_PARENT_SETTID;
         let pid;
         llvm_asm!("
             # Call clone syscall
@@ -344,33 +459,23 @@ impl Pal for Sys {
 
             # Check if child or parent
             test rax, rax
-            jnz .parent
+            jnz .rlct_parent
 
-            # Load registers
+            # Load function and argument
             pop rax
             pop rdi
-            pop rsi
-            pop rdx
-            pop rcx
-            pop r8
-            pop r9
 
-            # Call entry point
+            # Call function, which exits the thread itself
             call rax
 
-            # Exit
-            mov rax, 60
-            xor rdi, rdi
-            syscall
-
-            # Invalid instruction on failure to exit
+            # Invalid instruction if it returns
             ud2
 
-            # Return PID if parent
-            .parent:
+            # Return TID if parent
+            .rlct_parent:
             "
             : "={rax}"(pid)
-            : "{rax}"(SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack), "{rdx}"(0), "{rcx}"(0), "{r8}"(0)
+            : "{rax}"(SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack), "{rdx}"(tid), "{r10}"(0), "{r8}"(0)
             : "memory", "rbx", "rcx", "rdx", "rsi", "rdi", "r8",
               "r9", "r10", "r11", "r12", "r13", "r14", "r15"
             : "intel", "volatile"
@@ -378,28 +483,16 @@ impl Pal for Sys {
         e(pid) as pid_t
     }
 
-    fn read(fildes: c_int, buf: &mut [u8]) -> ssize_t {
-        e(unsafe { syscall!(READ, fildes, buf.as_mut_ptr(), buf.len()) }) as ssize_t
-    }
-
-    fn readlink(pathname: &CStr, out: &mut [u8]) -> ssize_t {
-        e(unsafe {
-            syscall!(
-                READLINKAT,
-                AT_FDCWD,
-                pathname.as_ptr(),
-                out.as_mut_ptr(),
-                out.len()
-            )
-        }) as ssize_t
+    fn rmdir(path: &CStr) -> c_int {
+        e(unsafe { syscall!(UNLINKAT, AT_FDCWD, path.as_ptr(), AT_REMOVEDIR) }) as c_int
     }
 
-    fn rename(old: &CStr, new: &CStr) -> c_int {
-        e(unsafe { syscall!(RENAMEAT, AT_FDCWD, old.as_ptr(), AT_FDCWD, new.as_ptr()) }) as c_int
+    fn sched_setparam(pid: pid_t, param: *const sched_param) -> c_int {
+        e(unsafe { syscall!(SCHED_SETPARAM, pid, param) }) as c_int
     }
 
-    fn rmdir(path: &CStr) -> c_int {
-        e(unsafe { syscall!(UNLINKAT, AT_FDCWD, path.as_ptr(), AT_REMOVEDIR) }) as c_int
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: *const sched_param) -> c_int {
+        e(unsafe { syscall!(SCHED_SETSCHEDULER, pid, policy, param) }) as c_int
     }
 
     fn sched_yield() -> c_int {
@@ -418,6 +511,49 @@ impl Pal for Sys {
         e(unsafe { syscall!(SETREUID, ruid, euid) }) as c_int
     }
 
+    fn setsid() -> pid_t {
+        e(unsafe { syscall!(SETSID) }) as pid_t
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn spawn_clone(stack: *mut usize) -> pid_t {
+        let flags = CLONE_VM | CLONE_VFORK | SIGCHLD;
+        let pid;
+        llvm_asm!("
+            # Call clone syscall
+            syscall
+
+            # Check if child or parent
+            test rax, rax
+            jnz .spawn_parent
+
+            # Load function and argument
+            pop rax
+            pop rdi
+
+            # Call function
+            call rax
+
+            # Exit with its return value
+            mov rdi, rax
+            mov rax, 60
+            syscall
+
+            # Invalid instruction on failure to exit
+            ud2
+
+            # Return PID if parent
+            .spawn_parent:
+            "
+            : "={rax}"(pid)
+            : "{rax}"(SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack), "{rdx}"(0), "{r10}"(0), "{r8}"(0)
+            : "memory", "rbx", "rcx", "rdx", "rsi", "rdi", "r8",
+              "r9", "r10", "r11", "r12", "r13", "r14", "r15"
+            : "intel", "volatile"
+        );
+        e(pid) as pid_t
+    }
+
     fn symlink(path1: &CStr, path2: &CStr) -> c_int {
         e(unsafe { syscall!(SYMLINKAT, path1.as_ptr(), AT_FDCWD, path2.as_ptr()) }) as c_int
     }
@@ -434,12 +570,38 @@ impl Pal for Sys {
         e(unsafe { syscall!(UNLINKAT, AT_FDCWD, path.as_ptr(), 0) }) as c_int
     }
 
+    #[cfg(target_arch = "x86_64")]
+    #[naked]
+    unsafe extern "C" fn vfork() -> pid_t {
+        // The return address is kept in a register, where the child can't
+        // overwrite it before the parent resumes, and pushed back by each
+        llvm_asm!("
+            pop rdx
+
+            # clone(CLONE_VM | CLONE_VFORK | SIGCHLD, 0)
+            mov eax, 56
+            mov edi, 0x4111
+            xor esi, esi
+            syscall
+
+            push rdx
+            mov rdi, rax
+            jmp __relibc_vfork_ret@PLT
+            "
+            :
+            :
+            :
+            : "intel", "volatile"
+        );
+        intrinsics::unreachable()
+    }
+
     fn waitpid(pid: pid_t, stat_loc: *mut c_int, options: c_int) -> pid_t {
-        e(unsafe { syscall!(WAIT4, pid, stat_loc, options, 0) }) as pid_t
+        e(unsafe { syscall_cp!(WAIT4, pid, stat_loc, options, 0) }) as pid_t
     }
 
     fn write(fildes: c_int, buf: &[u8]) -> ssize_t {
-        e(unsafe { syscall!(WRITE, fildes, buf.as_ptr(), buf.len()) }) as ssize_t
+        e(unsafe { syscall_cp!(WRITE, fildes, buf.as_ptr(), buf.len()) }) as ssize_t
     }
 
     fn verify() -> bool {
@@ -1,7 +1,7 @@
 use core::mem;
 
 use super::{
-    super::{types::*, PalSignal},
+    super::{types::*, Pal, PalSignal},
     e, Sys,
 };
 use crate::header::{
@@ -31,6 +31,11 @@ impl PalSignal for Sys {
         }
     }
 
+    fn rlct_kill(os_tid: pid_t, sig: c_int) -> c_int {
+        let tgid = Self::getpid();
+        e(unsafe { syscall!(TGKILL, tgid, os_tid, sig) }) as c_int
+    }
+
     fn setitimer(which: c_int, new: *const itimerval, old: *mut itimerval) -> c_int {
         e(unsafe { syscall!(SETITIMER, which, new, old) }) as c_int
     }
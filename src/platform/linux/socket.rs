@@ -6,7 +6,7 @@ use crate::header::sys_socket::{sockaddr, socklen_t};
 
 impl PalSocket for Sys {
     unsafe fn accept(socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int {
-        e(syscall!(ACCEPT, socket, address, address_len)) as c_int
+        e(syscall_cp!(ACCEPT, socket, address, address_len)) as c_int
     }
 
     unsafe fn bind(socket: c_int, address: *const sockaddr, address_len: socklen_t) -> c_int {
@@ -14,7 +14,7 @@ impl PalSocket for Sys {
     }
 
     unsafe fn connect(socket: c_int, address: *const sockaddr, address_len: socklen_t) -> c_int {
-        e(syscall!(CONNECT, socket, address, address_len)) as c_int
+        e(syscall_cp!(CONNECT, socket, address, address_len)) as c_int
     }
 
     unsafe fn getpeername(
@@ -64,7 +64,7 @@ impl PalSocket for Sys {
         address: *mut sockaddr,
         address_len: *mut socklen_t,
     ) -> ssize_t {
-        e(syscall!(
+        e(syscall_cp!(
             RECVFROM,
             socket,
             buf,
@@ -83,7 +83,7 @@ impl PalSocket for Sys {
         dest_addr: *const sockaddr,
         dest_len: socklen_t,
     ) -> ssize_t {
-        e(syscall!(
+        e(syscall_cp!(
             SENDTO, socket, buf, len, flags, dest_addr, dest_len
         )) as ssize_t
     }
@@ -16,6 +16,8 @@ pub use self::pal::{Pal, PalEpoll, PalPtrace, PalSignal, PalSocket};
 
 mod pal;
 
+pub mod futex;
+
 pub use self::sys::{e, Sys};
 
 #[cfg(all(not(feature = "no_std"), target_os = "linux"))]
@@ -29,8 +31,6 @@ mod sys;
 #[cfg(test)]
 mod test;
 
-mod pte;
-
 pub use self::rlb::{Line, RawLineBuffer};
 pub mod rlb;
 
@@ -53,6 +53,10 @@ pub static mut environ: *mut *mut c_char = ptr::null_mut();
 #[allow(non_upper_case_globals)]
 pub static mut inner_environ: Vec<*mut c_char> = Vec::new();
 
+/// The auxiliary vector passed by the kernel, ending with AT_NULL
+#[allow(non_upper_case_globals)]
+pub static mut auxv: *const (usize, usize) = ptr::null();
+
 pub trait WriteByte: fmt::Write {
     fn write_u8(&mut self, byte: u8) -> fmt::Result;
 }
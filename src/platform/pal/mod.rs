@@ -3,6 +3,7 @@ use crate::{
     c_str::CStr,
     header::{
         dirent::dirent,
+        sched::sched_param,
         sys_resource::rlimit,
         sys_stat::stat,
         sys_statvfs::statvfs,
@@ -47,6 +48,12 @@ pub trait Pal {
 
     fn exit(status: c_int) -> !;
 
+    /// Unmaps `stack`, then zeroes `alive` and wakes its futex waiters
+    /// unless it is null, and exits the calling thread. No part of the stack
+    /// is used after either, so a thread may free its own stack, and a
+    /// waiter on `alive` may free or reuse it.
+    unsafe fn exit_thread(stack: *mut c_void, stack_size: usize, alive: *mut c_int) -> !;
+
     fn fchdir(fildes: c_int) -> c_int;
 
     fn fchmod(fildes: c_int, mode: mode_t) -> c_int;
@@ -69,7 +76,18 @@ pub trait Pal {
 
     fn ftruncate(fildes: c_int, length: off_t) -> c_int;
 
-    fn futex(addr: *mut c_int, op: c_int, val: c_int) -> c_int;
+    /// Performs the futex operation `op` on `addr`, using the other
+    /// arguments as described in platform::futex. `val2` is a pointer to a
+    /// timespec for waits. Returns the result, or a negated error such as
+    /// -EAGAIN, -EINTR or -ETIMEDOUT.
+    fn futex(
+        addr: *mut c_int,
+        op: c_int,
+        val: c_int,
+        val2: usize,
+        addr2: *mut c_int,
+        val3: c_int,
+    ) -> c_int;
 
     fn futimens(fd: c_int, times: *const timespec) -> c_int;
 
@@ -130,16 +148,25 @@ pub trait Pal {
 
     fn pipe2(fildes: &mut [c_int], flags: c_int) -> c_int;
 
-    unsafe fn pte_clone(stack: *mut usize) -> pid_t;
-
     fn read(fildes: c_int, buf: &mut [u8]) -> ssize_t;
 
     fn readlink(pathname: &CStr, out: &mut [u8]) -> ssize_t;
 
     fn rename(old: &CStr, new: &CStr) -> c_int;
 
+    /// Starts a thread sharing the caller's memory, files and signal
+    /// handlers, which pops a function and its argument off `stack` and calls
+    /// it. The function must exit the thread instead of returning. Where the
+    /// kernel supports it, the thread ID is stored at `tid` before the thread
+    /// starts.
+    unsafe fn rlct_clone(stack: *mut usize, tid: *mut pid_t) -> pid_t;
+
     fn rmdir(path: &CStr) -> c_int;
 
+    fn sched_setparam(pid: pid_t, param: *const sched_param) -> c_int;
+
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: *const sched_param) -> c_int;
+
     fn sched_yield() -> c_int;
 
     fn setpgid(pid: pid_t, pgid: pid_t) -> c_int;
@@ -148,6 +175,13 @@ pub trait Pal {
 
     fn setreuid(ruid: uid_t, euid: uid_t) -> c_int;
 
+    fn setsid() -> pid_t;
+
+    /// Starts a child process sharing the caller's memory, which pops a
+    /// function and its argument off `stack`, calls it and exits with its
+    /// return value. The caller is suspended until the child execs or exits.
+    unsafe fn spawn_clone(stack: *mut usize) -> pid_t;
+
     fn symlink(path1: &CStr, path2: &CStr) -> c_int;
 
     fn umask(mask: mode_t) -> mode_t;
@@ -156,6 +190,16 @@ pub trait Pal {
 
     fn unlink(path: &CStr) -> c_int;
 
+    /// Creates a child that shares the memory of the calling process, which
+    /// is suspended until the child calls execve or exits. Until then the
+    /// child runs on the stack of the parent: it may not return from the
+    /// function that called vfork, and anything it pushes overwrites what the
+    /// parent left below its stack pointer. So this keeps nothing on the
+    /// stack across the system call, and has to be reached by a jump from the
+    /// caller's own call, as C's vfork does, rather than from a frame of
+    /// Rust code the child would pop.
+    unsafe extern "C" fn vfork() -> pid_t;
+
     fn waitpid(pid: pid_t, stat_loc: *mut c_int, options: c_int) -> pid_t;
 
     fn write(fildes: c_int, buf: &[u8]) -> ssize_t;
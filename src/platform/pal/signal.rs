@@ -13,6 +13,8 @@ pub trait PalSignal: Pal {
 
     fn raise(sig: c_int) -> c_int;
 
+    fn rlct_kill(os_tid: pid_t, sig: c_int) -> c_int;
+
     fn setitimer(which: c_int, new: *const itimerval, old: *mut itimerval) -> c_int;
 
     fn sigaction(sig: c_int, act: Option<&sigaction>, oact: Option<&mut sigaction>) -> c_int;
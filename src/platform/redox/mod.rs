@@ -1,4 +1,9 @@
-use core::{mem, ptr, result::Result as CoreResult, slice};
+use core::{
+    intrinsics, mem, ptr,
+    result::Result as CoreResult,
+    slice,
+    sync::atomic::{AtomicI32, Ordering},
+};
 use syscall::{
     self,
     data::{Map2, Stat as redox_stat, StatVfs as redox_statvfs, TimeSpec as redox_timespec},
@@ -10,8 +15,9 @@ use crate::{
     fs::File,
     header::{
         dirent::dirent,
-        errno::{EINVAL, EIO, EPERM, ERANGE},
+        errno::{EINVAL, EIO, EPERM, ERANGE, ETIMEDOUT},
         fcntl,
+        sched::sched_param,
         sys_mman::MAP_ANON,
         sys_random,
         sys_resource::{rlimit, RLIM_INFINITY},
@@ -20,13 +26,13 @@ use crate::{
         sys_time::{timeval, timezone},
         sys_utsname::{utsname, UTSLENGTH},
         sys_wait,
-        time::timespec,
+        time::{timespec, CLOCK_MONOTONIC, CLOCK_REALTIME},
         unistd::{F_OK, R_OK, W_OK, X_OK},
     },
     io::{self, prelude::*, BufReader, SeekFrom},
 };
 
-use super::{errno, types::*, Pal, Read};
+use super::{errno, futex, types::*, Pal, Read};
 
 mod epoll;
 mod extra;
@@ -46,6 +52,76 @@ pub fn e(sys: Result<usize>) -> usize {
     }
 }
 
+/// Sets errno for Sys::vfork
+#[no_mangle]
+unsafe extern "C" fn __relibc_vfork_ret(ret: usize) -> pid_t {
+    e(syscall::Error::demux(ret)) as pid_t
+}
+
+/// FUTEX_WAKE_OP, which Redox does not have. The operation on `addr2` is
+/// atomic, but the wakes are done separately.
+unsafe fn futex_wake_op(
+    addr: *mut c_int,
+    val: c_int,
+    val2: usize,
+    addr2: *mut c_int,
+    val3: c_int,
+) -> Result<usize> {
+    let op = (val3 >> 28) & 0xf;
+    let cmp = (val3 >> 24) & 0xf;
+    // Both arguments are sign extended 12 bit numbers
+    let mut oparg = (val3 << 8) >> 20;
+    let cmparg = (val3 << 20) >> 20;
+    if op & futex::FUTEX_OP_OPARG_SHIFT != 0 {
+        oparg = 1 << (oparg & 31);
+    }
+
+    let atomic = &*(addr2 as *const AtomicI32);
+    let mut old = atomic.load(Ordering::SeqCst);
+    loop {
+        let new = match op & !futex::FUTEX_OP_OPARG_SHIFT {
+            futex::FUTEX_OP_SET => oparg,
+            futex::FUTEX_OP_ADD => old.wrapping_add(oparg),
+            futex::FUTEX_OP_OR => old | oparg,
+            futex::FUTEX_OP_ANDN => old & !oparg,
+            futex::FUTEX_OP_XOR => old ^ oparg,
+            _ => return Err(syscall::Error::new(syscall::ENOSYS)),
+        };
+        match atomic.compare_exchange_weak(old, new, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => break,
+            Err(value) => old = value,
+        }
+    }
+
+    let wake = match cmp {
+        futex::FUTEX_OP_CMP_EQ => old == cmparg,
+        futex::FUTEX_OP_CMP_NE => old != cmparg,
+        futex::FUTEX_OP_CMP_LT => old < cmparg,
+        futex::FUTEX_OP_CMP_LE => old <= cmparg,
+        futex::FUTEX_OP_CMP_GT => old > cmparg,
+        futex::FUTEX_OP_CMP_GE => old >= cmparg,
+        _ => return Err(syscall::Error::new(syscall::ENOSYS)),
+    };
+
+    let mut woken = syscall::futex(
+        addr as *mut i32,
+        syscall::FUTEX_WAKE,
+        val as i32,
+        0,
+        ptr::null_mut(),
+    )?;
+    if wake {
+        woken += syscall::futex(
+            addr2 as *mut i32,
+            syscall::FUTEX_WAKE,
+            val2 as i32,
+            0,
+            ptr::null_mut(),
+        )?;
+    }
+    Ok(woken)
+}
+
 pub struct Sys;
 
 impl Pal for Sys {
@@ -147,6 +223,41 @@ impl Pal for Sys {
         loop {}
     }
 
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn exit_thread(stack: *mut c_void, stack_size: usize, alive: *mut c_int) -> ! {
+        // Nothing may touch the stack once it is unmapped
+        llvm_asm!("
+            # Unmap the stack
+            syscall
+
+            # Clear alive and wake everyone waiting on it
+            test r9, r9
+            jz .exit_thread_exit
+            mov dword ptr [r9], 0
+            mov rax, r8
+            mov rdi, r9
+            mov rsi, r10
+            mov rdx, 0x7fffffff
+            syscall
+
+            # Exit the thread
+            .exit_thread_exit:
+            mov rax, 1
+            xor rdi, rdi
+            syscall
+
+            # Invalid instruction on failure to exit
+            ud2
+            "
+            :
+            : "{rax}"(syscall::SYS_FUNMAP2), "{rdi}"(stack), "{rsi}"(stack_size),
+              "{r8}"(syscall::SYS_FUTEX), "{r9}"(alive), "{r10}"(syscall::FUTEX_WAKE)
+            : "memory"
+            : "intel", "volatile"
+        );
+        unreachable!()
+    }
+
     unsafe fn execve(
         path: &CStr,
         mut argv: *const *mut c_char,
@@ -384,16 +495,100 @@ impl Pal for Sys {
         e(syscall::ftruncate(fd as usize, len as usize)) as c_int
     }
 
-    fn futex(addr: *mut c_int, op: c_int, val: c_int) -> c_int {
-        match unsafe {
-            syscall::futex(
-                addr as *mut i32,
-                op as usize,
-                val as i32,
-                0,
-                ptr::null_mut(),
-            )
-        } {
+    fn futex(
+        addr: *mut c_int,
+        op: c_int,
+        val: c_int,
+        val2: usize,
+        addr2: *mut c_int,
+        val3: c_int,
+    ) -> c_int {
+        // Redox has no bitsets, so all waiters match and every wake may
+        // wake more than asked for, which futex users have to expect anyway
+        let res = match op & futex::FUTEX_CMD_MASK {
+            futex::FUTEX_WAIT | futex::FUTEX_WAIT_BITSET => {
+                let mut redox_timeout = redox_timespec::default();
+                let timeout = val2 as *const timespec;
+                let timeout_ptr = if timeout.is_null() {
+                    0
+                } else {
+                    let (mut tv_sec, mut tv_nsec) =
+                        unsafe { ((*timeout).tv_sec, (*timeout).tv_nsec) };
+                    if tv_nsec < 0 || tv_nsec >= 1_000_000_000 {
+                        return -EINVAL;
+                    }
+
+                    // Timeouts on Redox are relative
+                    if op & futex::FUTEX_CMD_MASK == futex::FUTEX_WAIT_BITSET {
+                        let clock = if op & futex::FUTEX_CLOCK_REALTIME != 0 {
+                            CLOCK_REALTIME
+                        } else {
+                            CLOCK_MONOTONIC
+                        };
+                        let mut now = timespec::default();
+                        Sys::clock_gettime(clock, &mut now);
+                        tv_sec -= now.tv_sec;
+                        tv_nsec -= now.tv_nsec;
+                        if tv_nsec < 0 {
+                            tv_sec -= 1;
+                            tv_nsec += 1_000_000_000;
+                        }
+                        if tv_sec < 0 {
+                            return -ETIMEDOUT;
+                        }
+                    }
+
+                    redox_timeout.tv_sec = tv_sec;
+                    redox_timeout.tv_nsec = tv_nsec as i32;
+                    &mut redox_timeout as *mut redox_timespec as usize
+                };
+                unsafe {
+                    syscall::futex(
+                        addr as *mut i32,
+                        syscall::FUTEX_WAIT,
+                        val as i32,
+                        timeout_ptr,
+                        ptr::null_mut(),
+                    )
+                }
+            }
+            futex::FUTEX_WAKE | futex::FUTEX_WAKE_BITSET => unsafe {
+                syscall::futex(
+                    addr as *mut i32,
+                    syscall::FUTEX_WAKE,
+                    val as i32,
+                    0,
+                    ptr::null_mut(),
+                )
+            },
+            futex::FUTEX_CMP_REQUEUE if unsafe { *addr } != val3 => {
+                Err(syscall::Error::new(syscall::EAGAIN))
+            }
+            // Redox can't compare and requeue atomically, so every waiter is
+            // woken instead. Those that would have been requeued just see a
+            // spurious wakeup and wait again.
+            futex::FUTEX_CMP_REQUEUE => unsafe {
+                syscall::futex(
+                    addr as *mut i32,
+                    syscall::FUTEX_WAKE,
+                    i32::max_value(),
+                    0,
+                    ptr::null_mut(),
+                )
+            },
+            futex::FUTEX_REQUEUE => unsafe {
+                syscall::futex(
+                    addr as *mut i32,
+                    syscall::FUTEX_REQUEUE,
+                    val as i32,
+                    val2,
+                    addr2 as *mut i32,
+                )
+            },
+            futex::FUTEX_WAKE_OP => unsafe { futex_wake_op(addr, val, val2, addr2, val3) },
+            _ => Err(syscall::Error::new(syscall::ENOSYS)),
+        };
+        match res {
             Ok(success) => success as c_int,
             Err(err) => -(err.errno as c_int),
         }
@@ -758,54 +953,6 @@ impl Pal for Sys {
         res as c_int
     }
 
-    #[cfg(target_arch = "x86_64")]
-    unsafe fn pte_clone(stack: *mut usize) -> pid_t {
-        let flags = syscall::CLONE_VM
-            | syscall::CLONE_FS
-            | syscall::CLONE_FILES
-            | syscall::CLONE_SIGHAND
-            | syscall::CLONE_STACK;
-        let pid;
-        llvm_asm!("
-            # Call clone syscall
-            syscall
-
-            # Check if child or parent
-            test rax, rax
-            jnz .parent
-
-            # Load registers
-            pop rax
-            pop rdi
-            pop rsi
-            pop rdx
-            pop rcx
-            pop r8
-            pop r9
-
-            # Call entry point
-            call rax
-
-            # Exit
-            mov rax, 1
-            xor rdi, rdi
-            syscall
-
-            # Invalid instruction on failure to exit
-            ud2
-
-            # Return PID if parent
-            .parent:
-            "
-            : "={rax}"(pid)
-            : "{rax}"(syscall::SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack)
-            : "memory", "rbx", "rcx", "rdx", "rsi", "rdi", "r8",
-              "r9", "r10", "r11", "r12", "r13", "r14", "r15"
-            : "intel", "volatile"
-        );
-        e(syscall::Error::demux(pid)) as pid_t
-    }
-
     fn read(fd: c_int, buf: &mut [u8]) -> ssize_t {
         e(syscall::read(fd as usize, buf)) as ssize_t
     }
@@ -844,10 +991,56 @@ impl Pal for Sys {
         }
     }
 
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn rlct_clone(stack: *mut usize, _tid: *mut pid_t) -> pid_t {
+        let flags = syscall::CLONE_VM
+            | syscall::CLONE_FS
+            | syscall::CLONE_FILES
+            | syscall::CLONE_SIGHAND
+            | syscall::CLONE_STACK;
+        let pid;
+        llvm_asm!("
+            # Call clone syscall
+            syscall
+
+            # Check if child or parent
+            test rax, rax
+            jnz .rlct_parent
+
+            # Load function and argument
+            pop rax
+            pop rdi
+
+            # Call function, which exits the thread itself
+            call rax
+
+            # Invalid instruction if it returns
+            ud2
+
+            # Return PID if parent
+            .rlct_parent:
+            "
+            : "={rax}"(pid)
+            : "{rax}"(syscall::SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack)
+            : "memory", "rbx", "rcx", "rdx", "rsi", "rdi", "r8",
+              "r9", "r10", "r11", "r12", "r13", "r14", "r15"
+            : "intel", "volatile"
+        );
+        e(syscall::Error::demux(pid)) as pid_t
+    }
+
     fn rmdir(path: &CStr) -> c_int {
         e(syscall::rmdir(path.to_bytes())) as c_int
     }
 
+    fn sched_setparam(pid: pid_t, param: *const sched_param) -> c_int {
+        e(Err(syscall::Error::new(syscall::ENOSYS))) as c_int
+    }
+
+    fn sched_setscheduler(pid: pid_t, policy: c_int, param: *const sched_param) -> c_int {
+        e(Err(syscall::Error::new(syscall::ENOSYS))) as c_int
+    }
+
     fn sched_yield() -> c_int {
         e(syscall::sched_yield()) as c_int
     }
@@ -864,6 +1057,49 @@ impl Pal for Sys {
         e(syscall::setreuid(ruid as usize, euid as usize)) as c_int
     }
 
+    fn setsid() -> pid_t {
+        e(Err(syscall::Error::new(syscall::ENOSYS))) as pid_t
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn spawn_clone(stack: *mut usize) -> pid_t {
+        let flags = syscall::CLONE_VM | syscall::CLONE_VFORK | syscall::CLONE_STACK;
+        let pid;
+        llvm_asm!("
+            # Call clone syscall
+            syscall
+
+            # Check if child or parent
+            test rax, rax
+            jnz .spawn_parent
+
+            # Load function and argument
+            pop rax
+            pop rdi
+
+            # Call function
+            call rax
+
+            # Exit with its return value
+            mov rdi, rax
+            mov rax, 1
+            syscall
+
+            # Invalid instruction on failure to exit
+            ud2
+
+            # Return PID if parent
+            .spawn_parent:
+            "
+            : "={rax}"(pid)
+            : "{rax}"(syscall::SYS_CLONE), "{rdi}"(flags), "{rsi}"(stack)
+            : "memory", "rbx", "rcx", "rdx", "rsi", "rdi", "r8",
+              "r9", "r10", "r11", "r12", "r13", "r14", "r15"
+            : "intel", "volatile"
+        );
+        e(syscall::Error::demux(pid)) as pid_t
+    }
+
     fn symlink(path1: &CStr, path2: &CStr) -> c_int {
         let mut file = match File::create(
             path2,
@@ -974,6 +1210,32 @@ impl Pal for Sys {
         e(syscall::unlink(path.to_bytes())) as c_int
     }
 
+    #[cfg(target_arch = "x86_64")]
+    #[naked]
+    unsafe extern "C" fn vfork() -> pid_t {
+        // Without CLONE_STACK the child gets a copy of the stack, but the
+        // return address is still kept in a register like on Linux, so that
+        // nothing of the parent's stack is relied on
+        llvm_asm!("
+            pop rdx
+
+            # clone(CLONE_VM | CLONE_VFORK)
+            mov eax, 120
+            mov edi, 0x4100
+            syscall
+
+            push rdx
+            mov rdi, rax
+            jmp __relibc_vfork_ret@PLT
+            "
+            :
+            :
+            :
+            : "intel", "volatile"
+        );
+        intrinsics::unreachable()
+    }
+
     fn waitpid(mut pid: pid_t, stat_loc: *mut c_int, options: c_int) -> pid_t {
         if pid == !0 {
             pid = 0;
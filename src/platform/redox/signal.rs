@@ -60,6 +60,11 @@ impl PalSignal for Sys {
         Self::kill(Self::getpid(), sig)
     }
 
+    fn rlct_kill(os_tid: pid_t, sig: c_int) -> c_int {
+        // Threads are contexts with their own ID
+        e(syscall::kill(os_tid as usize, sig as usize)) as c_int
+    }
+
     fn setitimer(which: c_int, new: *const itimerval, old: *mut itimerval) -> c_int {
         let path = match which {
             ITIMER_REAL => "itimer:1",
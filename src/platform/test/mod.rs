@@ -1,9 +1,5 @@
 use crate::platform::{Pal, Sys};
 
-// Stub for call used in exit
-#[no_mangle]
-pub extern "C" fn pthread_terminate() {}
-
 mod epoll;
 
 #[test]
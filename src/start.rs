@@ -2,7 +2,10 @@ use alloc::vec::Vec;
 use core::{intrinsics, ptr};
 
 use crate::{
-    header::{stdio, stdlib},
+    header::{
+        pthread, stdio, stdlib,
+        sys_auxv::{self, AT_RANDOM},
+    },
     ld_so,
     platform::{self, new_mspace, types::*, Pal, Sys},
     ALLOCATOR,
@@ -61,6 +64,39 @@ pub unsafe fn relibc_verify_host() {
         intrinsics::abort();
     }
 }
+
+/// Keeps the auxiliary vector for getauxval, and seeds the stack protector
+/// canary from the random bytes the kernel put in it. This has to run before
+/// any code compiled with the stack protector, as a function that returns
+/// after the canary changes looks like it smashed its stack. With the
+/// hardened_malloc feature it also seeds the secret of the allocator, so it
+/// has to run before the first allocation too.
+pub unsafe fn init_auxv(auxv: *const (usize, usize)) {
+    extern "C" {
+        static mut __stack_chk_guard: usize;
+    }
+
+    platform::auxv = auxv;
+    if let Some(random) = sys_auxv::auxval(AT_RANDOM) {
+        // The zero low byte stops string functions from reading or writing
+        // past it into the rest of the canary
+        __stack_chk_guard = ptr::read_unaligned(random as *const usize) & !0xff;
+    }
+
+    #[cfg(feature = "hardened_malloc")]
+    {
+        // The allocator gets the other half of the random bytes
+        let mut secret = [0; core::mem::size_of::<usize>()];
+        match sys_auxv::auxval(AT_RANDOM) {
+            Some(random) => secret = ptr::read_unaligned((random + 8) as *const _),
+            None => {
+                Sys::getrandom(&mut secret, 0);
+            }
+        }
+        platform::init_secret(usize::from_ne_bytes(secret));
+    }
+}
+
 #[link_section = ".init_array"]
 #[used]
 static INIT_ARRAY: [extern "C" fn(); 1] = [init_array];
@@ -108,7 +144,6 @@ pub unsafe extern "C" fn relibc_start(sp: &'static Stack) -> ! {
         static __init_array_start: extern "C" fn();
         static __init_array_end: extern "C" fn();
 
-        fn pthread_init();
         fn _init();
         fn main(argc: isize, argv: *mut *mut c_char, envp: *mut *mut c_char) -> c_int;
     }
@@ -116,6 +151,8 @@ pub unsafe extern "C" fn relibc_start(sp: &'static Stack) -> ! {
     // Ensure correct host system before executing more system calls
     relibc_verify_host();
 
+    init_auxv(sp.auxv());
+
     // Initialize TLS, if necessary
     ld_so::init(sp);
 
@@ -141,7 +178,7 @@ pub unsafe extern "C" fn relibc_start(sp: &'static Stack) -> ! {
     if !init_complete {
         init_array();
     }
-    pthread_init();
+    pthread::init();
 
     // Run preinit array
     {
@@ -0,0 +1,41 @@
+use super::{AtomicLock, Mutex};
+use crate::platform::types::*;
+use core::sync::atomic::Ordering::SeqCst;
+
+pub struct Barrier {
+    /// The number of threads waiting, and how many to wait for
+    lock: Mutex<(c_uint, c_uint)>,
+    /// Bumped every time the barrier is passed
+    generation: AtomicLock,
+}
+unsafe impl Send for Barrier {}
+unsafe impl Sync for Barrier {}
+impl Barrier {
+    /// Create a barrier for `count` threads, which must not be zero
+    pub const fn new(count: c_uint) -> Self {
+        Self {
+            lock: Mutex::new((0, count)),
+            generation: AtomicLock::new(0),
+        }
+    }
+    /// Waits until `count` threads are waiting. Returns true in exactly one
+    /// of them.
+    pub fn wait(&self) -> bool {
+        let mut lock = self.lock.lock();
+        lock.0 += 1;
+        if lock.0 == lock.1 {
+            // The last thread to arrive releases the others
+            lock.0 = 0;
+            self.generation.fetch_add(1, SeqCst);
+            self.generation.notify_all();
+            return true;
+        }
+        let generation = self.generation.load(SeqCst);
+        drop(lock);
+
+        while self.generation.load(SeqCst) == generation {
+            self.generation.wait_if(generation);
+        }
+        false
+    }
+}
@@ -0,0 +1,115 @@
+use super::{AtomicLock, MutexGuard};
+use crate::{
+    header::time::{timespec, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    platform::types::*,
+};
+use core::sync::atomic::Ordering::SeqCst;
+
+/// A condition variable. All zeroes is a valid one timing its waits
+/// against CLOCK_REALTIME.
+pub struct Condvar {
+    /// Bumped by every notification, so a waiter that unlocked its mutex
+    /// cannot miss one
+    seq: AtomicLock,
+    monotonic: c_int,
+}
+unsafe impl Send for Condvar {}
+unsafe impl Sync for Condvar {}
+impl Condvar {
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicLock::new(0),
+            monotonic: 0,
+        }
+    }
+    /// Create a condition variable timing its waits against `clock`, which
+    /// is CLOCK_REALTIME or CLOCK_MONOTONIC
+    pub const fn with_clock(clock: clockid_t) -> Self {
+        Self {
+            seq: AtomicLock::new(0),
+            monotonic: (clock == CLOCK_MONOTONIC) as c_int,
+        }
+    }
+    pub fn clock(&self) -> clockid_t {
+        if self.monotonic != 0 {
+            CLOCK_MONOTONIC
+        } else {
+            CLOCK_REALTIME
+        }
+    }
+
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, SeqCst);
+        self.seq.notify_one();
+    }
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, SeqCst);
+        self.seq.notify_all();
+    }
+
+    /// The general form of waiting, for mutexes other than `Mutex`: `unlock`
+    /// is called once no notification can be missed, and if it succeeds
+    /// `lock` is called after waking up, even on timeout. Like all waits,
+    /// this may wake up spuriously.
+    pub fn wait_with<U, L>(
+        &self,
+        unlock: U,
+        lock: L,
+        abstime: Option<&timespec>,
+    ) -> Result<(), c_int>
+    where
+        U: FnOnce() -> Result<(), c_int>,
+        L: FnOnce(),
+    {
+        let seq = self.seq.load(SeqCst);
+        unlock()?;
+
+        let ret = match abstime {
+            Some(abstime) => self.seq.wait_if_until(seq, self.clock(), abstime),
+            None => {
+                self.seq.wait_if(seq);
+                Ok(())
+            }
+        };
+
+        lock();
+        ret
+    }
+    /// Unlock `guard` and wait for a notification, locking it again before
+    /// returning
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex;
+        let _ = self.wait_with(
+            || {
+                drop(guard);
+                Ok(())
+            },
+            || (),
+            None,
+        );
+        mutex.lock()
+    }
+    /// Like `wait`, but gives up with ETIMEDOUT once the clock reaches
+    /// `abstime`
+    pub fn wait_until<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        abstime: &timespec,
+    ) -> (MutexGuard<'a, T>, Result<(), c_int>) {
+        let mutex = guard.mutex;
+        let ret = self.wait_with(
+            || {
+                drop(guard);
+                Ok(())
+            },
+            || (),
+            Some(abstime),
+        );
+        (mutex.lock(), ret)
+    }
+}
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
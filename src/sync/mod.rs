@@ -1,23 +1,41 @@
+pub mod barrier;
+pub mod condvar;
 pub mod mutex;
 pub mod once;
+pub mod rwlock;
+pub mod semaphore;
 
 pub use self::{
+    barrier::Barrier,
+    condvar::Condvar,
     mutex::{Mutex, MutexGuard},
     once::Once,
+    rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    semaphore::Semaphore,
 };
 
-use crate::platform::{types::*, Pal, Sys};
+use crate::{
+    header::{
+        errno::{EINTR, EINVAL, ETIMEDOUT},
+        time::{timespec, CLOCK_MONOTONIC, CLOCK_REALTIME},
+    },
+    platform::{
+        futex::{
+            FUTEX_BITSET_MATCH_ANY, FUTEX_CLOCK_REALTIME, FUTEX_WAIT, FUTEX_WAIT_BITSET, FUTEX_WAKE,
+        },
+        types::*,
+        Pal, Sys,
+    },
+};
 use core::{
     cell::UnsafeCell,
     ops::Deref,
+    ptr,
     sync::atomic::{self, AtomicI32 as AtomicInt},
 };
 
-const FUTEX_WAIT: c_int = 0;
-const FUTEX_WAKE: c_int = 1;
-
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum AttemptStatus {
+pub(crate) enum AttemptStatus {
     Desired,
     Waiting,
     Other,
@@ -25,7 +43,7 @@ enum AttemptStatus {
 
 /// Convenient wrapper around the "futex" system call for
 /// synchronization implementations
-struct AtomicLock {
+pub(crate) struct AtomicLock {
     atomic: UnsafeCell<AtomicInt>,
 }
 impl AtomicLock {
@@ -34,22 +52,75 @@ impl AtomicLock {
             atomic: UnsafeCell::new(AtomicInt::new(value)),
         }
     }
-    pub fn notify_one(&self) {
-        Sys::futex(unsafe { &mut *self.atomic.get() }.get_mut(), FUTEX_WAKE, 1);
+    /// The futex word, for waking waiters from assembly
+    pub fn as_mut_ptr(&self) -> *mut c_int {
+        unsafe { &mut *self.atomic.get() }.get_mut()
+    }
+    /// Wakes one waiter, returning whether there was one
+    pub fn notify_one(&self) -> bool {
+        Sys::futex(self.as_mut_ptr(), FUTEX_WAKE, 1, 0, ptr::null_mut(), 0) > 0
     }
     pub fn notify_all(&self) {
         Sys::futex(
-            unsafe { &mut *self.atomic.get() }.get_mut(),
+            self.as_mut_ptr(),
             FUTEX_WAKE,
             c_int::max_value(),
+            0,
+            ptr::null_mut(),
+            0,
         );
     }
     pub fn wait_if(&self, value: c_int) {
-        Sys::futex(
-            unsafe { &mut *self.atomic.get() }.get_mut(),
-            FUTEX_WAIT,
-            value,
-        );
+        Sys::futex(self.as_mut_ptr(), FUTEX_WAIT, value, 0, ptr::null_mut(), 0);
+    }
+    /// Like `wait_if`, but gives up with ETIMEDOUT once `clock`, which is
+    /// CLOCK_REALTIME or CLOCK_MONOTONIC, reaches `abstime`. Returns EINVAL
+    /// for other clocks or if `abstime` is not a valid time.
+    pub fn wait_if_until(
+        &self,
+        value: c_int,
+        clock: clockid_t,
+        abstime: &timespec,
+    ) -> Result<(), c_int> {
+        match self.wait_if_interruptible(value, Some((clock, abstime))) {
+            Err(EINTR) => Ok(()),
+            ret => ret,
+        }
+    }
+    /// Like `wait_if`, or `wait_if_until` given a deadline, but fails with
+    /// EINTR if a signal handler interrupts the wait
+    pub fn wait_if_interruptible(
+        &self,
+        value: c_int,
+        deadline: Option<(clockid_t, &timespec)>,
+    ) -> Result<(), c_int> {
+        let ret = match deadline {
+            Some((clock, abstime)) => {
+                if abstime.tv_nsec < 0 || abstime.tv_nsec >= 1_000_000_000 {
+                    return Err(EINVAL);
+                }
+                let op = match clock {
+                    CLOCK_REALTIME => FUTEX_WAIT_BITSET | FUTEX_CLOCK_REALTIME,
+                    CLOCK_MONOTONIC => FUTEX_WAIT_BITSET,
+                    _ => return Err(EINVAL),
+                };
+                Sys::futex(
+                    self.as_mut_ptr(),
+                    op,
+                    value,
+                    abstime as *const timespec as usize,
+                    ptr::null_mut(),
+                    FUTEX_BITSET_MATCH_ANY,
+                )
+            }
+            None => Sys::futex(self.as_mut_ptr(), FUTEX_WAIT, value, 0, ptr::null_mut(), 0),
+        };
+
+        match -ret {
+            err @ ETIMEDOUT | err @ EINTR => Err(err),
+            // Woken or the value changed
+            _ => Ok(()),
+        }
     }
     /// A general way to efficiently wait for what might be a long time, using two closures:
     ///
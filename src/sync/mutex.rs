@@ -1,9 +1,9 @@
-use super::{AtomicLock, AttemptStatus};
-use crate::platform::types::*;
+use super::AtomicLock;
+use crate::{header::time::timespec, platform::types::*};
 use core::{
     cell::UnsafeCell,
     ops::{Deref, DerefMut},
-    sync::atomic::Ordering::SeqCst,
+    sync::atomic::{self, Ordering::SeqCst},
 };
 
 const UNLOCKED: c_int = 0;
@@ -50,27 +50,36 @@ impl<T> Mutex<T> {
     /// your responsibility to unlock it after usage. Mostly useful for FFI:
     /// Prefer normal .lock() where possible.
     pub unsafe fn manual_lock(&self) -> &mut T {
-        self.lock.wait_until(
-            |lock| {
-                lock.compare_exchange_weak(UNLOCKED, LOCKED, SeqCst, SeqCst)
-                    .map(|_| AttemptStatus::Desired)
-                    .unwrap_or_else(|e| match e {
-                        WAITING => AttemptStatus::Waiting,
-                        _ => AttemptStatus::Other,
-                    })
-            },
-            |lock| match lock
-                .compare_exchange_weak(LOCKED, WAITING, SeqCst, SeqCst)
-                .unwrap_or_else(|e| e)
-            {
-                UNLOCKED => AttemptStatus::Desired,
-                WAITING => AttemptStatus::Waiting,
-                _ => AttemptStatus::Other,
-            },
-            WAITING,
-        );
+        // First, try spinning for really short durations
+        for _ in 0..999 {
+            if let Ok(content) = self.manual_try_lock() {
+                return content;
+            }
+            atomic::spin_loop_hint();
+        }
+
+        // Once we have slept there may be other sleepers we can't see, so
+        // the lock is taken as WAITING to have the unlock wake them
+        while self.lock.swap(WAITING, SeqCst) != UNLOCKED {
+            self.lock.wait_if(WAITING);
+        }
         &mut *self.content.get()
     }
+    /// Like `manual_lock`, but gives up once `clock` reaches `abstime`,
+    /// returning ETIMEDOUT.
+    pub unsafe fn manual_lock_until(
+        &self,
+        clock: clockid_t,
+        abstime: &timespec,
+    ) -> Result<&mut T, c_int> {
+        if let Ok(content) = self.manual_try_lock() {
+            return Ok(content);
+        }
+        while self.lock.swap(WAITING, SeqCst) != UNLOCKED {
+            self.lock.wait_if_until(WAITING, clock, abstime)?;
+        }
+        Ok(&mut *self.content.get())
+    }
     /// Unlock the mutex, if it's locked.
     pub unsafe fn manual_unlock(&self) {
         if self.lock.swap(UNLOCKED, SeqCst) == WAITING {
@@ -99,7 +108,7 @@ impl<T> Mutex<T> {
 }
 
 pub struct MutexGuard<'a, T: 'a> {
-    mutex: &'a Mutex<T>,
+    pub(super) mutex: &'a Mutex<T>,
     content: &'a mut T,
 }
 impl<'a, T> Deref for MutexGuard<'a, T> {
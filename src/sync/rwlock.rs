@@ -0,0 +1,390 @@
+use super::AtomicLock;
+use crate::{
+    header::{
+        errno::{EAGAIN, EBUSY, EPERM},
+        time::timespec,
+    },
+    platform::types::*,
+};
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{self, Ordering::SeqCst},
+};
+
+// The low bits of the state count readers, or are all set while write
+// locked. The top two bits are set while readers or writers wait.
+const MASK: c_int = (1 << 30) - 1;
+const READ_LOCKED: c_int = 1;
+const WRITE_LOCKED: c_int = MASK;
+const MAX_READERS: c_int = MASK - 1;
+const READERS_WAITING: c_int = 1 << 30;
+const WRITERS_WAITING: c_int = 1 << 31;
+
+fn is_unlocked(state: c_int) -> bool {
+    state & MASK == 0
+}
+fn is_write_locked(state: c_int) -> bool {
+    state & MASK == WRITE_LOCKED
+}
+fn has_readers_waiting(state: c_int) -> bool {
+    state & READERS_WAITING != 0
+}
+fn has_writers_waiting(state: c_int) -> bool {
+    state & WRITERS_WAITING != 0
+}
+/// New readers wait behind writers, so writers can't starve
+fn is_read_lockable(state: c_int) -> bool {
+    state & MASK < MAX_READERS && !has_readers_waiting(state) && !has_writers_waiting(state)
+}
+
+/// A writer-preferring reader-writer lock. All zeroes is an unlocked one.
+pub struct RwLock<T> {
+    state: AtomicLock,
+    /// Bumped whenever a writer is woken, which writers sleep on
+    writer_notify: AtomicLock,
+    content: UnsafeCell<T>,
+}
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+impl<T> RwLock<T> {
+    pub const fn new(content: T) -> Self {
+        Self {
+            state: AtomicLock::new(0),
+            writer_notify: AtomicLock::new(0),
+            content: UnsafeCell::new(content),
+        }
+    }
+
+    /// Tries to read lock, returning the state that blocked it on failure
+    fn try_read_state(&self) -> Result<(), c_int> {
+        let mut state = self.state.load(SeqCst);
+        while is_read_lockable(state) {
+            match self
+                .state
+                .compare_exchange_weak(state, state + READ_LOCKED, SeqCst, SeqCst)
+            {
+                Ok(_) => return Ok(()),
+                Err(value) => state = value,
+            }
+        }
+        Err(state)
+    }
+    /// Spins a short while, as long as the lock is held without anyone
+    /// waiting for it
+    fn spin(&self, done: impl Fn(c_int) -> bool) -> c_int {
+        let mut state = self.state.load(SeqCst);
+        for _ in 0..100 {
+            if done(state) || has_readers_waiting(state) || has_writers_waiting(state) {
+                break;
+            }
+            atomic::spin_loop_hint();
+            state = self.state.load(SeqCst);
+        }
+        state
+    }
+
+    /// Tries to read lock without blocking. Fails with EAGAIN if there are
+    /// too many readers, and with EBUSY if it is write locked or a writer is
+    /// waiting.
+    pub unsafe fn manual_try_read(&self) -> Result<&T, c_int> {
+        match self.try_read_state() {
+            Ok(()) => Ok(&*self.content.get()),
+            Err(state) if state & MASK == MAX_READERS => Err(EAGAIN),
+            Err(_) => Err(EBUSY),
+        }
+    }
+    /// Read locks, waiting at most until `clock` reaches `abstime` if
+    /// given. Fails with EAGAIN if there are too many readers.
+    pub unsafe fn manual_read(
+        &self,
+        deadline: Option<(clockid_t, &timespec)>,
+    ) -> Result<&T, c_int> {
+        let mut state = match self.try_read_state() {
+            Ok(()) => return Ok(&*self.content.get()),
+            Err(_) => self.spin(|state| !is_write_locked(state)),
+        };
+        loop {
+            if is_read_lockable(state) {
+                match self
+                    .state
+                    .compare_exchange_weak(state, state + READ_LOCKED, SeqCst, SeqCst)
+                {
+                    Ok(_) => return Ok(&*self.content.get()),
+                    Err(value) => {
+                        state = value;
+                        continue;
+                    }
+                }
+            }
+            if state & MASK == MAX_READERS {
+                return Err(EAGAIN);
+            }
+
+            // Make sure whoever unlocks knows to wake us
+            if !has_readers_waiting(state) {
+                if let Err(value) =
+                    self.state
+                        .compare_exchange(state, state | READERS_WAITING, SeqCst, SeqCst)
+                {
+                    state = value;
+                    continue;
+                }
+            }
+
+            match deadline {
+                Some((clock, abstime)) => {
+                    self.state
+                        .wait_if_until(state | READERS_WAITING, clock, abstime)?
+                }
+                None => self.state.wait_if(state | READERS_WAITING),
+            }
+            state = self.spin(|state| !is_write_locked(state));
+        }
+    }
+
+    /// Tries to write lock without blocking, failing with EBUSY if it is
+    /// locked
+    pub unsafe fn manual_try_write(&self) -> Result<&mut T, c_int> {
+        let mut state = self.state.load(SeqCst);
+        while is_unlocked(state) {
+            match self
+                .state
+                .compare_exchange_weak(state, state | WRITE_LOCKED, SeqCst, SeqCst)
+            {
+                Ok(_) => return Ok(&mut *self.content.get()),
+                Err(value) => state = value,
+            }
+        }
+        Err(EBUSY)
+    }
+    /// Write locks, waiting at most until `clock` reaches `abstime` if
+    /// given
+    pub unsafe fn manual_write(
+        &self,
+        deadline: Option<(clockid_t, &timespec)>,
+    ) -> Result<&mut T, c_int> {
+        if let Ok(content) = self.manual_try_write() {
+            return Ok(content);
+        }
+        let mut state = self.spin(is_unlocked);
+        // Once we have waited, other writers may be waiting too, and the
+        // bit has to stay set for them when we get the lock
+        let mut other_writers_waiting = 0;
+        loop {
+            if is_unlocked(state) {
+                match self.state.compare_exchange_weak(
+                    state,
+                    state | WRITE_LOCKED | other_writers_waiting,
+                    SeqCst,
+                    SeqCst,
+                ) {
+                    Ok(_) => return Ok(&mut *self.content.get()),
+                    Err(value) => {
+                        state = value;
+                        continue;
+                    }
+                }
+            }
+
+            if !has_writers_waiting(state) {
+                if let Err(value) =
+                    self.state
+                        .compare_exchange(state, state | WRITERS_WAITING, SeqCst, SeqCst)
+                {
+                    state = value;
+                    continue;
+                }
+            }
+            other_writers_waiting = WRITERS_WAITING;
+
+            // Take note of the notifications before looking at the state,
+            // so none are missed
+            let seq = self.writer_notify.load(SeqCst);
+            state = self.state.load(SeqCst);
+            if is_unlocked(state) || !has_writers_waiting(state) {
+                continue;
+            }
+
+            match deadline {
+                Some((clock, abstime)) => {
+                    if let Err(err) = self.writer_notify.wait_if_until(seq, clock, abstime) {
+                        self.give_up_write();
+                        return Err(err);
+                    }
+                }
+                None => self.writer_notify.wait_if(seq),
+            }
+            state = self.spin(is_unlocked);
+        }
+    }
+
+    /// Unlocks a read or write lock held by the caller, failing with EPERM
+    /// if it is not locked
+    pub unsafe fn manual_unlock(&self) -> Result<(), c_int> {
+        let state = self.state.load(SeqCst);
+        if is_write_locked(state) {
+            let state = self.state.fetch_sub(WRITE_LOCKED, SeqCst) - WRITE_LOCKED;
+            if has_readers_waiting(state) || has_writers_waiting(state) {
+                self.wake_writer_or_readers(state);
+            }
+        } else if !is_unlocked(state) {
+            let state = self.state.fetch_sub(READ_LOCKED, SeqCst) - READ_LOCKED;
+            // Readers only wait on a read locked lock if a writer does too
+            if is_unlocked(state) && has_writers_waiting(state) {
+                self.wake_writer_or_readers(state);
+            }
+        } else {
+            return Err(EPERM);
+        }
+        Ok(())
+    }
+
+    /// Wakes up one writer if there is one, and all readers otherwise. If
+    /// the lock is taken in the meantime, whoever took it wakes them
+    /// instead.
+    fn wake_writer_or_readers(&self, mut state: c_int) {
+        if state == WRITERS_WAITING {
+            match self.state.compare_exchange(state, 0, SeqCst, SeqCst) {
+                Ok(_) => {
+                    self.wake_writer();
+                    return;
+                }
+                Err(value) => state = value,
+            }
+        }
+
+        // Readers keep waiting while there is a writer to wake up
+        if state == READERS_WAITING | WRITERS_WAITING {
+            if self
+                .state
+                .compare_exchange(state, READERS_WAITING, SeqCst, SeqCst)
+                .is_err()
+            {
+                return;
+            }
+            if self.wake_writer() {
+                return;
+            }
+            // No writer was asleep, so don't leave the readers waiting for
+            // one
+            state = READERS_WAITING;
+        }
+
+        if state == READERS_WAITING
+            && self
+                .state
+                .compare_exchange(state, 0, SeqCst, SeqCst)
+                .is_ok()
+        {
+            self.state.notify_all();
+        }
+    }
+    /// Called by a writer that stops waiting. Readers may be waiting only
+    /// for this writer, and it may have been woken in place of another, so
+    /// everyone is woken up. Writers that are still waiting set their bit
+    /// again before they sleep.
+    fn give_up_write(&self) {
+        let state = self
+            .state
+            .fetch_and(!(READERS_WAITING | WRITERS_WAITING), SeqCst);
+        if has_readers_waiting(state) {
+            self.state.notify_all();
+        }
+        if has_writers_waiting(state) {
+            self.writer_notify.fetch_add(1, SeqCst);
+            self.writer_notify.notify_all();
+        }
+    }
+    fn wake_writer(&self) -> bool {
+        self.writer_notify.fetch_add(1, SeqCst);
+        self.writer_notify.notify_one()
+    }
+
+    /// Read locks and returns a guard that automatically unlocks it when it
+    /// falls out of scope
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        unsafe {
+            let content = self.manual_read(None).expect("too many readers of RwLock");
+            RwLockReadGuard {
+                lock: self,
+                content,
+            }
+        }
+    }
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        unsafe {
+            self.manual_try_read().ok().map(|content| RwLockReadGuard {
+                lock: self,
+                content,
+            })
+        }
+    }
+    /// Write locks and returns a guard that automatically unlocks it when
+    /// it falls out of scope
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        unsafe {
+            let content = match self.manual_write(None) {
+                Ok(content) => content,
+                Err(_) => unreachable!("untimed write lock failed"),
+            };
+            RwLockWriteGuard {
+                lock: self,
+                content,
+            }
+        }
+    }
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        unsafe {
+            self.manual_try_write()
+                .ok()
+                .map(|content| RwLockWriteGuard {
+                    lock: self,
+                    content,
+                })
+        }
+    }
+}
+
+pub struct RwLockReadGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+    content: &'a T,
+}
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.content
+    }
+}
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.lock.manual_unlock();
+        }
+    }
+}
+
+pub struct RwLockWriteGuard<'a, T: 'a> {
+    lock: &'a RwLock<T>,
+    content: &'a mut T,
+}
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.content
+    }
+}
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.content
+    }
+}
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.lock.manual_unlock();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::AtomicLock;
+use crate::{
+    header::{
+        errno::{EAGAIN, EOVERFLOW},
+        time::timespec,
+    },
+    platform::types::*,
+};
+use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+
+/// A counting semaphore. All zeroes is one with a count of zero.
+pub struct Semaphore {
+    count: AtomicLock,
+    /// The number of threads waiting for the count to go up, so posting
+    /// can skip waking nobody
+    waiters: AtomicU32,
+}
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+impl Semaphore {
+    pub const fn new(count: c_int) -> Self {
+        Self {
+            count: AtomicLock::new(count),
+            waiters: AtomicU32::new(0),
+        }
+    }
+    pub fn value(&self) -> c_int {
+        self.count.load(SeqCst)
+    }
+
+    /// Increments the count, waking a waiter. Fails with EOVERFLOW if the
+    /// count would overflow.
+    pub fn post(&self) -> Result<(), c_int> {
+        let mut count = self.count.load(SeqCst);
+        loop {
+            if count == c_int::max_value() {
+                return Err(EOVERFLOW);
+            }
+            match self
+                .count
+                .compare_exchange_weak(count, count + 1, SeqCst, SeqCst)
+            {
+                Ok(_) => break,
+                Err(value) => count = value,
+            }
+        }
+        if self.waiters.load(SeqCst) > 0 {
+            self.count.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Decrements the count if it is above zero, failing with EAGAIN
+    /// otherwise
+    pub fn try_wait(&self) -> Result<(), c_int> {
+        let mut count = self.count.load(SeqCst);
+        while count > 0 {
+            match self
+                .count
+                .compare_exchange_weak(count, count - 1, SeqCst, SeqCst)
+            {
+                Ok(_) => return Ok(()),
+                Err(value) => count = value,
+            }
+        }
+        Err(EAGAIN)
+    }
+    /// Waits until the count can be decremented, or at most until `clock`
+    /// reaches `abstime` if given. Fails with EINTR, leaving the count
+    /// alone, if a signal handler interrupts the wait.
+    pub fn wait(&self, deadline: Option<(clockid_t, &timespec)>) -> Result<(), c_int> {
+        loop {
+            if self.try_wait().is_ok() {
+                return Ok(());
+            }
+
+            self.waiters.fetch_add(1, SeqCst);
+            let ret = self.count.wait_if_interruptible(0, deadline);
+            self.waiters.fetch_sub(1, SeqCst);
+            ret?;
+        }
+    }
+}
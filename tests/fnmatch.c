@@ -1,4 +1,5 @@
 #include <fnmatch.h>
+#include <locale.h>
 #include <stdio.h>
 
 #include "test_helpers.h"
@@ -12,6 +13,8 @@ void test(char* pattern, char* input, int flags) {
 }
 
 int main(void) {
+    setlocale(LC_ALL, "C.UTF-8");
+
     puts("Should succeed:");
     test("*World", "Hello World", 0);
     test("*World", "World", 0);
@@ -44,4 +47,87 @@ int main(void) {
     test("[a!][a!]", "ab", 0);
     test("hello[/+]world", "hello/world", FNM_PATHNAME);
     test("hello world", "HELLO WORLD", 0);
+
+    puts("");
+    puts("Escapes:");
+    test("\\*", "*", 0);
+    test("\\*", "a", 0);
+    test("\\*", "\\a", FNM_NOESCAPE);
+    test("a\\", "a\\", 0);
+
+    puts("");
+    puts("Bracket expressions:");
+    test("[[:alpha:]][[:digit:]]", "a1", 0);
+    test("[[:alpha:]][[:digit:]]", "1a", 0);
+    test("[[:upper:][:space:]]*", " x", 0);
+    test("[![:punct:]]", "!", 0);
+    test("[[:xdigit:]]", "F", 0);
+    test("[[:alnum:]_]*", "foo_1", 0);
+    test("[[:foo:]]", "f", 0);
+    test("[[:foo:]]", "[[:foo:]]", 0);
+    test("[[=a=]]b", "ab", 0);
+    test("[[=a=]]b", "bb", 0);
+    test("[[.-.]a]", "-", 0);
+    test("[[.a.]-c]", "b", 0);
+    test("[]a]", "]", 0);
+    test("[!]a]", "]", 0);
+    test("[a-", "[a-", 0);
+    test("[/]", "/", FNM_PATHNAME);
+    test("[.]*", ".x", FNM_PERIOD);
+    test("[A-Z]", "q", FNM_CASEFOLD);
+
+    puts("");
+    puts("Multibyte:");
+    test("?", "\u00e9", 0);
+    test("??", "\u00e9", 0);
+    test("caf?", "caf\u00e9", 0);
+    test("[\u00e0-\u00ff]", "\u00e9", 0);
+    test("[[:alpha:]]*", "\u00e9t\u00e9", 0);
+    test("[[:upper:]]", "\u00c9", 0);
+    test("\u00e9*", "\u00c9T\u00c9", FNM_CASEFOLD);
+    test("*\u2603*", "snow \u2603 man", 0);
+
+    puts("");
+    puts("FNM_LEADING_DIR:");
+    test("a/b", "a/b/c", FNM_LEADING_DIR);
+    test("a/*", "a/b/c", FNM_LEADING_DIR | FNM_PATHNAME);
+    test("a", "a/", FNM_LEADING_DIR);
+    test("a", "ab", FNM_LEADING_DIR);
+    test("a/b", "a/b/c", 0);
+
+    puts("");
+    puts("FNM_EXTMATCH:");
+    test("@(foo|bar)", "foo", FNM_EXTMATCH);
+    test("@(foo|bar)", "baz", FNM_EXTMATCH);
+    test("@(foo|bar)", "foobar", FNM_EXTMATCH);
+    test("+(foo|bar)", "foobarfoo", FNM_EXTMATCH);
+    test("+(foo|bar)", "", FNM_EXTMATCH);
+    test("*(foo|bar)", "", FNM_EXTMATCH);
+    test("*(foo|bar)x", "barx", FNM_EXTMATCH);
+    test("?(foo)bar", "bar", FNM_EXTMATCH);
+    test("?(foo)bar", "foobar", FNM_EXTMATCH);
+    test("?(foo)bar", "foofoobar", FNM_EXTMATCH);
+    test("!(*.o)", "main.c", FNM_EXTMATCH);
+    test("!(*.o)", "main.o", FNM_EXTMATCH);
+    test("!(foo)", "", FNM_EXTMATCH);
+    test("!(foo)bar", "foobar", FNM_EXTMATCH);
+    test("!(foo)bar", "xbar", FNM_EXTMATCH);
+    test("a!(x)b", "axxb", FNM_EXTMATCH);
+    test("@(a|@(b|c))d", "cd", FNM_EXTMATCH);
+    test("*(+(a)b)", "aabab", FNM_EXTMATCH);
+    test("@([[:digit:]]|x)", "7", FNM_EXTMATCH);
+    test("@(a\\|b)", "a|b", FNM_EXTMATCH);
+    test("@(foo", "@(foo", FNM_EXTMATCH);
+    test("@(foo)", "@(foo)", 0);
+    test("*(foo)/bar", "foofoo/bar", FNM_EXTMATCH | FNM_PATHNAME);
+    test("!(x)", "a/b", FNM_EXTMATCH | FNM_PATHNAME);
+    test("!(x)", ".a", FNM_EXTMATCH | FNM_PERIOD);
+    test("@(.a|b)", ".a", FNM_EXTMATCH | FNM_PERIOD);
+    test("@(*a|b)", ".a", FNM_EXTMATCH | FNM_PERIOD);
+    test("@(FOO|bar)", "foo", FNM_EXTMATCH | FNM_CASEFOLD);
+
+    // Nested repetitions that fail at the very end shouldn't take time
+    // exponential in the length of the input
+    test("+(a|aa)", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab", FNM_EXTMATCH);
+    test("*(+(a|aa))*a*a*a*c", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab", FNM_EXTMATCH);
 }
@@ -0,0 +1,129 @@
+#include <fcntl.h>
+#include <setjmp.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include <wchar.h>
+
+#include "test_helpers.h"
+
+// Tests are not built with optimizations, so the fortified wrappers are not
+// used and the checked functions have to be called by hand
+void *__memcpy_chk(void *dest, const void *src, size_t len, size_t destlen);
+char *__strcpy_chk(char *dest, const char *src, size_t destlen);
+char *__strncat_chk(char *dest, const char *src, size_t len, size_t destlen);
+int __snprintf_chk(char *s, size_t maxlen, int flag, size_t slen, const char *format, ...);
+int __sprintf_chk(char *s, int flag, size_t slen, const char *format, ...);
+ssize_t __read_chk(int fd, void *buf, size_t nbytes, size_t buflen);
+wchar_t *__wmemcpy_chk(wchar_t *dest, const wchar_t *src, size_t n, size_t destlen);
+int __open_2(const char *path, int oflag);
+void __longjmp_chk(jmp_buf env, int val);
+
+static jmp_buf env;
+
+void memcpy_overflow(void) {
+    char buf[4];
+    __memcpy_chk(buf, "abcdef", 6, sizeof(buf));
+}
+
+void strcpy_overflow(void) {
+    char buf[4];
+    __strcpy_chk(buf, "abcd", sizeof(buf));
+}
+
+void strncat_overflow(void) {
+    char buf[4] = "ab";
+    __strncat_chk(buf, "cdef", 2, sizeof(buf));
+}
+
+void sprintf_overflow(void) {
+    char buf[4];
+    __sprintf_chk(buf, 1, sizeof(buf), "%d", 1234);
+}
+
+void snprintf_overflow(void) {
+    char buf[4];
+    // Claiming more room than there is aborts, even if the output would fit
+    __snprintf_chk(buf, 8, 1, sizeof(buf), "%d", 1);
+}
+
+void read_overflow(void) {
+    char buf[4];
+    int fd = open("/dev/zero", O_RDONLY);
+    __read_chk(fd, buf, 8, sizeof(buf));
+}
+
+void wmemcpy_overflow(void) {
+    wchar_t buf[2];
+    __wmemcpy_chk(buf, L"abc", 3, 2);
+}
+
+void open_without_mode(void) {
+    __open_2("/tmp/fortify_open", O_WRONLY | O_CREAT);
+}
+
+void jump_up(void) {
+    __longjmp_chk(env, 1);
+}
+
+// Leaves env pointing into a frame that is gone once it returns, and deep
+// enough to be below that of __longjmp_chk
+void set_returned(void) {
+    char pad[1024];
+    memset(pad, 0, sizeof(pad));
+    setjmp(env);
+}
+
+void jump_down(void) {
+    set_returned();
+    __longjmp_chk(env, 1);
+}
+
+int main(void) {
+    char buf[8];
+    wchar_t wbuf[4];
+
+    // Calls that fit behave like the unchecked functions
+    __memcpy_chk(buf, "abcdefgh", 8, sizeof(buf));
+    printf("memcpy: %.8s\n", buf);
+    __strcpy_chk(buf, "1234567", sizeof(buf));
+    printf("strcpy: %s\n", buf);
+    __strcpy_chk(buf, "ab", sizeof(buf));
+    __strncat_chk(buf, "cdefghij", 5, sizeof(buf));
+    printf("strncat: %s\n", buf);
+    int ret = __sprintf_chk(buf, 1, sizeof(buf), "%d-%s", 42, "ok");
+    printf("sprintf: %d %s\n", ret, buf);
+    ret = __snprintf_chk(buf, 4, 1, sizeof(buf), "%s", "truncated");
+    printf("snprintf: %d %s\n", ret, buf);
+
+    int fd = open("/dev/zero", O_RDONLY);
+    ERROR_IF(open, fd, == -1);
+    ssize_t len = __read_chk(fd, buf, sizeof(buf), sizeof(buf));
+    ERROR_IF(read, len, == -1);
+    printf("read: %zd %d\n", len, buf[7]);
+    close(fd);
+
+    __wmemcpy_chk(wbuf, L"wide", 4, 4);
+    printf("wmemcpy: %c%c%c%c\n", (char) wbuf[0], (char) wbuf[1], (char) wbuf[2], (char) wbuf[3]);
+
+    fd = __open_2("/dev/zero", O_RDONLY);
+    ERROR_IF(__open_2, fd, == -1);
+    printf("open: %d\n", fd >= 0);
+    close(fd);
+
+    int jumped = setjmp(env);
+    if (!jumped) {
+        jump_up();
+    }
+    printf("longjmp: %d\n", jumped);
+
+    printf("memcpy: aborted %d\n", killed_by_signal(memcpy_overflow));
+    printf("strcpy: aborted %d\n", killed_by_signal(strcpy_overflow));
+    printf("strncat: aborted %d\n", killed_by_signal(strncat_overflow));
+    printf("sprintf: aborted %d\n", killed_by_signal(sprintf_overflow));
+    printf("snprintf: aborted %d\n", killed_by_signal(snprintf_overflow));
+    printf("read: aborted %d\n", killed_by_signal(read_overflow));
+    printf("wmemcpy: aborted %d\n", killed_by_signal(wmemcpy_overflow));
+    printf("open: aborted %d\n", killed_by_signal(open_without_mode));
+    printf("longjmp: aborted %d\n", killed_by_signal(jump_down));
+}
@@ -0,0 +1,276 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <ftw.h>
+#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define MAX_ENTRIES 64
+#define DEEP 1000
+
+static const char *types[] = {"F", "D", "DNR", "NS", "SL", "DP", "SLN"};
+
+static char entries[MAX_ENTRIES][PATH_MAX];
+static int count = 0;
+
+static int compare_entries(const void *a, const void *b) {
+    return strcmp(a, b);
+}
+
+// Entries are printed sorted, since the order of directory entries is up to
+// the file system
+static void print_entries(const char *name, int ret) {
+    printf("%s: %d\n", name, ret);
+    qsort(entries, count, sizeof(entries[0]), compare_entries);
+    for (int i = 0; i < count; i++) {
+        printf("  %s\n", entries[i]);
+    }
+    count = 0;
+}
+
+static void record(const char *fpath, int typeflag, int level) {
+    if (count < MAX_ENTRIES) {
+        snprintf(entries[count++], PATH_MAX, "%s %s %d", fpath, types[typeflag], level);
+    }
+}
+
+static int record_ftw(const char *fpath, const struct stat *sb, int typeflag) {
+    record(fpath, typeflag, -1);
+    return 0;
+}
+
+static int bad_base = 0;
+
+static int record_nftw(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    const char *slash = strrchr(fpath, '/');
+    int base = slash ? slash - fpath + 1 : 0;
+    if (ftwbuf->base != base) {
+        bad_base++;
+    }
+    record(fpath, typeflag, ftwbuf->level);
+    return 0;
+}
+
+// Visiting order, to check that directories come before or after their
+// contents
+static char order[MAX_ENTRIES][PATH_MAX];
+static int visited = 0;
+
+static int record_order(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    if (visited < MAX_ENTRIES) {
+        strcpy(order[visited++], fpath);
+    }
+    return 0;
+}
+
+static int directories_first(void) {
+    for (int i = 0; i < visited; i++) {
+        for (int j = 0; j < visited; j++) {
+            size_t len = strlen(order[i]);
+            int inside = strncmp(order[j], order[i], len) == 0 && order[j][len] == '/';
+            if (inside && j < i) {
+                return 0;
+            }
+        }
+    }
+    return 1;
+}
+
+static int directories_last(void) {
+    for (int i = 0; i < visited; i++) {
+        for (int j = 0; j < visited; j++) {
+            size_t len = strlen(order[i]);
+            int inside = strncmp(order[j], order[i], len) == 0 && order[j][len] == '/';
+            if (inside && j > i) {
+                return 0;
+            }
+        }
+    }
+    return 1;
+}
+
+static int actions(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    record(fpath, typeflag, ftwbuf->level);
+    const char *name = fpath + ftwbuf->base;
+    if (strcmp(name, "a") == 0 && typeflag == FTW_D) {
+        return FTW_SKIP_SUBTREE;
+    }
+    if (strcmp(name, "c") == 0 || strcmp(name, "d") == 0) {
+        return FTW_SKIP_SIBLINGS;
+    }
+    return FTW_CONTINUE;
+}
+
+static int stop(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    return ftwbuf->level == 1 ? FTW_STOP : FTW_CONTINUE;
+}
+
+static int stop_value(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    return ftwbuf->level == 2 ? 42 : 0;
+}
+
+static char start_cwd[PATH_MAX];
+static int bad_cwd = 0;
+
+static int check_cwd(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    char expected[PATH_MAX];
+    char cwd[PATH_MAX];
+    if (ftwbuf->level == 0) {
+        strcpy(expected, start_cwd);
+    } else {
+        snprintf(expected, sizeof(expected), "%s/%.*s", start_cwd, ftwbuf->base - 1, fpath);
+    }
+    if (getcwd(cwd, sizeof(cwd)) == NULL || strcmp(cwd, expected) != 0) {
+        bad_cwd++;
+    }
+    // Names relative to the working directory must work
+    struct stat st;
+    if (typeflag != FTW_SLN && lstat(fpath + ftwbuf->base, &st) != 0) {
+        bad_cwd++;
+    }
+    return 0;
+}
+
+static int max_level = 0;
+static int max_fd = 0;
+
+static int check_fds(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    if (ftwbuf->level > max_level) {
+        max_level = ftwbuf->level;
+    }
+    int fd = dup(0);
+    if (fd > max_fd) {
+        max_fd = fd;
+    }
+    close(fd);
+    return 0;
+}
+
+static int removed = 0;
+
+static int remove_entry(const char *fpath, const struct stat *sb, int typeflag, struct FTW *ftwbuf) {
+    int ret = remove(fpath + ftwbuf->base);
+    if (ret == 0) {
+        removed++;
+    }
+    return ret;
+}
+
+static void create_file(const char *path) {
+    int fd = open(path, O_CREAT | O_WRONLY, 0644);
+    ERROR_IF(open, fd, == -1);
+    close(fd);
+}
+
+int main(void) {
+    // ftw_tmp
+    // ├── a
+    // │   ├── b
+    // │   │   ├── c
+    // │   │   └── d
+    // │   ├── file
+    // │   └── loop -> ..
+    // ├── dangling -> missing
+    // ├── file
+    // └── link -> ../ftw_tmp_target
+    // ftw_tmp_target
+    // └── target
+    const char *dirs[] = {"ftw_tmp", "ftw_tmp/a", "ftw_tmp/a/b", "ftw_tmp_target"};
+    for (size_t i = 0; i < sizeof(dirs) / sizeof(dirs[0]); i++) {
+        int ret = mkdir(dirs[i], 0755);
+        ERROR_IF(mkdir, ret, == -1);
+    }
+    create_file("ftw_tmp/a/b/c");
+    create_file("ftw_tmp/a/b/d");
+    create_file("ftw_tmp/a/file");
+    create_file("ftw_tmp/file");
+    create_file("ftw_tmp_target/target");
+    ERROR_IF(symlink, symlink("..", "ftw_tmp/a/loop"), == -1);
+    ERROR_IF(symlink, symlink("missing", "ftw_tmp/dangling"), == -1);
+    ERROR_IF(symlink, symlink("../ftw_tmp_target", "ftw_tmp/link"), == -1);
+
+    // Symbolic links are followed, except loops
+    int ret = ftw("ftw_tmp", record_ftw, 4);
+    print_entries("ftw", ret);
+
+    ret = nftw("ftw_tmp/", record_nftw, 4, 0);
+    print_entries("nftw", ret);
+
+    ret = nftw("ftw_tmp", record_nftw, 4, FTW_PHYS);
+    print_entries("FTW_PHYS", ret);
+
+    ret = nftw("ftw_tmp", record_nftw, 4, FTW_PHYS | FTW_DEPTH);
+    print_entries("FTW_DEPTH", ret);
+
+    ret = nftw("ftw_tmp", record_nftw, 4, FTW_MOUNT);
+    print_entries("FTW_MOUNT", ret);
+    printf("bad base: %d\n", bad_base);
+
+    ret = nftw("ftw_tmp/dangling", record_nftw, 4, 0);
+    print_entries("dangling start", ret);
+
+    errno = 0;
+    ret = nftw("ftw_tmp/missing", record_nftw, 4, 0);
+    printf("missing start: %d, ENOENT: %d\n", ret, errno == ENOENT);
+
+    nftw("ftw_tmp", record_order, 4, FTW_PHYS);
+    printf("preorder: %d\n", directories_first());
+    visited = 0;
+    nftw("ftw_tmp", record_order, 4, FTW_PHYS | FTW_DEPTH);
+    printf("postorder: %d\n", directories_last());
+    visited = 0;
+
+    // Skipping subtrees and siblings
+    ret = nftw("ftw_tmp", actions, 4, FTW_PHYS | FTW_ACTIONRETVAL);
+    print_entries("FTW_SKIP_SUBTREE", ret);
+    ret = nftw("ftw_tmp/a", actions, 4, FTW_PHYS | FTW_DEPTH | FTW_ACTIONRETVAL);
+    printf("FTW_SKIP_SIBLINGS: %d, ", ret);
+    int b_files = 0;
+    for (int i = 0; i < count; i++) {
+        b_files += strncmp(entries[i], "ftw_tmp/a/b/", 12) == 0;
+    }
+    printf("%d of 2 files in b\n", b_files);
+    count = 0;
+
+    ret = nftw("ftw_tmp", stop, 4, FTW_PHYS | FTW_ACTIONRETVAL);
+    printf("FTW_STOP: %d\n", ret);
+    ret = nftw("ftw_tmp", stop_value, 4, FTW_PHYS);
+    printf("stop value: %d\n", ret);
+
+    // FTW_CHDIR changes into each directory and back
+    ERROR_IF(getcwd, getcwd(start_cwd, sizeof(start_cwd)), == NULL);
+    ret = nftw("ftw_tmp", check_cwd, 4, FTW_PHYS | FTW_CHDIR);
+    char cwd[PATH_MAX];
+    getcwd(cwd, sizeof(cwd));
+    printf("FTW_CHDIR: %d, bad cwd: %d, restored: %d\n", ret, bad_cwd, strcmp(cwd, start_cwd) == 0);
+    ret = nftw("ftw_tmp", check_cwd, 1, FTW_PHYS | FTW_CHDIR | FTW_DEPTH);
+    getcwd(cwd, sizeof(cwd));
+    printf("FTW_CHDIR | FTW_DEPTH: %d, bad cwd: %d, restored: %d\n", ret, bad_cwd, strcmp(cwd, start_cwd) == 0);
+
+    // A deep hierarchy stays within the descriptor limit
+    ERROR_IF(chdir, chdir("ftw_tmp"), == -1);
+    for (int i = 0; i < DEEP; i++) {
+        ERROR_IF(mkdir, mkdir("deep", 0755), == -1);
+        ERROR_IF(chdir, chdir("deep"), == -1);
+    }
+    create_file("file");
+    ERROR_IF(chdir, chdir(start_cwd), == -1);
+
+    int fd = dup(0);
+    close(fd);
+    ret = nftw("ftw_tmp", check_fds, 3, FTW_PHYS | FTW_CHDIR);
+    printf("deep: %d, max level: %d, descriptors within limit: %d\n", ret, max_level, max_fd <= fd + 4);
+
+    // Removing everything is the classic use of FTW_DEPTH
+    ret = nftw("ftw_tmp", remove_entry, 2, FTW_PHYS | FTW_DEPTH | FTW_CHDIR);
+    printf("removed: %d, %d entries\n", ret, removed);
+    removed = 0;
+    ret = nftw("ftw_tmp_target", remove_entry, 2, FTW_PHYS | FTW_DEPTH | FTW_CHDIR);
+    printf("removed: %d, %d entries\n", ret, removed);
+    printf("cleaned up: %d\n", access("ftw_tmp", F_OK) != 0 && access("ftw_tmp_target", F_OK) != 0);
+}
@@ -0,0 +1,124 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <glob.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+void print(const char *name, int ret, glob_t *pglob) {
+    printf("%s: %d\n", name, ret);
+    if (ret != 0) {
+        return;
+    }
+    size_t offs = pglob->gl_flags & GLOB_DOOFFS ? pglob->gl_offs : 0;
+    for (size_t i = 0; i < offs + pglob->gl_pathc; i++) {
+        const char *path = pglob->gl_pathv[i];
+        if (path == NULL) {
+            puts("  (null)");
+            continue;
+        }
+        // Redox does not provide . or .., so leave them out to make the
+        // output match on all systems
+        size_t len = strlen(path);
+        if ((len >= 2 && strcmp(path + len - 2, "/.") == 0) ||
+            (len >= 3 && strcmp(path + len - 3, "/..") == 0)) {
+            continue;
+        }
+        printf("  %s\n", path);
+    }
+    if (pglob->gl_pathv[offs + pglob->gl_pathc] != NULL) {
+        puts("  missing NULL terminator");
+    }
+}
+
+void test(const char *pattern, int flags) {
+    glob_t pglob;
+    int ret = glob(pattern, flags, NULL, &pglob);
+    print(pattern, ret, &pglob);
+    if (ret == 0) {
+        globfree(&pglob);
+    }
+}
+
+int errfunc(const char *epath, int eerrno) {
+    printf("errfunc: %s: %s\n", epath, strerror(eerrno));
+    return 0;
+}
+
+int main(void) {
+    test("example_dir/*-you", 0);
+    test("example_dir/[1-3]*", 0);
+    test("example_dir/?-and-*", 0);
+    test("example_dir/[!1-7]*", 0);
+    test("example_d*/1*", 0);
+    test("example_dir/8-and-hurt-you", 0);
+
+    // No match
+    test("example_dir/*-me", 0);
+    test("example_dir/*-me", GLOB_NOCHECK);
+    test("example_dir/missing", 0);
+    test("missing_dir/*", 0);
+
+    // Escapes
+    test("example_dir/\\1*", 0);
+    test("example_dir/\\*", GLOB_NOCHECK);
+    test("example_dir/\\*", GLOB_NOCHECK | GLOB_NOESCAPE);
+
+    // Directories
+    test("example_d*", GLOB_MARK);
+    test("example_d*/", 0);
+    test("example_dir/", 0);
+    test("example_dir/*/", 0);
+    test("e*_dir", GLOB_ONLYDIR);
+
+    // GNU extensions
+    test("example_dir/{8,1,5}-*", GLOB_BRACE);
+    test("example_dir/{[4-5],{6,7}-never}-*", GLOB_BRACE);
+    test("example_dir/{1", GLOB_BRACE | GLOB_NOCHECK);
+    setenv("HOME", "example_dir", 1);
+    test("~/4*", GLOB_TILDE);
+    test("~", GLOB_TILDE);
+    test("~/4*", 0);
+
+    // Offsets and appending
+    glob_t pglob;
+    pglob.gl_offs = 2;
+    int ret = glob("example_dir/1*", GLOB_DOOFFS, NULL, &pglob);
+    print("GLOB_DOOFFS", ret, &pglob);
+    ret = glob("example_dir/2*", GLOB_DOOFFS | GLOB_APPEND, NULL, &pglob);
+    print("GLOB_APPEND", ret, &pglob);
+    ret = glob("example_dir/*-me", GLOB_DOOFFS | GLOB_APPEND | GLOB_NOCHECK, NULL, &pglob);
+    print("GLOB_APPEND | GLOB_NOCHECK", ret, &pglob);
+    printf("GLOB_MAGCHAR: %d\n", (pglob.gl_flags & GLOB_MAGCHAR) != 0);
+    globfree(&pglob);
+
+    // Hidden files and directories that can't be read
+    ret = mkdir("glob_tmp", 0755);
+    ERROR_IF(mkdir, ret, == -1);
+    int fd = open("glob_tmp/.hidden", O_CREAT | O_WRONLY, 0644);
+    ERROR_IF(open, fd, == -1);
+    close(fd);
+    fd = open("glob_tmp/visible", O_CREAT | O_WRONLY, 0644);
+    ERROR_IF(open, fd, == -1);
+    close(fd);
+    ret = symlink("loop", "glob_tmp/loop");
+    ERROR_IF(symlink, ret, == -1);
+
+    test("glob_tmp/*", 0);
+    test("glob_tmp/*", GLOB_PERIOD);
+    test("glob_tmp/.*", 0);
+
+    ret = glob("glob_tmp/loop/*", 0, errfunc, &pglob);
+    print("errfunc", ret, &pglob);
+    ret = glob("glob_tmp/loop/*", GLOB_ERR, NULL, &pglob);
+    print("GLOB_ERR", ret, &pglob);
+    globfree(&pglob);
+
+    unlink("glob_tmp/loop");
+    unlink("glob_tmp/visible");
+    unlink("glob_tmp/.hidden");
+    rmdir("glob_tmp");
+}
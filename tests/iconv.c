@@ -0,0 +1,65 @@
+#include <errno.h>
+#include <iconv.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+void convert(const char* to, const char* from, const char* input, size_t len, size_t outlen) {
+    iconv_t cd = iconv_open(to, from);
+    ERROR_IF(iconv_open, cd, == (iconv_t) -1);
+
+    char output[64];
+    char* in = (char*) input;
+    char* out = output;
+    size_t inleft = len;
+    size_t outleft = outlen;
+    errno = 0;
+    size_t status = iconv(cd, &in, &inleft, &out, &outleft);
+
+    printf("%s -> %s: ", from, to);
+    if (status == (size_t) -1) {
+        printf("error %s, ", strerror(errno));
+    } else {
+        printf("%zu irreversible, ", status);
+    }
+    printf("read %zu:", len - inleft);
+    for (char* c = output; c < out; c++) {
+        printf(" %02x", (unsigned char) *c);
+    }
+    printf("\n");
+
+    iconv_close(cd);
+}
+
+int main(void) {
+    convert("ISO-8859-1", "UTF-8", "h\xc3\xa9llo", 6, 64);
+    convert("UTF-8", "ISO-8859-15", "\xa4 5", 3, 64);
+    convert("WINDOWS-1252", "UTF-8", "\xe2\x80\x9cok\xe2\x80\x9d", 8, 64);
+    convert("UTF-16BE", "UTF-8", "a\xf0\x9f\x98\x80", 5, 64);
+    convert("UTF-8", "UTF-16LE", "a\x00\x3d\xd8\x00\xde", 6, 64);
+    convert("UTF-8", "UTF-16", "\xff\xfe" "a\x00", 4, 64);
+    convert("UTF-32BE", "UTF-8", "\xc3\xa9", 2, 64);
+
+    // Invalid, incomplete and unrepresentable input
+    convert("ASCII", "UTF-8", "caf\xc3\xa9!", 6, 64);
+    convert("UTF-16LE", "UTF-8", "ab\xe2\x82", 4, 64);
+    convert("UTF-16LE", "UTF-8", "ab\xff" "c", 4, 64);
+    convert("ISO-8859-1", "UTF-8", "abcdef", 6, 3);
+    convert("ASCII//IGNORE", "UTF-8", "caf\xc3\xa9!", 6, 64);
+    convert("ASCII//TRANSLIT", "UTF-8", "\xc3\xa9\xe2\x80\x9cx\xe2\x82\xac\xc3\x9f\xe6\x97\xa5", 14, 64);
+
+    // Multibyte charsets
+    convert("SHIFT_JIS", "UTF-8", "\xe6\x97\xa5\xe6\x9c\xac\xef\xbd\xb1", 9, 64);
+    convert("UTF-8", "SHIFT_JIS", "\x93\xfa\x96\x7b\xb1", 5, 64);
+    convert("EUC-JP", "UTF-8", "\xe6\x97\xa5\xe6\x9c\xac\xef\xbd\xb1", 9, 64);
+    convert("GBK", "UTF-8", "\xe4\xb8\xad\xe6\x96\x87", 6, 64);
+    convert("UTF-8", "GBK", "\xd6\xd0\xce\xc4", 4, 64);
+    convert("BIG5", "UTF-8", "\xe4\xb8\xad\xe6\x96\x87", 6, 64);
+    convert("EUC-KR", "UTF-8", "\xed\x95\x9c\xea\xb5\xad", 6, 64);
+    convert("UTF-8", "EUC-KR", "\xc7\xd1\xb1\xb9", 4, 64);
+    convert("UTF-8", "GBK", "\xd6", 1, 64);
+
+    iconv_t cd = iconv_open("UTF-8", "no-such-charset");
+    printf("unknown: %d %s\n", cd == (iconv_t) -1, strerror(errno));
+}
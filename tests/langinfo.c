@@ -0,0 +1,25 @@
+#include <langinfo.h>
+#include <locale.h>
+#include <stdio.h>
+
+#include "test_helpers.h"
+
+int main(void) {
+    nl_item items[] = {
+        CODESET, D_T_FMT, D_FMT, T_FMT, T_FMT_AMPM, AM_STR, PM_STR,
+        DAY_1, DAY_7, ABDAY_1, ABDAY_7, MON_1, MON_12, ABMON_1, ABMON_12,
+        RADIXCHAR, THOUSEP, YESEXPR, NOEXPR
+    };
+    for (int i = 0; i < sizeof(items) / sizeof(nl_item); i += 1) {
+        printf("\"%s\"\n", nl_langinfo(items[i]));
+    }
+
+    char* val = setlocale(LC_CTYPE, "C.UTF-8");
+    ERROR_IF(setlocale, val, == NULL);
+    printf("CODESET: %s\n", nl_langinfo(CODESET));
+
+    locale_t loc = newlocale(LC_ALL_MASK, "C", (locale_t) 0);
+    ERROR_IF(newlocale, loc, == (locale_t) 0);
+    printf("CODESET: %s\n", nl_langinfo_l(CODESET, loc));
+    freelocale(loc);
+}
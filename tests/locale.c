@@ -1,15 +1,71 @@
+#include <ctype.h>
 #include <locale.h>
 #include <stdio.h>
+#include <stdlib.h>
 #include <string.h>
 
 #include "test_helpers.h"
 
 int main(void) {
-    // TODO: Implement locale properly and test it here
     char* val = setlocale(LC_ALL, NULL);
-    if (strcmp(val, "C") == 0) {
-        puts("success!");
-    } else {
-        printf("setlocale returned the wrong value: %s", val);
-    }
+    printf("default: %s\n", val);
+
+    val = setlocale(LC_ALL, "C.UTF-8");
+    ERROR_IF(setlocale, val, == NULL);
+    printf("LC_ALL: %s\n", val);
+
+    val = setlocale(LC_NUMERIC, "POSIX");
+    ERROR_IF(setlocale, val, == NULL);
+    printf("LC_NUMERIC: %s\n", val);
+
+    // Composite names can be used to restore the locale later
+    char saved[256];
+    strcpy(saved, setlocale(LC_ALL, NULL));
+    printf("composite: %s\n", saved);
+    setlocale(LC_ALL, "C");
+    val = setlocale(LC_ALL, saved);
+    ERROR_IF(setlocale, val, == NULL);
+    printf("restored: %s\n", setlocale(LC_TIME, NULL));
+
+    val = setlocale(LC_ALL, "does_not_exist");
+    printf("unknown: %s\n", val ? val : "(null)");
+    printf("unchanged: %s\n", setlocale(LC_CTYPE, NULL));
+
+    // Load a locale from LOCPATH
+    FILE* f = fopen("/tmp/test_locale", "w");
+    ERROR_IF(fopen, f, == NULL);
+    fputs("# Test locale\n"
+          "[LC_NUMERIC]\n"
+          "decimal_point = ,\n"
+          "thousands_sep = \" \"\n"
+          "grouping = 3;2\n"
+          "[LC_MONETARY]\n"
+          "currency_symbol = EUR\n"
+          "frac_digits = 2\n", f);
+    fclose(f);
+    setenv("LOCPATH", "/tmp", 1);
+
+    locale_t loc = newlocale(LC_NUMERIC_MASK | LC_MONETARY_MASK, "test_locale", (locale_t) 0);
+    ERROR_IF(newlocale, loc, == (locale_t) 0);
+    locale_t dup = duplocale(loc);
+    ERROR_IF(duplocale, dup, == (locale_t) 0);
+    freelocale(loc);
+
+    locale_t old = uselocale(dup);
+    printf("old is global: %d\n", old == LC_GLOBAL_LOCALE);
+    struct lconv* lc = localeconv();
+    printf("decimal_point: \"%s\" thousands_sep: \"%s\" grouping: %d,%d\n",
+        lc->decimal_point, lc->thousands_sep, lc->grouping[0], lc->grouping[1]);
+    printf("currency_symbol: \"%s\" frac_digits: %d\n", lc->currency_symbol, lc->frac_digits);
+    printf("strtod: %d\n", strtod("1,5", NULL) == 1.5);
+    printf("isalpha_l: %d\n", isalpha_l('a', dup) != 0);
+
+    uselocale(LC_GLOBAL_LOCALE);
+    printf("strtod: %d\n", strtod("1,5", NULL) == 1.5);
+    freelocale(dup);
+
+    loc = newlocale(LC_ALL_MASK, "does_not_exist", (locale_t) 0);
+    printf("unknown: %d\n", loc == (locale_t) 0);
+
+    unlink("/tmp/test_locale");
 }
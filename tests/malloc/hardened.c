@@ -0,0 +1,66 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+// Only run against a libc built with the hardened_malloc feature, see
+// test-hardened in ../Makefile
+
+void overflow(void) {
+    char *p = malloc(10);
+    p[10] = 'x';
+    free(p);
+}
+
+void double_free(void) {
+    char *p = malloc(10);
+    free(p);
+    free(p);
+}
+
+void invalid_free(void) {
+    char *p = malloc(64);
+    free(p + 16);
+}
+
+void unmapped_free(void) {
+    // Nothing is mapped this low, so there is no header in front to read
+    free((void *) 4096);
+}
+
+void guard_page(void) {
+    size_t size = 1 << 20;
+    volatile char *p = malloc(size);
+    // The guard page comes before anything else mapped after the block
+    for (size_t i = size;; i++) {
+        p[i] = 1;
+    }
+}
+
+int main(void) {
+    // Blocks used within bounds behave as usual
+    char *p = malloc(16);
+    ERROR_IF(malloc, p, == NULL);
+    strcpy(p, "hardened");
+    p = realloc(p, 4096);
+    ERROR_IF(realloc, p, == NULL);
+    printf("realloc: %s\n", p);
+    free(p);
+
+    // A freed block waits in the quarantine, so the next allocation of the
+    // same size does not get it back
+    char *freed = malloc(32);
+    ERROR_IF(malloc, freed, == NULL);
+    free(freed);
+    char *next = malloc(32);
+    ERROR_IF(malloc, next, == NULL);
+    printf("reused right away: %d\n", next == freed);
+    free(next);
+
+    printf("overflow: signaled %d\n", killed_by_signal(overflow));
+    printf("double free: signaled %d\n", killed_by_signal(double_free));
+    printf("invalid free: signaled %d\n", killed_by_signal(invalid_free));
+    printf("unmapped free: signaled %d\n", killed_by_signal(unmapped_free));
+    printf("guard page: signaled %d\n", killed_by_signal(guard_page));
+}
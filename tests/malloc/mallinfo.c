@@ -0,0 +1,44 @@
+#include <fcntl.h>
+#include <malloc.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+int main(void) {
+    char *small = malloc(100);
+    ERROR_IF(malloc, small, == NULL);
+    printf("usable size fits: %d\n", malloc_usable_size(small) >= 100);
+    printf("usable size of NULL: %zu\n", malloc_usable_size(NULL));
+
+    struct mallinfo info = mallinfo();
+    printf("arena: %d\n", info.arena > 0);
+
+    // Large blocks may be mapped on their own, in which case they count in
+    // hblkhd rather than uordblks
+    struct mallinfo2 before = mallinfo2();
+    char *large = malloc(1024 * 1024);
+    ERROR_IF(malloc, large, == NULL);
+    struct mallinfo2 after = mallinfo2();
+    printf("in use grows: %d\n",
+        after.uordblks + after.hblkhd >= before.uordblks + before.hblkhd + 1024 * 1024);
+    free(large);
+    free(small);
+
+    printf("mallopt M_TRIM_THRESHOLD: %d\n", mallopt(M_TRIM_THRESHOLD, 1024 * 1024));
+    printf("mallopt M_MMAP_THRESHOLD: %d\n", mallopt(M_MMAP_THRESHOLD, 512 * 1024));
+
+    int ret = malloc_trim(0);
+    printf("malloc_trim: %d\n", ret == 0 || ret == 1);
+
+    // The statistics differ between runs, so they go nowhere
+    int saved = dup(STDERR_FILENO);
+    ERROR_IF(dup, saved, == -1);
+    int null = open("/dev/null", O_WRONLY);
+    ERROR_IF(open, null, == -1);
+    ERROR_IF(dup2, dup2(null, STDERR_FILENO), == -1);
+    malloc_stats();
+    ERROR_IF(dup2, dup2(saved, STDERR_FILENO), == -1);
+    puts("malloc_stats: done");
+}
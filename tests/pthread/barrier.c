@@ -0,0 +1,57 @@
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+#define THREADS 4
+#define ROUNDS 10
+
+static pthread_barrier_t barrier;
+static int arrived[ROUNDS];
+static int serial[ROUNDS];
+static int mismatched = 0;
+
+static void *worker(void *arg) {
+    for (int round = 0; round < ROUNDS; round++) {
+        __atomic_add_fetch(&arrived[round], 1, __ATOMIC_SEQ_CST);
+        int ret = pthread_barrier_wait(&barrier);
+        if (ret == PTHREAD_BARRIER_SERIAL_THREAD) {
+            __atomic_add_fetch(&serial[round], 1, __ATOMIC_SEQ_CST);
+        } else if (ret != 0) {
+            return (void *) (long) ret;
+        }
+        // Everyone got here before anyone left
+        if (__atomic_load_n(&arrived[round], __ATOMIC_SEQ_CST) != THREADS) {
+            __atomic_store_n(&mismatched, 1, __ATOMIC_SEQ_CST);
+        }
+    }
+    return NULL;
+}
+
+int main(void) {
+    printf("zero count: %s\n", strerror(pthread_barrier_init(&barrier, NULL, 0)));
+
+    int ret = pthread_barrier_init(&barrier, NULL, THREADS);
+    ERROR_IF(pthread_barrier_init, ret, != 0);
+
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        ret = pthread_create(&threads[i], NULL, worker, NULL);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        void *retval;
+        pthread_join(threads[i], &retval);
+        ERROR_IF(pthread_barrier_wait, (long) retval, != 0);
+    }
+
+    int serial_total = 0;
+    for (int round = 0; round < ROUNDS; round++) {
+        serial_total += serial[round];
+    }
+    printf("serial threads: %d over %d rounds\n", serial_total, ROUNDS);
+    printf("left early: %d\n", mismatched);
+    pthread_barrier_destroy(&barrier);
+}
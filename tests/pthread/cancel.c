@@ -0,0 +1,187 @@
+#include <errno.h>
+#include <pthread.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+static int fds[2];
+static int empty[2];
+static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t cond = PTHREAD_COND_INITIALIZER;
+static volatile int cleaned_up = 0;
+static volatile int reached = 0;
+static volatile char got = 0;
+
+static void cleanup(void *arg) {
+    printf("cleanup: %s\n", (char *) arg);
+    cleaned_up++;
+}
+
+static void unlock(void *arg) {
+    pthread_mutex_unlock(arg);
+    cleaned_up++;
+}
+
+static void *blocked_reader(void *arg) {
+    pthread_cleanup_push(cleanup, "reader");
+    char c;
+    read(fds[0], &c, 1);
+    reached = 1;
+    pthread_cleanup_pop(0);
+    return NULL;
+}
+
+static void *racing_reader(void *arg) {
+    char c;
+    if (read(fds[0], &c, 1) == 1) {
+        got = c;
+    }
+    // Deferred cancellation is not acted on outside cancellation points
+    while (!reached) {}
+    pthread_testcancel();
+    return NULL;
+}
+
+static void *entering_reader(void *arg) {
+    char c;
+    reached = 1;
+    read(empty[0], &c, 1);
+    return NULL;
+}
+
+static void *disabled(void *arg) {
+    pthread_setcancelstate(PTHREAD_CANCEL_DISABLE, NULL);
+    // Sleeping is a cancellation point, but cancellation is disabled
+    struct timespec ts = { 0, 100000000 };
+    nanosleep(&ts, NULL);
+    reached = 1;
+    int old;
+    pthread_setcancelstate(PTHREAD_CANCEL_ENABLE, &old);
+    printf("old state is disable: %d\n", old == PTHREAD_CANCEL_DISABLE);
+    pthread_testcancel();
+    reached = 2;
+    return NULL;
+}
+
+static void *spinning(void *arg) {
+    pthread_setcanceltype(PTHREAD_CANCEL_ASYNCHRONOUS, NULL);
+    for (;;) {
+        reached = 1;
+    }
+    return NULL;
+}
+
+static void *cond_waiter(void *arg) {
+    pthread_mutex_lock(&mutex);
+    pthread_cleanup_push(unlock, &mutex);
+    reached = 1;
+    for (;;) {
+        pthread_cond_wait(&cond, &mutex);
+    }
+    pthread_cleanup_pop(1);
+    return NULL;
+}
+
+static void *exiting(void *arg) {
+    pthread_cleanup_push(cleanup, "outer");
+    pthread_cleanup_push(cleanup, "popped");
+    pthread_cleanup_push(cleanup, "not run");
+    pthread_cleanup_pop(0);
+    pthread_cleanup_pop(1);
+    pthread_exit(arg);
+    pthread_cleanup_pop(0);
+    return NULL;
+}
+
+static void wait_reached(void) {
+    while (!reached) {
+        struct timespec ts = { 0, 1000000 };
+        nanosleep(&ts, NULL);
+    }
+}
+
+int main(void) {
+    pthread_t thread;
+    void *retval;
+
+    // Deferred cancellation of a thread blocked in read
+    int ret = pipe(fds);
+    ERROR_IF(pipe, ret, == -1);
+    pthread_create(&thread, NULL, blocked_reader, NULL);
+    struct timespec ts = { 0, 50000000 };
+    nanosleep(&ts, NULL);
+    pthread_cancel(thread);
+    pthread_join(thread, &retval);
+    printf("reader canceled: %d, returned from read: %d\n", retval == PTHREAD_CANCELED, reached);
+
+    // A read that completes keeps its data, even with a request on the way
+    pthread_create(&thread, NULL, racing_reader, NULL);
+    nanosleep(&ts, NULL);
+    write(fds[1], "x", 1);
+    pthread_cancel(thread);
+    reached = 1;
+    pthread_join(thread, &retval);
+    write(fds[1], "y", 1);
+    char buf[2];
+    ssize_t len = read(fds[0], buf, 2);
+    int kept = (got == 'x') + (len == 2 && buf[0] == 'x');
+    printf("racing reader canceled: %d, byte kept once: %d\n", retval == PTHREAD_CANCELED, kept == 1);
+
+    // A request sent just as the thread enters read is not missed, which
+    // would leave it blocked there
+    ret = pipe(empty);
+    ERROR_IF(pipe, ret, == -1);
+    int canceled = 0;
+    for (int i = 0; i < 1000; i++) {
+        reached = 0;
+        pthread_create(&thread, NULL, entering_reader, NULL);
+        while (!reached) {
+            sched_yield();
+        }
+        pthread_cancel(thread);
+        pthread_join(thread, &retval);
+        canceled += retval == PTHREAD_CANCELED;
+    }
+    printf("entering readers canceled: %d of 1000\n", canceled);
+
+    // Cancellation is held back while disabled
+    reached = 0;
+    pthread_create(&thread, NULL, disabled, NULL);
+    pthread_cancel(thread);
+    pthread_join(thread, &retval);
+    printf("disabled canceled: %d, reached: %d\n", retval == PTHREAD_CANCELED, reached);
+
+    // Asynchronous cancellation outside of any cancellation point
+    reached = 0;
+    pthread_create(&thread, NULL, spinning, NULL);
+    wait_reached();
+    pthread_cancel(thread);
+    pthread_join(thread, &retval);
+    printf("spinning canceled: %d\n", retval == PTHREAD_CANCELED);
+
+    // A condition variable waiter has the mutex when its handlers run
+    reached = 0;
+    cleaned_up = 0;
+    pthread_create(&thread, NULL, cond_waiter, NULL);
+    wait_reached();
+    pthread_mutex_lock(&mutex);
+    pthread_cancel(thread);
+    pthread_mutex_unlock(&mutex);
+    pthread_join(thread, &retval);
+    printf("waiter canceled: %d, cleaned up: %d\n", retval == PTHREAD_CANCELED, cleaned_up);
+    ret = pthread_mutex_trylock(&mutex);
+    printf("mutex unlocked by cleanup: %d\n", ret == 0);
+    pthread_mutex_unlock(&mutex);
+
+    // Handlers run in reverse order on pthread_exit
+    pthread_create(&thread, NULL, exiting, "exited");
+    pthread_join(thread, &retval);
+    printf("retval: %s\n", (char *) retval);
+
+    printf("bad state: %s\n", strerror(pthread_setcancelstate(42, NULL)));
+    printf("bad type: %s\n", strerror(pthread_setcanceltype(42, NULL)));
+}
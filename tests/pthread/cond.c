@@ -0,0 +1,115 @@
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+#define THREADS 4
+
+static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t cond = PTHREAD_COND_INITIALIZER;
+static int ready = 0;
+static int woken = 0;
+
+static void *waiter(void *arg) {
+    pthread_mutex_lock(&mutex);
+    while (!ready) {
+        pthread_cond_wait(&cond, &mutex);
+    }
+    woken++;
+    pthread_mutex_unlock(&mutex);
+    return NULL;
+}
+
+static int queue = 0;
+static int consumed = 0;
+
+static void *consumer(void *arg) {
+    for (int i = 0; i < 100; i++) {
+        pthread_mutex_lock(&mutex);
+        while (queue == 0) {
+            pthread_cond_wait(&cond, &mutex);
+        }
+        queue--;
+        consumed++;
+        pthread_cond_signal(&cond);
+        pthread_mutex_unlock(&mutex);
+    }
+    return NULL;
+}
+
+static void timeout_in(clockid_t clock, struct timespec *abstime, long nsec) {
+    clock_gettime(clock, abstime);
+    abstime->tv_nsec += nsec;
+    if (abstime->tv_nsec >= 1000000000) {
+        abstime->tv_sec++;
+        abstime->tv_nsec -= 1000000000;
+    }
+}
+
+int main(void) {
+    // Broadcast wakes all waiters
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        int ret = pthread_create(&threads[i], NULL, waiter, NULL);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    pthread_mutex_lock(&mutex);
+    ready = 1;
+    pthread_cond_broadcast(&cond);
+    pthread_mutex_unlock(&mutex);
+    for (int i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    printf("woken: %d\n", woken);
+
+    // A bounded producer and consumer
+    pthread_t thread;
+    pthread_create(&thread, NULL, consumer, NULL);
+    for (int i = 0; i < 100; i++) {
+        pthread_mutex_lock(&mutex);
+        while (queue == 1) {
+            pthread_cond_wait(&cond, &mutex);
+        }
+        queue++;
+        pthread_cond_signal(&cond);
+        pthread_mutex_unlock(&mutex);
+    }
+    pthread_join(thread, NULL);
+    printf("consumed: %d\n", consumed);
+
+    // Timeouts, with the mutex held again afterwards
+    struct timespec abstime;
+    timeout_in(CLOCK_REALTIME, &abstime, 50000000);
+    pthread_mutex_lock(&mutex);
+    printf("timedwait: %s\n", strerror(pthread_cond_timedwait(&cond, &mutex, &abstime)));
+    printf("mutex held: %s\n", strerror(pthread_mutex_trylock(&mutex)));
+    pthread_mutex_unlock(&mutex);
+
+    abstime.tv_nsec = 1000000000;
+    pthread_mutex_lock(&mutex);
+    printf("bad abstime: %s\n", strerror(pthread_cond_timedwait(&cond, &mutex, &abstime)));
+    pthread_mutex_unlock(&mutex);
+
+    // Attributes
+    pthread_condattr_t attr;
+    pthread_condattr_init(&attr);
+    clockid_t clock;
+    pthread_condattr_getclock(&attr, &clock);
+    printf("default clock is realtime: %d\n", clock == CLOCK_REALTIME);
+    printf("bad clock: %s\n", strerror(pthread_condattr_setclock(&attr, 42)));
+    pthread_condattr_setclock(&attr, CLOCK_MONOTONIC);
+    pthread_condattr_getclock(&attr, &clock);
+    printf("clock is monotonic: %d\n", clock == CLOCK_MONOTONIC);
+
+    pthread_cond_t monotonic;
+    pthread_cond_init(&monotonic, &attr);
+    pthread_condattr_destroy(&attr);
+    timeout_in(CLOCK_MONOTONIC, &abstime, 50000000);
+    pthread_mutex_lock(&mutex);
+    printf("monotonic timedwait: %s\n", strerror(pthread_cond_timedwait(&monotonic, &mutex, &abstime)));
+    pthread_mutex_unlock(&mutex);
+    pthread_cond_destroy(&monotonic);
+}
@@ -0,0 +1,131 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+static int value = 0;
+
+static void *thread_main(void *arg) {
+    value = *(int *) arg;
+    return (void *) (long) (value * 2);
+}
+
+static void *thread_exit(void *arg) {
+    pthread_exit(arg);
+    return NULL;
+}
+
+static void *thread_self(void *arg) {
+    *(pthread_t *) arg = pthread_self();
+    return NULL;
+}
+
+static void *thread_stack(void *arg) {
+    pthread_attr_t attr;
+    pthread_getattr_np(pthread_self(), &attr);
+    void *stackaddr;
+    size_t stacksize;
+    pthread_attr_getstack(&attr, &stackaddr, &stacksize);
+    pthread_attr_destroy(&attr);
+
+    // This frame is on the thread's stack
+    char *local = (char *) &attr;
+    return (void *) (long) (local >= (char *) stackaddr && local < (char *) stackaddr + stacksize);
+}
+
+static void *thread_detached(void *arg) {
+    __atomic_store_n((int *) arg, 1, __ATOMIC_SEQ_CST);
+    return NULL;
+}
+
+int main(void) {
+    pthread_t thread;
+    int arg = 21;
+    int ret = pthread_create(&thread, NULL, thread_main, &arg);
+    ERROR_IF(pthread_create, ret, != 0);
+    void *retval;
+    ret = pthread_join(thread, &retval);
+    ERROR_IF(pthread_join, ret, != 0);
+    printf("value: %d, retval: %ld\n", value, (long) retval);
+
+    ret = pthread_create(&thread, NULL, thread_exit, (void *) 7);
+    ERROR_IF(pthread_create, ret, != 0);
+    pthread_join(thread, &retval);
+    printf("pthread_exit: %ld\n", (long) retval);
+
+    pthread_t seen;
+    pthread_create(&thread, NULL, thread_self, &seen);
+    pthread_join(thread, NULL);
+    printf("pthread_self matches: %d\n", pthread_equal(thread, seen) != 0);
+    printf("main is not the thread: %d\n", pthread_equal(pthread_self(), seen) == 0);
+    printf("join self: %s\n", strerror(pthread_join(pthread_self(), NULL)));
+
+    // Attributes
+    pthread_attr_t attr;
+    pthread_attr_init(&attr);
+    int detachstate;
+    size_t size;
+    pthread_attr_getdetachstate(&attr, &detachstate);
+    printf("default detachstate: %d\n", detachstate == PTHREAD_CREATE_JOINABLE);
+    printf("small stack: %s\n", strerror(pthread_attr_setstacksize(&attr, 1)));
+    printf("bad detachstate: %s\n", strerror(pthread_attr_setdetachstate(&attr, 42)));
+
+    pthread_attr_setstacksize(&attr, 256 * 1024);
+    pthread_attr_getstacksize(&attr, &size);
+    printf("stacksize: %zu\n", size);
+    pthread_attr_setguardsize(&attr, 8192);
+    pthread_attr_getguardsize(&attr, &size);
+    printf("guardsize: %zu\n", size);
+    pthread_create(&thread, &attr, thread_stack, NULL);
+    pthread_join(thread, &retval);
+    printf("on its own stack: %ld\n", (long) retval);
+
+    // A caller provided stack
+    size_t stack_size = 256 * 1024;
+    void *stack = malloc(stack_size);
+    ERROR_IF(malloc, stack, == NULL);
+    pthread_attr_setstack(&attr, stack, stack_size);
+    pthread_create(&thread, &attr, thread_stack, NULL);
+    pthread_join(thread, &retval);
+    printf("on the provided stack: %ld\n", (long) retval);
+    free(stack);
+    pthread_attr_destroy(&attr);
+
+    // Detached threads cannot be joined
+    int done = 0;
+    pthread_attr_init(&attr);
+    pthread_attr_setdetachstate(&attr, PTHREAD_CREATE_DETACHED);
+    ret = pthread_create(&thread, &attr, thread_detached, &done);
+    ERROR_IF(pthread_create, ret, != 0);
+    pthread_attr_destroy(&attr);
+    while (!__atomic_load_n(&done, __ATOMIC_SEQ_CST)) {
+        sched_yield();
+    }
+    puts("detached thread ran");
+
+    done = 0;
+    pthread_create(&thread, NULL, thread_detached, &done);
+    ret = pthread_detach(thread);
+    ERROR_IF(pthread_detach, ret, != 0);
+    while (!__atomic_load_n(&done, __ATOMIC_SEQ_CST)) {
+        sched_yield();
+    }
+    puts("pthread_detach done");
+
+    // Many threads at once
+    pthread_t threads[16];
+    for (long i = 0; i < 16; i++) {
+        ret = pthread_create(&threads[i], NULL, thread_exit, (void *) i);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    long sum = 0;
+    for (int i = 0; i < 16; i++) {
+        pthread_join(threads[i], &retval);
+        sum += (long) retval;
+    }
+    printf("sum: %ld\n", sum);
+}
@@ -0,0 +1,164 @@
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define THREADS 8
+#define ITERATIONS 10000
+
+static pthread_mutex_t counter_lock = PTHREAD_MUTEX_INITIALIZER;
+static long counter = 0;
+
+static pthread_spinlock_t spin;
+static long spin_counter = 0;
+
+static void *increment(void *arg) {
+    for (int i = 0; i < ITERATIONS; i++) {
+        pthread_mutex_lock(&counter_lock);
+        counter++;
+        pthread_mutex_unlock(&counter_lock);
+
+        pthread_spin_lock(&spin);
+        spin_counter++;
+        pthread_spin_unlock(&spin);
+    }
+    return NULL;
+}
+
+static void *try_other(void *arg) {
+    return (void *) (long) pthread_mutex_trylock(arg);
+}
+
+static void *unlock_other(void *arg) {
+    return (void *) (long) pthread_mutex_unlock(arg);
+}
+
+static int in_other_thread(void *(*f)(void *), pthread_mutex_t *mutex) {
+    pthread_t thread;
+    void *retval;
+    pthread_create(&thread, NULL, f, mutex);
+    pthread_join(thread, &retval);
+    return (int) (long) retval;
+}
+
+int main(void) {
+    // Contended counting
+    pthread_spin_init(&spin, PTHREAD_PROCESS_PRIVATE);
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        int ret = pthread_create(&threads[i], NULL, increment, NULL);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    printf("counter: %ld\n", counter);
+    printf("spin counter: %ld\n", spin_counter);
+    pthread_spin_lock(&spin);
+    printf("spin trylock held: %s\n", strerror(pthread_spin_trylock(&spin)));
+    pthread_spin_unlock(&spin);
+    pthread_spin_destroy(&spin);
+
+    // Normal mutexes
+    pthread_mutex_t mutex;
+    pthread_mutex_init(&mutex, NULL);
+    pthread_mutex_lock(&mutex);
+    printf("normal trylock held: %s\n", strerror(pthread_mutex_trylock(&mutex)));
+    printf("normal trylock held elsewhere: %s\n", strerror(in_other_thread(try_other, &mutex)));
+    pthread_mutex_unlock(&mutex);
+    printf("normal trylock free: %s\n", strerror(pthread_mutex_trylock(&mutex)));
+    pthread_mutex_unlock(&mutex);
+    pthread_mutex_destroy(&mutex);
+
+    // Recursive mutexes
+    pthread_mutexattr_t attr;
+    pthread_mutexattr_init(&attr);
+    int type;
+    pthread_mutexattr_gettype(&attr, &type);
+    printf("default type: %d\n", type == PTHREAD_MUTEX_DEFAULT);
+    printf("bad type: %s\n", strerror(pthread_mutexattr_settype(&attr, 42)));
+    pthread_mutexattr_settype(&attr, PTHREAD_MUTEX_RECURSIVE);
+    pthread_mutex_init(&mutex, &attr);
+    printf("recursive lock: %s\n", strerror(pthread_mutex_lock(&mutex)));
+    printf("recursive lock again: %s\n", strerror(pthread_mutex_lock(&mutex)));
+    printf("recursive trylock: %s\n", strerror(pthread_mutex_trylock(&mutex)));
+    printf("recursive trylock elsewhere: %s\n", strerror(in_other_thread(try_other, &mutex)));
+    printf("recursive unlock elsewhere: %s\n", strerror(in_other_thread(unlock_other, &mutex)));
+    for (int i = 0; i < 3; i++) {
+        pthread_mutex_unlock(&mutex);
+    }
+    printf("recursive trylock elsewhere after unlocking: %s\n",
+           strerror(in_other_thread(try_other, &mutex)));
+    pthread_mutex_destroy(&mutex);
+
+    // Error checking mutexes
+    pthread_mutexattr_settype(&attr, PTHREAD_MUTEX_ERRORCHECK);
+    pthread_mutex_init(&mutex, &attr);
+    printf("errorcheck unlock unlocked: %s\n", strerror(pthread_mutex_unlock(&mutex)));
+    pthread_mutex_lock(&mutex);
+    printf("errorcheck relock: %s\n", strerror(pthread_mutex_lock(&mutex)));
+    printf("errorcheck trylock: %s\n", strerror(pthread_mutex_trylock(&mutex)));
+    printf("errorcheck unlock: %s\n", strerror(pthread_mutex_unlock(&mutex)));
+    pthread_mutex_destroy(&mutex);
+    pthread_mutexattr_destroy(&attr);
+
+    // Timed locking
+    pthread_mutex_init(&mutex, NULL);
+    pthread_mutex_lock(&mutex);
+    struct timespec abstime;
+    clock_gettime(CLOCK_REALTIME, &abstime);
+    abstime.tv_nsec += 50000000;
+    if (abstime.tv_nsec >= 1000000000) {
+        abstime.tv_sec++;
+        abstime.tv_nsec -= 1000000000;
+    }
+    printf("timedlock held: %s\n", strerror(pthread_mutex_timedlock(&mutex, &abstime)));
+    pthread_mutex_unlock(&mutex);
+    printf("timedlock free: %s\n", strerror(pthread_mutex_timedlock(&mutex, &abstime)));
+    pthread_mutex_unlock(&mutex);
+    pthread_mutex_destroy(&mutex);
+
+    // A process shared recursive mutex held by the parent is not owned by a
+    // forked child, even though its thread looks the same
+    pthread_mutex_t *shared = mmap(NULL, sizeof(pthread_mutex_t), PROT_READ | PROT_WRITE,
+                                   MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    ERROR_IF(mmap, shared, == MAP_FAILED);
+    pthread_mutexattr_init(&attr);
+    pthread_mutexattr_settype(&attr, PTHREAD_MUTEX_RECURSIVE);
+    printf("setpshared: %s\n", strerror(pthread_mutexattr_setpshared(&attr, PTHREAD_PROCESS_SHARED)));
+    pthread_mutex_init(shared, &attr);
+    pthread_mutexattr_destroy(&attr);
+    pthread_mutex_lock(shared);
+    fflush(stdout);
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        printf("shared trylock in child: %s\n", strerror(pthread_mutex_trylock(shared)));
+        printf("shared unlock in child: %s\n", strerror(pthread_mutex_unlock(shared)));
+        fflush(stdout);
+        _exit(0);
+    }
+    int status;
+    ERROR_IF(waitpid, waitpid(pid, &status, 0), == -1);
+    pthread_mutex_unlock(shared);
+
+    pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        printf("shared lock in child: %s\n", strerror(pthread_mutex_lock(shared)));
+        fflush(stdout);
+        _exit(0);
+    }
+    ERROR_IF(waitpid, waitpid(pid, &status, 0), == -1);
+    printf("shared trylock held by child: %s\n", strerror(pthread_mutex_trylock(shared)));
+
+    pthread_mutexattr_init(&attr);
+    printf("setrobust: %s\n", strerror(pthread_mutexattr_setrobust(&attr, PTHREAD_MUTEX_ROBUST)));
+    pthread_mutexattr_destroy(&attr);
+}
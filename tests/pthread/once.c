@@ -0,0 +1,31 @@
+#include <pthread.h>
+#include <stdio.h>
+
+#include "test_helpers.h"
+
+#define THREADS 8
+
+static pthread_once_t once = PTHREAD_ONCE_INIT;
+static int calls = 0;
+
+static void init(void) {
+    __atomic_add_fetch(&calls, 1, __ATOMIC_SEQ_CST);
+}
+
+static void *worker(void *arg) {
+    pthread_once(&once, init);
+    return NULL;
+}
+
+int main(void) {
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        int ret = pthread_create(&threads[i], NULL, worker, NULL);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    pthread_once(&once, init);
+    printf("calls: %d\n", calls);
+}
@@ -0,0 +1,118 @@
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+#define THREADS 8
+#define ITERATIONS 1000
+
+static pthread_rwlock_t rwlock = PTHREAD_RWLOCK_INITIALIZER;
+static long a = 0;
+static long b = 0;
+static int torn = 0;
+
+static void *worker(void *arg) {
+    for (int i = 0; i < ITERATIONS; i++) {
+        if (i % 4 == 0) {
+            pthread_rwlock_wrlock(&rwlock);
+            a++;
+            b++;
+            pthread_rwlock_unlock(&rwlock);
+        } else {
+            pthread_rwlock_rdlock(&rwlock);
+            if (a != b) {
+                __atomic_store_n(&torn, 1, __ATOMIC_SEQ_CST);
+            }
+            pthread_rwlock_unlock(&rwlock);
+        }
+    }
+    return NULL;
+}
+
+// The helpers release whatever they got, so only main holds the lock
+static void *try_read(void *arg) {
+    int ret = pthread_rwlock_tryrdlock(&rwlock);
+    if (ret == 0) {
+        pthread_rwlock_unlock(&rwlock);
+    }
+    return (void *) (long) ret;
+}
+
+static void *try_write(void *arg) {
+    int ret = pthread_rwlock_trywrlock(&rwlock);
+    if (ret == 0) {
+        pthread_rwlock_unlock(&rwlock);
+    }
+    return (void *) (long) ret;
+}
+
+static void *timed_write(void *arg) {
+    int ret = pthread_rwlock_timedwrlock(&rwlock, arg);
+    if (ret == 0) {
+        pthread_rwlock_unlock(&rwlock);
+    }
+    return (void *) (long) ret;
+}
+
+static void *write_lock(void *arg) {
+    pthread_rwlock_wrlock(&rwlock);
+    pthread_rwlock_unlock(&rwlock);
+    return NULL;
+}
+
+static int in_other_thread(void *(*f)(void *), void *arg) {
+    pthread_t thread;
+    void *retval;
+    pthread_create(&thread, NULL, f, arg);
+    pthread_join(thread, &retval);
+    return (int) (long) retval;
+}
+
+int main(void) {
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        int ret = pthread_create(&threads[i], NULL, worker, NULL);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    printf("writes: %ld, torn reads: %d\n", a, torn);
+
+    // Readers share, writers exclude
+    pthread_rwlock_rdlock(&rwlock);
+    printf("read while read: %s\n", strerror(in_other_thread(try_read, NULL)));
+    printf("write while read: %s\n", strerror(in_other_thread(try_write, NULL)));
+    pthread_rwlock_unlock(&rwlock);
+
+    pthread_rwlock_wrlock(&rwlock);
+    printf("read while written: %s\n", strerror(in_other_thread(try_read, NULL)));
+    printf("write while written: %s\n", strerror(in_other_thread(try_write, NULL)));
+
+    struct timespec abstime;
+    clock_gettime(CLOCK_REALTIME, &abstime);
+    abstime.tv_nsec += 50000000;
+    if (abstime.tv_nsec >= 1000000000) {
+        abstime.tv_sec++;
+        abstime.tv_nsec -= 1000000000;
+    }
+    printf("timed write while written: %s\n", strerror(in_other_thread(timed_write, &abstime)));
+    pthread_rwlock_unlock(&rwlock);
+
+    printf("write when free: %s\n", strerror(in_other_thread(try_write, NULL)));
+
+    // Waiting writers keep new readers out
+    pthread_rwlock_rdlock(&rwlock);
+    pthread_t writer;
+    pthread_create(&writer, NULL, write_lock, NULL);
+    struct timespec delay = { .tv_sec = 0, .tv_nsec = 100000000 };
+    nanosleep(&delay, NULL);
+    printf("read while a writer waits: %s\n", strerror(in_other_thread(try_read, NULL)));
+    pthread_rwlock_unlock(&rwlock);
+    pthread_join(writer, NULL);
+    printf("read after the writer: %s\n", strerror(in_other_thread(try_read, NULL)));
+    pthread_rwlock_destroy(&rwlock);
+}
@@ -0,0 +1,78 @@
+#include <pthread.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define STACK_SIZE (256 * 1024)
+
+static char *stack_start;
+
+static void *short_lived(void *arg) {
+    return arg;
+}
+
+static void *in_stack(void *arg) {
+    char local;
+    return (void *) (long) (&local >= stack_start && &local < stack_start + STACK_SIZE);
+}
+
+static int recurse(int depth) {
+    volatile char frame[1024];
+    frame[0] = (char) depth;
+    return recurse(depth + 1) + frame[0];
+}
+
+static void *overflow(void *arg) {
+    recurse(0);
+    return NULL;
+}
+
+int main(void) {
+    pthread_attr_t attr;
+    pthread_attr_init(&attr);
+    size_t guardsize;
+    pthread_attr_getguardsize(&attr, &guardsize);
+    printf("default guard size: %d\n", guardsize > 0);
+
+    // A caller provided stack is used as is
+    stack_start = malloc(STACK_SIZE);
+    int ret = pthread_attr_setstack(&attr, stack_start, STACK_SIZE);
+    ERROR_IF(pthread_attr_setstack, ret, != 0);
+    pthread_t thread;
+    ret = pthread_create(&thread, &attr, in_stack, NULL);
+    ERROR_IF(pthread_create, ret, != 0);
+    void *result;
+    pthread_join(thread, &result);
+    printf("caller stack used: %ld\n", (long) result);
+    free(stack_start);
+    pthread_attr_destroy(&attr);
+
+    // Short lived threads one after the other, which reuse stacks
+    for (int i = 0; i < 100; i++) {
+        ret = pthread_create(&thread, NULL, short_lived, (void *) (long) i);
+        ERROR_IF(pthread_create, ret, != 0);
+        pthread_join(thread, &result);
+        ERROR_IF(pthread_join, (long) result, != i);
+    }
+    printf("created 100 threads\n");
+
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        // Running off the end of the stack hits the guard
+        pthread_attr_init(&attr);
+        pthread_attr_setstacksize(&attr, STACK_SIZE);
+        pthread_attr_setguardsize(&attr, 4096);
+        pthread_create(&thread, &attr, overflow, NULL);
+        pthread_join(thread, NULL);
+        _exit(0);
+    }
+    int status;
+    ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    printf("overflow killed by SIGSEGV: %d\n", WIFSIGNALED(status) && WTERMSIG(status) == SIGSEGV);
+}
@@ -0,0 +1,57 @@
+#include <errno.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+static pthread_key_t key;
+static int destructed = 0;
+
+static void destructor(void *value) {
+    __atomic_add_fetch(&destructed, *(int *) value, __ATOMIC_SEQ_CST);
+}
+
+static void *worker(void *arg) {
+    if (pthread_getspecific(key) != NULL) {
+        return (void *) 1;
+    }
+    pthread_setspecific(key, arg);
+    if (pthread_getspecific(key) != arg) {
+        return (void *) 1;
+    }
+    return NULL;
+}
+
+int main(void) {
+    int ret = pthread_key_create(&key, destructor);
+    ERROR_IF(pthread_key_create, ret, != 0);
+
+    int main_value = 100;
+    pthread_setspecific(key, &main_value);
+
+    int values[] = {1, 2, 3};
+    pthread_t threads[3];
+    for (int i = 0; i < 3; i++) {
+        ret = pthread_create(&threads[i], NULL, worker, &values[i]);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < 3; i++) {
+        void *retval;
+        pthread_join(threads[i], &retval);
+        ERROR_IF(pthread_getspecific, (long) retval, != 0);
+    }
+
+    printf("main value: %d\n", *(int *) pthread_getspecific(key));
+    printf("destructed: %d\n", destructed);
+
+    ret = pthread_key_delete(key);
+    ERROR_IF(pthread_key_delete, ret, != 0);
+    printf("setspecific deleted: %s\n", strerror(pthread_setspecific(key, &main_value)));
+
+    // A new key does not see the values of the old one
+    pthread_key_t other;
+    pthread_key_create(&other, NULL);
+    printf("new key value: %p\n", pthread_getspecific(other));
+    pthread_key_delete(other);
+}
@@ -1,9 +1,334 @@
 #include <regex.h>
 #include <stdio.h>
 #include <stdlib.h>
+#include <string.h>
 
 #include "test_helpers.h"
 
+// Extended expressions from the AT&T regex test vectors (basic.dat,
+// categorize.dat, nullsubexpr.dat, repetition.dat and rightassoc.dat). The
+// expected result is the list of submatches, NOMATCH or the regcomp error.
+// Vectors where implementations disagree on the submatches of the last
+// iteration of a bounded repetition are left out.
+struct test {
+    int cflags;
+    int eflags;
+    const char *pattern;
+    const char *input;
+    const char *expected;
+};
+
+static const struct test tests[] = {
+    // basic.dat
+    { 0, 0, "a...b", "abababbb", "(2,7)" },
+    { 0, 0, "XXXXXX", "..XXXXXX", "(2,8)" },
+    { 0, 0, "\\)", "()", "(1,2)" },
+    { 0, 0, "a]", "a]a", "(0,2)" },
+    { 0, 0, "}", "}", "(0,1)" },
+    { 0, 0, "\\}", "}", "(0,1)" },
+    { 0, 0, "\\]", "]", "(0,1)" },
+    { 0, 0, "]", "]", "(0,1)" },
+    { 0, 0, "{", "{", "BADRPT" },
+    { 0, 0, "^a", "ax", "(0,1)" },
+    { 0, 0, "\\^a", "a^a", "(1,3)" },
+    { 0, 0, "a\\^", "a^", "(0,2)" },
+    { 0, 0, "a$", "aa", "(1,2)" },
+    { 0, 0, "a\\$", "a$", "(0,2)" },
+    { 0, 0, "^$", "", "(0,0)" },
+    { 0, 0, "$^", "", "(0,0)" },
+    { 0, 0, "a($)", "aa", "(1,2)(2,2)" },
+    { 0, 0, "a*(^a)", "aa", "(0,1)(0,1)" },
+    { 0, 0, "(..)*(...)*", "a", "(0,0)" },
+    { 0, 0, "(..)*(...)*", "abcd", "(0,4)(2,4)" },
+    { 0, 0, "(ab|a)(bc|c)", "abc", "(0,3)(0,2)(2,3)" },
+    { 0, 0, "(ab)c|abc", "abc", "(0,3)(0,2)" },
+    { 0, 0, "a{0}b", "ab", "(1,2)" },
+    { 0, 0, "(a*)(b?)(b+)b{3}", "aaabbbbbbb", "(0,10)(0,3)(3,4)(4,7)" },
+    { 0, 0, "(a*)(b{0,1})(b{1,})b{3}", "aaabbbbbbb", "(0,10)(0,3)(3,4)(4,7)" },
+    { 0, 0, "((a|a)|a)", "a", "(0,1)(0,1)(0,1)" },
+    { 0, 0, "(a*)(a|aa)", "aaaa", "(0,4)(0,3)(3,4)" },
+    { 0, 0, "a*(a.|aa)", "aaaa", "(0,4)(2,4)" },
+    { 0, 0, "a(b)|c(d)|a(e)f", "aef", "(0,3)(?,?)(?,?)(1,2)" },
+    { 0, 0, "(a|b)?.*", "b", "(0,1)(0,1)" },
+    { 0, 0, "(a|b)c|a(b|c)", "ac", "(0,2)(0,1)" },
+    { 0, 0, "(a|b)c|a(b|c)", "ab", "(0,2)(?,?)(1,2)" },
+    { 0, 0, "(a|b)*c|(a|ab)*c", "abc", "(0,3)(1,2)" },
+    { 0, 0, "(a|b)*c|(a|ab)*c", "xc", "(1,2)" },
+    { 0, 0, "(.a|.b).*|.*(.a|.b)", "xa", "(0,2)(0,2)" },
+    { 0, 0, "a?(ab|ba)ab", "abab", "(0,4)(0,2)" },
+    { 0, 0, "a?(ac{0}b|ab)ab", "abab", "(0,4)(0,2)" },
+    { 0, 0, "ab|abab", "abbabab", "(0,2)" },
+    { 0, 0, "aba|bab|bba", "baaabbbaba", "(5,8)" },
+    { 0, 0, "aba|bab", "baaabbbaba", "(6,9)" },
+    { 0, 0, "(aa|aaa)*|(a|aaaaa)", "aa", "(0,2)(0,2)" },
+    { 0, 0, "(a.|.a.)*|(a|.a...)", "aa", "(0,2)(0,2)" },
+    { 0, 0, "ab|a", "xabc", "(1,3)" },
+    { 0, 0, "ab|a", "xxabc", "(2,4)" },
+    { REG_ICASE, 0, "(Ab|cD)*", "aBcD", "(0,4)(2,4)" },
+    { 0, 0, "[^-]", "--a", "(2,3)" },
+    { 0, 0, "[a-]*", "--a", "(0,3)" },
+    { 0, 0, "[a-m-]*", "--amoma--", "(0,4)" },
+    { 0, 0, ":::1:::0:|:::1:1:0:", ":::0:::1:::1:::0:", "(8,17)" },
+    { 0, 0, ":::1:::0:|:::1:1:1:", ":::0:::1:::1:::0:", "(8,17)" },
+    { 0, 0, "[[:upper:]]", "A", "(0,1)" },
+    { 0, 0, "[[:lower:]]+", "`az{", "(1,3)" },
+    { 0, 0, "[[:upper:]]+", "@AZ[", "(1,3)" },
+    { 0, 0, "[[-]]", "[[-]]", "(2,4)" },
+    { 0, 0, "[[.NIL.]]", "NIL", "ECOLLATE" },
+    { 0, 0, "[[=aleph=]]", "aleph", "ECOLLATE" },
+    { 0, 0, "\n", "\n", "(0,1)" },
+    { REG_NEWLINE, 0, "\n", "\n", "(0,1)" },
+    { 0, 0, "[^a]", "\n", "(0,1)" },
+    { REG_NEWLINE, 0, "[^a]", "\n", "NOMATCH" },
+    { 0, 0, "\na", "\na", "(0,2)" },
+    { 0, 0, "(a)(b)(c)", "abc", "(0,3)(0,1)(1,2)(2,3)" },
+    { 0, 0, "xxx", "xxx", "(0,3)" },
+    { 0, 0, "(^|[ (,;])((([Ff]eb[^ ]* *|0*2/|\\* */?)0*[6-7]))([^0-9]|$)", "feb 6,", "(0,6)(0,0)(0,5)(0,5)(0,4)(5,6)" },
+    { 0, 0, "(^|[ (,;])((([Ff]eb[^ ]* *|0*2/|\\* */?)0*[6-7]))([^0-9]|$)", "2/7", "(0,3)(0,0)(0,3)(0,3)(0,2)(3,3)" },
+    { 0, 0, "(^|[ (,;])((([Ff]eb[^ ]* *|0*2/|\\* */?)0*[6-7]))([^0-9]|$)", "feb 1,Feb 6", "(5,11)(5,6)(6,11)(6,11)(6,10)(11,11)" },
+    { 0, 0, "((((((((((((((((((((((((((((((x))))))))))))))))))))))))))))))", "x", "(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)" },
+    { 0, 0, "((((((((((((((((((((((((((((((x))))))))))))))))))))))))))))))*", "xx", "(0,2)(1,2)(1,2)(1,2)(1,2)(1,2)(1,2)(1,2)(1,2)(1,2)" },
+    { 0, 0, "a?(ab|ba)*", "ababababababababababababababababababababababababababababababababababababababababa", "(0,81)(79,81)" },
+    { 0, 0, "abaa|abbaa|abbbaa|abbbbaa", "ababbabbbabbbabbbbabbbbaa", "(18,25)" },
+    { 0, 0, "abaa|abbaa|abbbaa|abbbbaa", "ababbabbbabbbabbbbabaa", "(18,22)" },
+    { 0, 0, "aaac|aabc|abac|abbc|baac|babc|bbac|bbbc", "baaabbbabac", "(7,11)" },
+    { 0, 0, ".*", "\x01\xff", "(0,2)" },
+    { 0, 0, "aaaa|bbbb|cccc|ddddd|eeeeee|fffffff|gggg|hhhh|iiiii|jjjjj|kkkkk|llll", "XaaaXbbbXcccXdddXeeeXfffXgggXhhhXiiiXjjjXkkkXlllXcbaXaaaa", "(53,57)" },
+    { 0, 0, "a*a*a*a*a*b", "aaaaaaaaab", "(0,10)" },
+    { 0, 0, "^", "", "(0,0)" },
+    { 0, 0, "$", "", "(0,0)" },
+    { 0, 0, "^$", "", "(0,0)" },
+    { 0, 0, "^a$", "a", "(0,1)" },
+    { 0, 0, "abc", "abc", "(0,3)" },
+    { 0, 0, "abc", "xabcy", "(1,4)" },
+    { 0, 0, "abc", "ababc", "(2,5)" },
+    { 0, 0, "ab*c", "abc", "(0,3)" },
+    { 0, 0, "ab*bc", "abbbbc", "(0,6)" },
+    { 0, 0, "ab+bc", "abbbbc", "(0,6)" },
+    { 0, 0, "ab?bc", "abbc", "(0,4)" },
+    { 0, 0, "ab?c", "abc", "(0,3)" },
+    { 0, 0, "^abc$", "abc", "(0,3)" },
+    { 0, 0, "^abc", "abcc", "(0,3)" },
+    { 0, 0, "abc$", "aabc", "(1,4)" },
+    { 0, 0, "a.c", "axc", "(0,3)" },
+    { 0, 0, "a.*c", "axyzc", "(0,5)" },
+    { 0, 0, "a[bc]d", "abd", "(0,3)" },
+    { 0, 0, "a[b-d]e", "ace", "(0,3)" },
+    { 0, 0, "a[b-d]", "aac", "(1,3)" },
+    { 0, 0, "a[-b]", "a-", "(0,2)" },
+    { 0, 0, "a[b-]", "a-", "(0,2)" },
+    { 0, 0, "a]", "a]", "(0,2)" },
+    { 0, 0, "a[]]b", "a]b", "(0,3)" },
+    { 0, 0, "a[^bc]d", "aed", "(0,3)" },
+    { 0, 0, "a[^-b]c", "adc", "(0,3)" },
+    { 0, 0, "a[^]b]c", "adc", "(0,3)" },
+    { 0, 0, "ab|cd", "abc", "(0,2)" },
+    { 0, 0, "ab|cd", "abcd", "(0,2)" },
+    { 0, 0, "a\\(b", "a(b", "(0,3)" },
+    { 0, 0, "a\\(*b", "ab", "(0,2)" },
+    { 0, 0, "a\\(*b", "a((b", "(0,4)" },
+    { 0, 0, "((a))", "abc", "(0,1)(0,1)(0,1)" },
+    { 0, 0, "(a)b(c)", "abc", "(0,3)(0,1)(2,3)" },
+    { 0, 0, "a+b+c", "aabbabc", "(4,7)" },
+    { 0, 0, "a*", "aaa", "(0,3)" },
+    { 0, 0, "(a*)*", "-", "(0,0)(0,0)" },
+    { 0, 0, "(a*)+", "-", "(0,0)(0,0)" },
+    { 0, 0, "(a*|b)*", "-", "(0,0)(0,0)" },
+    { 0, 0, "(a+|b)*", "ab", "(0,2)(1,2)" },
+    { 0, 0, "(a+|b)+", "ab", "(0,2)(1,2)" },
+    { 0, 0, "(a+|b)?", "ab", "(0,1)(0,1)" },
+    { 0, 0, "[^ab]*", "cde", "(0,3)" },
+    { 0, 0, "(^)*", "-", "(0,0)(0,0)" },
+    { 0, 0, "a*", "", "(0,0)" },
+    { 0, 0, "([abc])*d", "abbbcd", "(0,6)(4,5)" },
+    { 0, 0, "a|b|c|d|e", "e", "(0,1)" },
+    { 0, 0, "(a|b|c|d|e)f", "ef", "(0,2)(0,1)" },
+    { 0, 0, "((a*|b))*", "-", "(0,0)(0,0)(0,0)" },
+    { 0, 0, "abcd*efg", "abcdefg", "(0,7)" },
+    { 0, 0, "ab*", "xabyabbbz", "(1,3)" },
+    { 0, 0, "ab*", "xayabbbz", "(1,2)" },
+    { 0, 0, "(ab|cd)e", "abcde", "(2,5)(2,4)" },
+    { 0, 0, "[abhgefdc]ij", "hij", "(0,3)" },
+    { 0, 0, "(a|b)c*d", "abcd", "(1,4)(1,2)" },
+    { 0, 0, "(ab|ab*)bc", "abc", "(0,3)(0,1)" },
+    { 0, 0, "a([bc]*)c*", "abc", "(0,3)(1,3)" },
+    { 0, 0, "a([bc]*)(c*d)", "abcd", "(0,4)(1,3)(3,4)" },
+    { 0, 0, "a([bc]+)(c*d)", "abcd", "(0,4)(1,3)(3,4)" },
+    { 0, 0, "a([bc]*)(c+d)", "abcd", "(0,4)(1,2)(2,4)" },
+    { 0, 0, "a[bcd]*dcdcde", "adcdcde", "(0,7)" },
+    { 0, 0, "(ab|a)b*c", "abc", "(0,3)(0,2)" },
+    { 0, 0, "((a)(b)c)(d)", "abcd", "(0,4)(0,3)(0,1)(1,2)(3,4)" },
+    { 0, 0, "[A-Za-z_][A-Za-z0-9_]*", "alpha", "(0,5)" },
+    { 0, 0, "^a(bc+|b[eh])g|.h$", "abh", "(1,3)" },
+    { 0, 0, "(bc+d$|ef*g.|h?i(j|k))", "effgz", "(0,5)(0,5)" },
+    { 0, 0, "(bc+d$|ef*g.|h?i(j|k))", "ij", "(0,2)(0,2)(1,2)" },
+    { 0, 0, "(bc+d$|ef*g.|h?i(j|k))", "reffgz", "(1,6)(1,6)" },
+    { 0, 0, "(((((((((a)))))))))", "a", "(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)(0,1)" },
+    { 0, 0, "multiple words", "multiple words yeah", "(0,14)" },
+    { 0, 0, "(.*)c(.*)", "abcde", "(0,5)(0,2)(3,5)" },
+    { 0, 0, "abcd", "abcd", "(0,4)" },
+    { 0, 0, "a(bc)d", "abcd", "(0,4)(1,3)" },
+    { 0, 0, "a[\x01-\x03]?c", "a\x02" "c", "(0,3)" },
+    { 0, 0, "M[ou]'?am+[ae]r .*([AEae]l[- ])?[GKQ]h?[aeu]+([dtz][dhz]?)+af[iy]", "Muammar Qaddafi", "(0,15)(?,?)(10,12)" },
+    { 0, 0, "M[ou]'?am+[ae]r .*([AEae]l[- ])?[GKQ]h?[aeu]+([dtz][dhz]?)+af[iy]", "Mo'ammar Gadhafi", "(0,16)(?,?)(11,13)" },
+    { REG_ICASE, 0, "ABC", "ABC", "(0,3)" },
+    { REG_ICASE, 0, "ABC", "xabcy", "(1,4)" },
+    { REG_ICASE, 0, "A[B-D]E", "aCe", "(0,3)" },
+    { REG_ICASE, 0, "(A|B)C*D", "ABCD", "(1,4)(1,2)" },
+    { REG_ICASE, 0, "[[:lower:]]+", "AbC", "(0,3)" },
+    { 0, 0, "a[b-a]", "-", "ERANGE" },
+    { 0, 0, "a[]b", "-", "EBRACK" },
+    { 0, 0, "a[", "-", "EBRACK" },
+    { 0, 0, "*a", "-", "BADRPT" },
+    { 0, 0, "(*)b", "-", "BADRPT" },
+    { 0, 0, "a\\", "-", "EESCAPE" },
+    { 0, 0, "abc)", "abc)", "(0,4)" },
+    { 0, 0, "(abc", "-", "EPAREN" },
+    { 0, 0, "a**", "-", "(0,0)" },
+    { 0, 0, "a*+", "-", "(0,0)" },
+    { 0, 0, "a??", "-", "(0,0)" },
+    { 0, 0, "a{1", "-", "EBRACE" },
+    { 0, 0, "a{1,2,3}", "-", "BADBR" },
+    { 0, 0, "a{2,1}", "-", "BADBR" },
+    { 0, 0, "[[:foo:]]", "-", "ECTYPE" },
+    { 0, 0, "(a)\\2", "-", "ESUBREG" },
+
+    // categorize.dat
+    { 0, 0, "(a*)*", "a", "(0,1)(0,1)" },
+    { 0, 0, "(a*)+", "a", "(0,1)(0,1)" },
+    { 0, 0, "(a+)*", "a", "(0,1)(0,1)" },
+    { 0, 0, "(a+)+", "a", "(0,1)(0,1)" },
+    { 0, 0, "(a|b)*", "ab", "(0,2)(1,2)" },
+    { 0, 0, "(a|ab)(c|bcd)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(a*)(b|abc)(c*)", "abc", "(0,3)(0,1)(1,2)(2,3)" },
+    { 0, 0, "(a|ab)(bcd|c)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(ab|a)(c|bcd)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(a*)(b|abc)", "abc", "(0,3)(0,0)(0,3)" },
+    { 0, 0, "(..)*(...)*", "abcd", "(0,4)(2,4)" },
+
+    // nullsubexpr.dat
+    { 0, 0, "(a*)*", "x", "(0,0)(0,0)" },
+    { 0, 0, "(a*)*", "aaaaaax", "(0,6)(0,6)" },
+    { 0, 0, "(a*)+", "aaaaaax", "(0,6)(0,6)" },
+    { 0, 0, "(a+)*", "x", "(0,0)" },
+    { 0, 0, "(a+)*", "aaaaaax", "(0,6)(0,6)" },
+    { 0, 0, "([a]*)*", "x", "(0,0)(0,0)" },
+    { 0, 0, "([a]*)+", "aaaaaax", "(0,6)(0,6)" },
+    { 0, 0, "([^b]*)*", "b", "(0,0)(0,0)" },
+    { 0, 0, "([^b]*)*", "aaaaaab", "(0,6)(0,6)" },
+    { 0, 0, "([ab]*)*", "ababab", "(0,6)(0,6)" },
+    { 0, 0, "([ab]*)*", "cccccc", "(0,0)(0,0)" },
+    { 0, 0, "(a*)*(x)", "ax", "(0,2)(0,1)(1,2)" },
+    { 0, 0, "(a*)+(x)", "aax", "(0,3)(0,2)(2,3)" },
+    { 0, 0, "(a*){2}(x)", "x", "(0,1)(0,0)(0,1)" },
+
+    // repetition.dat
+    { 0, 0, "((..)|(.))", "", "NOMATCH" },
+    { 0, 0, "((..)|(.))((..)|(.))", "", "NOMATCH" },
+    { 0, 0, "((..)|(.)){1}", "", "NOMATCH" },
+    { 0, 0, "((..)|(.)){2}", "", "NOMATCH" },
+    { 0, 0, "((..)|(.))", "a", "(0,1)(0,1)(?,?)(0,1)" },
+    { 0, 0, "((..)|(.))((..)|(.))", "a", "NOMATCH" },
+    { 0, 0, "((..)|(.))", "aa", "(0,2)(0,2)(0,2)" },
+    { 0, 0, "((..)|(.))((..)|(.))", "aa", "(0,2)(0,1)(?,?)(0,1)(1,2)(?,?)(1,2)" },
+    { 0, 0, "((..)|(.))((..)|(.))((..)|(.))", "aaa", "(0,3)(0,1)(?,?)(0,1)(1,2)(?,?)(1,2)(2,3)(?,?)(2,3)" },
+    { 0, 0, "X(.?){0,}Y", "X1234567Y", "(0,9)(7,8)" },
+    { 0, 0, "X(.?){1,}Y", "X1234567Y", "(0,9)(7,8)" },
+    { 0, 0, "X(.?){7,}Y", "X1234567Y", "(0,9)(7,8)" },
+    { 0, 0, "(a|ab|c|bcd){0,}(d*)", "ababcd", "(0,6)(3,6)(6,6)" },
+    { 0, 0, "(a|ab|c|bcd){1,}(d*)", "ababcd", "(0,6)(3,6)(6,6)" },
+    { 0, 0, "(a|ab|c|bcd)+(d*)", "ababcd", "(0,6)(3,6)(6,6)" },
+    { 0, 0, "(ab|a|c|bcd){0,}(d*)", "ababcd", "(0,6)(3,6)(6,6)" },
+    { 0, 0, "(a+|b+)*c", "aabbc", "(0,5)(2,4)" },
+
+    // rightassoc.dat
+    { 0, 0, "(a|ab)(c|bcd)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(a|ab)(bcd|c)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(ab|a)(c|bcd)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(ab|a)(bcd|c)(d*)", "abcd", "(0,4)(0,2)(2,3)(3,4)" },
+    { 0, 0, "(a*)(b|abc)(c*)", "abc", "(0,3)(0,1)(1,2)(2,3)" },
+    { 0, 0, "(a*)(abc|b)(c*)", "abc", "(0,3)(0,1)(1,2)(2,3)" },
+
+    // Newlines, anchors in alternations and execution flags
+    { REG_NEWLINE, 0, "^b", "a\nb", "(2,3)" },
+    { REG_NEWLINE, 0, "a$", "a\nb", "(0,1)" },
+    { 0, 0, "^b", "a\nb", "NOMATCH" },
+    { REG_NEWLINE, 0, "a.b", "a\nb", "NOMATCH" },
+    { 0, 0, "a.b", "a\nb", "(0,3)" },
+    { REG_NEWLINE, 0, "a[[:space:]]b", "a\nb", "(0,3)" },
+    { REG_NEWLINE, 0, "a[^x]b", "a\nb", "NOMATCH" },
+    { 0, 0, "x|^a", "ba", "NOMATCH" },
+    { 0, 0, "^a|b$", "cab", "(2,3)" },
+    { 0, 0, "(^a|b)c", "abc", "(1,3)(1,2)" },
+    { 0, REG_NOTBOL, "^a", "a", "NOMATCH" },
+    { 0, REG_NOTEOL, "a$", "a", "NOMATCH" },
+    { REG_NEWLINE, REG_NOTBOL, "^b", "a\nb", "(2,3)" },
+
+    // Back-references, which glibc allows in extended expressions too
+    { 0, 0, "(a+)b\\1", "aabaa", "(0,5)(0,2)" },
+    { 0, 0, "(a*)b\\1", "aaba", "(1,4)(1,2)" },
+    { REG_ICASE, 0, "(a)\\1", "aA", "(0,2)(0,1)" },
+};
+
+static const char *error_name(int error) {
+    switch (error) {
+        case REG_NOMATCH: return "NOMATCH";
+        case REG_BADPAT: return "BADPAT";
+        case REG_ECOLLATE: return "ECOLLATE";
+        case REG_ECTYPE: return "ECTYPE";
+        case REG_EESCAPE: return "EESCAPE";
+        case REG_ESUBREG: return "ESUBREG";
+        case REG_EBRACK: return "EBRACK";
+        case REG_EPAREN: return "EPAREN";
+        case REG_EBRACE: return "EBRACE";
+        case REG_BADBR: return "BADBR";
+        case REG_ERANGE: return "ERANGE";
+        case REG_ESPACE: return "ESPACE";
+        case REG_BADRPT: return "BADRPT";
+        default: return "?";
+    }
+}
+
+static void run_test(const struct test *test) {
+    char result[256] = "";
+    regex_t regex;
+    int error = regcomp(&regex, test->pattern, REG_EXTENDED | test->cflags);
+    if (error) {
+        strcpy(result, error_name(error));
+    } else {
+        regmatch_t matches[10];
+        error = regexec(&regex, test->input, 10, matches, test->eflags);
+        if (error) {
+            strcpy(result, error_name(error));
+        } else {
+            // Trailing unset subexpressions are left out, like in the vectors
+            size_t count = 1;
+            for (size_t i = 1; i < 10 && i <= regex.re_nsub; i++) {
+                if ((int) matches[i].rm_so != -1) {
+                    count = i + 1;
+                }
+            }
+            for (size_t i = 0; i < count; i++) {
+                size_t len = strlen(result);
+                if ((int) matches[i].rm_so == -1) {
+                    snprintf(result + len, sizeof(result) - len, "(?,?)");
+                } else {
+                    snprintf(result + len, sizeof(result) - len, "(%d,%d)",
+                             (int) matches[i].rm_so, (int) matches[i].rm_eo);
+                }
+            }
+        }
+        regfree(&regex);
+    }
+
+    if (strcmp(result, test->expected) == 0) {
+        printf("ok: %s\n", test->expected);
+    } else {
+        printf("FAIL: /%s/ on \"%s\": expected %s, got %s\n",
+               test->pattern, test->input, test->expected, result);
+    }
+}
+
 int main(void) {
     regex_t regex;
     char error_buf[256];
@@ -31,4 +356,36 @@ int main(void) {
     for (int group = 0; group < 3; group += 1) {
         printf("Matching group: %d - %d\n", matches[group].rm_so, matches[group].rm_eo);
     }
+
+    error = regcomp(&regex, "(h.llo|hey),? (w.rld)+", REG_EXTENDED | REG_ICASE);
+    if (error) {
+        regerror(error, &regex, error_buf, 255);
+        printf("regcomp error: %d = %s\n", error, error_buf);
+        exit(EXIT_FAILURE);
+    }
+    printf("re_nsub: %zu\n", regex.re_nsub);
+    error = regexec(&regex, "Hey, how are you? Hello? Hallo Wurld??", 3, matches, 0);
+    regfree(&regex);
+    if (error) {
+        printf("regexec error: %d\n", error);
+        exit(EXIT_FAILURE);
+    }
+    for (int group = 0; group < 3; group += 1) {
+        printf("Matching group: %d - %d\n", (int) matches[group].rm_so, (int) matches[group].rm_eo);
+    }
+
+    for (size_t i = 0; i < sizeof(tests) / sizeof(tests[0]); i++) {
+        run_test(&tests[i]);
+    }
+
+    // Nested repetitions take time linear in the input, rather than trying
+    // every way of splitting it up
+    static char long_input[100002];
+    memset(long_input, 'a', sizeof(long_input) - 2);
+    long_input[sizeof(long_input) - 2] = 'b';
+    struct test nested = { 0, 0, "(a|aa)*(a*)*c", long_input, "NOMATCH" };
+    run_test(&nested);
+    nested.pattern = "((a|b)*)*b";
+    nested.expected = "(0,100001)(0,100000)(99999,100000)";
+    run_test(&nested);
 }
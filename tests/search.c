@@ -0,0 +1,208 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <search.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "test_helpers.h"
+
+int compare_int(const void *a, const void *b) {
+    int x = *(const int *) a;
+    int y = *(const int *) b;
+    return (x > y) - (x < y);
+}
+
+int compare_str(const void *a, const void *b) {
+    return strcmp(a, b);
+}
+
+static int max_depth = 0;
+static int nodes = 0;
+
+void print_node(const void *node, VISIT visit, int depth) {
+    if (visit == postorder || visit == leaf) {
+        printf(" %d", **(int **) node);
+        nodes++;
+    }
+    if (depth > max_depth) {
+        max_depth = depth;
+    }
+}
+
+static int freed = 0;
+
+void free_node(void *key) {
+    freed++;
+}
+
+struct element {
+    struct element *next;
+    struct element *prev;
+    const char *name;
+};
+
+void print_list(struct element *first) {
+    for (struct element *e = first; e != NULL; e = e->next) {
+        printf(" %s", e->name);
+        if (e->next != NULL && e->next->prev != e) {
+            printf(" (bad back link)");
+        }
+    }
+    puts("");
+}
+
+void test_tsearch(void) {
+    static int keys[1000];
+    void *root = NULL;
+
+    // Insert 0..999 in a scrambled order, and every key a second time
+    for (int i = 0; i < 1000; i++) {
+        keys[i] = (i * 379) % 1000;
+        void *node = tsearch(&keys[i], &root, compare_int);
+        ERROR_IF(tsearch, node, == NULL);
+        UNEXP_IF(tsearch, *(int **) node != &keys[i], != 0);
+    }
+    for (int i = 0; i < 1000; i++) {
+        int key = i;
+        void *node = tsearch(&key, &root, compare_int);
+        UNEXP_IF(tsearch, **(int **) node != i, != 0);
+    }
+
+    int key = 500;
+    void *node = tfind(&key, &root, compare_int);
+    printf("tfind 500: %d\n", node ? **(int **) node : -1);
+    key = 1000;
+    printf("tfind 1000: %d\n", tfind(&key, &root, compare_int) != NULL);
+
+    // Delete every key that isn't a multiple of 10
+    for (int i = 0; i < 1000; i++) {
+        if (i % 10 != 0) {
+            key = i;
+            UNEXP_IF(tdelete, tdelete(&key, &root, compare_int), == NULL);
+        }
+    }
+    key = 5;
+    printf("tdelete 5 again: %d\n", tdelete(&key, &root, compare_int) != NULL);
+
+    printf("twalk:");
+    twalk(root, print_node);
+    printf("\n%d nodes, balanced: %d\n", nodes, max_depth < 14);
+
+    tdestroy(root, free_node);
+    printf("tdestroy freed %d keys\n", freed);
+
+    // Deleting the only node leaves an empty tree
+    root = NULL;
+    key = 1;
+    tsearch(&key, &root, compare_int);
+    printf("tdelete root: %d\n", tdelete(&key, &root, compare_int) != NULL);
+    printf("empty: %d\n", root == NULL);
+    twalk(root, print_node);
+}
+
+void test_hsearch(void) {
+    static char *words[] = {"alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta"};
+
+    int ret = hcreate(8);
+    ERROR_IF(hcreate, ret, == 0);
+
+    for (size_t i = 0; i < sizeof(words) / sizeof(words[0]); i++) {
+        ENTRY item = {words[i], (void *) (i + 1)};
+        ENTRY *found = hsearch(item, ENTER);
+        ERROR_IF(hsearch, found, == NULL);
+    }
+
+    // Entering an existing key keeps the old data
+    ENTRY item = {"gamma", (void *) 100};
+    ENTRY *found = hsearch(item, ENTER);
+    printf("gamma: %zu\n", (size_t) found->data);
+
+    item.key = "epsilon";
+    found = hsearch(item, FIND);
+    printf("epsilon: %zu\n", found ? (size_t) found->data : 0);
+
+    item.key = "omega";
+    errno = 0;
+    found = hsearch(item, FIND);
+    printf("omega: %d, ESRCH: %d\n", found != NULL, errno == ESRCH);
+
+    hdestroy();
+
+    // The reentrant versions work on a zeroed hsearch_data
+    struct hsearch_data htab;
+    memset(&htab, 0, sizeof(htab));
+    ret = hcreate_r(4, &htab);
+    ERROR_IF(hcreate_r, ret, == 0);
+
+    // The table fills up at some point
+    char keys[100][8];
+    int full = 0;
+    for (int i = 0; i < 100; i++) {
+        snprintf(keys[i], sizeof(keys[i]), "key%d", i);
+        ENTRY item = {keys[i], NULL};
+        ENTRY *retval;
+        if (!hsearch_r(item, ENTER, &retval, &htab)) {
+            full = errno == ENOMEM;
+            break;
+        }
+    }
+    printf("full: %d\n", full);
+
+    ENTRY *retval;
+    item.key = "key1";
+    ret = hsearch_r(item, FIND, &retval, &htab);
+    printf("key1: %d %s\n", ret, ret ? retval->key : "");
+
+    hdestroy_r(&htab);
+}
+
+void test_lsearch(void) {
+    int array[10] = {5, 3, 8};
+    size_t len = 3;
+
+    int key = 8;
+    int *found = lfind(&key, array, &len, sizeof(int), compare_int);
+    printf("lfind 8: index %td\n", found - array);
+    key = 4;
+    found = lfind(&key, array, &len, sizeof(int), compare_int);
+    printf("lfind 4: %d\n", found != NULL);
+
+    found = lsearch(&key, array, &len, sizeof(int), compare_int);
+    printf("lsearch 4: index %td, len %zu\n", found - array, len);
+    found = lsearch(&key, array, &len, sizeof(int), compare_int);
+    printf("lsearch 4 again: index %td, len %zu\n", found - array, len);
+
+    char names[4][8] = {"one", "two"};
+    len = 2;
+    char *name = lsearch("three", names, &len, sizeof(names[0]), compare_str);
+    printf("lsearch three: %s, len %zu\n", name, len);
+}
+
+void test_insque(void) {
+    struct element a = {.name = "a"}, b = {.name = "b"}, c = {.name = "c"}, d = {.name = "d"};
+
+    insque(&a, NULL);
+    insque(&c, &a);
+    insque(&b, &a);
+    insque(&d, &c);
+    printf("insque:");
+    print_list(&a);
+
+    remque(&b);
+    printf("remque b:");
+    print_list(&a);
+    remque(&d);
+    printf("remque d:");
+    print_list(&a);
+    remque(&a);
+    printf("remque a:");
+    print_list(&c);
+}
+
+int main(void) {
+    test_tsearch();
+    test_hsearch();
+    test_lsearch();
+    test_insque();
+}
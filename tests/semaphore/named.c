@@ -0,0 +1,79 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <semaphore.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define NAME "/relibc-test-semaphore"
+
+int main(void) {
+    sem_unlink(NAME);
+
+    sem_t *sem = sem_open(NAME, O_CREAT | O_EXCL, 0600, 1);
+    ERROR_IF(sem_open, sem, == SEM_FAILED);
+
+    sem_t *again = sem_open(NAME, O_CREAT | O_EXCL, 0600, 1);
+    int failed = again == SEM_FAILED;
+    printf("exclusive create of existing: %d (%s)\n", failed, strerror(errno));
+
+    // Opening it again gives the same semaphore at the same address
+    again = sem_open(NAME, 0);
+    ERROR_IF(sem_open, again, == SEM_FAILED);
+    printf("same address: %d\n", again == sem);
+    int value;
+    sem_getvalue(again, &value);
+    printf("value: %d\n", value);
+    sem_wait(sem);
+
+    // Another process posts to it by name
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        sem_t *child = sem_open(NAME, 0);
+        if (child == SEM_FAILED) {
+            _exit(1);
+        }
+        sem_post(child);
+        sem_close(child);
+        _exit(0);
+    }
+    int ret = sem_wait(sem);
+    ERROR_IF(sem_wait, ret, == -1);
+    int status;
+    waitpid(pid, &status, 0);
+    printf("child exited: %d\n", WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+    // Unlinking removes the name, but not the open semaphore
+    ret = sem_unlink(NAME);
+    ERROR_IF(sem_unlink, ret, == -1);
+    ret = sem_unlink(NAME);
+    printf("unlink again: %d (%s)\n", ret, strerror(errno));
+    failed = sem_open(NAME, 0) == SEM_FAILED;
+    printf("open unlinked: %d (%s)\n", failed, strerror(errno));
+    sem_post(sem);
+    sem_getvalue(sem, &value);
+    printf("value after unlink: %d\n", value);
+
+    // A new semaphore of the same name is a different one
+    sem_t *other = sem_open(NAME, O_CREAT, 0600, 5);
+    ERROR_IF(sem_open, other, == SEM_FAILED);
+    sem_getvalue(other, &value);
+    printf("new semaphore value: %d\n", value);
+    sem_close(other);
+    sem_unlink(NAME);
+
+    // Each open needs its own close
+    ret = sem_close(sem);
+    ERROR_IF(sem_close, ret, == -1);
+    sem_getvalue(again, &value);
+    printf("value after first close: %d\n", value);
+    ret = sem_close(again);
+    ERROR_IF(sem_close, ret, == -1);
+
+    failed = sem_open("/a/b", O_CREAT, 0600, 0) == SEM_FAILED;
+    printf("bad name: %d (%s)\n", failed, strerror(errno));
+}
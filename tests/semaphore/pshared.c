@@ -0,0 +1,54 @@
+#include <semaphore.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+#define ROUNDS 100
+
+struct shared {
+    sem_t ping;
+    sem_t pong;
+    int counter;
+};
+
+int main(void) {
+    struct shared *shared = mmap(NULL, sizeof(struct shared), PROT_READ | PROT_WRITE,
+                                 MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    ERROR_IF(mmap, shared, == MAP_FAILED);
+
+    int ret = sem_init(&shared->ping, 1, 0);
+    ERROR_IF(sem_init, ret, == -1);
+    ret = sem_init(&shared->pong, 1, 0);
+    ERROR_IF(sem_init, ret, == -1);
+
+    // The processes take turns incrementing the counter
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        for (int i = 0; i < ROUNDS; i++) {
+            sem_wait(&shared->ping);
+            shared->counter++;
+            sem_post(&shared->pong);
+        }
+        _exit(0);
+    }
+
+    for (int i = 0; i < ROUNDS; i++) {
+        shared->counter++;
+        sem_post(&shared->ping);
+        sem_wait(&shared->pong);
+    }
+
+    int status;
+    ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    printf("child exited: %d\n", WIFEXITED(status) && WEXITSTATUS(status) == 0);
+    printf("counter: %d\n", shared->counter);
+
+    sem_destroy(&shared->ping);
+    sem_destroy(&shared->pong);
+    munmap(shared, sizeof(struct shared));
+}
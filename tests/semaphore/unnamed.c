@@ -0,0 +1,98 @@
+#include <errno.h>
+#include <limits.h>
+#include <pthread.h>
+#include <semaphore.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+#define THREADS 4
+#define ITEMS 1000
+
+static sem_t items;
+static sem_t slots;
+static int consumed[THREADS];
+
+static void *consumer(void *arg) {
+    int *count = arg;
+    for (int i = 0; i < ITEMS / THREADS; i++) {
+        int ret = sem_wait(&items);
+        ERROR_IF(sem_wait, ret, == -1);
+        (*count)++;
+        sem_post(&slots);
+    }
+    return NULL;
+}
+
+static void timeout_in(clockid_t clock, struct timespec *abstime, long nsec) {
+    clock_gettime(clock, abstime);
+    abstime->tv_nsec += nsec;
+    if (abstime->tv_nsec >= 1000000000) {
+        abstime->tv_sec++;
+        abstime->tv_nsec -= 1000000000;
+    }
+}
+
+int main(void) {
+    sem_t sem;
+    int ret = sem_init(&sem, 0, 2);
+    ERROR_IF(sem_init, ret, == -1);
+
+    int value;
+    sem_getvalue(&sem, &value);
+    printf("value: %d\n", value);
+    printf("trywait: %d\n", sem_trywait(&sem));
+    printf("trywait: %d\n", sem_trywait(&sem));
+    ret = sem_trywait(&sem);
+    printf("trywait: %d (%s)\n", ret, strerror(errno));
+    sem_post(&sem);
+    sem_getvalue(&sem, &value);
+    printf("value after post: %d\n", value);
+    sem_destroy(&sem);
+
+    ret = sem_init(&sem, 0, (unsigned) SEM_VALUE_MAX + 1);
+    printf("init above SEM_VALUE_MAX: %d (%s)\n", ret, strerror(errno));
+    sem_init(&sem, 0, SEM_VALUE_MAX);
+    ret = sem_post(&sem);
+    printf("post at SEM_VALUE_MAX: %d (%s)\n", ret, strerror(errno));
+    sem_destroy(&sem);
+
+    // A bounded queue with several consumers
+    sem_init(&items, 0, 0);
+    sem_init(&slots, 0, 8);
+    pthread_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        ret = pthread_create(&threads[i], NULL, consumer, &consumed[i]);
+        ERROR_IF(pthread_create, ret, != 0);
+    }
+    for (int i = 0; i < ITEMS; i++) {
+        sem_wait(&slots);
+        sem_post(&items);
+    }
+    int total = 0;
+    for (int i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+        total += consumed[i];
+    }
+    printf("consumed: %d\n", total);
+    sem_getvalue(&slots, &value);
+    printf("slots left: %d\n", value);
+
+    // Timeouts
+    struct timespec abstime;
+    timeout_in(CLOCK_REALTIME, &abstime, 50000000);
+    ret = sem_timedwait(&items, &abstime);
+    printf("timedwait: %d (%s)\n", ret, strerror(errno));
+    timeout_in(CLOCK_MONOTONIC, &abstime, 50000000);
+    ret = sem_clockwait(&items, CLOCK_MONOTONIC, &abstime);
+    printf("clockwait: %d (%s)\n", ret, strerror(errno));
+
+    abstime.tv_nsec = 1000000000;
+    ret = sem_timedwait(&items, &abstime);
+    printf("bad abstime: %d (%s)\n", ret, strerror(errno));
+
+    sem_destroy(&items);
+    sem_destroy(&slots);
+}
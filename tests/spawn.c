@@ -0,0 +1,203 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <sched.h>
+#include <signal.h>
+#include <spawn.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+extern char **environ;
+
+static pid_t run(const char *command, const posix_spawn_file_actions_t *file_actions, const posix_spawnattr_t *attr) {
+    char *argv[] = {"sh", "-c", (char *) command, NULL};
+    pid_t pid;
+    fflush(stdout);
+    int ret = posix_spawn(&pid, "/bin/sh", file_actions, attr, argv, environ);
+    if (ret != 0) {
+        printf("posix_spawn: %s\n", strerror(ret));
+        return -1;
+    }
+    return pid;
+}
+
+static void print_status(const char *name, pid_t pid) {
+    int status;
+    pid_t ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    if (WIFEXITED(status)) {
+        printf("%s: exit %d\n", name, WEXITSTATUS(status));
+    } else if (WIFSIGNALED(status)) {
+        printf("%s: signal %d\n", name, WTERMSIG(status));
+    }
+}
+
+// Runs a command with its standard output going to a pipe, and prints what
+// it wrote
+static void capture(const char *name, const char *command, posix_spawn_file_actions_t *file_actions) {
+    int fds[2];
+    ERROR_IF(pipe, pipe(fds), == -1);
+    posix_spawn_file_actions_adddup2(file_actions, fds[1], 1);
+    posix_spawn_file_actions_addclose(file_actions, fds[0]);
+    posix_spawn_file_actions_addclose(file_actions, fds[1]);
+
+    pid_t pid = run(command, file_actions, NULL);
+    close(fds[1]);
+    char buf[256] = {0};
+    ssize_t len = read(fds[0], buf, sizeof(buf) - 1);
+    close(fds[0]);
+    if (len > 0 && buf[len - 1] == '\n') {
+        buf[len - 1] = 0;
+    }
+    printf("%s: %s\n", name, buf);
+    if (pid != -1) {
+        waitpid(pid, NULL, 0);
+    }
+}
+
+int main(void) {
+    // Plain spawning
+    print_status("exit 3", run("exit 3", NULL, NULL));
+
+    char *echo_argv[] = {"echo", "found in PATH", NULL};
+    pid_t pid;
+    fflush(stdout);
+    int ret = posix_spawnp(&pid, "echo", NULL, NULL, echo_argv, environ);
+    ERROR_IF(posix_spawnp, ret, != 0);
+    print_status("posix_spawnp", pid);
+
+    // Failing to exec is reported by the return value
+    char *argv[] = {"missing", NULL};
+    ret = posix_spawn(&pid, "/nonexistent/missing", NULL, NULL, argv, environ);
+    printf("missing path: %s\n", strerror(ret));
+    ret = posix_spawnp(&pid, "relibc-missing-command", NULL, NULL, argv, environ);
+    printf("missing command: %s\n", strerror(ret));
+
+    int fd = open("spawn_tmp", O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    ERROR_IF(open, fd, == -1);
+    close(fd);
+    ret = posix_spawnp(&pid, "./spawn_tmp", NULL, NULL, argv, environ);
+    printf("not executable: %s\n", strerror(ret));
+
+    // File actions
+    posix_spawn_file_actions_t file_actions;
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_addopen(&file_actions, 1, "spawn_tmp", O_WRONLY | O_TRUNC, 0);
+    print_status("addopen", run("echo redirected", &file_actions, NULL));
+    posix_spawn_file_actions_destroy(&file_actions);
+
+    char buf[64] = {0};
+    fd = open("spawn_tmp", O_RDONLY);
+    ERROR_IF(open, fd, == -1);
+    read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    printf("spawn_tmp: %s", buf);
+    unlink("spawn_tmp");
+
+    posix_spawn_file_actions_init(&file_actions);
+    capture("adddup2", "echo piped", &file_actions);
+    posix_spawn_file_actions_destroy(&file_actions);
+
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_addchdir_np(&file_actions, "/");
+    capture("addchdir_np", "pwd", &file_actions);
+    posix_spawn_file_actions_destroy(&file_actions);
+
+    int dir = open("example_dir", O_RDONLY | O_DIRECTORY);
+    ERROR_IF(open, dir, == -1);
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_addfchdir_np(&file_actions, dir);
+    capture("addfchdir_np", "basename \"$(pwd)\"", &file_actions);
+    posix_spawn_file_actions_destroy(&file_actions);
+    close(dir);
+
+    ERROR_IF(dup2, dup2(1, 9), == -1);
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_addclose(&file_actions, 9);
+    print_status("addclose", run("(echo unreachable >&9) 2>/dev/null", &file_actions, NULL));
+    posix_spawn_file_actions_destroy(&file_actions);
+    close(9);
+
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_addopen(&file_actions, 3, "/nonexistent/file", O_RDONLY, 0);
+    ret = posix_spawn(&pid, "/bin/sh", &file_actions, NULL, argv, environ);
+    printf("failed file action: %s\n", strerror(ret));
+    posix_spawn_file_actions_destroy(&file_actions);
+
+    posix_spawn_file_actions_init(&file_actions);
+    printf("addclose -1: %s\n", strerror(posix_spawn_file_actions_addclose(&file_actions, -1)));
+    posix_spawn_file_actions_destroy(&file_actions);
+
+    // Attributes
+    posix_spawnattr_t attr;
+    posix_spawnattr_init(&attr);
+    printf("invalid flags: %s\n", strerror(posix_spawnattr_setflags(&attr, 0x4000)));
+
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    posix_spawnattr_setsigmask(&attr, &set);
+    posix_spawnattr_setflags(&attr, POSIX_SPAWN_SETSIGMASK);
+    print_status("blocked", run("kill -USR1 $$; exit 0", NULL, &attr));
+    posix_spawnattr_setflags(&attr, 0);
+    print_status("default", run("kill -USR1 $$; exit 0", NULL, &attr));
+
+    signal(SIGUSR1, SIG_IGN);
+    print_status("ignored", run("kill -USR1 $$; exit 0", NULL, &attr));
+    posix_spawnattr_setsigdefault(&attr, &set);
+    posix_spawnattr_setflags(&attr, POSIX_SPAWN_SETSIGDEF);
+    print_status("sigdefault", run("kill -USR1 $$; exit 0", NULL, &attr));
+    signal(SIGUSR1, SIG_DFL);
+
+    // The child is kept waiting on a pipe while its process group is checked
+    posix_spawnattr_setflags(&attr, POSIX_SPAWN_SETPGROUP);
+    posix_spawnattr_setpgroup(&attr, 0);
+    int fds[2];
+    ERROR_IF(pipe, pipe(fds), == -1);
+    posix_spawn_file_actions_init(&file_actions);
+    posix_spawn_file_actions_adddup2(&file_actions, fds[0], 0);
+    posix_spawn_file_actions_addclose(&file_actions, fds[1]);
+    pid = run("read line", &file_actions, &attr);
+    posix_spawn_file_actions_destroy(&file_actions);
+    printf("own process group: %d\n", getpgid(pid) == pid);
+    close(fds[0]);
+    close(fds[1]);
+    print_status("pgroup", pid);
+
+    struct sched_param param = {0};
+    posix_spawnattr_setschedpolicy(&attr, SCHED_OTHER);
+    posix_spawnattr_setschedparam(&attr, &param);
+    posix_spawnattr_setflags(&attr, POSIX_SPAWN_SETSCHEDULER | POSIX_SPAWN_RESETIDS);
+    print_status("scheduler", run("exit 0", NULL, &attr));
+
+    short flags;
+    int policy;
+    posix_spawnattr_getflags(&attr, &flags);
+    posix_spawnattr_getschedpolicy(&attr, &policy);
+    printf("flags: %#x, policy: %d\n", flags, policy);
+    posix_spawnattr_destroy(&attr);
+
+    // system and popen are built on top
+    int status = system("exit 5");
+    printf("system: %d\n", WEXITSTATUS(status));
+    printf("system(NULL): %d\n", system(NULL) != 0);
+
+    FILE *fp = popen("echo from popen", "r");
+    ERROR_IF(popen, fp, == NULL);
+    char line[64];
+    fgets(line, sizeof(line), fp);
+    printf("popen: %s", line);
+    printf("pclose: %d\n", WEXITSTATUS(pclose(fp)));
+
+    fflush(stdout);
+    fp = popen("cat", "w");
+    ERROR_IF(popen, fp, == NULL);
+    fputs("written to popen\n", fp);
+    pclose(fp);
+}
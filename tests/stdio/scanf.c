@@ -1,5 +1,7 @@
 #include <stdarg.h>
 #include <stdio.h>
+#include <stdlib.h>
+#include <wchar.h>
 
 #include "test_helpers.h"
 
@@ -78,4 +80,34 @@ int main(void) {
     }
 
     printf("%d \"%s\" \"%s\" \"%s\" \"%s\"\n", ret, &protobuf, &slashbuf, &hostbuf, &pathbuf);
+
+    // Floating point conversions share their parser with strtod
+    float f1 = 0, f2 = 0;
+    double d1 = 0, d2 = 0, d3 = 0, d4 = 0;
+    ret = sscanf("-1.5e3 0x1.8p1 INFINITY -nan 2.5E-1x", "%f %la %lg %le %lf", &f1, &d1, &d2, &d3, &d4);
+    printf("%d %f %f %f %f %f\n", ret, f1, d1, d2, d3, d4);
+    ret = sscanf("3.14159", "%4f", &f2);
+    printf("%d %f\n", ret, f2);
+
+    // Signed integers and %n with length modifiers
+    int i1 = 0;
+    signed char hhn = 0;
+    long ln = 0;
+    size_t zn = 0;
+    ret = sscanf("-42 +7 end", "%d%hhn %*d%ln %3s%zn", &i1, &hhn, &ln, protobuf, &zn);
+    printf("%d %d %d %ld \"%s\" %zu\n", ret, i1, hhn, ln, protobuf, zn);
+
+    // Ranges in scansets, and the assignment-allocation modifier
+    char *alloc1 = NULL, *alloc2 = NULL, *alloc3 = NULL;
+    ret = sscanf("abc-xyz123 hello", "%m[a-z-]%m[0-9] %2mc", &alloc1, &alloc2, &alloc3);
+    printf("%d \"%s\" \"%s\" \"%.2s\"\n", ret, alloc1, alloc2, alloc3);
+    free(alloc1);
+    free(alloc2);
+    free(alloc3);
+
+    // Wide conversions decode UTF-8 input
+    wchar_t wbuf[8] = { 0 };
+    wchar_t wc = 0;
+    ret = sscanf("\xc3\xa9t\xc3\xa9 \xe2\x82\xac", "%ls %lc", wbuf, &wc);
+    printf("%d %d %x %x %x %x\n", ret, (int) wcslen(wbuf), wbuf[0], wbuf[1], wbuf[2], wc);
 }
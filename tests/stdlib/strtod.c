@@ -1,3 +1,4 @@
+#include <errno.h>
 #include <stdlib.h>
 #include <stdio.h>
 
@@ -10,10 +11,14 @@ int main(void) {
     char* inputs[] = {
         "a 1 hello", " 1 hello", "1 hello 2",
         "10.123", "010.123", "-5.3",
-        "0x10.123", "0x1.23", "0x3.21"
+        "0x10.123", "0x1.23", "0x3.21",
+        "1e", "1.5e+3x", "0x1p-3", "-INFINITY", "nan(123)x",
+        "2.2250738585072011e-308", "9007199254740993",
+        "1e400", "1e-400", "0x"
     };
     for (int i = 0; i < sizeof(inputs) / sizeof(char*); i += 1) {
+        errno = 0;
         d = strtod(inputs[i], &endptr);
-        printf("d: %f Endptr: \"%s\"\n", d, endptr);
+        printf("d: %.17g Endptr: \"%s\" ERANGE: %d\n", d, endptr, errno == ERANGE);
     }
 }
@@ -0,0 +1,30 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+extern uintptr_t __stack_chk_guard;
+void __stack_chk_fail(void);
+
+int main(void) {
+    printf("AT_PAGESZ: %d\n", getauxval(AT_PAGESZ) == (unsigned long) getpagesize());
+    printf("AT_UID: %d\n", getauxval(AT_UID) == getuid());
+    printf("AT_EUID: %d\n", getauxval(AT_EUID) == geteuid());
+    printf("AT_GID: %d\n", getauxval(AT_GID) == getgid());
+    printf("AT_EGID: %d\n", getauxval(AT_EGID) == getegid());
+    printf("AT_RANDOM: %d\n", getauxval(AT_RANDOM) != 0);
+
+    errno = 0;
+    unsigned long missing = getauxval(0xdead);
+    printf("missing: %lu %s\n", missing, strerror(errno));
+
+    // The first bytes of AT_RANDOM, with a zero low byte
+    unsigned long random = *(unsigned long *) getauxval(AT_RANDOM);
+    printf("canary from AT_RANDOM: %d\n", __stack_chk_guard == (random & ~0xffUL));
+
+    printf("__stack_chk_fail aborts: %d\n", killed_by_signal(__stack_chk_fail));
+}
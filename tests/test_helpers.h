@@ -5,6 +5,7 @@
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <sys/wait.h>
 #include <unistd.h>
 
 // Throws errors on a well-defined API error values.
@@ -78,6 +79,28 @@
         } \
     } while (0)
 
+// Runs `f` in a child process and returns whether a signal killed it, as
+// expected of checks that abort or accesses that fault. abort() may trap
+// rather than raise SIGABRT, so any signal counts.
+//
+// Usage example:
+//
+// printf("overflow aborts: %d\n", killed_by_signal(overflow));
+//
+static inline int killed_by_signal(void (*f)(void)) {
+    fflush(stdout);
+    pid_t pid = fork();
+    ERROR_IF(fork, pid, == -1);
+    if (pid == 0) {
+        f();
+        _exit(0);
+    }
+    int status;
+    pid_t ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    return WIFSIGNALED(status);
+}
+
 // A convenience macro to show where the test fail.
 #define exit(code) \
     do { \
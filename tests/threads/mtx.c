@@ -0,0 +1,101 @@
+#include <stdio.h>
+#include <threads.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+#define THREADS 4
+#define ROUNDS 10000
+
+static mtx_t lock;
+static cnd_t cond;
+static int counter = 0;
+static int ready = 0;
+
+static int adder(void *arg) {
+    for (int i = 0; i < ROUNDS; i++) {
+        mtx_lock(&lock);
+        counter++;
+        mtx_unlock(&lock);
+    }
+    return 0;
+}
+
+static int signaler(void *arg) {
+    mtx_lock(&lock);
+    ready = 1;
+    cnd_signal(&cond);
+    mtx_unlock(&lock);
+    return 0;
+}
+
+static int trylocker(void *arg) {
+    return mtx_trylock((mtx_t *) arg);
+}
+
+static struct timespec soon(void) {
+    struct timespec ts;
+    clock_gettime(CLOCK_REALTIME, &ts);
+    ts.tv_nsec += 10000000;
+    if (ts.tv_nsec >= 1000000000) {
+        ts.tv_sec++;
+        ts.tv_nsec -= 1000000000;
+    }
+    return ts;
+}
+
+int main(void) {
+    int ret = mtx_init(&lock, mtx_plain);
+    ERROR_IF(mtx_init, ret, != thrd_success);
+    ret = cnd_init(&cond);
+    ERROR_IF(cnd_init, ret, != thrd_success);
+
+    thrd_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        thrd_create(&threads[i], adder, NULL);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        thrd_join(threads[i], NULL);
+    }
+    printf("counter: %d\n", counter);
+
+    thrd_t thread;
+    mtx_lock(&lock);
+    thrd_create(&thread, signaler, NULL);
+    while (!ready) {
+        cnd_wait(&cond, &lock);
+    }
+    mtx_unlock(&lock);
+    thrd_join(thread, NULL);
+    printf("signaled: %d\n", ready);
+
+    mtx_lock(&lock);
+    struct timespec ts = soon();
+    printf("cnd_timedwait timeout: %d\n", cnd_timedwait(&cond, &lock, &ts) == thrd_timedout);
+    mtx_unlock(&lock);
+
+    mtx_t timed;
+    ret = mtx_init(&timed, mtx_timed);
+    ERROR_IF(mtx_init, ret, != thrd_success);
+    mtx_lock(&timed);
+    thrd_create(&thread, trylocker, &timed);
+    thrd_join(thread, &ret);
+    printf("trylock held: %d\n", ret == thrd_busy);
+    mtx_unlock(&timed);
+    ts = soon();
+    printf("timedlock free: %d\n", mtx_timedlock(&timed, &ts) == thrd_success);
+    mtx_unlock(&timed);
+    mtx_destroy(&timed);
+
+    mtx_t recursive;
+    ret = mtx_init(&recursive, mtx_plain | mtx_recursive);
+    ERROR_IF(mtx_init, ret, != thrd_success);
+    printf("recursive lock: %d\n", mtx_lock(&recursive) == thrd_success);
+    printf("recursive relock: %d\n", mtx_trylock(&recursive) == thrd_success);
+    mtx_unlock(&recursive);
+    mtx_unlock(&recursive);
+    mtx_destroy(&recursive);
+
+    cnd_destroy(&cond);
+    mtx_destroy(&lock);
+}
@@ -0,0 +1,60 @@
+#include <stdio.h>
+#include <threads.h>
+#include <time.h>
+
+#include "test_helpers.h"
+
+static thrd_t main_thread;
+
+static int worker(void *arg) {
+    if (thrd_equal(thrd_current(), main_thread)) {
+        return -1;
+    }
+    thrd_yield();
+    return *(int *) arg * 2;
+}
+
+static int exiter(void *arg) {
+    thrd_exit(42);
+    return 0;
+}
+
+static int detached(void *arg) {
+    __atomic_store_n((int *) arg, 1, __ATOMIC_SEQ_CST);
+    return 0;
+}
+
+int main(void) {
+    main_thread = thrd_current();
+    printf("equal self: %d\n", thrd_equal(main_thread, thrd_current()) != 0);
+
+    int values[] = {1, 2, 3};
+    thrd_t threads[3];
+    for (int i = 0; i < 3; i++) {
+        int ret = thrd_create(&threads[i], worker, &values[i]);
+        ERROR_IF(thrd_create, ret, != thrd_success);
+    }
+    for (int i = 0; i < 3; i++) {
+        int res;
+        int ret = thrd_join(threads[i], &res);
+        ERROR_IF(thrd_join, ret, != thrd_success);
+        printf("thread %d returned %d\n", i, res);
+    }
+
+    thrd_t thread;
+    thrd_create(&thread, exiter, NULL);
+    int res;
+    thrd_join(thread, &res);
+    printf("thrd_exit: %d\n", res);
+
+    int done = 0;
+    thrd_create(&thread, detached, &done);
+    int ret = thrd_detach(thread);
+    ERROR_IF(thrd_detach, ret, != thrd_success);
+    struct timespec delay = {0, 1000000};
+    while (!__atomic_load_n(&done, __ATOMIC_SEQ_CST)) {
+        ret = thrd_sleep(&delay, NULL);
+        ERROR_IF(thrd_sleep, ret, != 0);
+    }
+    printf("detached thread ran\n");
+}
@@ -0,0 +1,58 @@
+#include <stdio.h>
+#include <threads.h>
+
+#include "test_helpers.h"
+
+#define THREADS 4
+
+static tss_t key;
+static int destructed = 0;
+static once_flag once = ONCE_FLAG_INIT;
+static int calls = 0;
+
+static void destructor(void *value) {
+    __atomic_add_fetch(&destructed, *(int *) value, __ATOMIC_SEQ_CST);
+}
+
+static void init(void) {
+    __atomic_add_fetch(&calls, 1, __ATOMIC_SEQ_CST);
+}
+
+static int worker(void *arg) {
+    call_once(&once, init);
+    if (tss_get(key) != NULL) {
+        return 1;
+    }
+    int ret = tss_set(key, arg);
+    if (ret != thrd_success || tss_get(key) != arg) {
+        return 1;
+    }
+    return 0;
+}
+
+int main(void) {
+    int ret = tss_create(&key, destructor);
+    ERROR_IF(tss_create, ret, != thrd_success);
+
+    int main_value = 100;
+    tss_set(key, &main_value);
+
+    int values[THREADS] = {1, 2, 3, 4};
+    thrd_t threads[THREADS];
+    for (int i = 0; i < THREADS; i++) {
+        ret = thrd_create(&threads[i], worker, &values[i]);
+        ERROR_IF(thrd_create, ret, != thrd_success);
+    }
+    for (int i = 0; i < THREADS; i++) {
+        int res;
+        thrd_join(threads[i], &res);
+        ERROR_IF(tss_set, res, != 0);
+    }
+    call_once(&once, init);
+
+    printf("main value: %d\n", *(int *) tss_get(key));
+    printf("destructed: %d\n", destructed);
+    printf("call_once calls: %d\n", calls);
+    printf("destructor iterations: %d\n", TSS_DTOR_ITERATIONS);
+    tss_delete(key);
+}
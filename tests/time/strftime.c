@@ -1,6 +1,8 @@
+#include <locale.h>
 #include <stdlib.h>
 #include <stdio.h>
 #include <time.h>
+#include <unistd.h>
 
 #include "test_helpers.h"
 
@@ -20,5 +22,27 @@ int main(void) {
     print(1531839600, "%H %s %u");
     print(1531839600, "%j %U");
     print(1531839600, "%+");
+    print(1531839600, "%c");
+    print(1531808742, "%x %X %P");
     print(1533669431, "%+%+%+%+%+"); // will overflow 50 characters
+
+    // The formats of a locale may not refer to each other, as one referring
+    // to itself would never end
+    FILE* f = fopen("/tmp/strftime_locale", "w");
+    ERROR_IF(fopen, f, == NULL);
+    fputs("[LC_TIME]\n"
+          "d_t_fmt = %c\n"
+          "d_fmt = %d.%m.%Y\n", f);
+    fclose(f);
+    setenv("LOCPATH", "/tmp", 1);
+    locale_t loc = newlocale(LC_TIME_MASK, "strftime_locale", (locale_t) 0);
+    ERROR_IF(newlocale, loc, == (locale_t) 0);
+    uselocale(loc);
+    print(1531808742, "%x");
+    char buf[50];
+    time_t timestamp = 1531808742;
+    printf("looping %%c: %zu\n", strftime(buf, sizeof(buf), "%c", localtime(&timestamp)));
+    uselocale(LC_GLOBAL_LOCALE);
+    freelocale(loc);
+    unlink("/tmp/strftime_locale");
 }
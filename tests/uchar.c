@@ -0,0 +1,105 @@
+#include <errno.h>
+#include <locale.h>
+#include <stdio.h>
+#include <string.h>
+#include <uchar.h>
+
+static void print_result(size_t rc) {
+    if (rc == (size_t) -1) {
+        printf("-1 (%s)", errno == EILSEQ ? "EILSEQ" : "?");
+    } else if (rc == (size_t) -2) {
+        printf("-2");
+    } else if (rc == (size_t) -3) {
+        printf("-3");
+    } else {
+        printf("%zu", rc);
+    }
+}
+
+int main(void) {
+    setlocale(LC_ALL, "C.UTF-8");
+
+    const char *in = "a\xc3\xa9\xe6\xb0\xb4\xf0\x9f\x8d\x8c";
+    const char *p = in;
+    const char *end = in + strlen(in) + 1;
+    mbstate_t state;
+    memset(&state, 0, sizeof state);
+
+    // Characters outside the BMP come out as two calls
+    char16_t utf16[16];
+    size_t n16 = 0;
+    for (;;) {
+        char16_t c16 = 0;
+        size_t rc = mbrtoc16(&c16, p, end - p, &state);
+        printf("mbrtoc16: ");
+        print_result(rc);
+        printf(" c16=%04x\n", c16);
+        if (rc == 0) {
+            break;
+        }
+        utf16[n16++] = c16;
+        if (rc != (size_t) -3) {
+            p += rc;
+        }
+    }
+
+    // And go back together
+    char out[32];
+    size_t len = 0;
+    memset(&state, 0, sizeof state);
+    for (size_t i = 0; i < n16; i++) {
+        size_t rc = c16rtomb(out + len, utf16[i], &state);
+        printf("c16rtomb(%04x): ", utf16[i]);
+        print_result(rc);
+        printf("\n");
+        len += rc;
+    }
+    printf("round trip: %s\n", len == strlen(in) && memcmp(out, in, len) == 0 ? "ok" : "mismatch");
+
+    memset(&state, 0, sizeof state);
+    errno = 0;
+    printf("lone low surrogate: ");
+    print_result(c16rtomb(out, 0xdc00, &state));
+    printf("\n");
+
+    memset(&state, 0, sizeof state);
+    c16rtomb(out, 0xd83c, &state);
+    errno = 0;
+    printf("unpaired high surrogate: ");
+    print_result(c16rtomb(out, 'x', &state));
+    printf("\n");
+
+    // char32_t is plain UTF-32
+    memset(&state, 0, sizeof state);
+    p = in;
+    for (;;) {
+        char32_t c32 = 0;
+        size_t rc = mbrtoc32(&c32, p, end - p, &state);
+        printf("mbrtoc32: ");
+        print_result(rc);
+        printf(" c32=%x\n", c32);
+        if (rc == 0 || rc > 4) {
+            break;
+        }
+        p += rc;
+    }
+
+    memset(&state, 0, sizeof state);
+    printf("mbrtoc32 partial: ");
+    print_result(mbrtoc32(NULL, "\xf0\x9f", 2, &state));
+    printf("\n");
+
+    memset(&state, 0, sizeof state);
+    size_t rc = c32rtomb(out, 0x1f34c, &state);
+    printf("c32rtomb: ");
+    print_result(rc);
+    for (size_t i = 0; rc != (size_t) -1 && i < rc; i++) {
+        printf(" %02x", (unsigned char) out[i]);
+    }
+    printf("\n");
+
+    errno = 0;
+    printf("c32rtomb(0xd800): ");
+    print_result(c32rtomb(out, 0xd800, &state));
+    printf("\n");
+}
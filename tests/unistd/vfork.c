@@ -0,0 +1,52 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test_helpers.h"
+
+// Deep enough that the child's calls overwrite the frames below vfork's caller
+static int depth(int n) {
+    volatile char buf[256];
+    buf[0] = n;
+    return n == 0 ? buf[0] : depth(n - 1) + buf[0];
+}
+
+static int shared = 0;
+
+static int spawn(int code) {
+    pid_t pid = vfork();
+    ERROR_IF(vfork, pid, == -1);
+    if (pid == 0) {
+        // Memory is shared with the parent, which waits until this exits
+        shared = code;
+        depth(16);
+        _exit(code);
+    }
+    printf("shared: %d\n", shared);
+
+    int status;
+    pid_t ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    UNEXP_IF(waitpid, ret, != pid);
+    return WEXITSTATUS(status);
+}
+
+int main(void) {
+    fflush(stdout);
+    printf("exit status: %d\n", spawn(3));
+    printf("exit status: %d\n", spawn(5));
+
+    fflush(stdout);
+    pid_t pid = vfork();
+    ERROR_IF(vfork, pid, == -1);
+    if (pid == 0) {
+        char *argv[] = {"sh", "-c", "echo exec from child", NULL};
+        execv("/bin/sh", argv);
+        _exit(127);
+    }
+    int status;
+    pid_t ret = waitpid(pid, &status, 0);
+    ERROR_IF(waitpid, ret, == -1);
+    printf("exec status: %d\n", WEXITSTATUS(status));
+}
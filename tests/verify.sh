@@ -11,7 +11,7 @@ do
 	echo "# ${name} #"
 	mkdir -p "gen/$(dirname ${name})" || exit 1
 
-	"bins/${name}" test args > "gen/${name}.stdout" 2> "gen/${name}.stderr"
+	"${BINS_DIR:-bins}/${name}" test args > "gen/${name}.stdout" 2> "gen/${name}.stderr"
     status="$?"
 
     for output in stdout stderr
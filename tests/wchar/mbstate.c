@@ -0,0 +1,131 @@
+#include <errno.h>
+#include <locale.h>
+#include <stdio.h>
+#include <string.h>
+#include <wchar.h>
+
+static void print_result(size_t rc) {
+    if (rc == (size_t) -1) {
+        printf("-1 (%s)", errno == EILSEQ ? "EILSEQ" : "?");
+    } else if (rc == (size_t) -2) {
+        printf("-2");
+    } else {
+        printf("%zu", rc);
+    }
+}
+
+int main(void) {
+    setlocale(LC_ALL, "C.UTF-8");
+
+    // Feed one byte at a time
+    const char *in = "z\xc3\x9f\xe6\xb0\xb4\xf0\x9f\x8d\x8c";
+    mbstate_t state;
+    memset(&state, 0, sizeof state);
+    printf("mbsinit(initial) = %d\n", mbsinit(&state) != 0);
+    for (size_t i = 0; i < strlen(in); i++) {
+        wchar_t wc = 0;
+        size_t rc = mbrtowc(&wc, &in[i], 1, &state);
+        printf("byte %02x: ", (unsigned char) in[i]);
+        print_result(rc);
+        if (rc != (size_t) -2) {
+            printf(" wc=%x", wc);
+        }
+        printf(" mbsinit=%d\n", mbsinit(&state) != 0);
+    }
+
+    // Split a character across two calls with more than one byte each
+    wchar_t wc = 0;
+    memset(&state, 0, sizeof state);
+    printf("first half: ");
+    print_result(mbrtowc(&wc, "\xf0\x9f", 2, &state));
+    printf("\nsecond half: ");
+    print_result(mbrtowc(&wc, "\x8d\x8cz", 3, &state));
+    printf(" wc=%x\n", wc);
+
+    printf("empty input: ");
+    print_result(mbrtowc(&wc, "", 0, &state));
+    printf("\nnull character: ");
+    print_result(mbrtowc(&wc, "", 1, &state));
+    printf(" wc=%x\n", wc);
+
+    // Invalid sequences
+    const char *invalid[] = { "\x80", "\xc0\x80", "\xe0\x80\x80", "\xed\xa0\x80", "\xc3z", "\xff" };
+    for (size_t i = 0; i < sizeof(invalid) / sizeof(invalid[0]); i++) {
+        memset(&state, 0, sizeof state);
+        errno = 0;
+        printf("invalid %zu: ", i);
+        print_result(mbrtowc(&wc, invalid[i], strlen(invalid[i]), &state));
+        printf("\n");
+    }
+
+    // Resetting with a character pending is an error
+    memset(&state, 0, sizeof state);
+    mbrtowc(&wc, "\xe6", 1, &state);
+    errno = 0;
+    printf("reset while pending: ");
+    print_result(mbrtowc(NULL, NULL, 0, &state));
+    printf("\n");
+
+    // mbrlen shares the semantics
+    memset(&state, 0, sizeof state);
+    printf("mbrlen: ");
+    print_result(mbrlen("\xe6\xb0", 2, &state));
+    printf(" ");
+    print_result(mbrlen("\xb4", 1, &state));
+    printf("\n");
+
+    // mbsnrtowcs stopping in the middle of a character
+    wchar_t wbuf[16];
+    const char *src = in;
+    memset(&state, 0, sizeof state);
+    size_t n = mbsnrtowcs(wbuf, &src, 5, 16, &state);
+    printf("mbsnrtowcs: %zu consumed=%td mbsinit=%d\n", n, src - in, mbsinit(&state) != 0);
+    n = mbsnrtowcs(wbuf + n, &src, 16, 16, &state);
+    printf("mbsnrtowcs: %zu src=%s\n", n, src == NULL ? "NULL" : "set");
+    for (size_t i = 0; i < 4; i++) {
+        printf(" %x", wbuf[i]);
+    }
+    printf("\n");
+
+    // Counting leaves the source alone
+    src = in;
+    memset(&state, 0, sizeof state);
+    n = mbsrtowcs(NULL, &src, 0, &state);
+    printf("mbsrtowcs(NULL): %zu same=%d\n", n, src == in);
+
+    src = "ab\xff";
+    errno = 0;
+    n = mbsrtowcs(wbuf, &src, 16, &state);
+    printf("mbsrtowcs(invalid): ");
+    print_result(n);
+    printf(" at=%02x\n", (unsigned char) *src);
+
+    // wcsnrtombs and wcsrtombs
+    const wchar_t *wsrc = L"zß水\U0001F34C";
+    const wchar_t *wp = wsrc;
+    char mbuf[32];
+    memset(&state, 0, sizeof state);
+    n = wcsnrtombs(mbuf, &wp, 2, sizeof mbuf, &state);
+    printf("wcsnrtombs: %zu consumed=%td\n", n, wp - wsrc);
+    wp = wsrc;
+    n = wcsrtombs(mbuf, &wp, 5, &state);
+    printf("wcsrtombs(5): %zu consumed=%td\n", n, wp - wsrc);
+    n = wcsrtombs(NULL, &wp, 0, &state);
+    printf("wcsrtombs(NULL): %zu\n", n);
+    wp = wsrc;
+    n = wcsrtombs(mbuf, &wp, sizeof mbuf, &state);
+    printf("wcsrtombs: %zu src=%s\n", n, wp == NULL ? "NULL" : "set");
+    for (size_t i = 0; i <= n; i++) {
+        printf(" %02x", (unsigned char) mbuf[i]);
+    }
+    printf("\n");
+
+    const wchar_t bad[] = { L'a', 0xD800, 0 };
+    wp = bad;
+    errno = 0;
+    printf("wcsrtombs(surrogate): ");
+    print_result(wcsrtombs(mbuf, &wp, sizeof mbuf, &state));
+    printf(" consumed=%td\n", wp - bad);
+
+    printf("wcrtomb(NULL): %zu\n", wcrtomb(NULL, L'x', &state));
+}
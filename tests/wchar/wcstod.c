@@ -13,4 +13,7 @@ int main() {
     attempt(L"   19.2 wat");
     attempt(L"365.24 29.53");
     attempt(L" 29.53");
+    attempt(L"0x1.8p1z");
+    attempt(L"-infinity!");
+    attempt(L"1e+");
 }
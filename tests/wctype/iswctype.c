@@ -0,0 +1,49 @@
+#include <locale.h>
+#include <stdio.h>
+#include <wchar.h>
+#include <wctype.h>
+
+int main(void) {
+    setlocale(LC_ALL, "C.UTF-8");
+
+    const char *classes[] = {
+        "alnum", "alpha", "blank", "cntrl", "digit", "graph",
+        "lower", "print", "punct", "space", "upper", "xdigit",
+    };
+    wint_t chars[] = {
+        L'\0', L'\t', L'\n', L' ', L'!', L'0', L'7', L'A', L'f', L'z', L'~',
+        0x7F, 0x85, 0xA0, 0xAA, 0xDF, 0xE9, 0x3A3, 0x3C3, 0x416, 0x5D0, 0x660,
+        0x300, 0x1680, 0x2003, 0x2028, 0x20AC, 0x3000, 0x3042, 0x4E2D,
+        0xAC00, 0xE000, 0xFFFD, 0x1F600, 0x10FFFF, WEOF,
+    };
+    size_t nchars = sizeof(chars) / sizeof(chars[0]);
+
+    for (size_t i = 0; i < nchars; i++) {
+        printf("%06X:", chars[i]);
+        for (size_t j = 0; j < sizeof(classes) / sizeof(classes[0]); j++) {
+            if (iswctype(chars[i], wctype(classes[j]))) {
+                printf(" %s", classes[j]);
+            }
+        }
+        printf("\n");
+    }
+
+    printf("iswalpha(0xE9) = %d\n", iswalpha(0xE9) != 0);
+    printf("iswupper(0x3A3) = %d\n", iswupper(0x3A3) != 0);
+    printf("iswspace(0x2003) = %d\n", iswspace(0x2003) != 0);
+    printf("wctype(\"bogus\") = %ld\n", (long) wctype("bogus"));
+
+    wctrans_t upper = wctrans("toupper");
+    wctrans_t lower = wctrans("tolower");
+    printf("towctrans(0x3C3, toupper) = %X\n", towctrans(0x3C3, upper));
+    printf("towctrans(0x416, tolower) = %X\n", towctrans(0x416, lower));
+    printf("wctrans(\"bogus\") = %d\n", wctrans("bogus") == 0);
+
+    for (size_t i = 0; i < nchars - 1; i++) {
+        printf("wcwidth(%06X) = %d\n", chars[i], wcwidth(chars[i]));
+    }
+    printf("wcswidth(L\"abc\") = %d\n", wcswidth(L"abc", 3));
+    printf("wcswidth(L\"\\u4E2D\\u6587e\\u0301\") = %d\n", wcswidth(L"中文é", 10));
+    printf("wcswidth(L\"ab\\ncd\") = %d\n", wcswidth(L"ab\ncd", 5));
+    printf("wcswidth(L\"ab\\ncd\", 2) = %d\n", wcswidth(L"ab\ncd", 2));
+}
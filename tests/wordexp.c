@@ -0,0 +1,114 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <wordexp.h>
+
+#include "test_helpers.h"
+
+void print(const char *name, int ret, wordexp_t *p) {
+    printf("%s: %d\n", name, ret);
+    if (ret != 0) {
+        return;
+    }
+    for (size_t i = 0; i < p->we_offs + p->we_wordc; i++) {
+        printf("  [%s]\n", p->we_wordv[i] ? p->we_wordv[i] : "(null)");
+    }
+    if (p->we_wordv[p->we_offs + p->we_wordc] != NULL) {
+        puts("  missing NULL terminator");
+    }
+}
+
+void test(const char *words, int flags) {
+    wordexp_t p;
+    int ret = wordexp(words, &p, flags);
+    print(words, ret, &p);
+    if (ret == 0) {
+        wordfree(&p);
+    }
+}
+
+int main(void) {
+    setenv("HOME", "/home/user", 1);
+    setenv("NAME", "world", 1);
+    setenv("SPACED", "  a  b c ", 1);
+    setenv("EMPTY", "", 1);
+    setenv("FILE", "archive.tar.gz", 1);
+    setenv("STAR", "example_dir/*-you", 1);
+    unsetenv("UNSET");
+
+    // Splitting and quoting
+    test("hello world", 0);
+    test("  leading   and trailing  ", 0);
+    test("'single quoted' \"double quoted\"", 0);
+    test("a\\ b \\\"c\\\"", 0);
+    test("'' \"\" x", 0);
+    test("\"a\\$b\\x\"", 0);
+    test("con'cat'\"enated\"", 0);
+
+    // Parameters
+    test("$NAME ${NAME}s \"hello $NAME\"", 0);
+    test("$SPACED", 0);
+    test("\"$SPACED\"", 0);
+    test("x${EMPTY}y $EMPTY \"$EMPTY\"", 0);
+    test("$UNSET", 0);
+    test("$UNSET", WRDE_UNDEF);
+    test("${UNSET:-default value}", 0);
+    test("\"${UNSET:-default value}\"", 0);
+    test("${EMPTY:-empty} ${EMPTY-set}", 0);
+    test("${NAME:+alternative} ${UNSET:+alternative}", 0);
+    test("${#NAME} ${#EMPTY}", 0);
+    test("${FILE%.*} ${FILE%%.*} ${FILE#*.} ${FILE##*.}", 0);
+    test("${UNSET:=assigned}", 0);
+    test("$UNSET", 0);
+    test("${EMPTY:?}", 0);
+    test("$ a$ \"$\"", 0);
+
+    // Tilde
+    test("~ ~/bin x~ '~'", 0);
+    test("~root", 0);
+
+    // Command substitution
+    test("$(echo hello world)", 0);
+    test("\"$(echo hello world)\"", 0);
+    test("`echo back quoted`", 0);
+    test("a$(printf 'b\\n\\n\\n')c", 0);
+    test("$(echo \"(nested)\" $(echo inner))", 0);
+    test("$(echo hello)", WRDE_NOCMD);
+    test("\"`echo hello`\"", WRDE_NOCMD);
+
+    // Field splitting with IFS
+    setenv("LIST", "a:b::c", 1);
+    setenv("IFS", ":", 1);
+    test("$LIST", 0);
+    test("\"$LIST\"", 0);
+    unsetenv("IFS");
+
+    // Pathname expansion
+    test("example_dir/*-you", 0);
+    test("\"example_dir/*-you\"", 0);
+    test("$STAR", 0);
+    test("example_dir/\\*", 0);
+    test("example_dir/*-me", 0);
+
+    // Errors
+    test("a|b", 0);
+    test("a;b", 0);
+    test("a > b", 0);
+    test("{a,b}", 0);
+    test("'unterminated", 0);
+    test("\"unterminated", 0);
+    test("${NAME", 0);
+    test("$(echo", 0);
+    test("${NAME/a/b}", 0);
+    test("\"a|b;c\" 'a>b' a\\&b", 0);
+
+    // Offsets, appending and reuse
+    wordexp_t p;
+    p.we_offs = 2;
+    int ret = wordexp("first second", &p, WRDE_DOOFFS);
+    print("WRDE_DOOFFS", ret, &p);
+    ret = wordexp("third", &p, WRDE_DOOFFS | WRDE_APPEND);
+    print("WRDE_APPEND", ret, &p);
+    ret = wordexp("fourth", &p, WRDE_DOOFFS | WRDE_REUSE);
+    print("WRDE_REUSE", ret, &p);
+    wordfree(&p);
+}